@@ -0,0 +1,95 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Key required on mutating requests, `None` when the API is left open
+pub type ApiKey = Option<Arc<str>>;
+
+/// Read the optional `API_KEY` env var, treating an empty value as unset
+pub fn api_key_from_env() -> ApiKey {
+    std::env::var("API_KEY")
+        .ok()
+        .filter(|key| !key.is_empty())
+        .map(Arc::from)
+}
+
+fn is_mutating(method: &Method) -> bool {
+    matches!(*method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE)
+}
+
+// Require `Authorization: Bearer <API_KEY>` on mutating routes, GETs stay public
+pub async fn require_api_key(
+    State(api_key): State<ApiKey>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(expected) = api_key else {
+        return next.run(request).await;
+    };
+
+    if !is_mutating(request.method()) {
+        return next.run(request).await;
+    }
+
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if provided == Some(&*expected) {
+        next.run(request).await
+    } else {
+        tracing::warn!("Rejected {} {} without a valid API key", request.method(), request.uri().path());
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Missing or invalid API key" })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::Service;
+
+    fn app(api_key: ApiKey) -> Router {
+        Router::new()
+            .route("/", get(|| async { "ok" }).post(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(api_key, require_api_key))
+    }
+
+    async fn status(api_key: ApiKey, method: Method, authorization: Option<&str>) -> StatusCode {
+        let mut builder = Request::builder().method(method).uri("/");
+        if let Some(value) = authorization {
+            builder = builder.header(header::AUTHORIZATION, value);
+        }
+        let request = builder.body(Body::empty()).unwrap();
+
+        app(api_key).call(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_open_when_api_key_unset() {
+        assert_eq!(status(None, Method::POST, None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_required_on_mutating_requests() {
+        let key: ApiKey = Some(Arc::from("secret"));
+
+        assert_eq!(status(key.clone(), Method::GET, None).await, StatusCode::OK);
+        assert_eq!(status(key.clone(), Method::POST, None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(key.clone(), Method::POST, Some("Bearer wrong")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(key.clone(), Method::POST, Some("secret")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(key, Method::POST, Some("Bearer secret")).await, StatusCode::OK);
+    }
+}
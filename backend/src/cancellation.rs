@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Cancellation tokens for running work, found by the job ids callers gave it. Jobs started for
+/// the same key share one token, matching how `InFlight` shares one run between them, so
+/// cancelling any of them stops the shared run.
+pub struct Cancellations<K> {
+    registry: Mutex<Registry<K>>,
+}
+
+struct Registry<K> {
+    runs: HashMap<K, CancellationToken>,
+    jobs: HashMap<String, K>,
+}
+
+impl<K> Cancellations<K>
+where
+    K: Eq + Hash + Clone,
+{
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            registry: Mutex::new(Registry { runs: HashMap::new(), jobs: HashMap::new() }),
+        })
+    }
+
+    /// Register `job_id` against the run for `key`. `None` if the id is already in use.
+    pub fn start(self: &Arc<Self>, job_id: String, key: K) -> Option<Job<K>> {
+        let mut registry = self.registry.lock().unwrap();
+        if registry.jobs.contains_key(&job_id) {
+            return None;
+        }
+        let token = registry.runs.entry(key.clone()).or_default().clone();
+        registry.jobs.insert(job_id.clone(), key);
+        Some(Job { registry: Arc::clone(self), job_id, token })
+    }
+
+    /// Signal the run `job_id` belongs to to stop. `false` if no such job is running.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        let registry = self.registry.lock().unwrap();
+        let Some(token) = registry.jobs.get(job_id).and_then(|key| registry.runs.get(key)) else {
+            return false;
+        };
+        token.cancel();
+        true
+    }
+}
+
+/// A registered job, deregistered when dropped. The run's token is dropped with its last job.
+pub struct Job<K: Eq + Hash> {
+    registry: Arc<Cancellations<K>>,
+    job_id: String,
+    pub token: CancellationToken,
+}
+
+impl<K: Eq + Hash> Drop for Job<K> {
+    fn drop(&mut self) {
+        let Ok(mut registry) = self.registry.registry.lock() else {
+            return;
+        };
+        if let Some(key) = registry.jobs.remove(&self.job_id) {
+            if !registry.jobs.values().any(|other| *other == key) {
+                registry.runs.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_on_one_key_share_cancellation() {
+        let cancellations = Cancellations::<&str>::new();
+        let first = cancellations.start("job-1".to_string(), "playlist").unwrap();
+        let second = cancellations.start("job-2".to_string(), "playlist").unwrap();
+        let other = cancellations.start("job-3".to_string(), "other").unwrap();
+        assert!(cancellations.start("job-1".to_string(), "other").is_none());
+
+        assert!(cancellations.cancel("job-2"));
+        assert!(first.token.is_cancelled());
+        assert!(!other.token.is_cancelled());
+        assert!(!cancellations.cancel("unknown"));
+
+        // The run's token outlives the first job but not the last
+        drop(first);
+        assert!(cancellations.cancel("job-2"));
+        drop(second);
+        assert!(!cancellations.cancel("job-2"));
+        let fresh = cancellations.start("job-4".to_string(), "playlist").unwrap();
+        assert!(!fresh.token.is_cancelled());
+    }
+}
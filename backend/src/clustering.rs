@@ -0,0 +1,175 @@
+use crate::models::Track;
+use crate::similarity::DEFAULT_TEMPO_SCALE;
+
+/// Upper bound on k-means iterations when assignments keep changing
+const MAX_ITERATIONS: usize = 50;
+/// Fixed seed so the same library always clusters the same way
+const SEED: u64 = 0x5EED_CAFE;
+
+/// A track's point in feature space: valence, energy, danceability, acousticness, tempo / `DEFAULT_TEMPO_SCALE`
+pub type FeatureVector = [f64; 5];
+
+/// The features clustering uses, or `None` if the track is missing any of them
+pub fn feature_vector(track: &Track) -> Option<FeatureVector> {
+    Some([
+        track.valence?,
+        track.energy?,
+        track.danceability?,
+        track.acousticness?,
+        track.tempo? / DEFAULT_TEMPO_SCALE,
+    ])
+}
+
+pub struct Clusters {
+    /// Cluster index for each input point, in input order
+    pub assignments: Vec<usize>,
+    pub centroids: Vec<FeatureVector>,
+}
+
+/// Lloyd's k-means with k-means++ seeding from a fixed seed, so results are reproducible.
+/// `k` is capped at the number of points.
+pub fn kmeans(points: &[FeatureVector], k: usize) -> Clusters {
+    let k = k.min(points.len());
+    if k == 0 {
+        return Clusters { assignments: vec![0; points.len()], centroids: Vec::new() };
+    }
+
+    let mut rng = SplitMix64(SEED);
+    let mut centroids = initial_centroids(points, k, &mut rng);
+    let mut assignments = vec![usize::MAX; points.len()];
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (point, assignment) in points.iter().zip(assignments.iter_mut()) {
+            let nearest = nearest_centroid(point, &centroids);
+            if nearest != *assignment {
+                *assignment = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![[0.0; 5]; k];
+        let mut counts = vec![0usize; k];
+        for (point, &cluster) in points.iter().zip(&assignments) {
+            counts[cluster] += 1;
+            for (sum, value) in sums[cluster].iter_mut().zip(point) {
+                *sum += value;
+            }
+        }
+        // An emptied cluster keeps its previous centroid
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            if count > 0 {
+                *centroid = sum.map(|total| total / count as f64);
+            }
+        }
+    }
+
+    Clusters { assignments, centroids }
+}
+
+// k-means++: each further centroid is picked with probability proportional to its squared distance
+fn initial_centroids(points: &[FeatureVector], k: usize, rng: &mut SplitMix64) -> Vec<FeatureVector> {
+    let mut centroids = vec![points[rng.below(points.len())]];
+
+    while centroids.len() < k {
+        let weights: Vec<f64> = points
+            .iter()
+            .map(|point| squared_distance(point, &centroids[nearest_centroid(point, &centroids)]))
+            .collect();
+        let total: f64 = weights.iter().sum();
+
+        // Every point already sits on a centroid, so any choice is as good as another
+        if total == 0.0 {
+            centroids.push(points[rng.below(points.len())]);
+            continue;
+        }
+
+        let mut target = rng.next_f64() * total;
+        let mut chosen = points.len() - 1;
+        for (index, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                chosen = index;
+                break;
+            }
+            target -= weight;
+        }
+        centroids.push(points[chosen]);
+    }
+
+    centroids
+}
+
+fn nearest_centroid(point: &FeatureVector, centroids: &[FeatureVector]) -> usize {
+    centroids
+        .iter()
+        .map(|centroid| squared_distance(point, centroid))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+fn squared_distance(a: &FeatureVector, b: &FeatureVector) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+/// Small deterministic PRNG, enough for picking starting centroids
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kmeans_separates_distinct_groups() {
+        let points = vec![
+            [0.1, 0.1, 0.1, 0.9, 0.4],
+            [0.15, 0.12, 0.1, 0.85, 0.42],
+            [0.9, 0.9, 0.8, 0.1, 0.7],
+            [0.88, 0.92, 0.85, 0.05, 0.68],
+            [0.12, 0.08, 0.15, 0.88, 0.41],
+        ];
+
+        let clusters = kmeans(&points, 2);
+
+        assert_eq!(clusters.centroids.len(), 2);
+        let calm = clusters.assignments[0];
+        let upbeat = clusters.assignments[2];
+        assert_ne!(calm, upbeat);
+        assert_eq!(clusters.assignments, vec![calm, calm, upbeat, upbeat, calm]);
+    }
+
+    #[test]
+    fn test_kmeans_is_deterministic_and_caps_k() {
+        let points = vec![[0.2, 0.4, 0.6, 0.8, 0.5], [0.3, 0.1, 0.9, 0.2, 0.6], [0.7, 0.7, 0.2, 0.4, 0.3]];
+
+        let first = kmeans(&points, 10);
+        let second = kmeans(&points, 10);
+
+        assert_eq!(first.centroids.len(), 3);
+        assert_eq!(first.assignments, second.assignments);
+        assert_eq!(first.centroids, second.centroids);
+    }
+}
@@ -1,23 +1,126 @@
 use crate::{
+    clustering,
     models::*, 
-    spotify::SpotifyClient, 
-    neo4j_db::{self, Neo4jClient}, 
-    youtube::YouTubeClient
+    spotify::{SpotifyClient, SpotifyError}, 
+    neo4j_db::{self, CandidateFilter, Neo4jClient, TrackFilter, TrackSort}, 
+    m3u,
+    pagination::PageCursor,
+    prerender,
+    recommendations,
+    similarity::{self, SimilarityWeights},
+    state::{ImportJobs, ImportRegistry, PlaylistStore, TrackEvents},
+    youtube::{PlaylistOptions, YouTubeClient, YouTubeError}
 };
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::Json,
-    Json as JsonBody,
+    body::Body,
+    extract::{
+        rejection::JsonRejection,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        FromRequest, Path, Query, Request, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{AppendHeaders, Html, IntoResponse, Json, Response},
 };
+use serde::de::DeserializeOwned;
+use futures::{StreamExt, TryStreamExt};
 use serde_json::{json, Value};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn, error, debug, instrument};
 
+// How many nearest tracks to fetch per requested result when diversifying
+const DIVERSITY_CANDIDATE_FACTOR: i32 = 5;
+const DEFAULT_ARTISTS_PAGE_SIZE: i64 = 60;
+const MAX_ARTISTS_PAGE_SIZE: i64 = 200;
+const DEFAULT_TRACKS_PAGE_SIZE: i64 = 100;
+const MAX_TRACKS_PAGE_SIZE: i64 = 500;
+const DEFAULT_RANDOM_TRACKS: i64 = 10;
+const MAX_RANDOM_TRACKS: i64 = 50;
+const DEFAULT_TRACK_RECOMMENDATIONS: i64 = 20;
+const DEFAULT_IMPORTS_PAGE_SIZE: i64 = 50;
+const MAX_IMPORTS_PAGE_SIZE: i64 = 200;
+// Total number of items across all pages of a paginated listing
+const TOTAL_COUNT_HEADER: &str = "x-total-count";
+// Opaque cursor for the page after this one, absent on the last page
+const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+// How many tracks could have been recommended, and whether that fell short of the requested limit
+const AVAILABLE_COUNT_HEADER: &str = "x-available-count";
+const LIMIT_EXCEEDED_HEADER: &str = "x-limit-exceeded";
+// How many recommendations `max_distance` dropped from the list that would otherwise have been returned
+const FILTERED_COUNT_HEADER: &str = "x-filtered-count";
+// How many candidates `exclude_explicit` left out
+const EXPLICIT_FILTERED_HEADER: &str = "x-explicit-filtered-count";
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+const DEFAULT_CLUSTERS: usize = 5;
+const MAX_CLUSTERS: usize = 20;
+
+fn availability_headers(limit: i32, available: i64) -> [(&'static str, String); 2] {
+    [
+        (AVAILABLE_COUNT_HEADER, available.to_string()),
+        (LIMIT_EXCEEDED_HEADER, (i64::from(limit) > available).to_string()),
+    ]
+}
+
+/// Candidates a recommendation could pick from, and how many explicit ones the filter left out
+#[derive(Debug, Clone, Copy, Default)]
+struct CandidateCounts {
+    available: i64,
+    explicit_filtered: i64,
+}
+
+// Run `count` under `filter`, and again allowing explicit tracks when the filter excludes them
+async fn count_candidates<F, Fut>(filter: &CandidateFilter, count: F) -> Result<CandidateCounts, StatusCode>
+where
+    F: Fn(CandidateFilter) -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<i64>>,
+{
+    let counts = if filter.exclude_explicit {
+        let (available, allowing_explicit) = tokio::try_join!(count(filter.clone()), count(filter.allowing_explicit()))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        CandidateCounts { available, explicit_filtered: (allowing_explicit - available).max(0) }
+    } else {
+        let available = count(filter.clone()).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        CandidateCounts { available, explicit_filtered: 0 }
+    };
+    Ok(counts)
+}
+
+fn explicit_filtered_header(counts: CandidateCounts) -> (&'static str, String) {
+    (EXPLICIT_FILTERED_HEADER, counts.explicit_filtered.to_string())
+}
+
+// `availability_headers` plus how many of the `limit` nearest tracks `max_distance` filtered out,
+// and how many candidates were explicit
+fn recommendation_headers(limit: i32, counts: CandidateCounts, returned: usize) -> [(&'static str, String); 4] {
+    let [available_count, limit_exceeded] = availability_headers(limit, counts.available);
+    let unfiltered = i64::from(limit).clamp(0, counts.available.max(0));
+    let filtered = (unfiltered - returned as i64).max(0);
+    [
+        available_count,
+        limit_exceeded,
+        (FILTERED_COUNT_HEADER, filtered.to_string()),
+        explicit_filtered_header(counts),
+    ]
+}
+
+// `exclude_explicit=true|false`, off when absent
+fn parse_exclude_explicit(params: &HashMap<String, String>) -> Result<bool, StatusCode> {
+    params
+        .get("exclude_explicit")
+        .map(|value| value.parse::<bool>().map_err(|_| StatusCode::BAD_REQUEST))
+        .transpose()
+        .map(|exclude| exclude.unwrap_or(false))
+}
+
 // Helper function to extract playlist ID from Spotify URL
 fn extract_playlist_id(url: &str) -> Option<String> {
     // Handle URLs like: https://open.spotify.com/playlist/441K4rF3u0qfg9m4X1WSQJ
-    if let Some(captures) = url.split('/').last() {
+    if let Some(captures) = url.split('/').next_back() {
         // Remove any query parameters
         let playlist_id = captures.split('?').next()?;
         if !playlist_id.is_empty() {
@@ -27,23 +130,177 @@ fn extract_playlist_id(url: &str) -> Option<String> {
     None
 }
 
-pub async fn health_check() -> Json<Value> {
+// Map Spotify client failures onto the status we report to our own callers
+fn spotify_error_status(error: &SpotifyError) -> StatusCode {
+    match error {
+        SpotifyError::Auth(_) => StatusCode::UNAUTHORIZED,
+        SpotifyError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        SpotifyError::NotFound(_) => StatusCode::NOT_FOUND,
+        SpotifyError::Api { status, .. } if status.is_client_error() => StatusCode::BAD_REQUEST,
+        SpotifyError::Network(_) | SpotifyError::Parse(_) | SpotifyError::Api { .. } => StatusCode::BAD_GATEWAY,
+    }
+}
+
+fn youtube_error_status(error: &YouTubeError) -> StatusCode {
+    match error {
+        YouTubeError::QuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+        YouTubeError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+        YouTubeError::NotFound(_) => StatusCode::NOT_FOUND,
+        YouTubeError::Network(_) | YouTubeError::Parse(_) | YouTubeError::Api { .. } => StatusCode::BAD_GATEWAY,
+    }
+}
+
+/// Error response with a JSON `{"error": ...}` body, the same shape the auth middleware uses
+#[derive(Debug, Clone)]
+pub struct ApiError(StatusCode, Cow<'static, str>);
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<Cow<'static, str>>) -> Self {
+        ApiError(status, message.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let ApiError(status, message) = self;
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        ApiError::new(status, status.canonical_reason().unwrap_or("Request failed"))
+    }
+}
+
+/// `Json` request body whose rejections name what was wrong, such as a missing or mistyped field
+pub struct JsonBody<T>(pub T);
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        Json::<T>::from_request(request, state)
+            .await
+            .map(|Json(value)| JsonBody(value))
+            .map_err(json_rejection_error)
+    }
+}
+
+// Keeps axum's status (400 for bad syntax, 422 for the wrong shape, 415 for a missing content type)
+fn json_rejection_error(rejection: JsonRejection) -> ApiError {
+    ApiError::new(rejection.status(), rejection.body_text())
+}
+
+// The client's playlist defaults with the request's overrides applied
+fn playlist_options(
+    youtube_client: &YouTubeClient,
+    min_confidence: Option<f64>,
+    batch_size: Option<usize>,
+    batch_delay_ms: Option<u64>,
+) -> Result<PlaylistOptions, ApiError> {
+    youtube_client
+        .playlist_defaults()
+        .with_overrides(min_confidence, batch_size, batch_delay_ms)
+        .map_err(|message| ApiError::new(StatusCode::BAD_REQUEST, message))
+}
+
+// Optional `Idempotency-Key` header; present but empty or overlong keys are rejected
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, ApiError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    match value.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => Ok(Some(key.to_string())),
+        _ => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "Idempotency-Key must be 1-255 visible ASCII characters",
+        )),
+    }
+}
+
+// Create a playlist once per idempotency key, or every time when the client sent none
+async fn create_playlist_once<F>(
+    playlists: &PlaylistStore,
+    key: Option<String>,
+    work: F,
+) -> Result<CreatedPlaylist, ApiError>
+where
+    F: std::future::Future<Output = Result<CreatedPlaylist, ApiError>> + Send + 'static,
+{
+    match key {
+        Some(key) => playlists
+            .run(key, work)
+            .await
+            .unwrap_or_else(|| Err(StatusCode::INTERNAL_SERVER_ERROR.into())),
+        None => work.await,
+    }
+}
+
+fn youtube_api_key(youtube_client: &YouTubeClient) -> Result<&str, ApiError> {
+    youtube_client
+        .api_key()
+        .ok_or(ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "YOUTUBE_API_KEY not configured"))
+}
+
+pub async fn health_check(State(neo4j_client): State<Neo4jClient>) -> Json<Value> {
+    let neo4j_up = neo4j_db::ping(&neo4j_client).await.is_ok();
     Json(json!({
-        "status": "healthy",
+        "status": if neo4j_up { "healthy" } else { "degraded" },
         "service": "Spotify Neo4j Backend",
-        "version": "0.1.0"
+        "version": "0.1.0",
+        "neo4j": if neo4j_up { "up" } else { "down" }
     }))
 }
 
-#[instrument(skip(neo4j_client))]
+/// Liveness probe: the process is up and serving requests
+pub async fn livez() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Readiness probe: 503 while Neo4j is unreachable
+pub async fn readyz(State(neo4j_client): State<Neo4jClient>) -> StatusCode {
+    match neo4j_db::ping(&neo4j_client).await {
+        Ok(()) => StatusCode::OK,
+        Err(e) => {
+            warn!("Readiness check failed, Neo4j unreachable: {}", e);
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
+/// Longest `job_id` a client can name an import with
+const MAX_JOB_ID_LEN: usize = 64;
+
+#[instrument(skip(neo4j_client, spotify_client, imports, import_jobs, track_events))]
 pub async fn import_spotify_data(
     State(neo4j_client): State<Neo4jClient>,
+    State(spotify_client): State<Arc<SpotifyClient>>,
+    State(imports): State<Arc<ImportRegistry>>,
+    State(import_jobs): State<Arc<ImportJobs>>,
+    State(track_events): State<TrackEvents>,
+    Query(params): Query<HashMap<String, String>>,
     JsonBody(request): JsonBody<SpotifyImportRequest>,
-) -> Result<Json<Value>, StatusCode> {
-    let start_time = std::time::Instant::now();
+) -> Result<Json<Value>, ApiError> {
     info!("Starting Spotify playlist import for URL: {}", request.playlist_url);
-    
-    let spotify_client = SpotifyClient::new();
+
+    // The body wins over the query string when both set a minimum
+    let min_popularity = match request.min_popularity {
+        Some(min_popularity) => Some(min_popularity),
+        None => params
+            .get("min_popularity")
+            .map(|s| s.parse::<i32>().map_err(|_| StatusCode::BAD_REQUEST))
+            .transpose()?,
+    };
+    if min_popularity.is_some_and(|min_popularity| !(0..=100).contains(&min_popularity)) {
+        return Err(StatusCode::BAD_REQUEST.into());
+    }
+    let range = import_range(request.offset, request.limit)?;
     
     // Extract playlist ID from URL
     let playlist_id = match extract_playlist_id(&request.playlist_url) {
@@ -53,46 +310,331 @@ pub async fn import_spotify_data(
         }
         None => {
             error!("Failed to extract playlist ID from URL: {}", request.playlist_url);
-            return Err(StatusCode::BAD_REQUEST);
+            return Err(StatusCode::BAD_REQUEST.into());
         }
     };
-    
-    // Try to get access token from environment variable first, then generate one if not found
-    let access_token = match std::env::var("SPOTIFY_ACCESS_TOKEN") {
-        Ok(token) => {
-            debug!("Using Spotify access token from environment variable");
-            token
-        }
-        Err(_) => {
-            debug!("SPOTIFY_ACCESS_TOKEN not found in environment, generating new token");
-            match spotify_client.get_access_token().await {
-                Ok(token) => {
-                    debug!("Successfully generated new Spotify access token");
-                    token
-                }
-                Err(e) => {
-                    error!("Failed to generate Spotify access token: {}", e);
-                    return Err(StatusCode::UNAUTHORIZED);
-                }
-            }
+
+    let options = ImportOptions {
+        min_popularity,
+        fetch_features: request.fetch_features.unwrap_or(true),
+        exclude_explicit: request.exclude_explicit.unwrap_or(false),
+        range,
+    };
+
+    if request.dry_run.unwrap_or(false) {
+        return dry_run_import(&neo4j_client, &spotify_client, &playlist_id, options)
+            .await
+            .map(Json)
+            .map_err(playlist_error);
+    }
+
+    let job_id = import_job_id(request.job_id)?;
+
+    // A second request for the same import waits on the first and gets its summary
+    let key = (playlist_id.clone(), options);
+    let job = Arc::new(import_jobs.start(job_id.clone(), key.clone()).ok_or(StatusCode::CONFLICT)?);
+    let work = import_playlist(
+        neo4j_client,
+        spotify_client,
+        track_events,
+        playlist_id,
+        request.playlist_url,
+        options,
+        job.token.clone(),
+    );
+    let mut summary = imports
+        .run(key, holding_job(Arc::clone(&job), work))
+        .await
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(playlist_error)?;
+
+    summary["job_id"] = json!(job_id);
+    Ok(Json(summary))
+}
+
+/// Spotify answers `404` for private playlists as well as missing ones, without saying which
+const PLAYLIST_UNAVAILABLE: &str = "Playlist not found or not public — it may require user authorization.";
+
+// A `404` from a playlist fetch gets a message the frontend can show as-is
+fn playlist_error(status: StatusCode) -> ApiError {
+    if status == StatusCode::NOT_FOUND {
+        ApiError::new(status, PLAYLIST_UNAVAILABLE)
+    } else {
+        status.into()
+    }
+}
+
+/// Id of the playlist node a library import can link the saved tracks from
+const LIKED_SONGS_ID: &str = "liked-songs";
+const LIKED_SONGS_NAME: &str = "Liked Songs";
+
+/// Import the user's saved tracks the same way as a playlist, optionally linking them from a
+/// "Liked Songs" playlist node
+#[instrument(skip(neo4j_client, spotify_client, import_jobs, track_events, request))]
+pub async fn import_library(
+    State(neo4j_client): State<Neo4jClient>,
+    State(spotify_client): State<Arc<SpotifyClient>>,
+    State(import_jobs): State<Arc<ImportJobs>>,
+    State(track_events): State<TrackEvents>,
+    JsonBody(request): JsonBody<LibraryImportRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    if request.access_token.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if request.min_popularity.is_some_and(|min_popularity| !(0..=100).contains(&min_popularity)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let options = ImportOptions {
+        min_popularity: request.min_popularity,
+        fetch_features: request.fetch_features.unwrap_or(true),
+        exclude_explicit: request.exclude_explicit.unwrap_or(false),
+        range: import_range(request.offset, request.limit)?,
+    };
+    let job_id = import_job_id(request.job_id)?;
+
+    // Libraries belong to whoever's token it is, so library imports are never coalesced
+    let job = import_jobs
+        .start(job_id.clone(), (format!("library:{}", job_id), options))
+        .ok_or(StatusCode::CONFLICT)?;
+    info!("Starting Spotify library import");
+    let start_time = std::time::Instant::now();
+
+    let fetched = tokio::select! {
+        fetched = spotify_client.get_saved_tracks(&request.access_token, options.fetch_features, options.range) => fetched,
+        _ = job.token.cancelled() => {
+            info!("Library import cancelled while fetching tracks");
+            return Ok(Json(json!({
+                "message": "Spotify import cancelled",
+                "cancelled": true,
+                "imported_tracks": 0,
+                "imported_artists": 0,
+                "job_id": job_id
+            })));
         }
     };
-    
+    let slice = fetched.map_err(|e| {
+        error!("Failed to fetch saved tracks: {}", e);
+        spotify_error_status(&e)
+    })?;
+    let saved_ids: Vec<String> = slice.tracks.iter().map(|track| track.id.clone()).collect();
+
+    let (mut summary, track_ids) = store_import(
+        &neo4j_client,
+        &spotify_client,
+        &track_events,
+        LIKED_SONGS_ID,
+        slice.tracks,
+        options,
+        &job.token,
+    )
+    .await?;
+
+    if request.create_playlist.unwrap_or(false) {
+        let linked = neo4j_db::link_playlist_tracks(&neo4j_client, LIKED_SONGS_ID, LIKED_SONGS_NAME, &saved_ids, options.range.offset)
+            .await
+            .map_err(|e| {
+                error!("Failed to link saved tracks from the {} playlist: {}", LIKED_SONGS_NAME, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        summary["playlist_tracks"] = json!(linked);
+    }
+
+    let total_duration = start_time.elapsed();
+    metrics::histogram!("spotify_import_duration_seconds").record(total_duration.as_secs_f64());
+    info!("Library import finished in {:.2}s", total_duration.as_secs_f64());
+
+    summary["range"] = json!({ "start": options.range.offset, "end": slice.end });
+    summary["job_id"] = json!(job_id);
+    summary["duration_seconds"] = json!(total_duration.as_secs_f64());
+    let record = import_record(LIKED_SONGS_ID, None, &summary);
+    record_import(&neo4j_client, record, &track_ids, options.range.offset, &mut summary).await;
+    Ok(Json(summary))
+}
+
+// The spawned run keeps its starter's job registered, so the import can still be cancelled after
+// that client disconnects. A joiner's work is dropped unrun, leaving its request to hold its job.
+async fn holding_job<J, F>(job: Arc<J>, work: F) -> F::Output
+where
+    F: std::future::Future,
+{
+    let _job = job;
+    work.await
+}
+
+// Clients name the job up front so they can cancel it while the import request is still waiting
+fn import_job_id(job_id: Option<String>) -> Result<String, StatusCode> {
+    match job_id {
+        Some(job_id) if job_id.is_empty() || job_id.len() > MAX_JOB_ID_LEN => Err(StatusCode::BAD_REQUEST),
+        Some(job_id) => Ok(job_id),
+        None => Ok(uuid::Uuid::new_v4().to_string()),
+    }
+}
+
+/// Ask a running import to stop after the track it's storing. The import's own request then
+/// returns with `cancelled: true` and what was stored so far.
+pub async fn cancel_import(
+    State(import_jobs): State<Arc<ImportJobs>>,
+    Path(job_id): Path<String>,
+) -> StatusCode {
+    if import_jobs.cancel(&job_id) {
+        info!("Cancelling import job {}", job_id);
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+// The playlist positions an import covers; negative bounds are rejected
+fn import_range(offset: Option<i64>, limit: Option<i64>) -> Result<PlaylistRange, StatusCode> {
+    let non_negative = |value: i64| usize::try_from(value).map_err(|_| StatusCode::BAD_REQUEST);
+    Ok(PlaylistRange {
+        offset: offset.map(non_negative).transpose()?.unwrap_or(0),
+        limit: limit.map(non_negative).transpose()?,
+    })
+}
+
+/// List a playlist's tracks without storing anything, so it can be checked before importing.
+/// `with_features=false` skips the audio features lookups for a faster answer.
+#[instrument(skip(spotify_client))]
+pub async fn preview_playlist(
+    State(spotify_client): State<Arc<SpotifyClient>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Vec<Track>>, ApiError> {
+    let url = params.get("url").ok_or(StatusCode::BAD_REQUEST)?;
+    let with_features = params
+        .get("with_features")
+        .map(|s| s.parse::<bool>().map_err(|_| StatusCode::BAD_REQUEST))
+        .transpose()?
+        .unwrap_or(true);
+    let playlist_id = extract_playlist_id(url).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let tracks = spotify_client
+        .get_playlist_tracks(&playlist_id, with_features)
+        .await
+        .map_err(|e| {
+            error!("Failed to preview playlist {}: {}", playlist_id, e);
+            playlist_error(spotify_error_status(&e))
+        })?;
+
+    info!("Previewed {} tracks from playlist {}", tracks.len(), playlist_id);
+    Ok(Json(tracks))
+}
+
+/// The tracks an import keeps, with how many were skipped for low popularity and for being explicit
+fn filter_import_tracks(tracks: Vec<Track>, options: &ImportOptions) -> (Vec<Track>, usize, usize) {
+    let mut skipped_low_popularity = 0;
+    let mut skipped_explicit = 0;
+    let tracks = tracks
+        .into_iter()
+        .filter(|track| {
+            if options.min_popularity.is_some_and(|min_popularity| track.popularity < min_popularity) {
+                skipped_low_popularity += 1;
+                false
+            } else if options.exclude_explicit && track.explicit {
+                skipped_explicit += 1;
+                false
+            } else {
+                true
+            }
+        })
+        .collect();
+    (tracks, skipped_low_popularity, skipped_explicit)
+}
+
+/// What importing the playlist would add, found with existence checks only. Audio features
+/// aren't fetched since they don't change what is new.
+async fn dry_run_import(
+    neo4j_client: &Neo4jClient,
+    spotify_client: &SpotifyClient,
+    playlist_id: &str,
+    options: ImportOptions,
+) -> Result<Value, StatusCode> {
+    let slice = spotify_client
+        .get_playlist_range(playlist_id, false, options.range)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch playlist {} for a dry run: {}", playlist_id, e);
+            spotify_error_status(&e)
+        })?;
+
+    let (tracks, skipped_low_popularity, skipped_explicit) = filter_import_tracks(slice.tracks, &options);
+
+    let track_ids: Vec<String> = tracks.iter().map(|track| track.id.clone()).collect();
+    let mut artist_ids: Vec<String> = tracks
+        .iter()
+        .flat_map(|track| track.artist_ids.iter().chain(track.album_artists.iter().map(|(id, _)| id)).cloned())
+        .collect();
+    artist_ids.sort();
+    artist_ids.dedup();
+
+    let (existing_tracks, existing_artists) = tokio::try_join!(
+        neo4j_db::existing_track_ids(neo4j_client, &track_ids),
+        neo4j_db::existing_artist_ids(neo4j_client, &artist_ids),
+    )
+    .map_err(|e| {
+        error!("Failed to check existing tracks for playlist {}: {}", playlist_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let already_present = track_ids.iter().filter(|id| existing_tracks.contains(*id)).count();
+    let would_add_artists = artist_ids.iter().filter(|id| !existing_artists.contains(*id)).count();
+
+    info!(
+        "Dry run of playlist {}: {} new tracks, {} already present, {} new artists",
+        playlist_id,
+        track_ids.len() - already_present,
+        already_present,
+        would_add_artists
+    );
+
+    Ok(json!({
+        "dry_run": true,
+        "would_add_tracks": track_ids.len() - already_present,
+        "already_present": already_present,
+        "would_add_artists": would_add_artists,
+        "skipped_low_popularity": skipped_low_popularity,
+        "skipped_explicit": skipped_explicit,
+        "range": { "start": options.range.offset, "end": slice.end },
+        "playlist_id": playlist_id
+    }))
+}
+
+async fn import_playlist(
+    neo4j_client: Neo4jClient,
+    spotify_client: Arc<SpotifyClient>,
+    track_events: TrackEvents,
+    playlist_id: String,
+    playlist_url: String,
+    options: ImportOptions,
+    cancel: CancellationToken,
+) -> Result<Value, StatusCode> {
+    let start_time = std::time::Instant::now();
+
     // Get tracks from Spotify playlist
     debug!("Fetching playlist tracks from Spotify API");
     let fetch_start = std::time::Instant::now();
-    let tracks = match spotify_client
-        .get_playlist_tracks(&playlist_id, &access_token)
-        .await
-    {
-        Ok(tracks) => {
+    let fetched = tokio::select! {
+        fetched = spotify_client.get_playlist_range(&playlist_id, options.fetch_features, options.range) => fetched,
+        _ = cancel.cancelled() => {
+            info!("Import of playlist {} cancelled while fetching tracks", playlist_id);
+            return Ok(json!({
+                "message": "Spotify import cancelled",
+                "cancelled": true,
+                "imported_tracks": 0,
+                "imported_artists": 0,
+                "playlist_id": playlist_id
+            }));
+        }
+    };
+    let slice = match fetched {
+        Ok(slice) => {
             let fetch_duration = fetch_start.elapsed();
             info!(
                 "Successfully fetched {} tracks from playlist in {:.2}s", 
-                tracks.len(), 
+                slice.tracks.len(), 
                 fetch_duration.as_secs_f64()
             );
-            tracks
+            slice
         }
         Err(e) => {
             let fetch_duration = fetch_start.elapsed();
@@ -101,31 +643,170 @@ pub async fn import_spotify_data(
                 fetch_duration.as_secs_f64(),
                 e
             );
-            return Err(StatusCode::BAD_REQUEST);
+            return Err(spotify_error_status(&e));
         }
     };
 
-    let mut imported_tracks = 0;
+    let (mut summary, track_ids) = store_import(
+        &neo4j_client,
+        &spotify_client,
+        &track_events,
+        &playlist_id,
+        slice.tracks,
+        options,
+        &cancel,
+    )
+    .await?;
+
+    let total_duration = start_time.elapsed();
+    metrics::histogram!("spotify_import_duration_seconds").record(total_duration.as_secs_f64());
+    info!("Import of playlist {} finished in {:.2}s", playlist_id, total_duration.as_secs_f64());
+
+    summary["range"] = json!({ "start": options.range.offset, "end": slice.end });
+    summary["playlist_id"] = json!(playlist_id);
+    summary["duration_seconds"] = json!(total_duration.as_secs_f64());
+    let record = import_record(&playlist_id, Some(playlist_url), &summary);
+    record_import(&neo4j_client, record, &track_ids, options.range.offset, &mut summary).await;
+    Ok(summary)
+}
+
+// The history entry for a finished import, with the counts and duration from its summary
+fn import_record(source_id: &str, source_url: Option<String>, summary: &Value) -> ImportRecord {
+    let count = |field: &str| summary[field].as_i64().unwrap_or(0);
+    ImportRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        source_id: source_id.to_string(),
+        source_url,
+        imported_at: chrono::Utc::now().to_rfc3339(),
+        imported_tracks: count("imported_tracks"),
+        new_tracks: count("new_tracks"),
+        imported_artists: count("imported_artists"),
+        new_artists: count("new_artists"),
+        duration_seconds: summary["duration_seconds"].as_f64().unwrap_or(0.0),
+        cancelled: summary["cancelled"].as_bool().unwrap_or(false),
+    }
+}
+
+// Add an import to the history, linked to the tracks it stored, and give its id in the summary.
+// `first_position` is where in the source the import's range starts, so the stored tracks keep
+// their playlist order. The tracks are already stored, so failing to record the import doesn't
+// fail it.
+async fn record_import(
+    neo4j_client: &Neo4jClient,
+    record: ImportRecord,
+    track_ids: &StoredTrackIds,
+    first_position: usize,
+    summary: &mut Value,
+) {
+    let stored = neo4j_db::store_import_record(neo4j_client, &record, &track_ids.stored, &track_ids.created, first_position);
+    match stored.await {
+        Ok(()) => summary["import_id"] = json!(record.id),
+        Err(e) => warn!("Failed to record import of {}: {}", record.source_id, e),
+    }
+}
+
+/// Past imports, newest first, with what each stored and how long it took
+pub async fn get_imports(
+    State(neo4j_client): State<Neo4jClient>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Vec<ImportRecord>>, StatusCode> {
+    let limit = params
+        .get("limit")
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_IMPORTS_PAGE_SIZE)
+        .clamp(1, MAX_IMPORTS_PAGE_SIZE);
+
+    let imports = neo4j_db::get_imports(&neo4j_client, limit)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch import history: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(imports))
+}
+
+/// Ids of the tracks an import stored, and of those among them it stored for the first time
+#[derive(Debug, Default)]
+struct StoredTrackIds {
+    stored: Vec<String>,
+    created: Vec<String>,
+}
+
+/// Remove what an import added, for when the wrong playlist was imported. Tracks another import
+/// also stored are kept, as are albums and artists that still have tracks.
+pub async fn undo_import(
+    State(neo4j_client): State<Neo4jClient>,
+    Path(import_id): Path<String>,
+) -> Result<Json<UndoneImport>, StatusCode> {
+    let undone = neo4j_db::undo_import(&neo4j_client, &import_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to undo import {}: {}", import_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(undone))
+}
+
+/// Store fetched tracks and their artists, linking new tracks by similarity, and summarise what
+/// was stored along with the ids of the tracks stored. `source_id` names where the tracks came
+/// from in events and logs.
+async fn store_import(
+    neo4j_client: &Neo4jClient,
+    spotify_client: &SpotifyClient,
+    track_events: &TrackEvents,
+    source_id: &str,
+    tracks: Vec<Track>,
+    options: ImportOptions,
+    cancel: &CancellationToken,
+) -> Result<(Value, StoredTrackIds), StatusCode> {
+    let (tracks, skipped_low_popularity, skipped_explicit) = filter_import_tracks(tracks, &options);
+    if skipped_low_popularity > 0 {
+        info!("Skipping {} tracks below the minimum popularity", skipped_low_popularity);
+    }
+    if skipped_explicit > 0 {
+        info!("Skipping {} explicit tracks", skipped_explicit);
+    }
+
+    let mut stored_track_ids = Vec::new();
     let mut imported_artists = 0;
+    let mut new_artists = 0;
     let mut processed_artists = std::collections::HashSet::new();
 
+    // Tracks already in the graph keep their similarity edges; only new ones get linked
+    let mut new_track_ids = Vec::new();
+
     info!("Starting database storage for {} tracks", tracks.len());
     let storage_start = std::time::Instant::now();
 
-    // Store tracks and artists in Neo4j
+    // Store tracks and artists in Neo4j, stopping between tracks if the import is cancelled
+    let mut cancelled = false;
     for (track_index, track) in tracks.iter().enumerate() {
+        if cancel.is_cancelled() {
+            info!("Import of {} cancelled after {} of {} tracks", source_id, track_index, tracks.len());
+            cancelled = true;
+            break;
+        }
         debug!("Processing track {}/{}: {}", track_index + 1, tracks.len(), track.name);
         
         // Store track
         let track_store_start = std::time::Instant::now();
-        match neo4j_db::store_track(&neo4j_client, track).await {
-            Ok(_) => {
-                imported_tracks += 1;
+        match neo4j_db::store_track(neo4j_client, track).await {
+            Ok(created) => {
+                stored_track_ids.push(track.id.clone());
+                if created {
+                    new_track_ids.push(track.id.clone());
+                }
                 debug!(
                     "Stored track '{}' in {:.3}s", 
                     track.name, 
                     track_store_start.elapsed().as_secs_f64()
                 );
+                // Only fails when nobody is subscribed, which is fine
+                let _ = track_events.send(TrackStoredEvent {
+                    playlist_id: source_id.to_string(),
+                    track: track.clone(),
+                });
             }
             Err(e) => {
                 error!("Failed to store track '{}': {}", track.name, e);
@@ -133,13 +814,13 @@ pub async fn import_spotify_data(
             }
         }
 
-        // Store artists (avoid duplicates)
-        for artist_id in &track.artist_ids {
+        // Store the track's and album's artists (avoid duplicates)
+        for artist_id in track.artist_ids.iter().chain(track.album_artists.iter().map(|(id, _)| id)) {
             if !processed_artists.contains(artist_id) {
                 debug!("Fetching artist details for ID: {}", artist_id);
                 let artist_fetch_start = std::time::Instant::now();
                 
-                match spotify_client.get_artist(artist_id, &access_token).await {
+                match spotify_client.get_artist(artist_id).await {
                     Ok(artist) => {
                         let fetch_duration = artist_fetch_start.elapsed();
                         debug!(
@@ -149,9 +830,12 @@ pub async fn import_spotify_data(
                         );
                         
                         let artist_store_start = std::time::Instant::now();
-                        match neo4j_db::store_artist(&neo4j_client, &artist).await {
-                            Ok(_) => {
+                        match neo4j_db::store_artist(neo4j_client, &artist).await {
+                            Ok(created) => {
                                 imported_artists += 1;
+                                if created {
+                                    new_artists += 1;
+                                }
                                 processed_artists.insert(artist_id.clone());
                                 debug!(
                                     "Stored artist '{}' in {:.3}s", 
@@ -180,181 +864,1210 @@ pub async fn import_spotify_data(
     }
 
     let storage_duration = storage_start.elapsed();
-    let total_duration = start_time.elapsed();
+    let imported_tracks = stored_track_ids.len();
+
+    // Edges are a derived index, so failing to write them doesn't fail the import
+    let similarity_edges = match neo4j_db::compute_similarity_edges_for(neo4j_client, &new_track_ids).await {
+        Ok(edges) => edges,
+        Err(e) => {
+            warn!("Failed to compute similarity edges for {} new tracks: {}", new_track_ids.len(), e);
+            0
+        }
+    };
+
+    metrics::histogram!("spotify_import_storage_duration_seconds").record(storage_duration.as_secs_f64());
+    metrics::counter!("spotify_imported_tracks_total").increment(imported_tracks as u64);
+    metrics::counter!("spotify_imported_artists_total").increment(imported_artists as u64);
     
     info!(
-        "Spotify import completed successfully in {:.2}s (storage: {:.2}s). Imported {} tracks and {} artists from playlist {}",
-        total_duration.as_secs_f64(),
+        "Stored tracks in {:.2}s. Imported {} tracks and {} artists from {}",
         storage_duration.as_secs_f64(),
         imported_tracks,
         imported_artists,
-        playlist_id
+        source_id
     );
 
-    Ok(Json(json!({
-        "message": "Spotify data imported successfully",
+    let summary = json!({
+        "message": if cancelled { "Spotify import cancelled" } else { "Spotify data imported successfully" },
+        "cancelled": cancelled,
         "imported_tracks": imported_tracks,
+        "new_tracks": new_track_ids.len(),
+        "updated_tracks": imported_tracks - new_track_ids.len(),
         "imported_artists": imported_artists,
-        "playlist_id": playlist_id,
-        "duration_seconds": total_duration.as_secs_f64()
-    })))
+        "new_artists": new_artists,
+        "updated_artists": imported_artists - new_artists,
+        "skipped_low_popularity": skipped_low_popularity,
+        "skipped_explicit": skipped_explicit,
+        "similarity_edges": similarity_edges,
+        "features_fetched": options.fetch_features
+    });
+    Ok((summary, StoredTrackIds { stored: stored_track_ids, created: new_track_ids }))
 }
 
-pub async fn get_artists(
-    State(neo4j_client): State<Neo4jClient>,
-) -> Result<Json<Vec<Artist>>, StatusCode> {
-    let artists = neo4j_db::get_all_artists(&neo4j_client)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+// Normalize an optional `updated_after` RFC 3339 timestamp, rejecting anything unparseable
+fn parse_updated_after(params: &HashMap<String, String>) -> Result<Option<String>, StatusCode> {
+    params
+        .get("updated_after")
+        .map(|value| {
+            chrono::DateTime::parse_from_rfc3339(value)
+                .map(|updated_after| updated_after.to_rfc3339())
+                .map_err(|_| StatusCode::BAD_REQUEST)
+        })
+        .transpose()
+}
 
-    Ok(Json(artists))
+// Decode an optional `cursor` parameter, rejecting one we didn't hand out
+fn parse_cursor(params: &HashMap<String, String>) -> Result<Option<PageCursor>, StatusCode> {
+    params
+        .get("cursor")
+        .map(|value| PageCursor::decode(value).ok_or(StatusCode::BAD_REQUEST))
+        .transpose()
 }
 
-pub async fn get_tracks(
-    State(neo4j_client): State<Neo4jClient>,
-) -> Result<Json<Vec<Track>>, StatusCode> {
-    let tracks = neo4j_db::get_all_tracks(&neo4j_client)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+// The cursor for the page after `items`, if it was a full page and so may not be the last
+fn next_cursor<T>(items: &[T], limit: i64, cursor: impl Fn(&T) -> PageCursor) -> Option<(&'static str, String)> {
+    if (items.len() as i64) < limit {
+        return None;
+    }
+    items.last().map(|last| (NEXT_CURSOR_HEADER, cursor(last).encode()))
+}
 
-    Ok(Json(tracks))
+/// Firehose of every track stored by any import, as JSON `TrackStoredEvent` text messages
+pub async fn track_events_ws(ws: WebSocketUpgrade, State(track_events): State<TrackEvents>) -> Response {
+    ws.on_upgrade(move |socket| stream_track_events(socket, track_events.subscribe()))
 }
 
-pub async fn get_recommendations(
+async fn stream_track_events(mut socket: WebSocket, mut events: broadcast::Receiver<TrackStoredEvent>) {
+    debug!("Track event subscriber connected");
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let text = match serde_json::to_string(&event) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            error!("Failed to serialize track event: {}", e);
+                            continue;
+                        }
+                    };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                // A subscriber too slow to keep up misses events rather than holding up imports
+                Err(RecvError::Lagged(skipped)) => warn!("Track event subscriber lagged, skipped {} events", skipped),
+                Err(RecvError::Closed) => break,
+            },
+            // Subscribers only listen, so anything they send other than a close is ignored
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    debug!("Track event subscriber disconnected");
+}
+
+pub async fn get_artists(
     State(neo4j_client): State<Neo4jClient>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<Vec<Track>>, StatusCode> {
-    let seed_tracks: Vec<String> = params
-        .get("seed_tracks")
-        .map(|s| s.split(',').map(|id| id.trim().to_string()).collect())
-        .unwrap_or_default();
-
-    if seed_tracks.is_empty() {
+) -> Result<Response, StatusCode> {
+    let cursor = parse_cursor(&params)?;
+    if cursor.is_some() && params.contains_key("offset") {
         return Err(StatusCode::BAD_REQUEST);
     }
-
+    let offset = params
+        .get("offset")
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or(0)
+        .max(0);
     let limit = params
         .get("limit")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(20);
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_ARTISTS_PAGE_SIZE)
+        .clamp(1, MAX_ARTISTS_PAGE_SIZE);
 
-    let recommendations = neo4j_db::get_similar_tracks(&neo4j_client, &seed_tracks, limit)
+    let updated_after = parse_updated_after(&params)?;
+    let summary = parse_summary_fields(&params)?;
+
+    let total = neo4j_db::count_artists(&neo4j_client, updated_after.as_deref())
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let total_header = (TOTAL_COUNT_HEADER, total.to_string());
 
-    Ok(Json(recommendations))
-}
-
-pub async fn create_youtube_playlist(
-    JsonBody(request): JsonBody<YouTubePlaylistRequest>,
-) -> Result<Json<CreatedPlaylist>, StatusCode> {
-    let youtube_client = YouTubeClient::new();
-    let youtube_api_key = std::env::var("YOUTUBE_API_KEY")
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if summary {
+        let artists = neo4j_db::get_artist_summaries(&neo4j_client, offset, limit, updated_after.as_deref(), cursor.as_ref())
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let next = next_cursor(&artists, limit, |artist| PageCursor::new(artist.popularity, &artist.id));
+        return Ok((AppendHeaders(std::iter::once(total_header).chain(next)), Json(artists)).into_response());
+    }
 
-    let playlist = youtube_client
-        .create_playlist_from_tracks(
-            &request.name,
-            request.description.as_deref(),
-            &request.track_names,
-            &request.access_token,
-            &youtube_api_key,
-        )
+    let artists = neo4j_db::get_all_artists(&neo4j_client, offset, limit, updated_after.as_deref(), cursor.as_ref())
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let next = next_cursor(&artists, limit, |artist| PageCursor::new(artist.popularity, &artist.id));
 
-    Ok(Json(playlist))
+    Ok((AppendHeaders(std::iter::once(total_header).chain(next)), Json(artists)).into_response())
 }
 
-// Additional handler for creating YouTube playlist from Neo4j recommendations
-pub async fn create_youtube_playlist_from_recommendations(
-    State(neo4j_client): State<Neo4jClient>,
-    JsonBody(request): JsonBody<Value>,
-) -> Result<Json<CreatedPlaylist>, StatusCode> {
-    // Extract parameters from request
-    let seed_tracks: Vec<String> = request["seed_tracks"]
-        .as_array()
-        .ok_or(StatusCode::BAD_REQUEST)?
-        .iter()
-        .filter_map(|v| v.as_str())
-        .map(|s| s.to_string())
-        .collect();
+// `fields=summary|full`, full when absent
+fn parse_summary_fields(params: &HashMap<String, String>) -> Result<bool, StatusCode> {
+    match params.get("fields").map(String::as_str) {
+        None | Some("full") => Ok(false),
+        Some("summary") => Ok(true),
+        Some(_) => Err(StatusCode::BAD_REQUEST),
+    }
+}
 
-    let playlist_name = request["playlist_name"]
-        .as_str()
-        .ok_or(StatusCode::BAD_REQUEST)?;
+// Whether the client asked for newline-delimited JSON over a plain array
+fn wants_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_type| media_type.split(';').next().unwrap_or("").trim() == NDJSON_CONTENT_TYPE)
+}
 
-    let youtube_access_token = request["youtube_access_token"]
-        .as_str()
-        .ok_or(StatusCode::BAD_REQUEST)?;
+pub async fn get_tracks(
+    State(neo4j_client): State<Neo4jClient>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    // A cursor or limit asks for one page; otherwise every track is returned
+    let cursor = parse_cursor(&params)?;
+    let sort = parse_track_sort(&params)?;
+    // Cursors are positions in popularity order
+    if cursor.is_some() && sort != TrackSort::Popularity {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let page_size = (cursor.is_some() || params.contains_key("limit")).then(|| {
+        params
+            .get("limit")
+            .and_then(|s| s.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_TRACKS_PAGE_SIZE)
+            .clamp(1, MAX_TRACKS_PAGE_SIZE)
+    });
 
-    let limit = request["limit"]
-        .as_i64()
-        .unwrap_or(20) as i32;
+    let filter = TrackFilter {
+        updated_after: parse_updated_after(&params)?,
+        has_preview: params
+            .get("has_preview")
+            .map(|value| value.parse::<bool>().map_err(|_| StatusCode::BAD_REQUEST))
+            .transpose()?,
+        after: cursor,
+        limit: page_size,
+        sort,
+    };
 
-    // Get recommendations from Neo4j
-    let recommendations = neo4j_db::get_similar_tracks(&neo4j_client, &seed_tracks, limit)
+    let total = neo4j_db::count_tracks(&neo4j_client, &filter)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let total_header = (TOTAL_COUNT_HEADER, total.to_string());
 
-    // Format track names for YouTube search
-    let track_names: Vec<String> = recommendations
-        .iter()
-        .map(|track| YouTubeClient::format_search_query(&track.name, &track.artist_names))
-        .collect();
+    if let Some(page_size) = page_size {
+        let tracks = neo4j_db::get_all_tracks(&neo4j_client, &filter)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let next = next_cursor(&tracks, page_size, |track| PageCursor::new(track.popularity, &track.id))
+            .filter(|_| sort == TrackSort::Popularity);
+        return Ok((AppendHeaders(std::iter::once(total_header).chain(next)), Json(tracks)).into_response());
+    }
 
-    // Create YouTube playlist
-    let youtube_client = YouTubeClient::new();
-    let youtube_api_key = std::env::var("YOUTUBE_API_KEY")
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if wants_ndjson(&headers) {
+        let tracks = neo4j_db::stream_all_tracks(&neo4j_client, &filter)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let lines = tracks.map(|track| {
+            let mut line = serde_json::to_vec(&track?)?;
+            line.push(b'\n');
+            Ok::<_, anyhow::Error>(line)
+        });
+        // An error mid-stream cuts the response short, so log it for the truncated client
+        let lines = lines.inspect_err(|e| error!("Failed to stream tracks: {}", e));
 
-    let playlist = youtube_client
-        .create_playlist_from_tracks(
-            playlist_name,
-            Some("Generated from Spotify recommendations via Neo4j"),
-            &track_names,
-            youtube_access_token,
-            &youtube_api_key,
+        return Ok((
+            [(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE), (header::VARY, "accept")],
+            AppendHeaders([total_header]),
+            Body::from_stream(lines),
         )
+            .into_response());
+    }
+
+    let tracks = neo4j_db::get_all_tracks(&neo4j_client, &filter)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok(Json(playlist))
+    Ok(([(header::VARY, "accept")], AppendHeaders([total_header]), Json(tracks)).into_response())
 }
 
-pub async fn get_similar_tracks_with_youtube(
-    State(neo4j_client): State<Neo4jClient>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<SimilarTracksResponse>, StatusCode> {
-    let track_id = params
-        .get("track_id")
-        .ok_or(StatusCode::BAD_REQUEST)?;
+// `sort=popularity|recent`, popularity when absent
+fn parse_track_sort(params: &HashMap<String, String>) -> Result<TrackSort, StatusCode> {
+    match params.get("sort").map(String::as_str) {
+        None | Some("popularity") => Ok(TrackSort::Popularity),
+        Some("recent") => Ok(TrackSort::Recent),
+        Some(_) => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+/// The frontend's shell with the track list already rendered in, for a fast first paint of `/tracks`
+pub async fn get_tracks_snapshot(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Html<String>, StatusCode> {
+    let shell = tokio::fs::read_to_string(format!("{}/index.html", prerender::FRONTEND_DIST))
+        .await
+        .map_err(|e| {
+            error!("Failed to read the frontend shell, is the frontend built? {}", e);
+            StatusCode::NOT_FOUND
+        })?;
+    let tracks = neo4j_db::get_all_tracks(&neo4j_client, &TrackFilter::default())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    prerender::tracks_page(&shell, &tracks)
+        .map(Html)
+        .map_err(|e| {
+            error!("Failed to render the tracks snapshot: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn get_artist_feature_vectors(
+    State(neo4j_client): State<Neo4jClient>,
+    Path(artist_id): Path<String>,
+) -> Result<Json<ArtistFeatureVectors>, StatusCode> {
+    let features = neo4j_db::get_artist_feature_vectors(&neo4j_client, &artist_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(features))
+}
+
+pub async fn get_genres(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    let genres = neo4j_db::get_all_genres(&neo4j_client)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(genres))
+}
+
+pub async fn get_track_detail(
+    State(neo4j_client): State<Neo4jClient>,
+    Path(track_id): Path<String>,
+) -> Result<Json<TrackDetail>, StatusCode> {
+    neo4j_db::get_track_detail(&neo4j_client, &track_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to load track detail for {}: {}", track_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// A track's audio analysis sections, fetched live from Spotify. The track doesn't need to be stored.
+pub async fn get_track_analysis(
+    State(spotify_client): State<Arc<SpotifyClient>>,
+    Path(track_id): Path<String>,
+) -> Result<Json<AudioAnalysis>, StatusCode> {
+    spotify_client
+        .get_audio_analysis(&track_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch audio analysis for {}: {}", track_id, e);
+            spotify_error_status(&e)
+        })?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Two tracks side by side with how their features differ, `b` relative to `a`
+pub async fn compare_tracks(
+    State(neo4j_client): State<Neo4jClient>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<TrackComparison>, StatusCode> {
+    let (Some(a_id), Some(b_id)) = (params.get("a"), params.get("b")) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+
+    let (a, b) = tokio::try_join!(
+        neo4j_db::get_track_by_id(&neo4j_client, a_id),
+        neo4j_db::get_track_by_id(&neo4j_client, b_id),
+    )
+    .map_err(|e| {
+        error!("Failed to load tracks {} and {} for comparison: {}", a_id, b_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let (Some(a), Some(b)) = (a, b) else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    Ok(Json(TrackComparison {
+        deltas: similarity::feature_deltas(&a, &b),
+        distance: similarity::feature_distance(&a, &b, &SimilarityWeights::from_env()),
+        a,
+        b,
+    }))
+}
+
+pub async fn get_genre_counts(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Json<Vec<GenreCount>>, StatusCode> {
+    let counts = neo4j_db::get_genre_counts(&neo4j_client)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(counts))
+}
+
+/// Min, max, mean, standard deviation and deciles of the main audio features across the library
+pub async fn get_feature_distributions(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Json<FeatureDistributions>, StatusCode> {
+    let distributions = neo4j_db::get_feature_distributions(&neo4j_client)
+        .await
+        .map_err(|e| {
+            error!("Failed to compute feature distributions: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(distributions))
+}
+
+pub async fn get_albums(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Json<Vec<Album>>, StatusCode> {
+    let albums = neo4j_db::get_all_albums(&neo4j_client)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(albums))
+}
+
+pub async fn get_incomplete_albums(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Json<Vec<Album>>, StatusCode> {
+    let albums = neo4j_db::get_incomplete_albums(&neo4j_client)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(albums))
+}
+
+pub async fn get_album_tracks(
+    State(neo4j_client): State<Neo4jClient>,
+    Path(album_id): Path<String>,
+) -> Result<Json<Vec<Track>>, StatusCode> {
+    let tracks = neo4j_db::get_album_tracks(&neo4j_client, &album_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(tracks))
+}
+
+/// A stored playlist as an extended M3U download
+pub async fn export_playlist_m3u(
+    State(neo4j_client): State<Neo4jClient>,
+    Path(playlist_id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let tracks = neo4j_db::get_playlist_tracks(&neo4j_client, &playlist_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to load tracks of playlist {}: {}", playlist_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if tracks.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let disposition = format!("attachment; filename=\"{}\"", m3u::file_name(&playlist_id));
+    Ok((
+        [(header::CONTENT_TYPE, m3u::CONTENT_TYPE.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        m3u::playlist(&tracks),
+    )
+        .into_response())
+}
+
+/// A random handful of tracks, for discovery
+pub async fn get_random_tracks(
+    State(neo4j_client): State<Neo4jClient>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Vec<Track>>, StatusCode> {
+    let count = params
+        .get("count")
+        .map(|s| s.parse::<i64>().map_err(|_| StatusCode::BAD_REQUEST))
+        .transpose()?
+        .unwrap_or(DEFAULT_RANDOM_TRACKS)
+        .clamp(1, MAX_RANDOM_TRACKS);
+
+    let tracks = neo4j_db::get_random_tracks(&neo4j_client, count)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(tracks))
+}
+
+pub async fn get_incomplete_tracks(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Json<Vec<Track>>, StatusCode> {
+    let tracks = neo4j_db::get_tracks_missing_features(&neo4j_client)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(tracks))
+}
+
+/// Tracks no artist is linked to, with the artists they should be linked to
+pub async fn get_orphan_tracks(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Json<Vec<Track>>, StatusCode> {
+    let tracks = neo4j_db::get_orphan_tracks(&neo4j_client)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(tracks))
+}
+
+pub async fn repair_orphan_tracks(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Json<Value>, StatusCode> {
+    let (repaired, unrepairable) = neo4j_db::repair_orphan_tracks(&neo4j_client)
+        .await
+        .map_err(|e| {
+            error!("Failed to repair orphan tracks: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    info!("Repaired {} orphan tracks, {} had no stored artists", repaired, unrepairable);
+
+    Ok(Json(json!({
+        "message": "Orphan tracks repaired",
+        "repaired_tracks": repaired,
+        "unrepairable_tracks": unrepairable
+    })))
+}
+
+#[instrument(skip(neo4j_client, spotify_client))]
+pub async fn refresh_track_features(
+    State(neo4j_client): State<Neo4jClient>,
+    State(spotify_client): State<Arc<SpotifyClient>>,
+) -> Result<Json<Value>, StatusCode> {
+    let tracks = neo4j_db::get_tracks_missing_features(&neo4j_client)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    info!("Refreshing audio features for {} tracks", tracks.len());
+
+    let mut refreshed = 0;
+    let mut still_missing = Vec::new();
+
+    for track in &tracks {
+        match spotify_client.get_track_features(&track.id).await {
+            Ok(Some(features)) => {
+                if let Err(e) = neo4j_db::update_track_features(&neo4j_client, &track.id, &features).await {
+                    error!("Failed to store audio features for '{}': {}", track.name, e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+                refreshed += 1;
+            }
+            Ok(None) => {
+                debug!("Spotify still has no audio features for '{}'", track.name);
+                still_missing.push(track.id.clone());
+            }
+            Err(e @ SpotifyError::Auth(_)) => {
+                error!("Spotify authentication failed while refreshing features: {}", e);
+                return Err(spotify_error_status(&e));
+            }
+            Err(e) => {
+                warn!("Failed to fetch audio features for '{}': {}", track.name, e);
+                still_missing.push(track.id.clone());
+            }
+        }
+    }
+
+    info!("Refreshed audio features for {} of {} tracks", refreshed, tracks.len());
+
+    Ok(Json(json!({
+        "message": "Audio features refreshed",
+        "refreshed_tracks": refreshed,
+        "still_missing": still_missing
+    })))
+}
+
+/// Re-derive track details from the Spotify JSON stored with `STORE_RAW_JSON`, without calling Spotify
+pub async fn reprocess_tracks(
+    State(neo4j_client): State<Neo4jClient>,
+) -> Result<Json<Value>, StatusCode> {
+    let raw_tracks = neo4j_db::get_raw_track_json(&neo4j_client)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut reprocessed = 0;
+    let mut failed = Vec::new();
+
+    for (track_id, raw_json) in raw_tracks {
+        let parsed = serde_json::from_str::<Value>(&raw_json)
+            .map_err(|e| e.to_string())
+            .and_then(|value| {
+                let track_data = value.as_object().ok_or("not a JSON object")?;
+                SpotifyClient::track_from_json(track_data, None).map_err(|e| e.to_string())
+            });
+
+        let track = match parsed {
+            Ok(track) => track,
+            Err(e) => {
+                warn!("Failed to parse stored JSON for track {}: {}", track_id, e);
+                failed.push(track_id);
+                continue;
+            }
+        };
+
+        match neo4j_db::update_track_details(&neo4j_client, &track).await {
+            Ok(()) => reprocessed += 1,
+            Err(e) => {
+                warn!("Failed to update track {} from stored JSON: {}", track_id, e);
+                failed.push(track_id);
+            }
+        }
+    }
+
+    info!("Reprocessed {} tracks from stored JSON, {} failed", reprocessed, failed.len());
+
+    Ok(Json(json!({
+        "message": "Tracks reprocessed from stored JSON",
+        "reprocessed_tracks": reprocessed,
+        "failed": failed
+    })))
+}
+
+/// Manually override some of a track's audio features, e.g. when Spotify's analysis is wrong or missing
+pub async fn override_track_features(
+    State(neo4j_client): State<Neo4jClient>,
+    Path(track_id): Path<String>,
+    JsonBody(patch): JsonBody<AudioFeaturesPatch>,
+) -> Result<Json<Track>, ApiError> {
+    if patch.provided().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "No audio features provided"));
+    }
+    patch.validate().map_err(|message| ApiError::new(StatusCode::BAD_REQUEST, message))?;
+
+    let updated = neo4j_db::update_track_features_partial(&neo4j_client, &track_id, &patch)
+        .await
+        .map_err(|e| {
+            error!("Failed to override audio features for {}: {}", track_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if !updated {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    info!("Manually overrode audio features for track {}", track_id);
+
+    neo4j_db::get_track_by_id(&neo4j_client, &track_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to load track {} after overriding features: {}", track_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(Json)
+        .ok_or_else(|| StatusCode::NOT_FOUND.into())
+}
+
+/// Re-fetch artists from Spotify and update their popularity, followers, genres and image
+#[instrument(skip(neo4j_client, spotify_client, request))]
+pub async fn refresh_artists(
+    State(neo4j_client): State<Neo4jClient>,
+    State(spotify_client): State<Arc<SpotifyClient>>,
+    JsonBody(request): JsonBody<RefreshArtistsRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let artist_ids = match request.artist_ids {
+        Some(ids) if ids.is_empty() => return Err(StatusCode::BAD_REQUEST),
+        Some(ids) => ids,
+        None => neo4j_db::get_all_artist_ids(&neo4j_client)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+    };
+
+    info!("Refreshing metadata for {} artists", artist_ids.len());
+
+    let ids: Vec<&str> = artist_ids.iter().map(String::as_str).collect();
+    let artists = spotify_client.get_artists(&ids).await.map_err(|e| {
+        error!("Failed to fetch artists for refresh: {}", e);
+        spotify_error_status(&e)
+    })?;
+
+    let mut refreshed = std::collections::HashSet::new();
+    for artist in &artists {
+        match neo4j_db::update_artist(&neo4j_client, artist).await {
+            Ok(true) => {
+                refreshed.insert(artist.id.as_str());
+            }
+            Ok(false) => debug!("Artist {} isn't in the graph, skipping", artist.id),
+            Err(e) => {
+                error!("Failed to update artist '{}': {}", artist.name, e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    // Unknown to Spotify or to the graph
+    let not_found: Vec<&String> = artist_ids.iter().filter(|id| !refreshed.contains(id.as_str())).collect();
+
+    info!("Refreshed {} of {} artists", refreshed.len(), artist_ids.len());
+
+    Ok(Json(json!({
+        "message": "Artists refreshed",
+        "refreshed_artists": refreshed.len(),
+        "not_found": not_found
+    })))
+}
+
+pub async fn merge_artists(
+    State(neo4j_client): State<Neo4jClient>,
+    JsonBody(request): JsonBody<MergeArtistsRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    if request.keep_id == request.merge_id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let moved_tracks = neo4j_db::merge_artists(&neo4j_client, &request.keep_id, &request.merge_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to merge artist {} into {}: {}", request.merge_id, request.keep_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({
+        "message": "Artists merged",
+        "artist_id": request.keep_id,
+        "merged_artist_id": request.merge_id,
+        "moved_tracks": moved_tracks
+    })))
+}
+
+/// Playlist names fitting the mood of the seed track
+pub async fn suggest_playlist_names(
+    State(neo4j_client): State<Neo4jClient>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<NameSuggestions>, StatusCode> {
+    let track_id = params.get("track_id").ok_or(StatusCode::BAD_REQUEST)?;
+
+    let track = neo4j_db::get_track_by_id(&neo4j_client, track_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mood = recommendations::classify_mood(&track);
+    Ok(Json(NameSuggestions {
+        track_id: track.id,
+        mood,
+        names: recommendations::playlist_names(mood).map(str::to_string).to_vec(),
+    }))
+}
+
+/// `get_recommendations` for a single seed track, by audio features
+// `limit` capped at a page of tracks; negative or unparseable limits are rejected rather than
+// reaching Cypher's LIMIT
+fn parse_recommendation_limit(params: &HashMap<String, String>) -> Result<i32, StatusCode> {
+    let limit = params
+        .get("limit")
+        .map(|s| s.parse::<i64>().ok().filter(|limit| *limit >= 0).ok_or(StatusCode::BAD_REQUEST))
+        .transpose()?
+        .unwrap_or(DEFAULT_TRACK_RECOMMENDATIONS)
+        .clamp(1, MAX_TRACKS_PAGE_SIZE);
+    Ok(limit as i32)
+}
+
+pub async fn get_track_recommendations(
+    State(neo4j_client): State<Neo4jClient>,
+    Path(track_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<([(&'static str, String); 2], Json<Vec<Track>>), StatusCode> {
+    let limit = parse_recommendation_limit(&params)?;
+
+    neo4j_db::get_track_by_id(&neo4j_client, &track_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let seed_ids = std::slice::from_ref(&track_id);
+    let available = neo4j_db::count_recommendation_candidates(
+        &neo4j_client,
+        recommendations::RecommendationMode::Features,
+        seed_ids,
+        &CandidateFilter::default(),
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let tracks = neo4j_db::get_similar_tracks(&neo4j_client, seed_ids, limit, &CandidateFilter::default())
+        .await
+        .map_err(|e| {
+            error!("Failed to load recommendations for {}: {}", track_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok((availability_headers(limit, available), Json(tracks)))
+}
+
+pub async fn get_recommendations(
+    State(neo4j_client): State<Neo4jClient>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<([(&'static str, String); 4], Json<Vec<Track>>), StatusCode> {
+    let seed_tracks: Vec<String> = params
+        .get("seed_tracks")
+        .map(|s| s.split(',').map(|id| id.trim().to_string()).collect())
+        .unwrap_or_default();
+    let seed_artist = params
+        .get("seed_artist")
+        .map(|id| id.trim())
+        .filter(|id| !id.is_empty());
+
+    // Exactly one kind of seed
+    if seed_tracks.is_empty() == seed_artist.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
     let limit = params
         .get("limit")
         .and_then(|s| s.parse().ok())
-        .unwrap_or(10);
+        .unwrap_or(20);
+
+    // Comma-separated; empty means no genre filter
+    let genres: Vec<String> = params
+        .get("genres")
+        .map(|s| {
+            s.split(',')
+                .map(|genre| genre.trim().to_string())
+                .filter(|genre| !genre.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let diversity = params
+        .get("diversity")
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|d| d.is_finite())
+        .unwrap_or(0.0)
+        .clamp(0.0, 1.0);
+
+    let mode = match params.get("mode") {
+        Some(mode) => recommendations::RecommendationMode::from_param(mode).ok_or(StatusCode::BAD_REQUEST)?,
+        None => recommendations::RecommendationMode::default(),
+    };
+
+    // Unset means no threshold; genre mode ranks by shared genres, so there's no distance to compare
+    let max_distance = match params.get("max_distance") {
+        Some(d) => Some(d.parse::<f64>().ok().filter(|d| d.is_finite() && *d >= 0.0).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    if max_distance.is_some() && mode == recommendations::RecommendationMode::Genre {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let filter = CandidateFilter {
+        genres,
+        exclude_explicit: parse_exclude_explicit(&params)?,
+        max_distance,
+    };
 
-    // Get the original track
-    let original_track = neo4j_db::get_track_by_id(&neo4j_client, track_id)
+    if let Some(artist_id) = seed_artist {
+        // An artist seed is a point in feature space, so genre-based modes don't apply
+        if mode != recommendations::RecommendationMode::Features {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        return recommend_for_artist(&neo4j_client, artist_id, limit, &filter, diversity).await;
+    }
+
+    let counts = count_candidates(&filter, |filter| {
+        let (neo4j_client, seed_tracks) = (&neo4j_client, &seed_tracks);
+        async move { neo4j_db::count_recommendation_candidates(neo4j_client, mode, seed_tracks, &filter).await }
+    })
+    .await?;
+
+    if diversity == 0.0 {
+        let recommendations = neo4j_db::get_recommended_tracks(&neo4j_client, mode, &seed_tracks, limit, &filter)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return Ok((recommendation_headers(limit, counts, recommendations.len()), Json(recommendations)));
+    }
+
+    // Over-fetch the nearest candidates and pick a spread-out subset of them
+    let candidates = neo4j_db::get_recommended_tracks(
+        &neo4j_client,
+        mode,
+        &seed_tracks,
+        limit.saturating_mul(DIVERSITY_CANDIDATE_FACTOR),
+        &filter,
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let seeds = neo4j_db::get_tracks_by_ids(&neo4j_client, &seed_tracks)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let picked = recommendations::select_diverse(&seeds, candidates, usize::try_from(limit).unwrap_or(0), diversity);
+    Ok((recommendation_headers(limit, counts, picked.len()), Json(picked)))
+}
+
+// Tracks nearest the artist's average features, optionally spread out like track-seeded recommendations
+async fn recommend_for_artist(
+    neo4j_client: &Neo4jClient,
+    artist_id: &str,
+    limit: i32,
+    filter: &CandidateFilter,
+    diversity: f64,
+) -> Result<([(&'static str, String); 4], Json<Vec<Track>>), StatusCode> {
+    let vectors = neo4j_db::get_artist_feature_vectors(neo4j_client, artist_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .ok_or(StatusCode::NOT_FOUND)?;
 
+    // None of the artist's tracks have features, so there's nothing to measure against
+    let Some(target) = recommendations::FeatureTarget::centroid(&vectors) else {
+        return Ok((recommendation_headers(limit, CandidateCounts::default(), 0), Json(Vec::new())));
+    };
+
+    let counts = count_candidates(filter, |filter| async move {
+        neo4j_db::count_target_candidates(neo4j_client, artist_id, &filter).await
+    })
+    .await?;
+
+    let fetch_limit = if diversity == 0.0 { limit } else { limit.saturating_mul(DIVERSITY_CANDIDATE_FACTOR) };
+    let candidates = neo4j_db::get_tracks_near_target(neo4j_client, &target, artist_id, fetch_limit, filter)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if diversity == 0.0 {
+        return Ok((recommendation_headers(limit, counts, candidates.len()), Json(candidates)));
+    }
+
+    // The artist's own tracks stand in for seed tracks when spreading the picks out
+    let seeds = neo4j_db::get_tracks_by_ids(neo4j_client, &vectors.track_ids)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let picked = recommendations::select_diverse(&seeds, candidates, usize::try_from(limit).unwrap_or(0), diversity);
+    Ok((recommendation_headers(limit, counts, picked.len()), Json(picked)))
+}
+
+/// Spotify's cap on seeds per `/recommendations` request
+const MAX_SPOTIFY_SEEDS: usize = 5;
+const MAX_SPOTIFY_RECOMMENDATIONS: i32 = 100;
+
+/// Header carrying a caller's own Spotify token. `Authorization` is left for the API key.
+const SPOTIFY_TOKEN_HEADER: &str = "x-spotify-token";
+
+// The caller's Spotify token, if the request has a non-empty one
+fn request_spotify_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(SPOTIFY_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Spotify's own recommendations for up to five seed tracks, which needn't be in the library.
+/// Uses the caller's `X-Spotify-Token` when given, otherwise the app's token.
+pub async fn get_spotify_recommendations(
+    State(spotify_client): State<Arc<SpotifyClient>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Vec<Track>>, StatusCode> {
+    let seed_tracks: Vec<String> = params
+        .get("seed_tracks")
+        .map(|s| s.split(',').map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect())
+        .unwrap_or_default();
+    if !(1..=MAX_SPOTIFY_SEEDS).contains(&seed_tracks.len()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let limit = match params.get("limit") {
+        Some(limit) => limit.parse::<i32>().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => 20,
+    };
+    if !(1..=MAX_SPOTIFY_RECOMMENDATIONS).contains(&limit) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Targets are unset unless given, and must be within 0.0..=1.0 when they are
+    let target = |name: &str| match params.get(name) {
+        Some(value) => value
+            .parse::<f64>()
+            .ok()
+            .filter(|value| (0.0..=1.0).contains(value))
+            .map(Some)
+            .ok_or(StatusCode::BAD_REQUEST),
+        None => Ok(None),
+    };
+    let (valence, energy, danceability) = (target("target_valence")?, target("target_energy")?, target("target_danceability")?);
+
+    let user_token = request_spotify_token(&headers);
+    spotify_client
+        .get_recommendations(user_token.as_deref(), &seed_tracks, valence, energy, danceability, limit)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Failed to fetch Spotify recommendations: {}", e);
+            spotify_error_status(&e)
+        })
+}
+
+/// Group the library into `k` mood clusters by k-means over its audio features
+pub async fn cluster_tracks(
+    State(neo4j_client): State<Neo4jClient>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ClusterResponse>, StatusCode> {
+    let k = match params.get("k") {
+        Some(k) => k.parse::<usize>().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => DEFAULT_CLUSTERS,
+    };
+    if !(1..=MAX_CLUSTERS).contains(&k) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let tracks = neo4j_db::get_all_tracks(&neo4j_client, &TrackFilter::default())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (track_ids, points): (Vec<String>, Vec<clustering::FeatureVector>) = tracks
+        .iter()
+        .filter_map(|track| clustering::feature_vector(track).map(|point| (track.id.clone(), point)))
+        .unzip();
+    let skipped_tracks = tracks.len() - track_ids.len();
+
+    // Pure CPU work over the whole library, so keep it off the async workers
+    let clusters = tokio::task::spawn_blocking(move || clustering::kmeans(&points, k))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut sizes = vec![0; clusters.centroids.len()];
+    for &cluster in &clusters.assignments {
+        sizes[cluster] += 1;
+    }
+    let centroids = clusters
+        .centroids
+        .iter()
+        .zip(sizes)
+        .enumerate()
+        .map(|(cluster, (&[valence, energy, danceability, acousticness, tempo], size))| ClusterCentroid {
+            cluster,
+            size,
+            valence,
+            energy,
+            danceability,
+            acousticness,
+            tempo: tempo * similarity::DEFAULT_TEMPO_SCALE,
+        })
+        .collect();
+    let assignments = track_ids
+        .into_iter()
+        .zip(clusters.assignments)
+        .map(|(track_id, cluster)| TrackCluster { track_id, cluster })
+        .collect();
+
+    Ok(Json(ClusterResponse {
+        k: clusters.centroids.len(),
+        centroids,
+        assignments,
+        skipped_tracks,
+    }))
+}
+
+pub async fn create_youtube_playlist(
+    State(youtube_client): State<Arc<YouTubeClient>>,
+    State(playlists): State<Arc<PlaylistStore>>,
+    headers: HeaderMap,
+    JsonBody(request): JsonBody<YouTubePlaylistRequest>,
+) -> Result<Json<CreatedPlaylist>, ApiError> {
+    let youtube_api_key = youtube_api_key(&youtube_client)?.to_string();
+    let key = idempotency_key(&headers)?.map(|key| format!("playlist:{}", key));
+    let options = playlist_options(
+        &youtube_client,
+        request.min_confidence,
+        request.batch_size,
+        request.batch_delay_ms,
+    )?;
+
+    let work = async move {
+        youtube_client
+            .create_playlist_from_tracks(
+                &request.name,
+                request.description.as_deref(),
+                &request.track_names,
+                &request.access_token,
+                &youtube_api_key,
+                options,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to create YouTube playlist: {}", e);
+                youtube_error_status(&e).into()
+            })
+    };
+
+    Ok(Json(create_playlist_once(&playlists, key, work).await?))
+}
+
+/// Search YouTube for each track without creating a playlist, so the matches can be approved first
+pub async fn preview_youtube_playlist(
+    State(youtube_client): State<Arc<YouTubeClient>>,
+    JsonBody(request): JsonBody<PlaylistPreviewRequest>,
+) -> Result<Json<Vec<TrackMatch>>, ApiError> {
+    let youtube_api_key = youtube_api_key(&youtube_client)?;
+    if request.track_names.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "track_names must not be empty"));
+    }
+
+    Ok(Json(youtube_client.resolve_matches(&request.track_names, youtube_api_key).await))
+}
+
+/// Create a playlist from videos approved in a preview, without searching again
+pub async fn create_youtube_playlist_from_videos(
+    State(youtube_client): State<Arc<YouTubeClient>>,
+    State(playlists): State<Arc<PlaylistStore>>,
+    headers: HeaderMap,
+    JsonBody(request): JsonBody<ApprovedPlaylistRequest>,
+) -> Result<Json<CreatedPlaylist>, ApiError> {
+    if request.videos.is_empty() == request.video_ids.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Send a non-empty list of either videos or video_ids"));
+    }
+    let key = idempotency_key(&headers)?.map(|key| format!("from-videos:{}", key));
+
+    let work = async move {
+        let (name, description) = (&request.name, request.description.as_deref());
+        let created = if request.video_ids.is_empty() {
+            youtube_client.create_playlist_from_videos(name, description, &request.videos, &request.access_token).await
+        } else {
+            youtube_client.create_playlist_from_video_ids(name, description, &request.video_ids, &request.access_token).await
+        };
+        created.map_err(|e| {
+            error!("Failed to create YouTube playlist: {}", e);
+            youtube_error_status(&e).into()
+        })
+    };
+
+    Ok(Json(create_playlist_once(&playlists, key, work).await?))
+}
+
+// Additional handler for creating YouTube playlist from Neo4j recommendations
+pub async fn create_youtube_playlist_from_recommendations(
+    State(neo4j_client): State<Neo4jClient>,
+    State(youtube_client): State<Arc<YouTubeClient>>,
+    State(playlists): State<Arc<PlaylistStore>>,
+    headers: HeaderMap,
+    JsonBody(request): JsonBody<RecommendationPlaylistRequest>,
+) -> Result<Json<CreatedPlaylist>, ApiError> {
+    // Check config before spending a query on recommendations
+    let youtube_api_key = youtube_api_key(&youtube_client)?.to_string();
+    let key = idempotency_key(&headers)?.map(|key| format!("from-recommendations:{}", key));
+
+    let options = playlist_options(
+        &youtube_client,
+        request.min_confidence,
+        request.batch_size,
+        request.batch_delay_ms,
+    )?;
+
+    let limit = request.limit.unwrap_or(20);
+    let filter = CandidateFilter {
+        exclude_explicit: request.exclude_explicit,
+        ..CandidateFilter::default()
+    };
+    let RecommendationPlaylistRequest { seed_tracks, playlist_name, youtube_access_token, .. } = request;
+
+    // A replayed request returns its original playlist without querying again
+    let work = async move {
+        let recommendations = neo4j_db::get_similar_tracks(&neo4j_client, &seed_tracks, limit, &filter)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        // Format track names for YouTube search
+        let track_names: Vec<String> = recommendations
+            .iter()
+            .map(|track| YouTubeClient::format_search_query(&track.name, &track.artist_names))
+            .collect();
+
+        youtube_client
+            .create_playlist_from_tracks(
+                &playlist_name,
+                Some("Generated from Spotify recommendations via Neo4j"),
+                &track_names,
+                &youtube_access_token,
+                &youtube_api_key,
+                options,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to create YouTube playlist: {}", e);
+                youtube_error_status(&e).into()
+            })
+    };
+
+    Ok(Json(create_playlist_once(&playlists, key, work).await?))
+}
+
+pub async fn get_similar_tracks_with_youtube(
+    State(neo4j_client): State<Neo4jClient>,
+    State(youtube_client): State<Arc<YouTubeClient>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<([(&'static str, String); 3], Json<SimilarTracksResponse>), ApiError> {
+    let track_id = params
+        .get("track_id")
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let limit = params
+        .get("limit")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(10);
+
+    // Get the original track, falling back to the closest name only when asked to
+    let mut original_track = neo4j_db::get_track_by_id(&neo4j_client, track_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if original_track.is_none() {
+        if let Some(name) = params.get("fallback_name") {
+            original_track = neo4j_db::find_track_by_name(&neo4j_client, name)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        }
+    }
+    let original_track = original_track
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Track {} not found", track_id)))?;
+
     // Get similar tracks
-    let similar_tracks = neo4j_db::get_similar_tracks(&neo4j_client, &[track_id.clone()], limit)
+    let seed_ids = std::slice::from_ref(&original_track.id);
+    let filter = CandidateFilter {
+        exclude_explicit: parse_exclude_explicit(&params)?,
+        ..CandidateFilter::default()
+    };
+    let similar_tracks = neo4j_db::get_similar_tracks_explained(&neo4j_client, seed_ids, limit, &filter)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let counts = count_candidates(&filter, |filter| {
+        let neo4j_client = &neo4j_client;
+        async move {
+            neo4j_db::count_recommendation_candidates(
+                neo4j_client,
+                recommendations::RecommendationMode::Features,
+                seed_ids,
+                &filter,
+            )
+            .await
+        }
+    })
+    .await?;
 
-    // Get YouTube API key from environment
-    let youtube_api_key = std::env::var("YOUTUBE_API_KEY").ok();
-    let youtube_client = YouTubeClient::new();
+    // Without an API key, similar tracks are returned without videos
+    let youtube_api_key = youtube_client.api_key();
 
     let mut tracks_with_youtube = Vec::new();
 
     // Search for each similar track on YouTube
-    for track in similar_tracks {
-        let youtube_video = if let Some(ref api_key) = youtube_api_key {
+    for (track, explanation) in similar_tracks {
+        let youtube_video = if let Some(api_key) = youtube_api_key {
             let search_query = YouTubeClient::format_search_query(&track.name, &track.artist_names);
             youtube_client.search_video(&search_query, api_key)
                 .await
@@ -367,11 +2080,275 @@ pub async fn get_similar_tracks_with_youtube(
         tracks_with_youtube.push(TrackWithYouTube {
             track,
             youtube_video,
+            explanation,
         });
     }
 
-    Ok(Json(SimilarTracksResponse {
-        original_track,
-        similar_tracks: tracks_with_youtube,
-    }))
+    let [available_count, limit_exceeded] = availability_headers(limit, counts.available);
+    Ok((
+        [available_count, limit_exceeded, explicit_filtered_header(counts)],
+        Json(SimilarTracksResponse {
+            original_track,
+            similar_tracks: tracks_with_youtube,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A client whose pool never connects, for handlers that should reject a request before querying
+    async fn unreachable_neo4j() -> Neo4jClient {
+        let config = neo4rs::ConfigBuilder::default()
+            .uri("127.0.0.1:1")
+            .user("neo4j")
+            .password("neo4j")
+            .build()
+            .unwrap();
+        Arc::new(neo4rs::Graph::connect(config).await.unwrap())
+    }
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, value.parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_api_error_has_json_body() {
+        let response = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "YOUTUBE_API_KEY not configured").into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, r#"{"error":"YOUTUBE_API_KEY not configured"}"#);
+
+        let response = ApiError::from(StatusCode::BAD_REQUEST).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, r#"{"error":"Bad Request"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_import_stays_cancellable_after_its_client_leaves() {
+        let imports = ImportRegistry::new();
+        let import_jobs = ImportJobs::new();
+        let options = ImportOptions {
+            min_popularity: None,
+            fetch_features: false,
+            exclude_explicit: false,
+            range: PlaylistRange::default(),
+        };
+        let key = ("playlist".to_string(), options);
+        let job = Arc::new(import_jobs.start("job-1".to_string(), key.clone()).unwrap());
+        let token = job.token.clone();
+        let work = async move {
+            token.cancelled().await;
+            Ok(json!({ "cancelled": true }))
+        };
+        let request = tokio::spawn({
+            let imports = Arc::clone(&imports);
+            let key = key.clone();
+            async move { imports.run(key, holding_job(job, work)).await }
+        });
+        tokio::task::yield_now().await;
+
+        // The client disconnecting drops its handler, not the import it started
+        request.abort();
+        assert!(request.await.unwrap_err().is_cancelled());
+        assert!(import_jobs.cancel("job-1"));
+
+        // Once the run has stopped the job is gone
+        let joiner = imports.run(key, async { Err(StatusCode::INTERNAL_SERVER_ERROR) }).await;
+        assert_eq!(joiner, Some(Ok(json!({ "cancelled": true }))));
+        tokio::task::yield_now().await;
+        assert!(!import_jobs.cancel("job-1"));
+    }
+
+    #[test]
+    fn test_import_record_takes_counts_from_summary() {
+        let summary = json!({
+            "cancelled": true,
+            "imported_tracks": 12,
+            "new_tracks": 5,
+            "imported_artists": 4,
+            "new_artists": 1,
+            "duration_seconds": 2.5
+        });
+        let record = import_record("37i9dQZF1DXcBWIGoYBM5M", Some("https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M".to_string()), &summary);
+        assert_eq!(record.source_id, "37i9dQZF1DXcBWIGoYBM5M");
+        assert_eq!((record.imported_tracks, record.new_tracks), (12, 5));
+        assert_eq!((record.imported_artists, record.new_artists), (4, 1));
+        assert_eq!(record.duration_seconds, 2.5);
+        assert!(record.cancelled);
+        assert!(chrono::DateTime::parse_from_rfc3339(&record.imported_at).is_ok());
+
+        let record = import_record(LIKED_SONGS_ID, None, &json!({}));
+        assert_eq!((record.imported_tracks, record.source_url), (0, None));
+        assert!(!record.cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_playlist_not_found_explains_private_playlists() {
+        let response = playlist_error(StatusCode::NOT_FOUND).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, json!({ "error": PLAYLIST_UNAVAILABLE }).to_string());
+
+        let response = playlist_error(StatusCode::UNAUTHORIZED).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, r#"{"error":"Unauthorized"}"#);
+    }
+
+    #[test]
+    fn test_recommendation_headers_count_filtered_tracks() {
+        let header = |headers: [(&'static str, String); 4], name: &str| {
+            headers.into_iter().find(|(key, _)| *key == name).unwrap().1
+        };
+        let available = |available| CandidateCounts { available, explicit_filtered: 0 };
+
+        // No threshold, or nothing beyond it
+        assert_eq!(header(recommendation_headers(20, available(100), 20), FILTERED_COUNT_HEADER), "0");
+        // Only 12 of the nearest 20 were close enough
+        assert_eq!(header(recommendation_headers(20, available(100), 12), FILTERED_COUNT_HEADER), "8");
+        // A small library caps what could have been returned at all
+        assert_eq!(header(recommendation_headers(20, available(5), 3), FILTERED_COUNT_HEADER), "2");
+        assert_eq!(header(recommendation_headers(20, available(5), 3), LIMIT_EXCEEDED_HEADER), "true");
+
+        let counts = CandidateCounts { available: 5, explicit_filtered: 7 };
+        assert_eq!(header(recommendation_headers(20, counts, 5), EXPLICIT_FILTERED_HEADER), "7");
+        assert_eq!(header(recommendation_headers(20, counts, 5), FILTERED_COUNT_HEADER), "0");
+    }
+
+    async fn json_body<T: DeserializeOwned>(content_type: Option<&str>, body: &str) -> Result<T, ApiError> {
+        let mut request = Request::builder().method("POST").uri("/");
+        if let Some(content_type) = content_type {
+            request = request.header(header::CONTENT_TYPE, content_type);
+        }
+        let request = request.body(Body::from(body.to_string())).unwrap();
+        JsonBody::<T>::from_request(request, &()).await.map(|JsonBody(value)| value)
+    }
+
+    #[tokio::test]
+    async fn test_json_body_rejections_name_the_problem() {
+        let json = Some("application/json");
+        let request: RecommendationPlaylistRequest = json_body(
+            json,
+            r#"{"seed_tracks": ["t1"], "playlist_name": "Mix", "youtube_access_token": "token"}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(request.seed_tracks, vec!["t1"]);
+        assert_eq!(request.limit, None);
+        assert!(!request.exclude_explicit);
+
+        let ApiError(status, message) =
+            json_body::<RecommendationPlaylistRequest>(json, r#"{"seed_tracks": ["t1"], "youtube_access_token": "token"}"#)
+                .await
+                .unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(message.contains("missing field `playlist_name`"), "{}", message);
+
+        let ApiError(status, message) = json_body::<RecommendationPlaylistRequest>(
+            json,
+            r#"{"seed_tracks": "t1", "playlist_name": "Mix", "youtube_access_token": "token"}"#,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(message.contains("seed_tracks"), "{}", message);
+
+        let request: ApprovedPlaylistRequest =
+            json_body(json, r#"{"name": "Mix", "access_token": "token", "video_ids": ["v1", "v2"]}"#).await.unwrap();
+        assert!(request.videos.is_empty());
+        assert_eq!(request.video_ids, vec!["v1", "v2"]);
+
+        let ApiError(status, _) = json_body::<RecommendationPlaylistRequest>(json, "{").await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let ApiError(status, _) = json_body::<RecommendationPlaylistRequest>(None, "{}").await.unwrap_err();
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn test_request_spotify_token_reads_its_own_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(request_spotify_token(&headers), None);
+
+        headers.insert(header::AUTHORIZATION, "Bearer api-key".parse().unwrap());
+        assert_eq!(request_spotify_token(&headers), None);
+
+        headers.insert(SPOTIFY_TOKEN_HEADER, " ".parse().unwrap());
+        assert_eq!(request_spotify_token(&headers), None);
+
+        headers.insert(SPOTIFY_TOKEN_HEADER, " user-token ".parse().unwrap());
+        assert_eq!(request_spotify_token(&headers).as_deref(), Some("user-token"));
+    }
+
+    #[test]
+    fn test_wants_ndjson() {
+        assert!(wants_ndjson(&accept("application/x-ndjson")));
+        assert!(wants_ndjson(&accept("application/json;q=0.5, application/x-ndjson")));
+        assert!(!wants_ndjson(&accept("application/json")));
+        assert!(!wants_ndjson(&accept("*/*")));
+        assert!(!wants_ndjson(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_availability_headers_flag_short_results() {
+        assert_eq!(
+            availability_headers(50, 7),
+            [(AVAILABLE_COUNT_HEADER, "7".to_string()), (LIMIT_EXCEEDED_HEADER, "true".to_string())]
+        );
+        assert_eq!(
+            availability_headers(20, 20),
+            [(AVAILABLE_COUNT_HEADER, "20".to_string()), (LIMIT_EXCEEDED_HEADER, "false".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_import_range_rejects_negative_bounds() {
+        assert_eq!(import_range(None, None), Ok(PlaylistRange::default()));
+        assert_eq!(import_range(Some(100), Some(50)), Ok(PlaylistRange { offset: 100, limit: Some(50) }));
+        assert_eq!(import_range(None, Some(0)), Ok(PlaylistRange { offset: 0, limit: Some(0) }));
+        assert_eq!(import_range(Some(-1), None), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(import_range(None, Some(-5)), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_parse_summary_fields() {
+        let params = |fields: &str| HashMap::from([("fields".to_string(), fields.to_string())]);
+        assert_eq!(parse_summary_fields(&HashMap::new()), Ok(false));
+        assert_eq!(parse_summary_fields(&params("full")), Ok(false));
+        assert_eq!(parse_summary_fields(&params("summary")), Ok(true));
+        assert_eq!(parse_summary_fields(&params("id,name")), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_parse_track_sort() {
+        let params = |sort: &str| HashMap::from([("sort".to_string(), sort.to_string())]);
+        assert_eq!(parse_track_sort(&HashMap::new()), Ok(TrackSort::Popularity));
+        assert_eq!(parse_track_sort(&params("popularity")), Ok(TrackSort::Popularity));
+        assert_eq!(parse_track_sort(&params("recent")), Ok(TrackSort::Recent));
+        assert_eq!(parse_track_sort(&params("newest")), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_track_recommendations_reject_invalid_limits() {
+        let neo4j = unreachable_neo4j().await;
+
+        for limit in ["-1", "ten"] {
+            let params = HashMap::from([("limit".to_string(), limit.to_string())]);
+            let result = get_track_recommendations(State(neo4j.clone()), Path("t1".to_string()), Query(params)).await;
+            assert_eq!(result.err(), Some(StatusCode::BAD_REQUEST), "limit={}", limit);
+        }
+    }
+
+    #[test]
+    fn test_recommendation_limit_is_capped() {
+        let limit = |value: &str| parse_recommendation_limit(&HashMap::from([("limit".to_string(), value.to_string())]));
+
+        assert_eq!(parse_recommendation_limit(&HashMap::new()), Ok(20));
+        assert_eq!(limit("1000000"), Ok(MAX_TRACKS_PAGE_SIZE as i32));
+        assert_eq!(limit("0"), Ok(1));
+        assert_eq!(limit("-1"), Err(StatusCode::BAD_REQUEST));
+    }
 }
@@ -0,0 +1,130 @@
+use crate::inflight::InFlight;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Successful results by idempotency key, kept for a TTL so a retried request gets the original
+/// result back. Concurrent requests with the same key share one run. Failures aren't kept, so a
+/// retry after an error tries again.
+pub struct IdempotencyStore<T, E> {
+    completed: Arc<Completed<T>>,
+    in_flight: Arc<InFlight<String, Result<T, E>>>,
+}
+
+impl<T, E> IdempotencyStore<T, E>
+where
+    T: Clone + Send + Sync + 'static,
+    E: Clone + Send + Sync + 'static,
+{
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            completed: Arc::new(Completed { ttl, results: Mutex::new(HashMap::new()) }),
+            in_flight: InFlight::new(),
+        }
+    }
+
+    /// The stored result for `key` if there is one, otherwise the result of `work`.
+    /// `None` means the work panicked.
+    pub async fn run<F>(&self, key: String, work: F) -> Option<Result<T, E>>
+    where
+        F: Future<Output = Result<T, E>> + Send + 'static,
+    {
+        if let Some(stored) = self.completed.get(&key) {
+            tracing::info!("Replaying stored result for idempotency key {}", key);
+            return Some(Ok(stored));
+        }
+
+        // The result is stored by the spawned run itself, before it stops being in flight, so it's
+        // kept even if the caller has gone away and a retry always finds one or the other
+        let completed = Arc::clone(&self.completed);
+        let stored_key = key.clone();
+        let work = async move {
+            // A run that finished since the check above has stored its result by now
+            if let Some(stored) = completed.get(&stored_key) {
+                return Ok(stored);
+            }
+            let result = work.await;
+            if let Ok(value) = &result {
+                completed.insert(stored_key, value.clone());
+            }
+            result
+        };
+
+        self.in_flight.run(key, work).await
+    }
+}
+
+struct Completed<T> {
+    ttl: Duration,
+    results: Mutex<HashMap<String, (Instant, T)>>,
+}
+
+impl<T: Clone> Completed<T> {
+    fn get(&self, key: &str) -> Option<T> {
+        let results = self.results.lock().unwrap();
+        results
+            .get(key)
+            .filter(|(stored_at, _)| stored_at.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn insert(&self, key: String, value: T) {
+        let mut results = self.results.lock().unwrap();
+        // Expired entries are dropped here rather than on a timer
+        results.retain(|_, (stored_at, _)| stored_at.elapsed() < self.ttl);
+        results.insert(key, (Instant::now(), value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_replays_successes_but_not_failures() {
+        let store = IdempotencyStore::<usize, String>::new(Duration::from_secs(60));
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let succeed = |runs: Arc<AtomicUsize>| async move { Ok(runs.fetch_add(1, Ordering::SeqCst) + 1) };
+        let fail = |runs: Arc<AtomicUsize>| async move {
+            runs.fetch_add(1, Ordering::SeqCst);
+            Err("quota exceeded".to_string())
+        };
+
+        assert_eq!(store.run("a".into(), succeed(runs.clone())).await, Some(Ok(1)));
+        assert_eq!(store.run("a".into(), succeed(runs.clone())).await, Some(Ok(1)));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        assert_eq!(store.run("b".into(), fail(runs.clone())).await, Some(Err("quota exceeded".to_string())));
+        assert_eq!(store.run("b".into(), succeed(runs.clone())).await, Some(Ok(3)));
+    }
+
+    #[tokio::test]
+    async fn test_expired_results_run_again() {
+        let store = IdempotencyStore::<usize, String>::new(Duration::ZERO);
+
+        assert_eq!(store.run("a".into(), async { Ok(1) }).await, Some(Ok(1)));
+        assert_eq!(store.run("a".into(), async { Ok(2) }).await, Some(Ok(2)));
+    }
+
+    #[tokio::test]
+    async fn test_result_is_kept_when_the_caller_goes_away() {
+        let store = IdempotencyStore::<usize, String>::new(Duration::from_secs(60));
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let work = |runs: Arc<AtomicUsize>| async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(runs.fetch_add(1, Ordering::SeqCst) + 1)
+        };
+
+        // The client disconnects before the playlist is created
+        let abandoned = tokio::time::timeout(Duration::from_millis(1), store.run("a".into(), work(runs.clone()))).await;
+        assert!(abandoned.is_err());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(store.run("a".into(), work(runs.clone())).await, Some(Ok(1)));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,94 @@
+use futures::future::{BoxFuture, FutureExt, Shared};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// Coalesces concurrent calls for the same key into one run whose result every caller shares
+pub struct InFlight<K, V> {
+    pending: Mutex<HashMap<K, Shared<BoxFuture<'static, Option<V>>>>>,
+}
+
+impl<K, V> InFlight<K, V>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self { pending: Mutex::new(HashMap::new()) })
+    }
+
+    /// Run `work` for `key`, or wait for the run already in flight for it.
+    /// The work is spawned so it finishes even if every caller goes away; `None` means it panicked.
+    pub async fn run<F>(self: &Arc<Self>, key: K, work: F) -> Option<V>
+    where
+        F: Future<Output = V> + Send + 'static,
+    {
+        let shared = {
+            let mut pending = self.pending.lock().unwrap();
+            match pending.get(&key) {
+                Some(existing) => {
+                    tracing::debug!("Joining a run already in flight");
+                    existing.clone()
+                }
+                None => {
+                    let guard = Deregister { registry: Arc::clone(self), key: key.clone() };
+                    let task = tokio::spawn(async move {
+                        // Deregistered when the work ends, panicking or not, so later calls start fresh
+                        let _guard = guard;
+                        work.await
+                    });
+                    let shared = task.map(Result::ok).boxed().shared();
+                    pending.insert(key, shared.clone());
+                    shared
+                }
+            }
+        };
+
+        shared.await
+    }
+}
+
+struct Deregister<K: Eq + Hash, V> {
+    registry: Arc<InFlight<K, V>>,
+    key: K,
+}
+
+impl<K: Eq + Hash, V> Drop for Deregister<K, V> {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.registry.pending.lock() {
+            pending.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_runs_share_one_result() {
+        let inflight = InFlight::<&str, usize>::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let work = |runs: Arc<AtomicUsize>| async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            runs.fetch_add(1, Ordering::SeqCst) + 1
+        };
+
+        let (first, second) = tokio::join!(
+            inflight.run("playlist", work(runs.clone())),
+            inflight.run("playlist", work(runs.clone())),
+        );
+
+        assert_eq!(first, Some(1));
+        assert_eq!(second, Some(1));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // Once finished the key is free again
+        assert_eq!(inflight.run("playlist", work(runs.clone())).await, Some(2));
+        assert!(inflight.pending.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::models::Track;
+use std::fmt::Write;
+
+/// Media type of an extended M3U playlist, always written as UTF-8
+pub const CONTENT_TYPE: &str = "audio/x-mpegurl; charset=utf-8";
+
+/// An extended M3U playlist of `tracks` in order, each with an `#EXTINF` duration and title.
+/// Matched YouTube videos aren't stored, so entries point at the track's Spotify preview, or at
+/// its Spotify page when it has none.
+pub fn playlist(tracks: &[Track]) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+    for track in tracks {
+        let _ = writeln!(m3u, "#EXTINF:{},{}", track.duration_ms.max(0) / 1000, title(track));
+        let _ = writeln!(m3u, "{}", location(track));
+    }
+    m3u
+}
+
+/// A file name for the download, keeping only characters that are safe in a header value
+pub fn file_name(playlist_id: &str) -> String {
+    let stem: String = playlist_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("{stem}.m3u8")
+}
+
+// `Artist, Artist - Name` on a single line, since a line break would end the entry
+fn title(track: &Track) -> String {
+    let title = if track.artist_names.is_empty() {
+        track.name.clone()
+    } else {
+        format!("{} - {}", track.artist_names.join(", "), track.name)
+    };
+    title.replace(['\r', '\n'], " ")
+}
+
+fn location(track: &Track) -> String {
+    match &track.preview_url {
+        Some(url) => url.clone(),
+        None => format!("https://open.spotify.com/track/{}", urlencoding::encode(&track.id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: &str, name: &str, preview_url: Option<&str>) -> Track {
+        Track {
+            id: id.to_string(),
+            name: name.to_string(),
+            artist_ids: vec!["a".to_string()],
+            artist_names: vec!["Slowdive".to_string()],
+            album_id: "al".to_string(),
+            album_name: "Souvlaki".to_string(),
+            album_image_url: None,
+            duration_ms: 232_600,
+            popularity: 61,
+            explicit: false,
+            danceability: None,
+            energy: None,
+            key: None,
+            loudness: None,
+            mode: None,
+            speechiness: None,
+            acousticness: None,
+            instrumentalness: None,
+            liveness: None,
+            valence: None,
+            tempo: None,
+            time_signature: None,
+            preview_url: preview_url.map(str::to_string),
+            updated_at: None,
+            created_at: None,
+            raw_json: None,
+            album_total_tracks: None,
+            album_artists: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_writes_extended_entries_in_order() {
+        let tracks = [
+            track("t1", "Alison", Some("https://p.scdn.co/mp3-preview/abc")),
+            track("t2", "Machine Gun", None),
+        ];
+
+        assert_eq!(
+            playlist(&tracks),
+            "#EXTM3U\n\
+             #EXTINF:232,Slowdive - Alison\n\
+             https://p.scdn.co/mp3-preview/abc\n\
+             #EXTINF:232,Slowdive - Machine Gun\n\
+             https://open.spotify.com/track/t2\n"
+        );
+        assert_eq!(playlist(&[]), "#EXTM3U\n");
+    }
+
+    #[test]
+    fn test_titles_stay_on_one_line() {
+        let mut untitled = track("t1", "Line\r\nBreak", None);
+        untitled.artist_names.clear();
+
+        assert!(playlist(&[untitled]).contains("#EXTINF:232,Line  Break\n"));
+    }
+
+    #[test]
+    fn test_file_name_is_header_safe() {
+        assert_eq!(file_name("liked-songs"), "liked-songs.m3u8");
+        assert_eq!(file_name("a\"b;c"), "a_b_c.m3u8");
+    }
+}
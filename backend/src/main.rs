@@ -4,45 +4,125 @@ mod youtube;
 mod models;
 mod handlers;
 mod rate_limiter;
+mod auth;
+mod state;
+mod pagination;
+mod prerender;
+mod m3u;
+mod recommendations;
+mod similarity;
+mod clustering;
+mod telemetry;
+mod inflight;
+mod idempotency;
+mod cancellation;
+mod static_files;
 
 use axum::{
-    routing::{get, post},
+    handler::HandlerWithoutStateExt,
+    middleware,
+    routing::{delete, get, post},
     Router,
 };
-use tower_http::{cors::CorsLayer, services::ServeDir};
+use tower_http::{
+    compression::{
+        predicate::{DefaultPredicate, NotForContentType, Predicate},
+        CompressionLayer,
+    },
+    cors::CorsLayer,
+    services::ServeDir,
+};
+use tower::ServiceBuilder;
 use std::net::SocketAddr;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "spotify_neo4j_backend=info,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
+    // Loaded first so `.env` can set RUST_LOG and LOG_FORMAT too
     dotenv::dotenv().ok();
+
+    telemetry::init_tracing(telemetry::LogFormat::from_env());
+
+    let metrics_handle = telemetry::install_recorder()?;
     
     // Initialize Neo4j connection
     let neo4j_client = neo4j_db::init_neo4j().await?;
+    let app_state = state::AppState::new(neo4j_client)?;
+    app_state.warn_on_missing_config();
+
+    let slow_request_threshold = telemetry::slow_request_threshold_from_env();
+
+    let api_key = auth::api_key_from_env();
+    if api_key.is_some() {
+        tracing::info!("API_KEY set, mutating endpoints require a bearer token");
+    }
     
     // Create routes
     let app = Router::new()
         // API routes
         .route("/api/health", get(handlers::health_check))
+        .route("/api/ws", get(handlers::track_events_ws))
+        .route("/livez", get(handlers::livez))
+        .route("/readyz", get(handlers::readyz))
         .route("/api/spotify/import", post(handlers::import_spotify_data))
+        .route("/api/spotify/import/library", post(handlers::import_library))
+        .route("/api/spotify/import/:job_id", delete(handlers::cancel_import))
+        .route("/api/spotify/imports", get(handlers::get_imports))
+        .route("/api/spotify/imports/:id/undo", post(handlers::undo_import))
+        .route("/api/spotify/playlist/preview", get(handlers::preview_playlist))
         .route("/api/spotify/artists", get(handlers::get_artists))
+        .route("/api/spotify/artists/refresh", post(handlers::refresh_artists))
+        .route("/api/spotify/artists/:id/tracks/features.json", get(handlers::get_artist_feature_vectors))
         .route("/api/spotify/tracks", get(handlers::get_tracks))
+        .route("/api/spotify/genres", get(handlers::get_genres))
+        .route("/api/spotify/genres/counts", get(handlers::get_genre_counts))
+        .route("/api/stats/features", get(handlers::get_feature_distributions))
+        .route("/api/spotify/albums", get(handlers::get_albums))
+        .route("/api/spotify/albums/incomplete", get(handlers::get_incomplete_albums))
+        .route("/api/spotify/albums/:id/tracks", get(handlers::get_album_tracks))
+        .route("/api/spotify/tracks/incomplete", get(handlers::get_incomplete_tracks))
+        .route("/api/spotify/tracks/random", get(handlers::get_random_tracks))
+        .route("/api/spotify/tracks/orphans", get(handlers::get_orphan_tracks))
+        .route("/api/spotify/tracks/orphans/repair", post(handlers::repair_orphan_tracks))
+        .route("/api/spotify/tracks/refresh-features", post(handlers::refresh_track_features))
+        .route("/api/spotify/tracks/compare", get(handlers::compare_tracks))
+        .route("/api/spotify/tracks/:id", get(handlers::get_track_detail))
+        .route("/api/spotify/tracks/:id/features", post(handlers::override_track_features))
+        .route("/api/spotify/tracks/:id/analysis", get(handlers::get_track_analysis))
+        .route("/api/spotify/tracks/:id/recommendations", get(handlers::get_track_recommendations))
+        .route("/api/spotify/reprocess", post(handlers::reprocess_tracks))
+        .route("/api/playlists/:id/export.m3u", get(handlers::export_playlist_m3u))
+        .route("/api/admin/artists/merge", post(handlers::merge_artists))
         .route("/api/youtube/playlist", post(handlers::create_youtube_playlist))
+        .route("/api/youtube/playlist/preview", post(handlers::preview_youtube_playlist))
+        .route("/api/youtube/playlist/from-videos", post(handlers::create_youtube_playlist_from_videos))
         .route("/api/youtube/playlist/from-recommendations", post(handlers::create_youtube_playlist_from_recommendations))
         .route("/api/recommendations", get(handlers::get_recommendations))
+        .route("/api/recommendations/spotify", get(handlers::get_spotify_recommendations))
+        .route("/api/recommendations/name-suggestion", get(handlers::suggest_playlist_names))
+        .route("/api/tracks/cluster", post(handlers::cluster_tracks))
         .route("/api/similar-tracks", get(handlers::get_similar_tracks_with_youtube))
-        .with_state(neo4j_client)
-        // Serve static files from frontend/dist
-        .nest_service("/", ServeDir::new("frontend/dist"))
+        .route("/tracks.html", get(handlers::get_tracks_snapshot))
+        .route("/metrics", get(move || std::future::ready(metrics_handle.render())))
+        .route_layer(middleware::from_fn(telemetry::track_requests))
+        .route_layer(middleware::from_fn_with_state(slow_request_threshold, telemetry::warn_slow_requests))
+        .with_state(app_state)
+        .layer(middleware::from_fn_with_state(api_key, auth::require_api_key))
+        // Serve static files from frontend/dist. Client routes like `/artists` that aren't files get
+        // `index.html` so deep links load the app
+        .nest_service(
+            "/",
+            ServiceBuilder::new()
+                .layer(middleware::from_fn(static_files::cache_control))
+                .service(
+                    ServeDir::new(prerender::FRONTEND_DIST)
+                        .fallback(static_files::spa_fallback.into_service()),
+                ),
+        )
+        // gzip/brotli when the client accepts it. Streams are left alone so each line is flushed as it's
+        // written; the default predicate already skips `text/event-stream`, gRPC and images
+        .layer(CompressionLayer::new().compress_when(
+            DefaultPredicate::new().and(NotForContentType::const_new(handlers::NDJSON_CONTENT_TYPE)),
+        ))
         .layer(CorsLayer::permissive());
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
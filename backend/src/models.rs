@@ -7,7 +7,35 @@ pub struct Artist {
     pub genres: Vec<String>,
     pub popularity: i32,
     pub followers: i32,
+    /// The largest of `image_urls`
     pub image_url: Option<String>,
+    /// Every size Spotify has of the artist's image, largest first
+    #[serde(default)]
+    pub image_urls: Vec<ArtistImage>,
+    /// RFC 3339 time the artist was last stored
+    pub updated_at: Option<String>,
+    /// RFC 3339 time the artist was first stored, `None` for artists stored before it was recorded
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// Just what a list of artists needs, for `GET /api/spotify/artists?fields=summary`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArtistSummary {
+    pub id: String,
+    pub name: String,
+    pub popularity: i32,
+    pub image_url: Option<String>,
+    #[serde(default)]
+    pub image_urls: Vec<ArtistImage>,
+}
+
+/// One resolution of an artist's image. Spotify doesn't always give the dimensions.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArtistImage {
+    pub url: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,9 +46,43 @@ pub struct Track {
     pub artist_names: Vec<String>,
     pub album_id: String,
     pub album_name: String,
+    pub album_image_url: Option<String>,
     pub duration_ms: i32,
     pub popularity: i32,
     pub explicit: bool,
+    // Audio features are `None` when Spotify had none for the track
+    pub danceability: Option<f64>,
+    pub energy: Option<f64>,
+    pub key: Option<i32>,
+    pub loudness: Option<f64>,
+    pub mode: Option<i32>,
+    pub speechiness: Option<f64>,
+    pub acousticness: Option<f64>,
+    pub instrumentalness: Option<f64>,
+    pub liveness: Option<f64>,
+    pub valence: Option<f64>,
+    pub tempo: Option<f64>,
+    pub time_signature: Option<i32>,
+    pub preview_url: Option<String>,
+    /// RFC 3339 time the track was last stored
+    pub updated_at: Option<String>,
+    /// RFC 3339 time the track was first stored, `None` for tracks stored before it was recorded
+    #[serde(default)]
+    pub created_at: Option<String>,
+    /// Spotify's original track object, kept only when `STORE_RAW_JSON` is enabled
+    #[serde(skip)]
+    pub raw_json: Option<String>,
+    /// Spotify's track count for the album, carried from an import onto the album node
+    #[serde(skip)]
+    pub album_total_tracks: Option<i32>,
+    /// The album's own `(id, name)` artists, such as "Various Artists" on a compilation, carried
+    /// from an import onto `ALBUM_ARTIST` relationships
+    #[serde(skip)]
+    pub album_artists: Vec<(String, String)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AudioFeatures {
     pub danceability: f64,
     pub energy: f64,
     pub key: i32,
@@ -33,7 +95,110 @@ pub struct Track {
     pub valence: f64,
     pub tempo: f64,
     pub time_signature: i32,
-    pub preview_url: Option<String>,
+}
+
+/// A stretch of a track with its own tempo and key, from Spotify's audio analysis.
+/// `key` is -1 when no key was detected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisSection {
+    /// Seconds from the start of the track
+    pub start: f64,
+    pub duration: f64,
+    pub tempo: f64,
+    pub key: i32,
+}
+
+/// The parts of Spotify's audio analysis a visualizer needs. It's fetched live rather than
+/// stored, since the full analysis is large.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AudioAnalysis {
+    pub track_id: String,
+    /// How sure Spotify is of the track's overall tempo, 0-1
+    pub tempo_confidence: f64,
+    pub sections: Vec<AnalysisSection>,
+}
+
+/// Hand-set audio features for a track; only the fields present are changed
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AudioFeaturesPatch {
+    pub danceability: Option<f64>,
+    pub energy: Option<f64>,
+    pub key: Option<i32>,
+    pub loudness: Option<f64>,
+    pub mode: Option<i32>,
+    pub speechiness: Option<f64>,
+    pub acousticness: Option<f64>,
+    pub instrumentalness: Option<f64>,
+    pub liveness: Option<f64>,
+    pub valence: Option<f64>,
+    pub tempo: Option<f64>,
+    pub time_signature: Option<i32>,
+}
+
+impl AudioFeaturesPatch {
+    /// Every provided feature as a `(property, value)` pair; integer features are widened to `f64`
+    pub fn provided(&self) -> Vec<(&'static str, f64)> {
+        [
+            ("danceability", self.danceability),
+            ("energy", self.energy),
+            ("key", self.key.map(f64::from)),
+            ("loudness", self.loudness),
+            ("mode", self.mode.map(f64::from)),
+            ("speechiness", self.speechiness),
+            ("acousticness", self.acousticness),
+            ("instrumentalness", self.instrumentalness),
+            ("liveness", self.liveness),
+            ("valence", self.valence),
+            ("tempo", self.tempo),
+            ("time_signature", self.time_signature.map(f64::from)),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+
+    /// Reject values outside the ranges Spotify reports, naming the first offending feature
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in self.provided() {
+            let (min, max) = feature_range(name);
+            if !(min..=max).contains(&value) {
+                return Err(format!("{} must be between {} and {}", name, min, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The range Spotify reports audio feature `name` in, with tempo bounded at a plausible 300 BPM.
+/// Features not named here are the 0-1 ones.
+pub fn feature_range(name: &str) -> (f64, f64) {
+    match name {
+        "key" => (-1.0, 11.0),
+        "loudness" => (-60.0, 0.0),
+        "mode" => (0.0, 1.0),
+        "tempo" => (0.0, 300.0),
+        "time_signature" => (3.0, 7.0),
+        _ => (0.0, 1.0),
+    }
+}
+
+/// Raw audio feature values across an artist's tracks, for charting.
+/// Arrays are index-aligned with `track_ids`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArtistFeatureVectors {
+    pub artist_id: String,
+    pub track_ids: Vec<String>,
+    pub track_names: Vec<String>,
+    pub danceability: Vec<f64>,
+    pub energy: Vec<f64>,
+    pub loudness: Vec<f64>,
+    pub speechiness: Vec<f64>,
+    pub acousticness: Vec<f64>,
+    pub instrumentalness: Vec<f64>,
+    pub liveness: Vec<f64>,
+    pub valence: Vec<f64>,
+    pub tempo: Vec<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,14 +206,189 @@ pub struct Album {
     pub id: String,
     pub name: String,
     pub artist_ids: Vec<String>,
-    pub release_date: String,
-    pub total_tracks: i32,
+    pub artist_names: Vec<String>,
+    pub release_date: Option<String>,
+    pub total_tracks: Option<i32>,
     pub image_url: Option<String>,
+    /// Number of the album's tracks stored in the graph
+    pub track_count: i32,
+}
+
+/// A track with its immediate graph neighbourhood, for a detail view
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackDetail {
+    pub track: Track,
+    pub artists: Vec<Artist>,
+    pub album: Option<Album>,
+    /// Nearest tracks by precomputed `SIMILAR` edge, closest first
+    pub similar: Vec<Track>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenreCount {
+    pub genre: String,
+    /// Artists tagged with the genre
+    pub artist_count: i64,
+    /// Distinct tracks performed by those artists
+    pub track_count: i64,
+}
+
+/// How one audio feature is spread across the stored tracks. Everything but `count` is `None`,
+/// and `deciles` is empty, when no track has the feature.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FeatureDistribution {
+    /// Tracks that have the feature
+    pub count: i64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    /// Population standard deviation
+    pub stddev: Option<f64>,
+    /// The 10th, 20th, ..., 90th percentiles
+    pub deciles: Vec<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeatureDistributions {
+    pub tempo: FeatureDistribution,
+    pub energy: FeatureDistribution,
+    pub valence: FeatureDistribution,
+    pub danceability: FeatureDistribution,
+    pub loudness: FeatureDistribution,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SpotifyImportRequest {
     pub playlist_url: String,
+    /// Skip tracks less popular than this (0-100)
+    #[serde(default)]
+    pub min_popularity: Option<i32>,
+    /// Look up audio features for each track (default true). Turning it off makes imports much
+    /// faster; the features can be backfilled later with `tracks/refresh-features`
+    #[serde(default)]
+    pub fetch_features: Option<bool>,
+    /// Report how many tracks and artists would be added without writing anything
+    #[serde(default)]
+    pub dry_run: Option<bool>,
+    /// Skip tracks Spotify marks as explicit
+    #[serde(default)]
+    pub exclude_explicit: Option<bool>,
+    /// Playlist position to start importing from (default 0)
+    #[serde(default)]
+    pub offset: Option<i64>,
+    /// Import at most this many playlist entries from `offset` (default all of them)
+    #[serde(default)]
+    pub limit: Option<i64>,
+    /// Id to cancel the import by with `DELETE /api/spotify/import/:job_id`, generated if absent
+    #[serde(default)]
+    pub job_id: Option<String>,
+}
+
+/// Import the saved tracks of whoever `access_token` belongs to. Filters, range and `job_id`
+/// work as for `SpotifyImportRequest`, with positions counted from the most recently saved track.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LibraryImportRequest {
+    /// User access token with the `user-library-read` scope
+    pub access_token: String,
+    #[serde(default)]
+    pub min_popularity: Option<i32>,
+    #[serde(default)]
+    pub fetch_features: Option<bool>,
+    #[serde(default)]
+    pub exclude_explicit: Option<bool>,
+    #[serde(default)]
+    pub offset: Option<i64>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+    /// Also link the saved tracks from a "Liked Songs" playlist node
+    #[serde(default)]
+    pub create_playlist: Option<bool>,
+    #[serde(default)]
+    pub job_id: Option<String>,
+}
+
+/// One import in the history, as recorded when it finished
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportRecord {
+    pub id: String,
+    /// Playlist id, or `liked-songs` for a library import
+    pub source_id: String,
+    /// The playlist URL the import was requested with; absent for library imports
+    pub source_url: Option<String>,
+    /// RFC 3339 timestamp
+    pub imported_at: String,
+    pub imported_tracks: i64,
+    pub new_tracks: i64,
+    pub imported_artists: i64,
+    pub new_artists: i64,
+    pub duration_seconds: f64,
+    pub cancelled: bool,
+}
+
+/// Ids of what undoing an import removed from the graph
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UndoneImport {
+    pub import_id: String,
+    pub removed_tracks: Vec<String>,
+    pub removed_albums: Vec<String>,
+    pub removed_artists: Vec<String>,
+}
+
+/// Which positions of a playlist to fetch: `limit` entries from `offset`, or everything after it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PlaylistRange {
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// Which of a playlist's tracks an import stores, and how
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImportOptions {
+    pub min_popularity: Option<i32>,
+    pub fetch_features: bool,
+    pub exclude_explicit: bool,
+    pub range: PlaylistRange,
+}
+
+/// Pushed to `/api/ws` subscribers each time an import stores a track
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackStoredEvent {
+    pub playlist_id: String,
+    pub track: Track,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshArtistsRequest {
+    /// Artists to refresh; every stored artist when omitted
+    #[serde(default)]
+    pub artist_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeArtistsRequest {
+    /// Artist that survives the merge
+    pub keep_id: String,
+    /// Duplicate artist folded into `keep_id` and then deleted
+    pub merge_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecommendationPlaylistRequest {
+    /// Tracks the playlist's recommendations are seeded from
+    pub seed_tracks: Vec<String>,
+    pub playlist_name: String,
+    pub youtube_access_token: String,
+    /// How many recommendations to look up, 20 when omitted
+    #[serde(default)]
+    pub limit: Option<i32>,
+    #[serde(default)]
+    pub exclude_explicit: bool,
+    #[serde(default)]
+    pub min_confidence: Option<f64>,
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    #[serde(default)]
+    pub batch_delay_ms: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -57,38 +397,166 @@ pub struct YouTubePlaylistRequest {
     pub description: Option<String>,
     pub track_names: Vec<String>,
     pub access_token: String,
+    /// Only add videos whose match confidence (0-1) is at least this
+    #[serde(default)]
+    pub min_confidence: Option<f64>,
+    /// Override `YOUTUBE_BATCH_SIZE` for this playlist
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    /// Override `YOUTUBE_BATCH_DELAY_MS` for this playlist
+    #[serde(default)]
+    pub batch_delay_ms: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct RecommendationRequest {
-    pub seed_track_ids: Vec<String>,
-    pub target_valence: Option<f64>,
-    pub target_energy: Option<f64>,
-    pub target_danceability: Option<f64>,
-    pub limit: Option<i32>,
+pub struct PlaylistPreviewRequest {
+    pub track_names: Vec<String>,
+}
+
+/// A track's YouTube search result from a playlist preview, before anything is created
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrackMatch {
+    pub track_name: String,
+    /// `None` when nothing usable was found or the search failed
+    pub video: Option<YouTubeVideo>,
+    /// Why the search failed, including a quota that ran out before the track was searched
+    pub error: Option<String>,
+}
+
+/// A video picked from a preview to go into the playlist
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApprovedVideo {
+    pub track_name: String,
+    pub video_id: String,
+    #[serde(default)]
+    pub video_title: String,
+    #[serde(default)]
+    pub confidence: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApprovedPlaylistRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub access_token: String,
+    /// Added in this order, without searching again
+    #[serde(default)]
+    pub videos: Vec<ApprovedVideo>,
+    /// Bare video ids, for callers without preview matches; send either these or `videos`
+    #[serde(default)]
+    pub video_ids: Vec<String>,
 }
 
+/// A search match as this API returns it, built from Google's camelCase response in `youtube.rs`
 #[derive(Debug, Serialize, Deserialize)]
 pub struct YouTubeVideo {
     pub id: String,
     pub title: String,
     pub channel_title: String,
     pub duration: String,
+    pub thumbnail_url: String,
+    /// 0-1 estimate of how well the video matches the search query
+    pub confidence: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CreatedPlaylist {
     pub id: String,
     pub name: String,
     pub url: String,
     pub tracks_added: i32,
     pub tracks_not_found: Vec<String>,
+    /// Tracks not added because the YouTube quota ran out
+    pub tracks_skipped: Vec<String>,
+    pub quota_exceeded: bool,
+    /// Every video the search found, with its confidence, whether or not it was added
+    pub matches: Vec<PlaylistMatch>,
+    /// Tracks whose search or insert failed with an error, and why
+    pub failures: Vec<PlaylistFailure>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlaylistFailure {
+    pub track_name: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlaylistMatch {
+    pub track_name: String,
+    pub video_id: String,
+    pub video_title: String,
+    pub confidence: f64,
+    pub added: bool,
+}
+
+/// How much each feature adds to a similar track's distance from the seeds,
+/// averaged over the seeds. Tempo is in BPM / 200 like the distance itself.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SimilarityBreakdown {
+    pub valence: f64,
+    pub energy: f64,
+    pub danceability: f64,
+    pub tempo: f64,
+    /// Sum of the above, lower is more similar
+    pub distance: f64,
+}
+
+/// Signed per-feature differences, `b` minus `a`. A feature is `None` when either track lacks it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FeatureDeltas {
+    pub danceability: Option<f64>,
+    pub energy: Option<f64>,
+    pub loudness: Option<f64>,
+    pub speechiness: Option<f64>,
+    pub acousticness: Option<f64>,
+    pub instrumentalness: Option<f64>,
+    pub liveness: Option<f64>,
+    pub valence: Option<f64>,
+    pub tempo: Option<f64>,
+}
+
+/// Rough feel of a track from its valence, energy and danceability
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mood {
+    /// Happy, energetic and danceable
+    Party,
+    /// Happy and energetic
+    FeelGood,
+    /// Happy and calm
+    Chill,
+    /// Dark and energetic
+    Intense,
+    /// Dark and calm
+    Melancholy,
+    /// Missing the features to tell
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NameSuggestions {
+    pub track_id: String,
+    pub mood: Mood,
+    /// Best fit first
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrackComparison {
+    pub a: Track,
+    pub b: Track,
+    pub deltas: FeatureDeltas,
+    /// Recommendation distance between the two, lower is more similar.
+    /// `None` when either track is missing a feature it ranks by.
+    pub distance: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TrackWithYouTube {
     pub track: Track,
     pub youtube_video: Option<YouTubeVideo>,
+    pub explanation: SimilarityBreakdown,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -96,3 +564,31 @@ pub struct SimilarTracksResponse {
     pub original_track: Track,
     pub similar_tracks: Vec<TrackWithYouTube>,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrackCluster {
+    pub track_id: String,
+    pub cluster: usize,
+}
+
+/// Mean feature values of a cluster's tracks
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClusterCentroid {
+    pub cluster: usize,
+    pub size: usize,
+    pub valence: f64,
+    pub energy: f64,
+    pub danceability: f64,
+    pub acousticness: f64,
+    /// In BPM
+    pub tempo: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClusterResponse {
+    pub k: usize,
+    pub centroids: Vec<ClusterCentroid>,
+    pub assignments: Vec<TrackCluster>,
+    /// Tracks left out because they're missing audio features
+    pub skipped_tracks: usize,
+}
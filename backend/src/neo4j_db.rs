@@ -1,18 +1,53 @@
-use crate::models::{Artist, Track};
-use neo4rs::{Graph, Query};
+use crate::models::{
+    feature_range, Album, Artist, ArtistFeatureVectors, ArtistImage, ArtistSummary, AudioFeatures, AudioFeaturesPatch, FeatureDistribution,
+    FeatureDistributions, GenreCount, ImportRecord,
+    SimilarityBreakdown, Track, TrackDetail, UndoneImport,
+};
+use crate::pagination::PageCursor;
+use crate::recommendations::{self, FeatureTarget, RecommendationMode};
+use crate::similarity::SimilarityWeights;
+use chrono::{DateTime, FixedOffset};
+use neo4rs::{BoltMap, BoltType, ConfigBuilder, Graph, Query, Row};
 use anyhow::Result;
+use futures::{Stream, TryStreamExt};
+use std::collections::HashSet;
 use std::sync::Arc;
 
 pub type Neo4jClient = Arc<Graph>;
 
+const DEFAULT_DATABASE: &str = "neo4j";
+const DEFAULT_MAX_CONNECTIONS: usize = 16;
+
 pub async fn init_neo4j() -> Result<Neo4jClient> {
     let uri = std::env::var("NEO4J_URI").unwrap_or_else(|_| "bolt://localhost:7687".to_string());
     let user = std::env::var("NEO4J_USER").unwrap_or_else(|_| "neo4j".to_string());
     let password = std::env::var("NEO4J_PASSWORD").expect("NEO4J_PASSWORD must be set");
+    let database = std::env::var("NEO4J_DATABASE").unwrap_or_else(|_| DEFAULT_DATABASE.to_string());
+    let max_connections = match std::env::var("NEO4J_MAX_CONNECTIONS") {
+        Ok(value) => value.parse::<usize>().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+            tracing::warn!("Invalid NEO4J_MAX_CONNECTIONS '{}', using {}", value, DEFAULT_MAX_CONNECTIONS);
+            DEFAULT_MAX_CONNECTIONS
+        }),
+        Err(_) => DEFAULT_MAX_CONNECTIONS,
+    };
+
+    tracing::info!(
+        "Connecting to Neo4j at {} with user {} (database: {}, max connections: {})",
+        uri, user, database, max_connections
+    );
 
-    tracing::info!("Connecting to Neo4j at {} with user {}", uri, user);
-    
-    let graph = Graph::new(uri.clone(), user.clone(), password).await?;
+    let mut config = ConfigBuilder::default()
+        .uri(uri.clone())
+        .user(user.clone())
+        .password(password)
+        .db(database.as_str())
+        .max_connections(max_connections);
+    // CA certificate for `neo4j+s://` / `bolt+s://` servers using a private CA
+    if let Ok(certificate) = std::env::var("NEO4J_CLIENT_CERTIFICATE") {
+        config = config.with_client_certificate(certificate);
+    }
+
+    let graph = Graph::connect(config.build()?).await?;
     
     // Test the connection
     let test_query = Query::new("RETURN 1 as test".to_string());
@@ -38,13 +73,52 @@ pub async fn init_neo4j() -> Result<Neo4jClient> {
     Ok(Arc::new(graph))
 }
 
+/// Round-trip a trivial query to check the database is reachable
+pub async fn ping(graph: &Graph) -> Result<()> {
+    let mut result = graph.execute(Query::new("RETURN 1 as test".to_string())).await?;
+    match result.next().await? {
+        Some(_) => Ok(()),
+        None => Err(anyhow::anyhow!("Neo4j returned no rows for RETURN 1")),
+    }
+}
+
+/// How many nearest tracks each newly imported track is linked to with `SIMILAR` edges
+const SIMILAR_NEIGHBOURS: usize = 10;
+/// How many of those neighbours a track detail includes
+const TRACK_DETAIL_SIMILAR: i64 = 5;
+
+// (plain id index the constraint replaces, uniqueness constraint)
+const ID_CONSTRAINTS: [(&str, &str); 3] = [
+    (
+        "DROP INDEX artist_id_index IF EXISTS",
+        "CREATE CONSTRAINT artist_id_unique IF NOT EXISTS FOR (a:Artist) REQUIRE a.id IS UNIQUE",
+    ),
+    (
+        "DROP INDEX track_id_index IF EXISTS",
+        "CREATE CONSTRAINT track_id_unique IF NOT EXISTS FOR (t:Track) REQUIRE t.id IS UNIQUE",
+    ),
+    (
+        "DROP INDEX album_id_index IF EXISTS",
+        "CREATE CONSTRAINT album_id_unique IF NOT EXISTS FOR (al:Album) REQUIRE al.id IS UNIQUE",
+    ),
+];
+
 async fn create_indexes(graph: &Graph) -> Result<()> {
+    create_id_constraints(graph).await;
+
+    // The id indexes are no-ops where a constraint already indexes the id, and
+    // keep lookups fast where the constraint couldn't be created
     let queries = vec![
         "CREATE INDEX artist_id_index IF NOT EXISTS FOR (a:Artist) ON (a.id)",
         "CREATE INDEX track_id_index IF NOT EXISTS FOR (t:Track) ON (t.id)",
         "CREATE INDEX album_id_index IF NOT EXISTS FOR (al:Album) ON (al.id)",
         "CREATE INDEX artist_name_index IF NOT EXISTS FOR (a:Artist) ON (a.name)",
         "CREATE INDEX track_name_index IF NOT EXISTS FOR (t:Track) ON (t.name)",
+        "CREATE FULLTEXT INDEX track_name_fulltext IF NOT EXISTS FOR (t:Track) ON EACH [t.name]",
+        "CREATE INDEX import_imported_at_index IF NOT EXISTS FOR (i:Import) ON (i.imported_at)",
+        "CREATE INDEX import_id_index IF NOT EXISTS FOR (i:Import) ON (i.id)",
+        "CREATE INDEX import_source_id_index IF NOT EXISTS FOR (i:Import) ON (i.source_id)",
+        "CREATE INDEX playlist_id_index IF NOT EXISTS FOR (p:Playlist) ON (p.id)",
     ];
 
     for query_str in queries {
@@ -64,30 +138,70 @@ async fn create_indexes(graph: &Graph) -> Result<()> {
     Ok(())
 }
 
-pub async fn store_artist(graph: &Graph, artist: &Artist) -> Result<()> {
-    let query = Query::new(
-        "MERGE (a:Artist {id: $id})
-         SET a.name = $name,
+/// Enforce unique ids per label. Failures (duplicate data already in the graph,
+/// or a Neo4j version with older constraint syntax) are logged rather than
+/// stopping startup.
+async fn create_id_constraints(graph: &Graph) {
+    for (drop_index, create_constraint) in ID_CONSTRAINTS {
+        // A constraint can't be created over an existing plain index on the same property
+        if let Err(e) = graph.run(Query::new(drop_index.to_string())).await {
+            tracing::warn!("Skipping constraint, couldn't drop {}: {}", drop_index, e);
+            continue;
+        }
+
+        match graph.run(Query::new(create_constraint.to_string())).await {
+            Ok(()) => tracing::debug!("Successfully created/verified constraint: {}", create_constraint),
+            Err(e) => tracing::warn!("Skipping constraint {}: {}", create_constraint, e),
+        }
+    }
+}
+
+// What `store_artist` and `update_artist` both write to `a`, with its parameters bound by `artist_details`
+const ARTIST_DETAILS: &str = "a.name = $name,
              a.genres = $genres,
              a.popularity = $popularity,
              a.followers = $followers,
              a.image_url = $image_url,
-             a.updated_at = datetime()
-         RETURN a".to_string()
-    )
-    .param("id", artist.id.clone())
-    .param("name", artist.name.clone())
-    .param("genres", artist.genres.clone())
-    .param("popularity", artist.popularity as i64)
-    .param("followers", artist.followers as i64)
-    .param("image_url", artist.image_url.clone().unwrap_or_default());
+             a.image_urls = $image_urls,
+             a.image_widths = $image_widths,
+             a.image_heights = $image_heights,
+             a.updated_at = datetime()";
+
+fn artist_details(query: Query, artist: &Artist) -> Query {
+    query
+        .param("id", artist.id.clone())
+        .param("name", artist.name.clone())
+        .param("genres", artist.genres.clone())
+        .param("popularity", artist.popularity as i64)
+        .param("followers", artist.followers as i64)
+        .param("image_url", artist.image_url.clone().unwrap_or_default())
+        // Lists stored as properties can't hold maps or nulls, so images are parallel arrays with 0
+        // for a missing dimension
+        .param("image_urls", artist.image_urls.iter().map(|image| image.url.clone()).collect::<Vec<_>>())
+        .param("image_widths", artist.image_urls.iter().map(|image| image.width.unwrap_or(0) as i64).collect::<Vec<_>>())
+        .param("image_heights", artist.image_urls.iter().map(|image| image.height.unwrap_or(0) as i64).collect::<Vec<_>>())
+}
+
+/// Upsert an artist. `true` if this is the first time the artist's details were stored, which
+/// includes an artist only created by name when a track was linked to it.
+pub async fn store_artist(graph: &Graph, artist: &Artist) -> Result<bool> {
+    let query = artist_details(
+        Query::new(format!(
+            "MERGE (a:Artist {{id: $id}})
+             ON CREATE SET a.created_at = datetime()
+             WITH a, a.updated_at IS NULL as created
+             SET {ARTIST_DETAILS}
+             RETURN created"
+        )),
+        artist,
+    );
 
     match graph.execute(query).await {
         Ok(mut result) => {
             // Check if we got a result back
-            if result.next().await?.is_some() {
+            if let Some(row) = result.next().await? {
                 tracing::debug!("Successfully stored artist: {}", artist.name);
-                Ok(())
+                Ok(row.get::<bool>("created").unwrap_or(false))
             } else {
                 tracing::error!("No result returned when storing artist: {}", artist.name);
                 Err(anyhow::anyhow!("Failed to store artist - no result returned"))
@@ -100,52 +214,83 @@ pub async fn store_artist(graph: &Graph, artist: &Artist) -> Result<()> {
     }
 }
 
-pub async fn store_track(graph: &Graph, track: &Track) -> Result<()> {
+/// A feature value as it's safe to store: `None` when it isn't a finite number, otherwise clamped
+/// into the feature's range so one bad value can't dominate similarity distances
+fn sanitize_feature(name: &str, value: f64) -> Option<f64> {
+    if !value.is_finite() {
+        return None;
+    }
+    let (min, max) = feature_range(name);
+    Some(value.clamp(min, max))
+}
+
+// `sanitize_feature`, logging when it changes what would have been stored
+fn checked_feature(track_id: &str, name: &str, value: Option<f64>) -> Option<f64> {
+    let value = value?;
+    let sanitized = sanitize_feature(name, value);
+    if sanitized != Some(value) {
+        tracing::warn!("Track {} has {} = {}, storing {:?} instead", track_id, name, value, sanitized);
+    }
+    sanitized
+}
+
+/// Upsert a track. Missing audio features leave any stored ones in place, so an import that
+/// skipped or failed the features lookup doesn't erase them. `true` if the track is new.
+pub async fn store_track(graph: &Graph, track: &Track) -> Result<bool> {
     let query = Query::new(
         "MERGE (t:Track {id: $id})
+         ON CREATE SET t.created_at = datetime()
+         WITH t, t.updated_at IS NULL as created
          SET t.name = $name,
              t.duration_ms = $duration_ms,
              t.popularity = $popularity,
              t.explicit = $explicit,
-             t.danceability = $danceability,
-             t.energy = $energy,
-             t.key = $key,
-             t.loudness = $loudness,
-             t.mode = $mode,
-             t.speechiness = $speechiness,
-             t.acousticness = $acousticness,
-             t.instrumentalness = $instrumentalness,
-             t.liveness = $liveness,
-             t.valence = $valence,
-             t.tempo = $tempo,
-             t.time_signature = $time_signature,
+             t.artist_ids = $artist_ids,
+             t.artist_names = $artist_names,
+             t.danceability = coalesce($danceability, t.danceability),
+             t.energy = coalesce($energy, t.energy),
+             t.key = coalesce($key, t.key),
+             t.loudness = coalesce($loudness, t.loudness),
+             t.mode = coalesce($mode, t.mode),
+             t.speechiness = coalesce($speechiness, t.speechiness),
+             t.acousticness = coalesce($acousticness, t.acousticness),
+             t.instrumentalness = coalesce($instrumentalness, t.instrumentalness),
+             t.liveness = coalesce($liveness, t.liveness),
+             t.valence = coalesce($valence, t.valence),
+             t.tempo = coalesce($tempo, t.tempo),
+             t.time_signature = coalesce($time_signature, t.time_signature),
              t.preview_url = $preview_url,
+             t.raw_json = coalesce($raw_json, t.raw_json),
              t.updated_at = datetime()
-         RETURN t".to_string()
+         RETURN created".to_string()
     )
     .param("id", track.id.clone())
     .param("name", track.name.clone())
     .param("duration_ms", track.duration_ms as i64)
     .param("popularity", track.popularity as i64)
     .param("explicit", track.explicit)
-    .param("danceability", track.danceability)
-    .param("energy", track.energy)
-    .param("key", track.key as i64)
-    .param("loudness", track.loudness)
-    .param("mode", track.mode as i64)
-    .param("speechiness", track.speechiness)
-    .param("acousticness", track.acousticness)
-    .param("instrumentalness", track.instrumentalness)
-    .param("liveness", track.liveness)
-    .param("valence", track.valence)
-    .param("tempo", track.tempo)
-    .param("time_signature", track.time_signature as i64)
-    .param("preview_url", track.preview_url.clone().unwrap_or_default());
-
-    match graph.execute(query).await {
+    .param("artist_ids", track.artist_ids.clone())
+    .param("artist_names", track.artist_names.clone())
+    .param("danceability", checked_feature(&track.id, "danceability", track.danceability))
+    .param("energy", checked_feature(&track.id, "energy", track.energy))
+    .param("key", track.key.map(|v| v as i64))
+    .param("loudness", checked_feature(&track.id, "loudness", track.loudness))
+    .param("mode", track.mode.map(|v| v as i64))
+    .param("speechiness", checked_feature(&track.id, "speechiness", track.speechiness))
+    .param("acousticness", checked_feature(&track.id, "acousticness", track.acousticness))
+    .param("instrumentalness", checked_feature(&track.id, "instrumentalness", track.instrumentalness))
+    .param("liveness", checked_feature(&track.id, "liveness", track.liveness))
+    .param("valence", checked_feature(&track.id, "valence", track.valence))
+    .param("tempo", checked_feature(&track.id, "tempo", track.tempo))
+    .param("time_signature", track.time_signature.map(|v| v as i64))
+    .param("preview_url", track.preview_url.clone().unwrap_or_default())
+    .param("raw_json", track.raw_json.clone());
+
+    let created = match graph.execute(query).await {
         Ok(mut result) => {
-            if result.next().await?.is_some() {
+            if let Some(row) = result.next().await? {
                 tracing::debug!("Successfully stored track: {}", track.name);
+                row.get::<bool>("created").unwrap_or(false)
             } else {
                 tracing::error!("No result returned when storing track: {}", track.name);
                 return Err(anyhow::anyhow!("Failed to store track - no result returned"));
@@ -155,45 +300,73 @@ pub async fn store_track(graph: &Graph, track: &Track) -> Result<()> {
             tracing::error!("Error storing track {}: {}", track.name, e);
             return Err(e.into());
         }
-    }
+    };
 
-    // Create relationships with artists
-    for artist_id in &track.artist_ids {
-        let rel_query = Query::new(
-            "MATCH (t:Track {id: $track_id}), (a:Artist {id: $artist_id})
-             MERGE (a)-[:PERFORMED]->(t)
-             RETURN a, t".to_string()
-        )
-        .param("track_id", track.id.clone())
-        .param("artist_id", artist_id.clone());
+    store_track_relationships(graph, track).await?;
+    Ok(created)
+}
 
-        match graph.execute(rel_query).await {
-            Ok(mut result) => {
-                if result.next().await?.is_some() {
-                    tracing::debug!("Successfully created relationship between artist {} and track {}", artist_id, track.name);
-                } else {
-                    tracing::warn!("No result when creating relationship between artist {} and track {}", artist_id, track.name);
-                }
-            }
-            Err(e) => {
-                tracing::error!("Error creating relationship between artist {} and track {}: {}", artist_id, track.name, e);
-                return Err(e.into());
+// Link a stored track to its artists and album
+async fn store_track_relationships(graph: &Graph, track: &Track) -> Result<()> {
+    // Create relationships with all the track's artists in one query. Imports store a track before
+    // fetching its artists' details, so artists not stored yet are created by name and filled in
+    // by `store_artist` later, rather than the edge silently not being created.
+    let (artist_ids, artist_names) = performers(track);
+    let rel_query = Query::new(
+        "MATCH (t:Track {id: $track_id})
+         UNWIND range(0, size($artist_ids) - 1) AS i
+         MERGE (a:Artist {id: $artist_ids[i]})
+         ON CREATE SET a.name = $artist_names[i], a.created_at = datetime()
+         MERGE (a)-[:PERFORMED]->(t)
+         RETURN count(a) as linked".to_string()
+    )
+    .param("track_id", track.id.clone())
+    .param("artist_ids", artist_ids)
+    .param("artist_names", artist_names);
+
+    match graph.execute(rel_query).await {
+        Ok(mut result) => {
+            let linked = match result.next().await? {
+                Some(row) => row.get::<i64>("linked").unwrap_or(0),
+                None => 0,
+            };
+            if linked as usize == track.artist_ids.len() {
+                tracing::debug!("Linked {} artists to track {}", linked, track.name);
+            } else {
+                tracing::warn!("Linked {} of {} artists to track {}", linked, track.artist_ids.len(), track.name);
             }
         }
+        Err(e) => {
+            tracing::error!("Error creating artist relationships for track {}: {}", track.name, e);
+            return Err(e.into());
+        }
     }
 
-    // Create relationship with album if it exists
+    // Create relationship with album if it exists. Album artists are created by name if they
+    // aren't stored yet, since an import only fetches details for the track's own artists first
     if !track.album_id.is_empty() {
+        let (album_artist_ids, album_artist_names): (Vec<String>, Vec<String>) =
+            track.album_artists.iter().cloned().unzip();
         let album_query = Query::new(
             "MERGE (al:Album {id: $album_id})
-             SET al.name = $album_name
+             SET al.name = $album_name,
+                 al.image_url = $album_image_url,
+                 al.total_tracks = coalesce($album_total_tracks, al.total_tracks)
              WITH al
              MATCH (t:Track {id: $track_id})
              MERGE (al)-[:CONTAINS]->(t)
+             FOREACH (i IN range(0, size($album_artist_ids) - 1) |
+                 MERGE (aa:Artist {id: $album_artist_ids[i]})
+                 ON CREATE SET aa.name = $album_artist_names[i], aa.created_at = datetime()
+                 MERGE (aa)-[:ALBUM_ARTIST]->(al))
              RETURN al, t".to_string()
         )
         .param("album_id", track.album_id.clone())
         .param("album_name", track.album_name.clone())
+        .param("album_image_url", track.album_image_url.clone())
+        .param("album_total_tracks", track.album_total_tracks.map(|v| v as i64))
+        .param("album_artist_ids", album_artist_ids)
+        .param("album_artist_names", album_artist_names)
         .param("track_id", track.id.clone());
 
         match graph.execute(album_query).await {
@@ -214,206 +387,497 @@ pub async fn store_track(graph: &Graph, track: &Track) -> Result<()> {
     Ok(())
 }
 
-pub async fn get_all_artists(graph: &Graph) -> Result<Vec<Artist>> {
-    let query = Query::new(
-        "MATCH (a:Artist)
-         RETURN a.id as id, a.name as name, a.genres as genres, 
-                a.popularity as popularity, a.followers as followers, 
-                a.image_url as image_url
-         ORDER BY a.popularity DESC".to_string()
-    );
-
-    let mut result = graph.execute(query).await?;
-    let mut artists = Vec::new();
+// The track's artist ids with a name for each, empty where Spotify didn't give one
+fn performers(track: &Track) -> (Vec<String>, Vec<String>) {
+    let names = track.artist_names.iter().cloned().chain(std::iter::repeat(String::new()));
+    track.artist_ids.iter().cloned().zip(names).unzip()
+}
 
-    while let Some(row) = result.next().await? {
-        artists.push(Artist {
-            id: row.get::<String>("id")?,
-            name: row.get::<String>("name")?,
-            genres: row.get::<Vec<String>>("genres").unwrap_or_default(),
-            popularity: row.get::<i64>("popularity").unwrap_or(0) as i32,
-            followers: row.get::<i64>("followers").unwrap_or(0) as i32,
-            image_url: row.get::<Option<String>>("image_url")?,
-        });
-    }
+fn track_from_row(row: &Row) -> Result<Track> {
+    Ok(Track {
+        id: row.get::<String>("id")?,
+        name: row.get::<String>("name")?,
+        artist_ids: row.get::<Vec<String>>("artist_ids").unwrap_or_default(),
+        artist_names: row.get::<Vec<String>>("artist_names").unwrap_or_default(),
+        album_id: row.get::<String>("album_id").unwrap_or_default(),
+        album_name: row.get::<String>("album_name").unwrap_or_default(),
+        album_image_url: row.get::<Option<String>>("album_image_url").unwrap_or(None),
+        duration_ms: row.get::<i64>("duration_ms").unwrap_or(0) as i32,
+        popularity: row.get::<i64>("popularity").unwrap_or(0) as i32,
+        explicit: row.get::<bool>("explicit").unwrap_or(false),
+        danceability: row.get::<Option<f64>>("danceability").unwrap_or(None),
+        energy: row.get::<Option<f64>>("energy").unwrap_or(None),
+        key: row.get::<Option<i64>>("key").unwrap_or(None).map(|v| v as i32),
+        loudness: row.get::<Option<f64>>("loudness").unwrap_or(None),
+        mode: row.get::<Option<i64>>("mode").unwrap_or(None).map(|v| v as i32),
+        speechiness: row.get::<Option<f64>>("speechiness").unwrap_or(None),
+        acousticness: row.get::<Option<f64>>("acousticness").unwrap_or(None),
+        instrumentalness: row.get::<Option<f64>>("instrumentalness").unwrap_or(None),
+        liveness: row.get::<Option<f64>>("liveness").unwrap_or(None),
+        valence: row.get::<Option<f64>>("valence").unwrap_or(None),
+        tempo: row.get::<Option<f64>>("tempo").unwrap_or(None),
+        time_signature: row.get::<Option<i64>>("time_signature").unwrap_or(None).map(|v| v as i32),
+        preview_url: row.get::<Option<String>>("preview_url")?,
+        updated_at: datetime_from_row(row, "updated_at"),
+        created_at: datetime_from_row(row, "created_at"),
+        raw_json: None,
+        album_total_tracks: None,
+        album_artists: Vec::new(),
+    })
+}
 
-    Ok(artists)
+// A `datetime()` column as RFC 3339, `None` when missing or null
+fn datetime_from_row(row: &Row, column: &str) -> Option<String> {
+    row.get::<Option<DateTime<FixedOffset>>>(column)
+        .unwrap_or(None)
+        .map(|datetime| datetime.to_rfc3339())
 }
 
-pub async fn get_all_tracks(graph: &Graph) -> Result<Vec<Track>> {
-    tracing::debug!("Executing get_all_tracks query");
-    let query = Query::new(
-        "MATCH (t:Track)
-         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
-         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(t)
-         RETURN t.id as id, t.name as name, 
-                collect(DISTINCT a.id) as artist_ids,
-                collect(DISTINCT a.name) as artist_names,
-                COALESCE(al.id, '') as album_id,
-                COALESCE(al.name, '') as album_name,
-                t.duration_ms as duration_ms, t.popularity as popularity,
-                t.explicit as explicit, t.danceability as danceability,
-                t.energy as energy, t.key as key, t.loudness as loudness,
-                t.mode as mode, t.speechiness as speechiness,
-                t.acousticness as acousticness, t.instrumentalness as instrumentalness,
-                t.liveness as liveness, t.valence as valence,
-                t.tempo as tempo, t.time_signature as time_signature,
-                t.preview_url as preview_url
-         ORDER BY t.popularity DESC".to_string()
+/// A page of artists by popularity. `after` resumes from a cursor instead of skipping `offset` artists.
+/// `updated_after` is an RFC 3339 timestamp; when set, only artists stored after it are returned
+pub async fn get_all_artists(
+    graph: &Graph,
+    offset: i64,
+    limit: i64,
+    updated_after: Option<&str>,
+    after: Option<&PageCursor>,
+) -> Result<Vec<Artist>> {
+    let query = artists_page_query(
+        "a.id as id, a.name as name, a.genres as genres,
+         a.popularity as popularity, a.followers as followers,
+         a.image_url as image_url, a.image_urls as image_urls, a.image_widths as image_widths,
+         a.image_heights as image_heights, a.updated_at as updated_at, a.created_at as created_at",
+        offset,
+        limit,
+        updated_after,
+        after,
     );
 
     let mut result = graph.execute(query).await?;
-    let mut tracks = Vec::new();
-    
-    tracing::debug!("Processing query results for get_all_tracks");
+    let mut artists = Vec::new();
+
     while let Some(row) = result.next().await? {
-        let track_name = row.get::<String>("name").unwrap_or_default();
-        tracing::debug!("Found track: {}", track_name);
-        tracks.push(Track {
-            id: row.get::<String>("id")?,
-            name: row.get::<String>("name")?,
-            artist_ids: row.get::<Vec<String>>("artist_ids").unwrap_or_default(),
-            artist_names: row.get::<Vec<String>>("artist_names").unwrap_or_default(),
-            album_id: row.get::<String>("album_id").unwrap_or_default(),
-            album_name: row.get::<String>("album_name").unwrap_or_default(),
-            duration_ms: row.get::<i64>("duration_ms").unwrap_or(0) as i32,
-            popularity: row.get::<i64>("popularity").unwrap_or(0) as i32,
-            explicit: row.get::<bool>("explicit").unwrap_or(false),
-            danceability: row.get::<f64>("danceability").unwrap_or(0.0),
-            energy: row.get::<f64>("energy").unwrap_or(0.0),
-            key: row.get::<i64>("key").unwrap_or(0) as i32,
-            loudness: row.get::<f64>("loudness").unwrap_or(0.0),
-            mode: row.get::<i64>("mode").unwrap_or(0) as i32,
-            speechiness: row.get::<f64>("speechiness").unwrap_or(0.0),
-            acousticness: row.get::<f64>("acousticness").unwrap_or(0.0),
-            instrumentalness: row.get::<f64>("instrumentalness").unwrap_or(0.0),
-            liveness: row.get::<f64>("liveness").unwrap_or(0.0),
-            valence: row.get::<f64>("valence").unwrap_or(0.0),
-            tempo: row.get::<f64>("tempo").unwrap_or(0.0),
-            time_signature: row.get::<i64>("time_signature").unwrap_or(4) as i32,
-            preview_url: row.get::<Option<String>>("preview_url")?,
-        });
+        artists.push(artist_from_row(&row)?);
     }
 
-    Ok(tracks)
+    Ok(artists)
 }
 
-pub async fn get_similar_tracks(graph: &Graph, track_ids: &[String], limit: i32) -> Result<Vec<Track>> {
-    let query = Query::new(
-        "MATCH (seed:Track) WHERE seed.id IN $seed_ids
-         MATCH (similar:Track)
-         WHERE similar.id <> seed.id
-         WITH similar, seed,
-              abs(similar.valence - seed.valence) as valence_diff,
-              abs(similar.energy - seed.energy) as energy_diff,
-              abs(similar.danceability - seed.danceability) as dance_diff,
-              abs(similar.tempo - seed.tempo) / 200.0 as tempo_diff
-         WITH similar, 
-              avg(valence_diff + energy_diff + dance_diff + tempo_diff) as similarity_score
-         ORDER BY similarity_score ASC
-         LIMIT $limit
-         MATCH (similar)<-[:PERFORMED]-(a:Artist)
-         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(similar)
-         RETURN similar.id as id, similar.name as name,
-                collect(DISTINCT a.id) as artist_ids,
-                collect(DISTINCT a.name) as artist_names,
-                COALESCE(al.id, '') as album_id,
-                COALESCE(al.name, '') as album_name,
-                similar.duration_ms as duration_ms, similar.popularity as popularity,
-                similar.explicit as explicit, similar.danceability as danceability,
-                similar.energy as energy, similar.key as key, similar.loudness as loudness,
-                similar.mode as mode, similar.speechiness as speechiness,
-                similar.acousticness as acousticness, similar.instrumentalness as instrumentalness,
-                similar.liveness as liveness, similar.valence as valence,
-                similar.tempo as tempo, similar.time_signature as time_signature,
-                similar.preview_url as preview_url".to_string()
-    )
-    .param("seed_ids", track_ids.to_vec())
-    .param("limit", limit as i64);
+/// The same page as `get_all_artists`, returning only the columns a list of artists shows
+pub async fn get_artist_summaries(
+    graph: &Graph,
+    offset: i64,
+    limit: i64,
+    updated_after: Option<&str>,
+    after: Option<&PageCursor>,
+) -> Result<Vec<ArtistSummary>> {
+    let query = artists_page_query(
+        "a.id as id, a.name as name, a.popularity as popularity, a.image_url as image_url,
+         a.image_urls as image_urls, a.image_widths as image_widths, a.image_heights as image_heights",
+        offset,
+        limit,
+        updated_after,
+        after,
+    );
 
     let mut result = graph.execute(query).await?;
-    let mut tracks = Vec::new();
+    let mut artists = Vec::new();
 
     while let Some(row) = result.next().await? {
-        tracks.push(Track {
+        artists.push(ArtistSummary {
             id: row.get::<String>("id")?,
             name: row.get::<String>("name")?,
-            artist_ids: row.get::<Vec<String>>("artist_ids").unwrap_or_default(),
-            artist_names: row.get::<Vec<String>>("artist_names").unwrap_or_default(),
-            album_id: row.get::<String>("album_id").unwrap_or_default(),
-            album_name: row.get::<String>("album_name").unwrap_or_default(),
-            duration_ms: row.get::<i64>("duration_ms").unwrap_or(0) as i32,
             popularity: row.get::<i64>("popularity").unwrap_or(0) as i32,
-            explicit: row.get::<bool>("explicit").unwrap_or(false),
-            danceability: row.get::<f64>("danceability").unwrap_or(0.0),
-            energy: row.get::<f64>("energy").unwrap_or(0.0),
-            key: row.get::<i64>("key").unwrap_or(0) as i32,
-            loudness: row.get::<f64>("loudness").unwrap_or(0.0),
-            mode: row.get::<i64>("mode").unwrap_or(0) as i32,
-            speechiness: row.get::<f64>("speechiness").unwrap_or(0.0),
-            acousticness: row.get::<f64>("acousticness").unwrap_or(0.0),
-            instrumentalness: row.get::<f64>("instrumentalness").unwrap_or(0.0),
-            liveness: row.get::<f64>("liveness").unwrap_or(0.0),
-            valence: row.get::<f64>("valence").unwrap_or(0.0),
-            tempo: row.get::<f64>("tempo").unwrap_or(0.0),
-            time_signature: row.get::<i64>("time_signature").unwrap_or(4) as i32,
-            preview_url: row.get::<Option<String>>("preview_url")?,
+            image_url: row.get::<Option<String>>("image_url")?,
+            image_urls: artist_images_from_row(&row),
         });
     }
 
-    Ok(tracks)
+    Ok(artists)
 }
 
+// A page of artists by popularity with `returns` as the RETURN columns
+fn artists_page_query(
+    returns: &str,
+    offset: i64,
+    limit: i64,
+    updated_after: Option<&str>,
+    after: Option<&PageCursor>,
+) -> Query {
+    Query::new(format!(
+        "MATCH (a:Artist)
+         WHERE ($updated_after IS NULL OR a.updated_at > datetime($updated_after))
+           AND ($after_id IS NULL OR a.popularity < $after_popularity
+                OR (a.popularity = $after_popularity AND a.id > $after_id))
+         RETURN {returns}
+         ORDER BY a.popularity DESC, a.id ASC
+         SKIP $offset
+         LIMIT $limit"
+    ))
+    .param("offset", offset)
+    .param("limit", limit)
+    .param("updated_after", updated_after.map(str::to_string))
+    .param("after_popularity", after.map(|cursor| cursor.popularity))
+    .param("after_id", after.map(|cursor| cursor.id.clone()))
+}
 
+fn artist_from_row(row: &Row) -> Result<Artist> {
+    Ok(Artist {
+        id: row.get::<String>("id")?,
+        name: row.get::<String>("name")?,
+        genres: row.get::<Vec<String>>("genres").unwrap_or_default(),
+        popularity: row.get::<i64>("popularity").unwrap_or(0) as i32,
+        followers: row.get::<i64>("followers").unwrap_or(0) as i32,
+        image_url: row.get::<Option<String>>("image_url")?,
+        image_urls: artist_images_from_row(row),
+        updated_at: datetime_from_row(row, "updated_at"),
+        created_at: datetime_from_row(row, "created_at"),
+    })
+}
 
+// The parallel `image_urls`, `image_widths` and `image_heights` arrays as images. Artists stored
+// before the arrays were kept have none.
+fn artist_images_from_row(row: &Row) -> Vec<ArtistImage> {
+    let urls = row.get::<Vec<String>>("image_urls").unwrap_or_default();
+    let widths = row.get::<Vec<i64>>("image_widths").unwrap_or_default();
+    let heights = row.get::<Vec<i64>>("image_heights").unwrap_or_default();
+    let dimension = |values: &[i64], index: usize| values.get(index).copied().filter(|&value| value > 0).map(|value| value as i32);
+
+    urls.into_iter()
+        .enumerate()
+        .map(|(index, url)| ArtistImage {
+            url,
+            width: dimension(&widths, index),
+            height: dimension(&heights, index),
+        })
+        .collect()
+}
 
+pub async fn get_all_artist_ids(graph: &Graph) -> Result<Vec<String>> {
+    let query = Query::new("MATCH (a:Artist) RETURN collect(a.id) as ids".to_string());
 
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<Vec<String>>("ids")?),
+        None => Ok(Vec::new()),
+    }
+}
 
+/// Overwrite a stored artist's metadata with fresh values from Spotify.
+/// Unlike `store_artist` this never creates an artist; `false` means it wasn't in the graph.
+pub async fn update_artist(graph: &Graph, artist: &Artist) -> Result<bool> {
+    let query = artist_details(
+        Query::new(format!(
+            "MATCH (a:Artist {{id: $id}})
+             SET {ARTIST_DETAILS}
+             RETURN a.id as id"
+        )),
+        artist,
+    );
 
+    let mut result = graph.execute(query).await?;
+    Ok(result.next().await?.is_some())
+}
 
+pub async fn count_artists(graph: &Graph, updated_after: Option<&str>) -> Result<i64> {
+    let query = Query::new(
+        "MATCH (a:Artist)
+         WHERE $updated_after IS NULL OR a.updated_at > datetime($updated_after)
+         RETURN count(a) as total".to_string()
+    )
+    .param("updated_after", updated_after.map(str::to_string));
 
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<i64>("total")?),
+        None => Ok(0),
+    }
+}
 
+/// Distinct genres across all artists, alphabetically
+pub async fn get_all_genres(graph: &Graph) -> Result<Vec<String>> {
+    let query = Query::new(
+        "MATCH (a:Artist)
+         UNWIND a.genres as genre
+         RETURN DISTINCT genre
+         ORDER BY genre ASC".to_string()
+    );
 
+    let mut result = graph.execute(query).await?;
+    let mut genres = Vec::new();
 
+    while let Some(row) = result.next().await? {
+        genres.push(row.get::<String>("genre")?);
+    }
 
+    Ok(genres)
+}
 
+/// Spread of tempo, energy, valence, danceability and loudness across every stored track,
+/// aggregated in one query
+pub async fn get_feature_distributions(graph: &Graph) -> Result<FeatureDistributions> {
+    let columns: Vec<String> = ["tempo", "energy", "valence", "danceability", "loudness"]
+        .iter()
+        .map(|feature| distribution_columns(feature))
+        .collect();
+    let query = Query::new(format!("MATCH (t:Track)\n         RETURN {}", columns.join(",\n                ")));
 
+    let mut result = graph.execute(query).await?;
+    let row = result
+        .next()
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Feature distribution query returned no row"))?;
+
+    Ok(FeatureDistributions {
+        tempo: distribution_from_row(&row, "tempo")?,
+        energy: distribution_from_row(&row, "energy")?,
+        valence: distribution_from_row(&row, "valence")?,
+        danceability: distribution_from_row(&row, "danceability")?,
+        loudness: distribution_from_row(&row, "loudness")?,
+    })
+}
 
+// Aggregates of `t.<feature>` as `<feature>_count`, `<feature>_min` and so on. Tracks without the
+// feature are left out of every aggregate.
+fn distribution_columns(feature: &str) -> String {
+    let deciles: Vec<String> = (1..10)
+        .map(|decile| format!("percentileCont(t.{feature}, 0.{decile})"))
+        .collect();
+    format!(
+        "count(t.{feature}) as {feature}_count, min(t.{feature}) as {feature}_min, \
+         max(t.{feature}) as {feature}_max, avg(t.{feature}) as {feature}_mean, \
+         stDevP(t.{feature}) as {feature}_stddev, [{}] as {feature}_deciles",
+        deciles.join(", ")
+    )
+}
 
+// An empty library aggregates to nulls, and a standard deviation of 0, so nothing is read past the count
+fn distribution_from_row(row: &Row, feature: &str) -> Result<FeatureDistribution> {
+    let count = row.get::<i64>(&format!("{feature}_count"))?;
+    if count == 0 {
+        return Ok(FeatureDistribution::default());
+    }
+    Ok(FeatureDistribution {
+        count,
+        min: row.get::<Option<f64>>(&format!("{feature}_min"))?,
+        max: row.get::<Option<f64>>(&format!("{feature}_max"))?,
+        mean: row.get::<Option<f64>>(&format!("{feature}_mean"))?,
+        stddev: row.get::<Option<f64>>(&format!("{feature}_stddev"))?,
+        deciles: row.get::<Vec<f64>>(&format!("{feature}_deciles"))?,
+    })
+}
 
+/// Every genre with how many artists carry it and how many tracks those artists performed, most common first.
+/// Artists with no genres simply don't contribute.
+pub async fn get_genre_counts(graph: &Graph) -> Result<Vec<GenreCount>> {
+    let query = Query::new(
+        "MATCH (a:Artist)
+         UNWIND a.genres as genre
+         OPTIONAL MATCH (a)-[:PERFORMED]->(t:Track)
+         WITH genre, count(DISTINCT a) as artist_count, count(DISTINCT t) as track_count
+         RETURN genre, artist_count, track_count
+         ORDER BY artist_count DESC, track_count DESC, genre ASC".to_string()
+    );
 
+    let mut result = graph.execute(query).await?;
+    let mut counts = Vec::new();
 
+    while let Some(row) = result.next().await? {
+        counts.push(GenreCount {
+            genre: row.get("genre")?,
+            artist_count: row.get("artist_count")?,
+            track_count: row.get("track_count")?,
+        });
+    }
 
+    Ok(counts)
+}
 
+/// Order of a track listing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrackSort {
+    /// Most popular first
+    #[default]
+    Popularity,
+    /// Most recently created first, with tracks stored before `created_at` was recorded last
+    Recent,
+}
 
+/// Optional restrictions on a track listing; the default returns every track
+#[derive(Debug, Default)]
+pub struct TrackFilter {
+    /// RFC 3339 timestamp; only tracks stored after it
+    pub updated_after: Option<String>,
+    /// Only tracks with (`true`) or without (`false`) a non-empty preview URL
+    pub has_preview: Option<bool>,
+    /// Only tracks after this position in the listing's popularity, id order
+    pub after: Option<PageCursor>,
+    /// At most this many tracks
+    pub limit: Option<i64>,
+    pub sort: TrackSort,
+}
 
+/// How many tracks match `filter`'s `updated_after` and `has_preview`, across all pages
+pub async fn count_tracks(graph: &Graph, filter: &TrackFilter) -> Result<i64> {
+    let query = Query::new(
+        "MATCH (t:Track)
+         WHERE ($updated_after IS NULL OR t.updated_at > datetime($updated_after))
+           AND ($has_preview IS NULL OR (coalesce(t.preview_url, '') <> '') = $has_preview)
+         RETURN count(t) as total".to_string()
+    )
+    .param("updated_after", filter.updated_after.clone())
+    .param("has_preview", filter.has_preview);
 
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<i64>("total")?),
+        None => Ok(0),
+    }
+}
 
+/// Every stored track matching `filter`. Artists are optional, so a track whose artists couldn't be
+/// fetched or linked is still returned, with empty `artist_ids` and `artist_names`.
+pub async fn get_all_tracks(graph: &Graph, filter: &TrackFilter) -> Result<Vec<Track>> {
+    stream_all_tracks(graph, filter).await?.try_collect().await
+}
 
+/// Same tracks as `get_all_tracks`, yielded as rows arrive instead of buffered into a `Vec`
+pub async fn stream_all_tracks(
+    graph: &Graph,
+    filter: &TrackFilter,
+) -> Result<impl Stream<Item = Result<Track>> + Send + 'static> {
+    tracing::debug!("Executing get_all_tracks query");
+    let limit = match filter.limit {
+        Some(_) => "LIMIT $limit",
+        None => "",
+    };
+    let order = match filter.sort {
+        TrackSort::Popularity => "popularity DESC, id ASC",
+        TrackSort::Recent => "created_at IS NULL, created_at DESC, id ASC",
+    };
+    let query = Query::new(format!(
+        "MATCH (t:Track)
+         WHERE ($updated_after IS NULL OR t.updated_at > datetime($updated_after))
+           AND ($has_preview IS NULL OR (coalesce(t.preview_url, '') <> '') = $has_preview)
+           AND ($after_id IS NULL OR t.popularity < $after_popularity
+                OR (t.popularity = $after_popularity AND t.id > $after_id))
+         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(t)
+         RETURN t.id as id, t.name as name, 
+                collect(DISTINCT a.id) as artist_ids,
+                collect(DISTINCT a.name) as artist_names,
+                COALESCE(al.id, '') as album_id,
+                COALESCE(al.name, '') as album_name,
+                al.image_url as album_image_url,
+                t.duration_ms as duration_ms, t.popularity as popularity,
+                t.explicit as explicit, t.danceability as danceability,
+                t.energy as energy, t.key as key, t.loudness as loudness,
+                t.mode as mode, t.speechiness as speechiness,
+                t.acousticness as acousticness, t.instrumentalness as instrumentalness,
+                t.liveness as liveness, t.valence as valence,
+                t.tempo as tempo, t.time_signature as time_signature,
+                t.preview_url as preview_url,
+                t.updated_at as updated_at, t.created_at as created_at
+         ORDER BY {order}
+         {limit}"
+    ))
+    .param("updated_after", filter.updated_after.clone())
+    .param("has_preview", filter.has_preview)
+    .param("after_popularity", filter.after.as_ref().map(|cursor| cursor.popularity))
+    .param("after_id", filter.after.as_ref().map(|cursor| cursor.id.clone()))
+    .param("limit", filter.limit);
+
+    let rows = graph.execute(query).await?.into_stream();
+    Ok(rows
+        .map_err(anyhow::Error::from)
+        .and_then(|row| futures::future::ready(track_from_row(&row))))
+}
 
+pub async fn get_all_albums(graph: &Graph) -> Result<Vec<Album>> {
+    let query = Query::new(
+        "MATCH (al:Album)
+         OPTIONAL MATCH (al)-[:CONTAINS]->(t:Track)
+         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
+         RETURN al.id as id, al.name as name,
+                collect(DISTINCT a.id) as artist_ids,
+                collect(DISTINCT a.name) as artist_names,
+                al.release_date as release_date,
+                al.total_tracks as total_tracks,
+                al.image_url as image_url,
+                count(DISTINCT t) as track_count
+         ORDER BY track_count DESC, name ASC".to_string()
+    );
 
+    let mut result = graph.execute(query).await?;
+    let mut albums = Vec::new();
 
+    while let Some(row) = result.next().await? {
+        albums.push(album_from_row(&row)?);
+    }
 
+    Ok(albums)
+}
 
+/// Albums with fewer tracks in the graph than Spotify lists, closest to complete first.
+/// Singles are left out, and so are albums imported before `total_tracks` was recorded.
+pub async fn get_incomplete_albums(graph: &Graph) -> Result<Vec<Album>> {
+    let query = Query::new(
+        "MATCH (al:Album)
+         WHERE al.total_tracks > 1
+         OPTIONAL MATCH (al)-[:CONTAINS]->(t:Track)
+         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
+         WITH al, count(DISTINCT t) as track_count,
+              collect(DISTINCT a.id) as artist_ids,
+              collect(DISTINCT a.name) as artist_names
+         WHERE track_count < al.total_tracks
+         RETURN al.id as id, al.name as name, artist_ids, artist_names,
+                al.release_date as release_date,
+                al.total_tracks as total_tracks,
+                al.image_url as image_url,
+                track_count
+         ORDER BY al.total_tracks - track_count ASC, name ASC".to_string()
+    );
 
+    let mut result = graph.execute(query).await?;
+    let mut albums = Vec::new();
 
+    while let Some(row) = result.next().await? {
+        albums.push(album_from_row(&row)?);
+    }
 
+    Ok(albums)
+}
 
+fn album_from_row(row: &Row) -> Result<Album> {
+    Ok(Album {
+        id: row.get::<String>("id")?,
+        name: row.get::<String>("name").unwrap_or_default(),
+        artist_ids: row.get::<Vec<String>>("artist_ids").unwrap_or_default(),
+        artist_names: row.get::<Vec<String>>("artist_names").unwrap_or_default(),
+        release_date: row.get::<Option<String>>("release_date").unwrap_or(None),
+        total_tracks: row.get::<Option<i64>>("total_tracks").unwrap_or(None).map(|v| v as i32),
+        image_url: row.get::<Option<String>>("image_url").unwrap_or(None),
+        track_count: row.get::<i64>("track_count").unwrap_or(0) as i32,
+    })
+}
 
+// Lets a map built in Cypher be read with the same `*_from_row` helpers as a top-level row
+fn row_from_map(map: BoltMap) -> Row {
+    let (keys, values): (Vec<BoltType>, Vec<BoltType>) =
+        map.value.into_iter().map(|(key, value)| (BoltType::String(key), value)).unzip();
+    Row::new(keys.into(), values.into())
+}
 
-
-
-
-pub async fn get_track_by_id(graph: &Graph, track_id: &str) -> Result<Option<Track>> {
+pub async fn get_album_tracks(graph: &Graph, album_id: &str) -> Result<Vec<Track>> {
     let query = Query::new(
-        "MATCH (t:Track) WHERE t.id = $track_id
-         MATCH (t)<-[:PERFORMED]-(a:Artist)
-         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(t)
+        "MATCH (al:Album {id: $album_id})-[:CONTAINS]->(t:Track)
+         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
          RETURN t.id as id, t.name as name,
                 collect(DISTINCT a.id) as artist_ids,
                 collect(DISTINCT a.name) as artist_names,
-                COALESCE(al.id, '') as album_id,
-                COALESCE(al.name, '') as album_name,
+                al.id as album_id,
+                al.name as album_name,
+                al.image_url as album_image_url,
                 t.duration_ms as duration_ms, t.popularity as popularity,
                 t.explicit as explicit, t.danceability as danceability,
                 t.energy as energy, t.key as key, t.loudness as loudness,
@@ -421,38 +885,1389 @@ pub async fn get_track_by_id(graph: &Graph, track_id: &str) -> Result<Option<Tra
                 t.acousticness as acousticness, t.instrumentalness as instrumentalness,
                 t.liveness as liveness, t.valence as valence,
                 t.tempo as tempo, t.time_signature as time_signature,
-                t.preview_url as preview_url".to_string()
+                t.preview_url as preview_url,
+                t.updated_at as updated_at, t.created_at as created_at
+         ORDER BY t.name ASC".to_string()
     )
-    .param("track_id", track_id);
+    .param("album_id", album_id.to_string());
 
     let mut result = graph.execute(query).await?;
-    
-    if let Some(row) = result.next().await? {
-        Ok(Some(Track {
-            id: row.get::<String>("id")?,
-            name: row.get::<String>("name")?,
-            artist_ids: row.get::<Vec<String>>("artist_ids")?,
-            artist_names: row.get::<Vec<String>>("artist_names")?,
-            album_id: row.get::<String>("album_id").unwrap_or_default(),
-            album_name: row.get::<String>("album_name").unwrap_or_default(),
-            duration_ms: row.get::<i64>("duration_ms").unwrap_or(0) as i32,
-            popularity: row.get::<i64>("popularity").unwrap_or(0) as i32,
-            explicit: row.get::<bool>("explicit").unwrap_or(false),
-            danceability: row.get::<f64>("danceability").unwrap_or(0.0),
-            energy: row.get::<f64>("energy").unwrap_or(0.0),
-            key: row.get::<i64>("key").unwrap_or(0) as i32,
-            loudness: row.get::<f64>("loudness").unwrap_or(0.0),
-            mode: row.get::<i64>("mode").unwrap_or(0) as i32,
-            speechiness: row.get::<f64>("speechiness").unwrap_or(0.0),
-            acousticness: row.get::<f64>("acousticness").unwrap_or(0.0),
-            instrumentalness: row.get::<f64>("instrumentalness").unwrap_or(0.0),
-            liveness: row.get::<f64>("liveness").unwrap_or(0.0),
-            valence: row.get::<f64>("valence").unwrap_or(0.0),
-            tempo: row.get::<f64>("tempo").unwrap_or(0.0),
-            time_signature: row.get::<i64>("time_signature").unwrap_or(4) as i32,
-            preview_url: row.get::<Option<String>>("preview_url")?,
-        }))
-    } else {
-        Ok(None)
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        tracks.push(track_from_row(&row)?);
+    }
+
+    Ok(tracks)
+}
+
+/// Narrows the tracks recommendations are picked from
+#[derive(Debug, Clone, Default)]
+pub struct CandidateFilter {
+    /// Non-empty keeps only tracks with an artist tagged with any of these genres
+    pub genres: Vec<String>,
+    /// Leave out tracks Spotify marks as explicit
+    pub exclude_explicit: bool,
+    /// Of the nearest tracks, drop those further than this rather than padding the list.
+    /// Only feature-ranked candidates have a distance; genre mode ignores it.
+    pub max_distance: Option<f64>,
+}
+
+impl CandidateFilter {
+    /// Bind as `$genres`, `$exclude_explicit` and `$max_distance`
+    fn bind(&self, query: Query) -> Query {
+        query
+            .param("genres", self.genres.clone())
+            .param("exclude_explicit", self.exclude_explicit)
+            .param("max_distance", self.max_distance)
+    }
+
+    /// The same filter, without anything excluded for being explicit
+    pub fn allowing_explicit(&self) -> Self {
+        Self { exclude_explicit: false, ..self.clone() }
+    }
+
+    // Hybrid mode's hard filter: the seeds' genres, narrowed by `genres` if given
+    async fn within_seed_genres(&self, graph: &Graph, track_ids: &[String]) -> Result<Self> {
+        let genres = get_seed_genres(graph, track_ids)
+            .await?
+            .into_iter()
+            .filter(|genre| self.genres.is_empty() || self.genres.contains(genre))
+            .collect();
+        Ok(Self { genres, ..self.clone() })
+    }
+}
+
+/// Recommendation candidates for the seeds using `mode`, best first
+pub async fn get_recommended_tracks(
+    graph: &Graph,
+    mode: RecommendationMode,
+    track_ids: &[String],
+    limit: i32,
+    filter: &CandidateFilter,
+) -> Result<Vec<Track>> {
+    match mode {
+        RecommendationMode::Features => get_similar_tracks(graph, track_ids, limit, filter).await,
+        RecommendationMode::Genre => get_genre_tracks(graph, track_ids, limit, filter).await,
+        RecommendationMode::Hybrid => {
+            // Only tracks sharing a genre with the seeds, ranked by features
+            let filter = filter.within_seed_genres(graph, track_ids).await?;
+            if filter.genres.is_empty() {
+                return Ok(Vec::new());
+            }
+            get_similar_tracks(graph, track_ids, limit, &filter).await
+        }
     }
-}
\ No newline at end of file
+}
+
+/// How many tracks `get_recommended_tracks` could return for the seeds with no limit
+pub async fn count_recommendation_candidates(
+    graph: &Graph,
+    mode: RecommendationMode,
+    track_ids: &[String],
+    filter: &CandidateFilter,
+) -> Result<i64> {
+    let query = match mode {
+        RecommendationMode::Features => similar_candidates_query(track_ids, filter),
+        RecommendationMode::Genre => filter.bind(Query::new(
+            "MATCH (seed:Track)<-[:PERFORMED]-(sa:Artist)
+             WHERE seed.id IN $seed_ids
+             UNWIND coalesce(sa.genres, []) as seed_genre
+             WITH collect(DISTINCT seed_genre) as seed_genres
+             MATCH (similar:Track)<-[:PERFORMED]-(ga:Artist)
+             WHERE NOT similar.id IN $seed_ids
+               AND any(genre IN coalesce(ga.genres, []) WHERE genre IN seed_genres)
+               AND (size($genres) = 0
+                    OR size([(fa:Artist)-[:PERFORMED]->(similar)
+                             WHERE any(genre IN fa.genres WHERE genre IN $genres) | fa]) > 0)
+               AND ($exclude_explicit = false OR coalesce(similar.explicit, false) = false)
+             RETURN count(DISTINCT similar) as total".to_string()
+        )
+        .param("seed_ids", track_ids.to_vec())),
+        RecommendationMode::Hybrid => {
+            let filter = filter.within_seed_genres(graph, track_ids).await?;
+            if filter.genres.is_empty() {
+                return Ok(0);
+            }
+            similar_candidates_query(track_ids, &filter)
+        }
+    };
+
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<i64>("total")?),
+        None => Ok(0),
+    }
+}
+
+// Counts the tracks `get_similar_tracks` can rank: everything but the seeds with the features it compares
+fn similar_candidates_query(track_ids: &[String], filter: &CandidateFilter) -> Query {
+    let query = Query::new(
+        "MATCH (similar:Track)
+         WHERE NOT similar.id IN $seed_ids
+           AND similar.valence IS NOT NULL AND similar.energy IS NOT NULL
+           AND similar.danceability IS NOT NULL AND similar.tempo IS NOT NULL
+           AND (size($genres) = 0
+                OR size([(ga:Artist)-[:PERFORMED]->(similar)
+                         WHERE any(genre IN ga.genres WHERE genre IN $genres) | ga]) > 0)
+           AND ($exclude_explicit = false OR coalesce(similar.explicit, false) = false)
+         RETURN count(similar) as total".to_string()
+    )
+    .param("seed_ids", track_ids.to_vec());
+    filter.bind(query)
+}
+
+// The `WHERE` shared by ranking and counting tracks near a target: tracks with features, not by `$artist_id`,
+// passing the `CandidateFilter`
+const NEAR_TARGET_CANDIDATES: &str = "MATCH (similar:Track)
+         WHERE NOT (:Artist {id: $artist_id})-[:PERFORMED]->(similar)
+           AND similar.valence IS NOT NULL AND similar.energy IS NOT NULL
+           AND similar.danceability IS NOT NULL AND similar.tempo IS NOT NULL
+           AND (size($genres) = 0
+                OR size([(ga:Artist)-[:PERFORMED]->(similar)
+                         WHERE any(genre IN ga.genres WHERE genre IN $genres) | ga]) > 0)
+           AND ($exclude_explicit = false OR coalesce(similar.explicit, false) = false)";
+
+/// How many tracks `get_tracks_near_target` can rank for an artist seed
+pub async fn count_target_candidates(graph: &Graph, artist_id: &str, filter: &CandidateFilter) -> Result<i64> {
+    let query = Query::new(format!("{NEAR_TARGET_CANDIDATES}\n         RETURN count(similar) as total"))
+        .param("artist_id", artist_id);
+    let query = filter.bind(query);
+
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<i64>("total")?),
+        None => Ok(0),
+    }
+}
+
+/// Tracks closest to `target` by audio features, closest first, leaving out the seed artist's own tracks
+pub async fn get_tracks_near_target(
+    graph: &Graph,
+    target: &FeatureTarget,
+    artist_id: &str,
+    limit: i32,
+    filter: &CandidateFilter,
+) -> Result<Vec<Track>> {
+    let query = Query::new(format!(
+        "{NEAR_TARGET_CANDIDATES}
+         WITH similar,
+              $valence_weight * abs(similar.valence - $valence)
+                + $energy_weight * abs(similar.energy - $energy)
+                + $danceability_weight * abs(similar.danceability - $danceability)
+                + $tempo_weight * abs(similar.tempo - $tempo)
+                as similarity_score
+         ORDER BY similarity_score ASC
+         LIMIT $limit
+         WITH * WHERE $max_distance IS NULL OR similarity_score <= $max_distance
+         MATCH (similar)<-[:PERFORMED]-(a:Artist)
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(similar)
+         RETURN similarity_score, similar.id as id, similar.name as name,
+                collect(DISTINCT a.id) as artist_ids,
+                collect(DISTINCT a.name) as artist_names,
+                COALESCE(al.id, '') as album_id,
+                COALESCE(al.name, '') as album_name,
+                al.image_url as album_image_url,
+                similar.duration_ms as duration_ms, similar.popularity as popularity,
+                similar.explicit as explicit, similar.danceability as danceability,
+                similar.energy as energy, similar.key as key, similar.loudness as loudness,
+                similar.mode as mode, similar.speechiness as speechiness,
+                similar.acousticness as acousticness, similar.instrumentalness as instrumentalness,
+                similar.liveness as liveness, similar.valence as valence,
+                similar.tempo as tempo, similar.time_signature as time_signature,
+                similar.preview_url as preview_url,
+                similar.updated_at as updated_at, similar.created_at as created_at
+         ORDER BY similarity_score ASC"
+    ))
+    .param("artist_id", artist_id)
+    .param("valence", target.valence)
+    .param("energy", target.energy)
+    .param("danceability", target.danceability)
+    .param("tempo", target.tempo)
+    .param("limit", limit as i64);
+    let query = SimilarityWeights::from_env().bind(filter.bind(query));
+
+    let mut result = graph.execute(query).await?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        tracks.push(track_from_row(&row)?);
+    }
+
+    Ok(tracks)
+}
+
+/// Distinct genres of the artists who performed the given tracks
+pub async fn get_seed_genres(graph: &Graph, track_ids: &[String]) -> Result<Vec<String>> {
+    let query = Query::new(
+        "MATCH (seed:Track)<-[:PERFORMED]-(a:Artist)
+         WHERE seed.id IN $seed_ids
+         UNWIND coalesce(a.genres, []) as genre
+         RETURN collect(DISTINCT genre) as genres".to_string()
+    )
+    .param("seed_ids", track_ids.to_vec());
+
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<Vec<String>>("genres")?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Tracks whose artists share the most genres with the seeds' artists, ties
+/// broken by popularity. Audio features aren't needed.
+pub async fn get_genre_tracks(graph: &Graph, track_ids: &[String], limit: i32, filter: &CandidateFilter) -> Result<Vec<Track>> {
+    let query = Query::new(
+        "MATCH (seed:Track)<-[:PERFORMED]-(sa:Artist)
+         WHERE seed.id IN $seed_ids
+         UNWIND coalesce(sa.genres, []) as seed_genre
+         WITH collect(DISTINCT seed_genre) as seed_genres
+         MATCH (similar:Track)<-[:PERFORMED]-(ga:Artist)
+         WHERE NOT similar.id IN $seed_ids
+           AND (size($genres) = 0
+                OR size([(fa:Artist)-[:PERFORMED]->(similar)
+                         WHERE any(genre IN fa.genres WHERE genre IN $genres) | fa]) > 0)
+           AND ($exclude_explicit = false OR coalesce(similar.explicit, false) = false)
+         UNWIND coalesce(ga.genres, []) as genre
+         WITH similar, genre, seed_genres
+         WHERE genre IN seed_genres
+         WITH similar, count(DISTINCT genre) as shared_genres
+         ORDER BY shared_genres DESC, similar.popularity DESC
+         LIMIT $limit
+         MATCH (similar)<-[:PERFORMED]-(a:Artist)
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(similar)
+         RETURN shared_genres, similar.id as id, similar.name as name,
+                collect(DISTINCT a.id) as artist_ids,
+                collect(DISTINCT a.name) as artist_names,
+                COALESCE(al.id, '') as album_id,
+                COALESCE(al.name, '') as album_name,
+                al.image_url as album_image_url,
+                similar.duration_ms as duration_ms, similar.popularity as popularity,
+                similar.explicit as explicit, similar.danceability as danceability,
+                similar.energy as energy, similar.key as key, similar.loudness as loudness,
+                similar.mode as mode, similar.speechiness as speechiness,
+                similar.acousticness as acousticness, similar.instrumentalness as instrumentalness,
+                similar.liveness as liveness, similar.valence as valence,
+                similar.tempo as tempo, similar.time_signature as time_signature,
+                similar.preview_url as preview_url,
+                similar.updated_at as updated_at, similar.created_at as created_at
+         ORDER BY shared_genres DESC, popularity DESC".to_string()
+    )
+    .param("seed_ids", track_ids.to_vec())
+    .param("limit", limit as i64);
+    let query = filter.bind(query);
+
+    let mut result = graph.execute(query).await?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        tracks.push(track_from_row(&row)?);
+    }
+
+    Ok(tracks)
+}
+
+/// Tracks closest to the seeds by audio features, closest first
+pub async fn get_similar_tracks(
+    graph: &Graph,
+    track_ids: &[String],
+    limit: i32,
+    filter: &CandidateFilter,
+) -> Result<Vec<Track>> {
+    let tracks = get_similar_tracks_explained(graph, track_ids, limit, filter).await?;
+    Ok(tracks.into_iter().map(|(track, _)| track).collect())
+}
+
+/// `get_similar_tracks`, with each track's per-feature share of its distance
+pub async fn get_similar_tracks_explained(
+    graph: &Graph,
+    track_ids: &[String],
+    limit: i32,
+    filter: &CandidateFilter,
+) -> Result<Vec<(Track, SimilarityBreakdown)>> {
+    let query = Query::new(
+        "MATCH (seed:Track) WHERE seed.id IN $seed_ids
+         MATCH (similar:Track)
+         WHERE similar.id <> seed.id
+           AND similar.valence IS NOT NULL AND similar.energy IS NOT NULL
+           AND similar.danceability IS NOT NULL AND similar.tempo IS NOT NULL
+           AND (size($genres) = 0
+                OR size([(ga:Artist)-[:PERFORMED]->(similar)
+                         WHERE any(genre IN ga.genres WHERE genre IN $genres) | ga]) > 0)
+           AND ($exclude_explicit = false OR coalesce(similar.explicit, false) = false)
+         WITH similar, seed,
+              $valence_weight * abs(similar.valence - seed.valence) as valence_diff,
+              $energy_weight * abs(similar.energy - seed.energy) as energy_diff,
+              $danceability_weight * abs(similar.danceability - seed.danceability) as dance_diff,
+              $tempo_weight * abs(similar.tempo - seed.tempo) as tempo_diff
+         WITH similar,
+              avg(valence_diff) as valence_diff, avg(energy_diff) as energy_diff,
+              avg(dance_diff) as dance_diff, avg(tempo_diff) as tempo_diff
+         WITH similar, valence_diff, energy_diff, dance_diff, tempo_diff,
+              valence_diff + energy_diff + dance_diff + tempo_diff as similarity_score
+         ORDER BY similarity_score ASC
+         LIMIT $limit
+         WITH * WHERE $max_distance IS NULL OR similarity_score <= $max_distance
+         MATCH (similar)<-[:PERFORMED]-(a:Artist)
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(similar)
+         RETURN similarity_score, valence_diff, energy_diff, dance_diff, tempo_diff,
+                similar.id as id, similar.name as name,
+                collect(DISTINCT a.id) as artist_ids,
+                collect(DISTINCT a.name) as artist_names,
+                COALESCE(al.id, '') as album_id,
+                COALESCE(al.name, '') as album_name,
+                al.image_url as album_image_url,
+                similar.duration_ms as duration_ms, similar.popularity as popularity,
+                similar.explicit as explicit, similar.danceability as danceability,
+                similar.energy as energy, similar.key as key, similar.loudness as loudness,
+                similar.mode as mode, similar.speechiness as speechiness,
+                similar.acousticness as acousticness, similar.instrumentalness as instrumentalness,
+                similar.liveness as liveness, similar.valence as valence,
+                similar.tempo as tempo, similar.time_signature as time_signature,
+                similar.preview_url as preview_url,
+                similar.updated_at as updated_at, similar.created_at as created_at
+         ORDER BY similarity_score ASC".to_string()
+    )
+    .param("seed_ids", track_ids.to_vec())
+    .param("limit", limit as i64);
+    // Same weights as `similarity::feature_breakdown`, so Cypher and Rust agree on distances
+    let query = SimilarityWeights::from_env().bind(filter.bind(query));
+
+    let mut result = graph.execute(query).await?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        let explanation = SimilarityBreakdown {
+            valence: row.get::<f64>("valence_diff")?,
+            energy: row.get::<f64>("energy_diff")?,
+            danceability: row.get::<f64>("dance_diff")?,
+            tempo: row.get::<f64>("tempo_diff")?,
+            distance: row.get::<f64>("similarity_score")?,
+        };
+        tracks.push((track_from_row(&row)?, explanation));
+    }
+
+    Ok(tracks)
+}
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+/// Which of the given track ids are already in the graph, without loading the tracks
+pub async fn existing_track_ids(graph: &Graph, track_ids: &[String]) -> Result<HashSet<String>> {
+    existing_ids(graph, "MATCH (n:Track) WHERE n.id IN $ids RETURN collect(n.id) as ids", track_ids).await
+}
+
+/// Which of the given artist ids are already in the graph
+pub async fn existing_artist_ids(graph: &Graph, artist_ids: &[String]) -> Result<HashSet<String>> {
+    existing_ids(graph, "MATCH (n:Artist) WHERE n.id IN $ids RETURN collect(n.id) as ids", artist_ids).await
+}
+
+async fn existing_ids(graph: &Graph, cypher: &str, ids: &[String]) -> Result<HashSet<String>> {
+    let query = Query::new(cypher.to_string()).param("ids", ids.to_vec());
+
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<Vec<String>>("ids")?.into_iter().collect()),
+        None => Ok(HashSet::new()),
+    }
+}
+
+/// `count` tracks picked at random. Only the picked tracks have their artists and album looked up.
+pub async fn get_random_tracks(graph: &Graph, count: i64) -> Result<Vec<Track>> {
+    let query = Query::new(track_query(
+        "MATCH (t:Track)
+         WITH t ORDER BY rand() LIMIT $count",
+    ))
+    .param("count", count);
+
+    let mut result = graph.execute(query).await?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        tracks.push(track_from_row(&row)?);
+    }
+
+    Ok(tracks)
+}
+
+pub async fn get_tracks_by_ids(graph: &Graph, track_ids: &[String]) -> Result<Vec<Track>> {
+    let query = Query::new(
+        "MATCH (t:Track) WHERE t.id IN $track_ids
+         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(t)
+         RETURN t.id as id, t.name as name,
+                collect(DISTINCT a.id) as artist_ids,
+                collect(DISTINCT a.name) as artist_names,
+                COALESCE(al.id, '') as album_id,
+                COALESCE(al.name, '') as album_name,
+                al.image_url as album_image_url,
+                t.duration_ms as duration_ms, t.popularity as popularity,
+                t.explicit as explicit, t.danceability as danceability,
+                t.energy as energy, t.key as key, t.loudness as loudness,
+                t.mode as mode, t.speechiness as speechiness,
+                t.acousticness as acousticness, t.instrumentalness as instrumentalness,
+                t.liveness as liveness, t.valence as valence,
+                t.tempo as tempo, t.time_signature as time_signature,
+                t.preview_url as preview_url,
+                t.updated_at as updated_at, t.created_at as created_at".to_string()
+    )
+    .param("track_ids", track_ids.to_vec());
+
+    let mut result = graph.execute(query).await?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        tracks.push(track_from_row(&row)?);
+    }
+
+    Ok(tracks)
+}
+
+/// A track with its artists, album and nearest `SIMILAR` neighbours, fetched in one query
+pub async fn get_track_detail(graph: &Graph, track_id: &str) -> Result<Option<TrackDetail>> {
+    let query = Query::new(
+        "MATCH (t:Track {id: $track_id})
+         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
+         WITH t, collect(DISTINCT a) as artist_nodes
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(t)
+         OPTIONAL MATCH (al)-[:CONTAINS]->(album_track:Track)
+         OPTIONAL MATCH (album_artist:Artist)-[:PERFORMED]->(album_track)
+         WITH t, artist_nodes, al,
+              collect(DISTINCT album_artist.id) as album_artist_ids,
+              collect(DISTINCT album_artist.name) as album_artist_names,
+              count(DISTINCT album_track) as album_track_count
+         CALL {
+             WITH t
+             OPTIONAL MATCH (t)-[s:SIMILAR]-(n:Track)
+             WITH n, s ORDER BY s.distance ASC LIMIT $similar_limit
+             OPTIONAL MATCH (na:Artist)-[:PERFORMED]->(n)
+             OPTIONAL MATCH (nal:Album)-[:CONTAINS]->(n)
+             WITH n, s, nal, collect(DISTINCT na.id) as ids, collect(DISTINCT na.name) as names
+             ORDER BY s.distance ASC
+             RETURN collect(CASE WHEN n IS NULL THEN null ELSE {
+                 id: n.id, name: n.name, artist_ids: ids, artist_names: names,
+                 album_id: COALESCE(nal.id, ''), album_name: COALESCE(nal.name, ''),
+                 album_image_url: nal.image_url,
+                 duration_ms: n.duration_ms, popularity: n.popularity, explicit: n.explicit,
+                 danceability: n.danceability, energy: n.energy, key: n.key, loudness: n.loudness,
+                 mode: n.mode, speechiness: n.speechiness, acousticness: n.acousticness,
+                 instrumentalness: n.instrumentalness, liveness: n.liveness, valence: n.valence,
+                 tempo: n.tempo, time_signature: n.time_signature,
+                 preview_url: n.preview_url, updated_at: n.updated_at, created_at: n.created_at
+             } END) as similar
+         }
+         RETURN t.id as id, t.name as name,
+                [a IN artist_nodes | a.id] as artist_ids,
+                [a IN artist_nodes | a.name] as artist_names,
+                COALESCE(al.id, '') as album_id,
+                COALESCE(al.name, '') as album_name,
+                al.image_url as album_image_url,
+                t.duration_ms as duration_ms, t.popularity as popularity,
+                t.explicit as explicit, t.danceability as danceability,
+                t.energy as energy, t.key as key, t.loudness as loudness,
+                t.mode as mode, t.speechiness as speechiness,
+                t.acousticness as acousticness, t.instrumentalness as instrumentalness,
+                t.liveness as liveness, t.valence as valence,
+                t.tempo as tempo, t.time_signature as time_signature,
+                t.preview_url as preview_url,
+                t.updated_at as updated_at, t.created_at as created_at,
+                [a IN artist_nodes | {
+                    id: a.id, name: a.name, genres: a.genres, popularity: a.popularity,
+                    followers: a.followers, image_url: a.image_url, image_urls: a.image_urls,
+                    image_widths: a.image_widths, image_heights: a.image_heights,
+                    updated_at: a.updated_at, created_at: a.created_at
+                }] as artists,
+                CASE WHEN al IS NULL THEN null ELSE {
+                    id: al.id, name: al.name,
+                    artist_ids: album_artist_ids, artist_names: album_artist_names,
+                    release_date: al.release_date, total_tracks: al.total_tracks,
+                    image_url: al.image_url, track_count: album_track_count
+                } END as album,
+                similar".to_string()
+    )
+    .param("track_id", track_id)
+    .param("similar_limit", TRACK_DETAIL_SIMILAR);
+
+    let mut result = graph.execute(query).await?;
+    let Some(row) = result.next().await? else {
+        return Ok(None);
+    };
+
+    let artists = row
+        .get::<Vec<BoltMap>>("artists")?
+        .into_iter()
+        .map(|artist| artist_from_row(&row_from_map(artist)))
+        .collect::<Result<Vec<_>>>()?;
+    let album = row
+        .get::<Option<BoltMap>>("album")?
+        .map(|album| album_from_row(&row_from_map(album)))
+        .transpose()?;
+    let similar = row
+        .get::<Vec<BoltMap>>("similar")?
+        .into_iter()
+        .map(|track| track_from_row(&row_from_map(track)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Some(TrackDetail { track: track_from_row(&row)?, artists, album, similar }))
+}
+
+/// Full-text index over `Track.name`, searched by `find_track_by_name`
+const TRACK_NAME_FULLTEXT_INDEX: &str = "track_name_fulltext";
+
+pub async fn get_track_by_id(graph: &Graph, track_id: &str) -> Result<Option<Track>> {
+    let query = Query::new(track_query("MATCH (t:Track) WHERE t.id = $track_id")).param("track_id", track_id);
+    first_track(graph, query).await
+}
+
+/// The track whose name best matches `name`, tolerating small spelling differences,
+/// or `None` if nothing is close
+pub async fn find_track_by_name(graph: &Graph, name: &str) -> Result<Option<Track>> {
+    let Some(search) = fuzzy_search(name) else {
+        return Ok(None);
+    };
+    let query = Query::new(track_query(
+        "CALL db.index.fulltext.queryNodes($index, $search) YIELD node, score
+         WITH node AS t ORDER BY score DESC LIMIT 1",
+    ))
+    .param("index", TRACK_NAME_FULLTEXT_INDEX)
+    .param("search", search);
+    first_track(graph, query).await
+}
+
+// A Lucene query matching each word of `text` approximately, or `None` if it has no words
+fn fuzzy_search(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| {
+            let mut term = String::with_capacity(word.len() + 1);
+            for c in word.chars() {
+                if "+-&|!(){}[]^\"~*?:\\/".contains(c) {
+                    term.push('\\');
+                }
+                term.push(c);
+            }
+            term.push('~');
+            term
+        })
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+// `matching` binds `t` to the track; the rest gathers its artists and album
+fn track_query(matching: &str) -> String {
+    format!(
+        "{matching}
+         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(t)
+         RETURN t.id as id, t.name as name,
+                collect(DISTINCT a.id) as artist_ids,
+                collect(DISTINCT a.name) as artist_names,
+                COALESCE(al.id, '') as album_id,
+                COALESCE(al.name, '') as album_name,
+                al.image_url as album_image_url,
+                t.duration_ms as duration_ms, t.popularity as popularity,
+                t.explicit as explicit, t.danceability as danceability,
+                t.energy as energy, t.key as key, t.loudness as loudness,
+                t.mode as mode, t.speechiness as speechiness,
+                t.acousticness as acousticness, t.instrumentalness as instrumentalness,
+                t.liveness as liveness, t.valence as valence,
+                t.tempo as tempo, t.time_signature as time_signature,
+                t.preview_url as preview_url,
+                t.updated_at as updated_at, t.created_at as created_at"
+    )
+}
+
+async fn first_track(graph: &Graph, query: Query) -> Result<Option<Track>> {
+    let mut result = graph.execute(query).await?;
+
+    if let Some(row) = result.next().await? {
+        // A corrupt artist list is reported rather than read as no artists
+        row.get::<Vec<String>>("artist_ids")?;
+        row.get::<Vec<String>>("artist_names")?;
+        Ok(Some(track_from_row(&row)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Audio features of every artist track that has them, or `None` if the artist
+/// doesn't exist. Features are always stored together, so checking `energy` is
+/// enough to keep the collected arrays aligned.
+pub async fn get_artist_feature_vectors(graph: &Graph, artist_id: &str) -> Result<Option<ArtistFeatureVectors>> {
+    let query = Query::new(
+        "MATCH (a:Artist {id: $artist_id})
+         OPTIONAL MATCH (a)-[:PERFORMED]->(t:Track)
+         WHERE t.energy IS NOT NULL
+           AND NOT (t.energy = 0.0 AND t.valence = 0.0 AND t.danceability = 0.0)
+         WITH a, t
+         ORDER BY t.name ASC
+         RETURN a.id as artist_id,
+                collect(t.id) as track_ids,
+                collect(t.name) as track_names,
+                collect(t.danceability) as danceability,
+                collect(t.energy) as energy,
+                collect(t.loudness) as loudness,
+                collect(t.speechiness) as speechiness,
+                collect(t.acousticness) as acousticness,
+                collect(t.instrumentalness) as instrumentalness,
+                collect(t.liveness) as liveness,
+                collect(t.valence) as valence,
+                collect(t.tempo) as tempo".to_string()
+    )
+    .param("artist_id", artist_id);
+
+    let mut result = graph.execute(query).await?;
+
+    let Some(row) = result.next().await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(ArtistFeatureVectors {
+        artist_id: row.get::<String>("artist_id")?,
+        track_ids: row.get::<Vec<String>>("track_ids").unwrap_or_default(),
+        track_names: row.get::<Vec<String>>("track_names").unwrap_or_default(),
+        danceability: row.get::<Vec<f64>>("danceability").unwrap_or_default(),
+        energy: row.get::<Vec<f64>>("energy").unwrap_or_default(),
+        loudness: row.get::<Vec<f64>>("loudness").unwrap_or_default(),
+        speechiness: row.get::<Vec<f64>>("speechiness").unwrap_or_default(),
+        acousticness: row.get::<Vec<f64>>("acousticness").unwrap_or_default(),
+        instrumentalness: row.get::<Vec<f64>>("instrumentalness").unwrap_or_default(),
+        liveness: row.get::<Vec<f64>>("liveness").unwrap_or_default(),
+        valence: row.get::<Vec<f64>>("valence").unwrap_or_default(),
+        tempo: row.get::<Vec<f64>>("tempo").unwrap_or_default(),
+    }))
+}
+
+/// Tracks stored without audio features. Tracks imported before features
+/// became optional were stored with all-zero values instead, so energy,
+/// valence and danceability all being zero is treated as missing too.
+pub async fn get_tracks_missing_features(graph: &Graph) -> Result<Vec<Track>> {
+    let query = Query::new(
+        "MATCH (t:Track)
+         WHERE t.energy IS NULL
+            OR (t.energy = 0.0 AND t.valence = 0.0 AND t.danceability = 0.0)
+         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(t)
+         RETURN t.id as id, t.name as name,
+                collect(DISTINCT a.id) as artist_ids,
+                collect(DISTINCT a.name) as artist_names,
+                COALESCE(al.id, '') as album_id,
+                COALESCE(al.name, '') as album_name,
+                al.image_url as album_image_url,
+                t.duration_ms as duration_ms, t.popularity as popularity,
+                t.explicit as explicit, t.danceability as danceability,
+                t.energy as energy, t.key as key, t.loudness as loudness,
+                t.mode as mode, t.speechiness as speechiness,
+                t.acousticness as acousticness, t.instrumentalness as instrumentalness,
+                t.liveness as liveness, t.valence as valence,
+                t.tempo as tempo, t.time_signature as time_signature,
+                t.preview_url as preview_url,
+                t.updated_at as updated_at, t.created_at as created_at
+         ORDER BY t.name".to_string()
+    );
+
+    let mut result = graph.execute(query).await?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        tracks.push(track_from_row(&row)?);
+    }
+
+    Ok(tracks)
+}
+
+/// Tracks of a stored playlist in playlist order: those a `Playlist` node with `playlist_id`
+/// includes, and those imported from the Spotify playlist with that id. A track linked both ways
+/// takes its earliest position. Empty if neither is in the graph.
+pub async fn get_playlist_tracks(graph: &Graph, playlist_id: &str) -> Result<Vec<Track>> {
+    // `position` is one more grouping key for the RETURN, so it can be ordered by. Tracks linked
+    // before positions were stored have none and come last.
+    let query = Query::new(format!(
+        "{}, position
+         ORDER BY position, name",
+        track_query(
+            "CALL {
+                 MATCH (:Playlist {id: $playlist_id})-[r:INCLUDES]->(t:Track)
+                 RETURN t, r.position as position
+                 UNION
+                 MATCH (:Import {source_id: $playlist_id})<-[r:IMPORTED_IN]-(t:Track)
+                 RETURN t, r.position as position
+             }
+             WITH t, min(position) as position"
+        )
+    ))
+    .param("playlist_id", playlist_id);
+
+    let mut result = graph.execute(query).await?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        tracks.push(track_from_row(&row)?);
+    }
+
+    Ok(tracks)
+}
+
+/// Link stored tracks from a playlist node, creating or renaming the playlist. Each `INCLUDES`
+/// edge's `position` is the track's place in the playlist, counting from `first_position`. Ids
+/// not in the graph are skipped. Returns how many tracks were linked.
+pub async fn link_playlist_tracks(
+    graph: &Graph,
+    playlist_id: &str,
+    name: &str,
+    track_ids: &[String],
+    first_position: usize,
+) -> Result<i64> {
+    let query = Query::new(
+        "MERGE (p:Playlist {id: $playlist_id})
+         SET p.name = $name,
+             p.updated_at = datetime()
+         WITH p
+         UNWIND range(0, size($track_ids) - 1) AS index
+         MATCH (t:Track {id: $track_ids[index]})
+         MERGE (p)-[r:INCLUDES]->(t)
+         SET r.position = $first_position + index
+         RETURN count(t) as linked".to_string()
+    )
+    .param("playlist_id", playlist_id)
+    .param("name", name)
+    .param("track_ids", track_ids.to_vec())
+    .param("first_position", first_position as i64);
+
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<i64>("linked").unwrap_or(0)),
+        None => Ok(0),
+    }
+}
+
+/// Record a finished import, linking the tracks it stored to it with `IMPORTED_IN`. The edge's
+/// `created` is whether the track was stored for the first time, one of `created_ids`, and its
+/// `position` is the track's place among `track_ids` counting from `first_position`.
+pub async fn store_import_record(
+    graph: &Graph,
+    record: &ImportRecord,
+    track_ids: &[String],
+    created_ids: &[String],
+    first_position: usize,
+) -> Result<()> {
+    let query = Query::new(
+        "CREATE (i:Import {id: $id})
+         SET i.source_id = $source_id,
+             i.source_url = $source_url,
+             i.imported_at = datetime($imported_at),
+             i.imported_tracks = $imported_tracks,
+             i.new_tracks = $new_tracks,
+             i.imported_artists = $imported_artists,
+             i.new_artists = $new_artists,
+             i.duration_seconds = $duration_seconds,
+             i.cancelled = $cancelled
+         WITH i
+         UNWIND range(0, size($track_ids) - 1) AS index
+         MATCH (t:Track {id: $track_ids[index]})
+         MERGE (t)-[r:IMPORTED_IN]->(i)
+         SET r.created = t.id IN $created_ids,
+             r.position = $first_position + index".to_string()
+    )
+    .param("id", record.id.clone())
+    .param("source_id", record.source_id.clone())
+    .param("source_url", record.source_url.clone())
+    .param("imported_at", record.imported_at.clone())
+    .param("imported_tracks", record.imported_tracks)
+    .param("new_tracks", record.new_tracks)
+    .param("imported_artists", record.imported_artists)
+    .param("new_artists", record.new_artists)
+    .param("duration_seconds", record.duration_seconds)
+    .param("cancelled", record.cancelled)
+    .param("track_ids", track_ids.to_vec())
+    .param("created_ids", created_ids.to_vec())
+    .param("first_position", first_position as i64);
+
+    graph.run(query).await?;
+    Ok(())
+}
+
+/// The most recent imports, newest first
+pub async fn get_imports(graph: &Graph, limit: i64) -> Result<Vec<ImportRecord>> {
+    let query = Query::new(
+        "MATCH (i:Import)
+         RETURN i.id as id, i.source_id as source_id, i.source_url as source_url,
+                i.imported_at as imported_at, i.imported_tracks as imported_tracks,
+                i.new_tracks as new_tracks, i.imported_artists as imported_artists,
+                i.new_artists as new_artists, i.duration_seconds as duration_seconds,
+                i.cancelled as cancelled
+         ORDER BY i.imported_at DESC
+         LIMIT $limit".to_string()
+    )
+    .param("limit", limit);
+
+    let mut result = graph.execute(query).await?;
+    let mut imports = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        imports.push(ImportRecord {
+            id: row.get::<String>("id")?,
+            source_id: row.get::<String>("source_id")?,
+            source_url: row.get::<Option<String>>("source_url")?,
+            imported_at: datetime_from_row(&row, "imported_at").unwrap_or_default(),
+            imported_tracks: row.get::<i64>("imported_tracks").unwrap_or(0),
+            new_tracks: row.get::<i64>("new_tracks").unwrap_or(0),
+            imported_artists: row.get::<i64>("imported_artists").unwrap_or(0),
+            new_artists: row.get::<i64>("new_artists").unwrap_or(0),
+            duration_seconds: row.get::<f64>("duration_seconds").unwrap_or(0.0),
+            cancelled: row.get::<bool>("cancelled").unwrap_or(false),
+        });
+    }
+
+    Ok(imports)
+}
+
+/// Remove what import `import_id` added: the tracks it stored for the first time that no other
+/// import has stored since, then the albums and artists left without tracks, then the record itself.
+/// Returns `None` if there's no such import.
+pub async fn undo_import(graph: &Graph, import_id: &str) -> Result<Option<UndoneImport>> {
+    // Ids are read before each delete, since a deleted node's properties can't be.
+    // An artist can be both performer and album artist, so the candidates are deduplicated.
+    let query = Query::new(
+        "MATCH (i:Import {id: $import_id})
+         OPTIONAL MATCH (t:Track)-[:IMPORTED_IN {created: true}]->(i)
+         WHERE size([(t)-[:IMPORTED_IN]->(other:Import) WHERE other <> i | other]) = 0
+         OPTIONAL MATCH (performer:Artist)-[:PERFORMED]->(t)
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(t)
+         OPTIONAL MATCH (album_artist:Artist)-[:ALBUM_ARTIST]->(al)
+         WITH i, collect(DISTINCT t) as tracks, collect(DISTINCT al) as albums,
+              collect(DISTINCT performer) + collect(DISTINCT album_artist) as candidates
+         WITH i, tracks, albums, [t IN tracks | t.id] as track_ids,
+              reduce(artists = [], a IN candidates |
+                  CASE WHEN a IN artists THEN artists ELSE artists + a END) as artists
+         FOREACH (t IN tracks | DETACH DELETE t)
+         WITH i, artists, track_ids,
+              [al IN albums WHERE size([(al)-[:CONTAINS]->(track:Track) | track]) = 0] as empty_albums
+         WITH i, artists, track_ids, empty_albums, [al IN empty_albums | al.id] as album_ids
+         FOREACH (al IN empty_albums | DETACH DELETE al)
+         WITH i, track_ids, album_ids,
+              [a IN artists WHERE size([(a)-[:PERFORMED]->(track:Track) | track]) = 0
+                              AND size([(a)-[:ALBUM_ARTIST]->(album:Album) | album]) = 0] as lone_artists
+         WITH i, track_ids, album_ids, lone_artists, [a IN lone_artists | a.id] as artist_ids
+         FOREACH (a IN lone_artists | DETACH DELETE a)
+         DETACH DELETE i
+         RETURN track_ids, album_ids, artist_ids".to_string()
+    )
+    .param("import_id", import_id);
+
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => {
+            let undone = UndoneImport {
+                import_id: import_id.to_string(),
+                removed_tracks: row.get::<Vec<String>>("track_ids")?,
+                removed_albums: row.get::<Vec<String>>("album_ids")?,
+                removed_artists: row.get::<Vec<String>>("artist_ids")?,
+            };
+            tracing::info!(
+                "Undid import {}: removed {} tracks, {} albums and {} artists",
+                import_id,
+                undone.removed_tracks.len(),
+                undone.removed_albums.len(),
+                undone.removed_artists.len()
+            );
+            Ok(Some(undone))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Tracks with no `PERFORMED` edge from any artist. Their `artist_ids` and `artist_names` are the
+/// artists stored on the track itself, which `repair_orphan_tracks` links them to.
+pub async fn get_orphan_tracks(graph: &Graph) -> Result<Vec<Track>> {
+    let query = Query::new(
+        "MATCH (t:Track)
+         OPTIONAL MATCH (a:Artist)-[:PERFORMED]->(t)
+         WITH t, count(a) as artist_count
+         WHERE artist_count = 0
+         OPTIONAL MATCH (al:Album)-[:CONTAINS]->(t)
+         RETURN t.id as id, t.name as name,
+                coalesce(t.artist_ids, []) as artist_ids,
+                coalesce(t.artist_names, []) as artist_names,
+                COALESCE(al.id, '') as album_id,
+                COALESCE(al.name, '') as album_name,
+                al.image_url as album_image_url,
+                t.duration_ms as duration_ms, t.popularity as popularity,
+                t.explicit as explicit, t.danceability as danceability,
+                t.energy as energy, t.key as key, t.loudness as loudness,
+                t.mode as mode, t.speechiness as speechiness,
+                t.acousticness as acousticness, t.instrumentalness as instrumentalness,
+                t.liveness as liveness, t.valence as valence,
+                t.tempo as tempo, t.time_signature as time_signature,
+                t.preview_url as preview_url,
+                t.updated_at as updated_at, t.created_at as created_at
+         ORDER BY t.name".to_string()
+    );
+
+    let mut result = graph.execute(query).await?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        tracks.push(track_from_row(&row)?);
+    }
+
+    Ok(tracks)
+}
+
+/// Link every orphan track to the artists stored on it, creating artists not in the graph by name
+/// the same way imports do. Returns how many tracks were repaired and how many couldn't be, which
+/// are tracks stored before their artist ids were kept on the track.
+pub async fn repair_orphan_tracks(graph: &Graph) -> Result<(i64, i64)> {
+    let query = Query::new(
+        "MATCH (t:Track)
+         OPTIONAL MATCH (linked:Artist)-[:PERFORMED]->(t)
+         WITH t, count(linked) as artist_count
+         WHERE artist_count = 0
+         WITH t, size(coalesce(t.artist_ids, [])) > 0 as repairable
+         CALL {
+             WITH t, repairable
+             WITH t WHERE repairable
+             UNWIND range(0, size(t.artist_ids) - 1) AS i
+             MERGE (a:Artist {id: t.artist_ids[i]})
+             ON CREATE SET a.name = coalesce(t.artist_names[i], ''), a.created_at = datetime()
+             MERGE (a)-[:PERFORMED]->(t)
+             RETURN count(a) as linked
+         }
+         RETURN sum(CASE WHEN repairable THEN 1 ELSE 0 END) as repaired,
+                sum(CASE WHEN repairable THEN 0 ELSE 1 END) as unrepairable".to_string()
+    );
+
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok((row.get::<i64>("repaired").unwrap_or(0), row.get::<i64>("unrepairable").unwrap_or(0))),
+        None => Ok((0, 0)),
+    }
+}
+
+pub async fn update_track_features(graph: &Graph, track_id: &str, features: &AudioFeatures) -> Result<()> {
+    let query = Query::new(
+        "MATCH (t:Track {id: $id})
+         SET t.danceability = $danceability,
+             t.energy = $energy,
+             t.key = $key,
+             t.loudness = $loudness,
+             t.mode = $mode,
+             t.speechiness = $speechiness,
+             t.acousticness = $acousticness,
+             t.instrumentalness = $instrumentalness,
+             t.liveness = $liveness,
+             t.valence = $valence,
+             t.tempo = $tempo,
+             t.time_signature = $time_signature,
+             t.updated_at = datetime()
+         RETURN t".to_string()
+    )
+    .param("id", track_id)
+    .param("danceability", checked_feature(track_id, "danceability", Some(features.danceability)))
+    .param("energy", checked_feature(track_id, "energy", Some(features.energy)))
+    .param("key", features.key as i64)
+    .param("loudness", checked_feature(track_id, "loudness", Some(features.loudness)))
+    .param("mode", features.mode as i64)
+    .param("speechiness", checked_feature(track_id, "speechiness", Some(features.speechiness)))
+    .param("acousticness", checked_feature(track_id, "acousticness", Some(features.acousticness)))
+    .param("instrumentalness", checked_feature(track_id, "instrumentalness", Some(features.instrumentalness)))
+    .param("liveness", checked_feature(track_id, "liveness", Some(features.liveness)))
+    .param("valence", checked_feature(track_id, "valence", Some(features.valence)))
+    .param("tempo", checked_feature(track_id, "tempo", Some(features.tempo)))
+    .param("time_signature", features.time_signature as i64);
+
+    let mut result = graph.execute(query).await?;
+    if result.next().await?.is_some() {
+        tracing::debug!("Updated audio features for track {}", track_id);
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Track {} not found", track_id))
+    }
+}
+
+/// Set only the features present in `patch`, leaving the rest as Spotify reported them.
+/// Returns `false` if the track doesn't exist.
+pub async fn update_track_features_partial(graph: &Graph, track_id: &str, patch: &AudioFeaturesPatch) -> Result<bool> {
+    let provided = patch.provided();
+    // Property names come from the patch's fixed field list, never from the request body
+    let assignments: Vec<String> = provided
+        .iter()
+        .map(|(name, _)| format!("t.{name} = ${name}"))
+        .chain(std::iter::once("t.updated_at = datetime()".to_string()))
+        .collect();
+
+    let mut query = Query::new(format!(
+        "MATCH (t:Track {{id: $id}})
+         SET {}
+         RETURN t.id as id",
+        assignments.join(", ")
+    ))
+    .param("id", track_id);
+
+    for (name, value) in provided {
+        query = match name {
+            "key" | "mode" | "time_signature" => query.param(name, value as i64),
+            _ => query.param(name, value),
+        };
+    }
+
+    let mut result = graph.execute(query).await?;
+    Ok(result.next().await?.is_some())
+}
+
+/// Fold `merge_id` into `keep_id`: its tracks move over, its genres are added, and the node is deleted.
+/// Returns the number of tracks repointed, or `None` if either artist doesn't exist.
+pub async fn merge_artists(graph: &Graph, keep_id: &str, merge_id: &str) -> Result<Option<i64>> {
+    // Genres live on the artist node, so they're merged as a list rather than as relationships.
+    // MERGE keeps tracks both artists performed from ending up with two PERFORMED edges.
+    let query = Query::new(
+        "MATCH (keep:Artist {id: $keep_id}), (merge:Artist {id: $merge_id})
+         OPTIONAL MATCH (merge)-[:PERFORMED]->(t:Track)
+         FOREACH (track IN CASE WHEN t IS NULL THEN [] ELSE [t] END |
+             MERGE (keep)-[:PERFORMED]->(track))
+         WITH keep, merge, count(t) as moved_tracks
+         SET keep.genres = coalesce(keep.genres, []) +
+                 [genre IN coalesce(merge.genres, []) WHERE NOT genre IN coalesce(keep.genres, [])],
+             keep.updated_at = datetime()
+         DETACH DELETE merge
+         RETURN moved_tracks".to_string()
+    )
+    .param("keep_id", keep_id)
+    .param("merge_id", merge_id);
+
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => {
+            let moved_tracks = row.get::<i64>("moved_tracks")?;
+            tracing::info!("Merged artist {} into {} ({} tracks repointed)", merge_id, keep_id, moved_tracks);
+            Ok(Some(moved_tracks))
+        }
+        None => Ok(None),
+    }
+}
+
+/// `(id, raw_json)` of every track stored with its original Spotify JSON
+pub async fn get_raw_track_json(graph: &Graph) -> Result<Vec<(String, String)>> {
+    let query = Query::new(
+        "MATCH (t:Track)
+         WHERE t.raw_json IS NOT NULL
+         RETURN t.id as id, t.raw_json as raw_json".to_string()
+    );
+
+    let mut result = graph.execute(query).await?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = result.next().await? {
+        tracks.push((row.get::<String>("id")?, row.get::<String>("raw_json")?));
+    }
+
+    Ok(tracks)
+}
+
+/// Overwrite a track's Spotify-derived details and relationships, leaving its audio features alone
+pub async fn update_track_details(graph: &Graph, track: &Track) -> Result<()> {
+    let query = Query::new(
+        "MATCH (t:Track {id: $id})
+         SET t.name = $name,
+             t.duration_ms = $duration_ms,
+             t.popularity = $popularity,
+             t.explicit = $explicit,
+             t.preview_url = $preview_url,
+             t.updated_at = datetime()
+         RETURN t".to_string()
+    )
+    .param("id", track.id.clone())
+    .param("name", track.name.clone())
+    .param("duration_ms", track.duration_ms as i64)
+    .param("popularity", track.popularity as i64)
+    .param("explicit", track.explicit)
+    .param("preview_url", track.preview_url.clone().unwrap_or_default());
+
+    let mut result = graph.execute(query).await?;
+    if result.next().await?.is_none() {
+        return Err(anyhow::anyhow!("Track {} not found", track.id));
+    }
+
+    store_track_relationships(graph, track).await
+}
+
+/// Link each of `new_track_ids` to its nearest tracks by audio features with
+/// `SIMILAR {distance}` edges, comparing only against the new tracks rather than
+/// recomputing every pair. Returns the number of edges written.
+pub async fn compute_similarity_edges_for(graph: &Graph, new_track_ids: &[String]) -> Result<usize> {
+    if new_track_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let tracks = get_all_tracks(graph, &TrackFilter::default()).await?;
+    let pairs = recommendations::similarity_pairs(&tracks, new_track_ids, SIMILAR_NEIGHBOURS);
+    if pairs.is_empty() {
+        return Ok(0);
+    }
+
+    let mut from_ids = Vec::with_capacity(pairs.len());
+    let mut to_ids = Vec::with_capacity(pairs.len());
+    let mut distances = Vec::with_capacity(pairs.len());
+    for (from_id, to_id, distance) in pairs {
+        from_ids.push(from_id);
+        to_ids.push(to_id);
+        distances.push(distance);
+    }
+
+    // Undirected MERGE so a pair already linked from the other side isn't duplicated
+    let query = Query::new(
+        "UNWIND range(0, size($from_ids) - 1) as i
+         MATCH (a:Track {id: $from_ids[i]}), (b:Track {id: $to_ids[i]})
+         MERGE (a)-[s:SIMILAR]-(b)
+         SET s.distance = $distances[i]
+         RETURN count(s) as edges".to_string()
+    )
+    .param("from_ids", from_ids)
+    .param("to_ids", to_ids)
+    .param("distances", distances);
+
+    let mut result = graph.execute(query).await?;
+    let edges = match result.next().await? {
+        Some(row) => row.get::<i64>("edges")? as usize,
+        None => 0,
+    };
+
+    tracing::info!("Wrote {} SIMILAR edges for {} new tracks", edges, new_track_ids.len());
+    Ok(edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neo4rs::BoltList;
+
+    fn map(entries: Vec<(&str, BoltType)>) -> BoltMap {
+        let mut map = BoltMap::new();
+        for (key, value) in entries {
+            map.put(key.into(), value);
+        }
+        map
+    }
+
+    #[test]
+    fn test_nested_maps_parse_like_rows() {
+        let artist = map(vec![
+            ("id", BoltType::from("artist-1")),
+            ("name", BoltType::from("Slowdive")),
+            ("genres", BoltType::List(BoltList::from(vec![BoltType::from("shoegaze")]))),
+            ("popularity", BoltType::from(61i64)),
+            ("image_url", BoltType::Null(neo4rs::BoltNull)),
+        ]);
+        let row = Row::new(
+            BoltList::from(vec![BoltType::from("artists")]),
+            BoltList::from(vec![BoltType::List(BoltList::from(vec![BoltType::Map(artist)]))]),
+        );
+
+        let artists = row.get::<Vec<BoltMap>>("artists").unwrap();
+        let parsed = artist_from_row(&row_from_map(artists.into_iter().next().unwrap())).unwrap();
+
+        assert_eq!(parsed.id, "artist-1");
+        assert_eq!(parsed.genres, vec!["shoegaze".to_string()]);
+        assert_eq!(parsed.popularity, 61);
+        assert_eq!(parsed.followers, 0);
+        assert_eq!(parsed.image_url, None);
+        assert!(parsed.image_urls.is_empty());
+    }
+
+    #[test]
+    fn test_artist_images_zip_parallel_arrays() {
+        let list = |values: Vec<BoltType>| BoltType::List(BoltList::from(values));
+        let row = row_from_map(map(vec![
+            ("image_urls", list(vec![BoltType::from("https://i.scdn.co/640"), BoltType::from("https://i.scdn.co/160")])),
+            ("image_widths", list(vec![BoltType::from(640i64), BoltType::from(0i64)])),
+            ("image_heights", list(vec![BoltType::from(640i64)])),
+        ]));
+
+        assert_eq!(
+            artist_images_from_row(&row),
+            vec![
+                ArtistImage { url: "https://i.scdn.co/640".to_string(), width: Some(640), height: Some(640) },
+                ArtistImage { url: "https://i.scdn.co/160".to_string(), width: None, height: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_track_without_linked_artists_parses() {
+        let empty = || BoltType::List(BoltList::from(Vec::<BoltType>::new()));
+        // What `OPTIONAL MATCH` and `collect` give for a track with no artist or album
+        let row = row_from_map(map(vec![
+            ("id", BoltType::from("track-1")),
+            ("name", BoltType::from("Alison")),
+            ("artist_ids", empty()),
+            ("artist_names", empty()),
+            ("album_id", BoltType::from("")),
+            ("album_name", BoltType::from("")),
+            ("album_image_url", BoltType::Null(neo4rs::BoltNull)),
+            ("duration_ms", BoltType::from(232_000i64)),
+            ("popularity", BoltType::from(61i64)),
+            ("preview_url", BoltType::Null(neo4rs::BoltNull)),
+        ]));
+
+        let track = track_from_row(&row).unwrap();
+        assert_eq!(track.id, "track-1");
+        assert!(track.artist_ids.is_empty());
+        assert!(track.artist_names.is_empty());
+        assert_eq!(track.album_id, "");
+        assert_eq!(track.duration_ms, 232_000);
+    }
+
+    #[test]
+    fn test_every_artist_id_is_linked_even_without_a_name() {
+        let track: Track = serde_json::from_value(serde_json::json!({
+            "id": "track-1",
+            "name": "Alison",
+            "artist_ids": ["artist-1", "artist-2", "artist-3"],
+            "artist_names": ["Slowdive", "Neil Halstead"],
+            "album_id": "",
+            "album_name": "",
+            "duration_ms": 232000,
+            "popularity": 61,
+            "explicit": false
+        }))
+        .unwrap();
+
+        let (ids, names) = performers(&track);
+        assert_eq!(ids, track.artist_ids);
+        assert_eq!(names, vec!["Slowdive".to_string(), "Neil Halstead".to_string(), String::new()]);
+    }
+
+    #[test]
+    fn test_fuzzy_search_escapes_and_fuzzes_each_word() {
+        assert_eq!(fuzzy_search("Alison").as_deref(), Some("Alison~"));
+        assert_eq!(fuzzy_search("  When the Sun  Hits ").as_deref(), Some("When~ the~ Sun~ Hits~"));
+        assert_eq!(fuzzy_search("AC/DC: Live!").as_deref(), Some(r"AC\/DC\:~ Live\!~"));
+        assert_eq!(fuzzy_search("   "), None);
+    }
+
+    #[test]
+    fn test_out_of_range_features_are_clamped_or_dropped() {
+        assert_eq!(sanitize_feature("energy", 0.42), Some(0.42));
+        assert_eq!(sanitize_feature("energy", 5.0), Some(1.0));
+        assert_eq!(sanitize_feature("valence", -0.3), Some(0.0));
+        assert_eq!(sanitize_feature("danceability", f64::NAN), None);
+        assert_eq!(sanitize_feature("tempo", f64::INFINITY), None);
+        assert_eq!(sanitize_feature("tempo", 128.0), Some(128.0));
+        assert_eq!(sanitize_feature("tempo", 1200.0), Some(300.0));
+        assert_eq!(sanitize_feature("tempo", -10.0), Some(0.0));
+        assert_eq!(sanitize_feature("loudness", -7.5), Some(-7.5));
+        assert_eq!(sanitize_feature("loudness", 3.0), Some(0.0));
+
+        assert_eq!(checked_feature("track-1", "energy", None), None);
+        assert_eq!(checked_feature("track-1", "energy", Some(1.5)), Some(1.0));
+    }
+
+    #[test]
+    fn test_empty_library_has_no_feature_distribution() {
+        let null = || BoltType::Null(neo4rs::BoltNull);
+        let row = |values: Vec<BoltType>| {
+            let columns = ["count", "min", "max", "mean", "stddev", "deciles"]
+                .map(|column| BoltType::from(format!("tempo_{column}")));
+            Row::new(BoltList::from(columns.to_vec()), BoltList::from(values))
+        };
+
+        // Cypher's stDevP is 0 rather than null over no values
+        let nulls = BoltType::List(BoltList::from(vec![null(); 9]));
+        let empty = row(vec![BoltType::from(0i64), null(), null(), null(), BoltType::from(0.0), nulls]);
+        assert_eq!(distribution_from_row(&empty, "tempo").unwrap(), FeatureDistribution::default());
+
+        let deciles: Vec<BoltType> = (1..10).map(|decile| BoltType::from(90.0 + decile as f64 * 5.0)).collect();
+        let library = row(vec![
+            BoltType::from(120i64),
+            BoltType::from(62.0),
+            BoltType::from(180.5),
+            BoltType::from(118.0),
+            BoltType::from(24.5),
+            BoltType::List(BoltList::from(deciles)),
+        ]);
+        let tempo = distribution_from_row(&library, "tempo").unwrap();
+        assert_eq!(tempo.count, 120);
+        assert_eq!((tempo.min, tempo.max, tempo.mean, tempo.stddev), (Some(62.0), Some(180.5), Some(118.0), Some(24.5)));
+        assert_eq!(tempo.deciles.len(), 9);
+        assert_eq!(tempo.deciles[4], 115.0);
+    }
+
+    #[test]
+    fn test_distribution_columns_name_each_aggregate() {
+        let columns = distribution_columns("energy");
+        assert!(columns.contains("count(t.energy) as energy_count"));
+        assert!(columns.contains("stDevP(t.energy) as energy_stddev"));
+        assert!(columns.contains("percentileCont(t.energy, 0.1), "));
+        assert!(columns.contains("percentileCont(t.energy, 0.9)] as energy_deciles"));
+    }
+
+    #[test]
+    fn test_updated_at_reads_neo4j_datetimes() {
+        let stored = DateTime::parse_from_rfc3339("2024-03-01T12:30:00+00:00").unwrap();
+        let row = |value: BoltType| {
+            Row::new(
+                BoltList::from(vec![
+                    BoltType::from("id"),
+                    BoltType::from("name"),
+                    BoltType::from("preview_url"),
+                    BoltType::from("updated_at"),
+                ]),
+                BoltList::from(vec![
+                    BoltType::from("track-1"),
+                    BoltType::from("Alison"),
+                    BoltType::Null(neo4rs::BoltNull),
+                    value,
+                ]),
+            )
+        };
+
+        // `datetime()` comes back as a Bolt DateTime, not a string
+        let track = track_from_row(&row(BoltType::DateTime(stored.into()))).unwrap();
+        let updated_at = track.updated_at.expect("updated_at should be read");
+        assert!(!updated_at.is_empty());
+        assert_eq!(DateTime::parse_from_rfc3339(&updated_at).unwrap(), stored);
+
+        // Tracks stored before timestamps were recorded have none
+        let untouched = track_from_row(&row(BoltType::Null(neo4rs::BoltNull))).unwrap();
+        assert_eq!(untouched.updated_at, None);
+        assert_eq!(untouched.created_at, None);
+    }
+}
@@ -0,0 +1,57 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+/// Position in a listing ordered by popularity (highest first) then id, marking the last item
+/// of the previous page. Keyset queries resume after it, so pages don't drift when items are
+/// added or removed between requests the way `SKIP` offsets do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageCursor {
+    pub popularity: i64,
+    pub id: String,
+}
+
+impl PageCursor {
+    pub fn new(popularity: i32, id: &str) -> Self {
+        Self { popularity: i64::from(popularity), id: id.to_string() }
+    }
+
+    /// Opaque form handed to clients
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", self.popularity, self.id))
+    }
+
+    /// `None` for anything `encode` didn't produce
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let text = String::from_utf8(bytes).ok()?;
+        let (popularity, id) = text.split_once(':')?;
+        if id.is_empty() {
+            return None;
+        }
+        Some(Self { popularity: popularity.parse().ok()?, id: id.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trips() {
+        let cursor = PageCursor::new(73, "4uLU6hMCjMI75M1A2tKUQC");
+        let encoded = cursor.encode();
+
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(PageCursor::decode(&encoded), Some(cursor));
+        // Ids containing the separator still decode, since only the first one splits
+        let odd = PageCursor::new(0, "a:b");
+        assert_eq!(PageCursor::decode(&odd.encode()), Some(odd));
+    }
+
+    #[test]
+    fn test_rejects_malformed_cursors() {
+        assert_eq!(PageCursor::decode("not base64!"), None);
+        assert_eq!(PageCursor::decode(&URL_SAFE_NO_PAD.encode("no-separator")), None);
+        assert_eq!(PageCursor::decode(&URL_SAFE_NO_PAD.encode("high:abc")), None);
+        assert_eq!(PageCursor::decode(&URL_SAFE_NO_PAD.encode("10:")), None);
+    }
+}
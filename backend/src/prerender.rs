@@ -0,0 +1,171 @@
+use crate::models::Track;
+use std::fmt::Write;
+
+/// Where the built frontend is served from
+pub const FRONTEND_DIST: &str = "frontend/dist";
+/// Id of the `<script type="application/json">` holding the tracks the snapshot was rendered from
+pub const INITIAL_TRACKS_ID: &str = "initial-tracks";
+/// Id of the server-rendered list, removed by the frontend once it takes over
+pub const PRERENDERED_ID: &str = "prerendered-tracks";
+
+const APP_ROOT: &str = r#"<div id="app">"#;
+
+/// The SPA shell with its loading fallback replaced by a static track list, so the first paint
+/// shows tracks before the wasm bundle loads. The tracks are also embedded as JSON for the
+/// frontend to start from instead of fetching them again.
+pub fn tracks_page(shell: &str, tracks: &[Track]) -> serde_json::Result<String> {
+    let mut app = format!(r#"{APP_ROOT}<div id="{PRERENDERED_ID}" class="max-w-7xl mx-auto px-4 py-8">"#);
+    app.push_str(r#"<h1 class="text-3xl font-bold text-gray-900 mb-6">Tracks</h1><div class="space-y-4">"#);
+    for track in tracks {
+        push_track(&mut app, track);
+    }
+    app.push_str("</div></div></div>");
+
+    // `</` can't appear inside a script element; `<\/` is the same string to a JSON parser
+    let json = serde_json::to_string(tracks)?.replace("</", r"<\/");
+    let data = format!(r#"<script id="{INITIAL_TRACKS_ID}" type="application/json">{json}</script>"#);
+
+    Ok(match app_root_span(shell) {
+        Some((start, end)) => format!("{}{}{}{}", &shell[..start], app, data, &shell[end..]),
+        // A shell without the usual root still gets the data, just without the first paint
+        None => shell.replacen("</body>", &format!("{data}</body>"), 1),
+    })
+}
+
+fn push_track(html: &mut String, track: &Track) {
+    let _ = write!(
+        html,
+        r#"<div class="bg-white rounded-lg shadow-md p-6"><h3 class="text-lg font-semibold text-gray-900 truncate"><a href="/tracks/{}" class="hover:text-purple-600">{}</a></h3><p class="text-gray-600 mt-1">by {}</p><p class="text-sm text-gray-500 mt-1">Album: {}</p><div class="flex items-center space-x-4 mt-2 text-sm text-gray-600"><span>{}</span><span>Popularity: {}/100</span></div></div>"#,
+        escape(&urlencoding::encode(&track.id)),
+        escape(&track.name),
+        escape(&track.artist_names.join(", ")),
+        escape(&track.album_name),
+        format_duration(track.duration_ms),
+        track.popularity,
+    );
+}
+
+// Same `M:SS` / `H:MM:SS` format as the frontend
+fn format_duration(ms: i32) -> String {
+    let total_seconds = ms.max(0) / 1000;
+    let (hours, minutes, seconds) = (total_seconds / 3600, (total_seconds % 3600) / 60, total_seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Byte range of the `<div id="app">` element, including its nested divs
+fn app_root_span(shell: &str) -> Option<(usize, usize)> {
+    let start = shell.find(APP_ROOT)?;
+    let mut depth = 0;
+    let mut pos = start;
+    loop {
+        let rest = &shell[pos..];
+        let open = rest.find("<div");
+        let close = rest.find("</div>")?;
+        match open {
+            Some(open) if open < close => {
+                depth += 1;
+                pos += open + "<div".len();
+            }
+            _ => {
+                depth -= 1;
+                pos += close + "</div>".len();
+                if depth == 0 {
+                    return Some((start, pos));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHELL: &str = r#"<body><div id="app"><div class="spinner"><div></div></div></div><script type="module">run()</script></body>"#;
+
+    fn track(id: &str, name: &str) -> Track {
+        Track {
+            id: id.to_string(),
+            name: name.to_string(),
+            artist_ids: vec!["a".to_string()],
+            artist_names: vec!["Slowdive".to_string()],
+            album_id: "al".to_string(),
+            album_name: "Souvlaki".to_string(),
+            album_image_url: None,
+            duration_ms: 232_000,
+            popularity: 61,
+            explicit: false,
+            danceability: None,
+            energy: None,
+            key: None,
+            loudness: None,
+            mode: None,
+            speechiness: None,
+            acousticness: None,
+            instrumentalness: None,
+            liveness: None,
+            valence: None,
+            tempo: None,
+            time_signature: None,
+            preview_url: None,
+            updated_at: None,
+            created_at: None,
+            raw_json: None,
+            album_total_tracks: None,
+            album_artists: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_replaces_loading_fallback_with_tracks() {
+        let page = tracks_page(SHELL, &[track("t1", "Alison")]).unwrap();
+
+        assert!(!page.contains("spinner"));
+        assert!(page.starts_with(r#"<body><div id="app"><div id="prerendered-tracks""#));
+        assert!(page.contains(r#"<a href="/tracks/t1" class="hover:text-purple-600">Alison</a>"#));
+        assert!(page.contains("by Slowdive"));
+        assert!(page.contains("<span>3:52</span>"));
+        assert!(page.ends_with(r#"</script><script type="module">run()</script></body>"#));
+
+        let start = page.find(r#"type="application/json">"#).unwrap() + r#"type="application/json">"#.len();
+        let end = start + page[start..].find("</script>").unwrap();
+        let embedded: Vec<Track> = serde_json::from_str(&page[start..end]).unwrap();
+        assert_eq!(embedded.len(), 1);
+        assert_eq!(embedded[0].name, "Alison");
+    }
+
+    #[test]
+    fn test_escapes_track_text() {
+        let page = tracks_page(SHELL, &[track("t1", "</script><b>Loud & Clear</b>")]).unwrap();
+
+        assert!(page.contains("&lt;/script&gt;&lt;b&gt;Loud &amp; Clear&lt;/b&gt;"));
+        // The only `</script>` closings are the data block's and the shell's own
+        assert_eq!(page.matches("</script>").count(), 2);
+        assert!(page.contains(r#"<\/script><b>Loud & Clear<\/b>"#));
+    }
+
+    #[test]
+    fn test_shell_without_app_root_still_gets_data() {
+        let page = tracks_page("<body></body>", &[]).unwrap();
+        assert_eq!(page, r#"<body><script id="initial-tracks" type="application/json">[]</script></body>"#);
+    }
+}
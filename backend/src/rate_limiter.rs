@@ -3,7 +3,10 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, Semaphore};
 use tokio::time::sleep;
-use tracing::{debug, warn, error};
+use tracing::{debug, info, warn, error};
+
+/// Number of requests between `info`-level wait summaries
+const SUMMARY_INTERVAL: u64 = 100;
 
 /// Configuration for rate limiting
 #[derive(Clone, Debug)]
@@ -20,6 +23,8 @@ pub struct RateLimitConfig {
     pub backoff_multiplier: f64,
     /// Maximum number of retry attempts
     pub max_retries: u32,
+    /// Pause between the chunks of `execute_batch`
+    pub batch_delay: Duration,
 }
 
 impl Default for RateLimitConfig {
@@ -31,6 +36,7 @@ impl Default for RateLimitConfig {
             max_backoff: Duration::from_secs(30),
             backoff_multiplier: 2.0,
             max_retries: 3,
+            batch_delay: Duration::from_millis(100),
         }
     }
 }
@@ -44,6 +50,7 @@ impl RateLimitConfig {
             max_backoff: Duration::from_secs(10),
             backoff_multiplier: 2.0,
             max_retries: 3,
+            batch_delay: Duration::from_millis(100),
         }
     }
 
@@ -55,15 +62,53 @@ impl RateLimitConfig {
             max_backoff: Duration::from_secs(15),
             backoff_multiplier: 1.5,
             max_retries: 3,
+            batch_delay: Duration::from_millis(100),
         }
     }
 }
 
+/// Running totals of time spent waiting, reported periodically
+#[derive(Debug, Default)]
+struct WaitStats {
+    requests: u64,
+    rate_limit_wait: Duration,
+    backoffs: u32,
+    backoff_wait: Duration,
+}
+
+impl WaitStats {
+    fn average_backoff(&self) -> Duration {
+        if self.backoffs == 0 {
+            Duration::ZERO
+        } else {
+            self.backoff_wait / self.backoffs
+        }
+    }
+}
+
+/// Outcome of `execute_batch`: every request's result in request order, with how many succeeded
+/// and how many failed after their retries
+#[derive(Debug)]
+pub struct BatchResults<T, E> {
+    pub results: Vec<Result<T, E>>,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+impl<T, E> BatchResults<T, E> {
+    fn new(results: Vec<Result<T, E>>) -> Self {
+        let succeeded = results.iter().filter(|result| result.is_ok()).count();
+        let failed = results.len() - succeeded;
+        Self { results, succeeded, failed }
+    }
+}
+
 /// Tracks request timestamps for rate limiting
 #[derive(Debug)]
 struct RequestTracker {
     requests: VecDeque<Instant>,
     config: RateLimitConfig,
+    stats: WaitStats,
 }
 
 impl RequestTracker {
@@ -71,6 +116,7 @@ impl RequestTracker {
         Self {
             requests: VecDeque::new(),
             config,
+            stats: WaitStats::default(),
         }
     }
 
@@ -98,25 +144,69 @@ impl RequestTracker {
         None
     }
 
-    /// Record a successful request
+    /// Record a request as it's let through, successful or not
     fn record_request(&mut self) {
         self.requests.push_back(Instant::now());
     }
 }
 
-/// Rate limiter with request queuing and exponential backoff
+/// Rate limiter with request queuing and exponential backoff. Requests start in the order they
+/// were submitted; a retry rejoins the back of the queue.
 pub struct RateLimiter {
+    /// Client name used to tell limiters apart in logs
+    name: &'static str,
     tracker: Arc<Mutex<RequestTracker>>,
     semaphore: Arc<Semaphore>,
+    /// Held while a request waits for a permit and for room in the window. Tokio's mutex is
+    /// FIFO, so whoever asked first is let through first.
+    admission: Mutex<()>,
 }
 
 impl RateLimiter {
-    pub fn new(config: RateLimitConfig) -> Self {
-        let max_concurrent = std::cmp::min(config.max_requests / 4, 10); // Conservative concurrent limit
+    pub fn new(name: &'static str, config: RateLimitConfig) -> Self {
+        let max_concurrent = (config.max_requests / 4).clamp(1, 10); // Conservative concurrent limit
         
         Self {
+            name,
             tracker: Arc::new(Mutex::new(RequestTracker::new(config))),
             semaphore: Arc::new(Semaphore::new(max_concurrent as usize)),
+            admission: Mutex::new(()),
+        }
+    }
+
+    /// Wait until the window has room, then count the request against it
+    async fn wait_for_window(&self) {
+        loop {
+            let mut tracker = self.tracker.lock().await;
+            let Some(delay) = tracker.check_rate_limit() else {
+                tracker.record_request();
+                return;
+            };
+            debug!("{} rate limit hit, waiting {:?}", self.name, delay);
+            tracker.stats.rate_limit_wait += delay;
+            drop(tracker); // Release the lock before sleeping
+            metrics::counter!("rate_limiter_waits_total", "client" => self.name).increment(1);
+            metrics::histogram!("rate_limiter_wait_seconds", "client" => self.name)
+                .record(delay.as_secs_f64());
+            sleep(delay).await;
+        }
+    }
+
+    /// Count a finished request and log a wait summary every `SUMMARY_INTERVAL` requests
+    async fn record_completion(&self) {
+        let mut tracker = self.tracker.lock().await;
+        let stats = &mut tracker.stats;
+        stats.requests += 1;
+
+        if stats.requests % SUMMARY_INTERVAL == 0 {
+            info!(
+                client = self.name,
+                total_requests = stats.requests,
+                rate_limit_wait_ms = stats.rate_limit_wait.as_millis() as u64,
+                backoff_wait_ms = stats.backoff_wait.as_millis() as u64,
+                avg_backoff_ms = stats.average_backoff().as_millis() as u64,
+                "Rate limiter summary"
+            );
         }
     }
 
@@ -127,34 +217,32 @@ impl RateLimiter {
         Fut: std::future::Future<Output = Result<T, E>>,
         E: std::fmt::Display,
     {
-        let _permit = self.semaphore.acquire().await.unwrap();
-        
         let config = {
             let tracker = self.tracker.lock().await;
             tracker.config.clone()
         };
 
         for attempt in 0..=config.max_retries {
-            // Check rate limit and wait if necessary
-            {
-                let mut tracker = self.tracker.lock().await;
-                if let Some(delay) = tracker.check_rate_limit() {
-                    debug!("Rate limit hit, waiting {:?}", delay);
-                    drop(tracker); // Release the lock before sleeping
-                    sleep(delay).await;
-                }
-            }
+            // The permit is per attempt, so a request backing off doesn't hold up those queued behind it
+            let permit = {
+                let _turn = self.admission.lock().await;
+                let permit = self.semaphore.acquire().await.unwrap();
+                self.wait_for_window().await;
+                permit
+            };
 
             // Execute the request
-            match request_fn().await {
+            metrics::counter!("api_requests_total", "client" => self.name).increment(1);
+            let outcome = request_fn().await;
+            drop(permit);
+            match outcome {
                 Ok(result) => {
-                    // Record successful request
-                    let mut tracker = self.tracker.lock().await;
-                    tracker.record_request();
                     debug!("Request successful on attempt {}", attempt + 1);
+                    self.record_completion().await;
                     return Ok(result);
                 }
                 Err(e) => {
+                    metrics::counter!("api_request_errors_total", "client" => self.name).increment(1);
                     if attempt < config.max_retries {
                         let backoff_duration = calculate_backoff(
                             config.initial_backoff,
@@ -170,9 +258,17 @@ impl RateLimiter {
                             backoff_duration
                         );
                         
+                        {
+                            let mut tracker = self.tracker.lock().await;
+                            tracker.stats.backoffs += 1;
+                            tracker.stats.backoff_wait += backoff_duration;
+                        }
+                        metrics::histogram!("rate_limiter_backoff_seconds", "client" => self.name)
+                            .record(backoff_duration.as_secs_f64());
                         sleep(backoff_duration).await;
                     } else {
                         error!("Request failed after {} attempts: {}", config.max_retries + 1, e);
+                        self.record_completion().await;
                         return Err(e);
                     }
                 }
@@ -182,36 +278,42 @@ impl RateLimiter {
         unreachable!()
     }
 
-    /// Execute a batch of requests with proper spacing
+    /// Execute a batch of requests, pausing `batch_delay` between chunks of `batch_size`. Requests
+    /// within a chunk run concurrently, bounded by the limiter's concurrency, and each is retried
+    /// like `execute`. Results come back in request order.
     pub async fn execute_batch<T, E, F, Fut>(
         &self,
         requests: Vec<F>,
         batch_size: usize,
-    ) -> Vec<Result<T, E>>
+    ) -> BatchResults<T, E>
     where
         F: Fn() -> Fut + Send,
         Fut: std::future::Future<Output = Result<T, E>> + Send,
         T: Send,
         E: std::fmt::Display + Send,
     {
-        let mut results = Vec::new();
+        let batch_delay = self.tracker.lock().await.config.batch_delay;
+        let mut results = Vec::with_capacity(requests.len());
         
-        for batch in requests.chunks(batch_size) {
+        for (index, batch) in requests.chunks(batch_size.max(1)).enumerate() {
+            // Space batches apart, with nothing to wait for after the last one
+            if index > 0 && !batch_delay.is_zero() {
+                sleep(batch_delay).await;
+            }
+
             let batch_futures: Vec<_> = batch
                 .iter()
                 .map(|req| self.execute(req))
                 .collect();
             
-            let batch_results = futures::future::join_all(batch_futures).await;
-            results.extend(batch_results);
-            
-            // Add a small delay between batches to be extra safe
-            if results.len() < requests.len() {
-                sleep(Duration::from_millis(100)).await;
-            }
+            results.extend(futures::future::join_all(batch_futures).await);
         }
         
-        results
+        let batch = BatchResults::new(results);
+        if batch.failed > 0 {
+            warn!("{} batch finished with {} of {} requests failed", self.name, batch.failed, requests.len());
+        }
+        batch
     }
 }
 
@@ -242,9 +344,10 @@ mod tests {
             max_backoff: Duration::from_secs(1),
             backoff_multiplier: 2.0,
             max_retries: 1,
+            batch_delay: Duration::from_millis(100),
         };
         
-        let rate_limiter = RateLimiter::new(config);
+        let rate_limiter = RateLimiter::new("test", config);
         
         // These should succeed quickly
         let start = Instant::now();
@@ -267,6 +370,16 @@ mod tests {
         assert!(elapsed >= Duration::from_millis(800)); // Should have waited
     }
 
+    #[test]
+    fn test_average_backoff() {
+        let mut stats = WaitStats::default();
+        assert_eq!(stats.average_backoff(), Duration::ZERO);
+
+        stats.backoffs = 2;
+        stats.backoff_wait = Duration::from_millis(300);
+        assert_eq!(stats.average_backoff(), Duration::from_millis(150));
+    }
+
     #[tokio::test]
     async fn test_exponential_backoff() {
         let backoff1 = calculate_backoff(
@@ -293,4 +406,118 @@ mod tests {
         );
         assert_eq!(backoff3, Duration::from_millis(150)); // Capped at max
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_execute_batch_runs_concurrently_and_keeps_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let rate_limiter = RateLimiter::new("test", RateLimitConfig::youtube_config());
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        // Later requests finish first, so the order comes from the batch rather than completion
+        let requests: Vec<_> = (0..4u64)
+            .map(|i| {
+                let in_flight = in_flight.clone();
+                let peak = peak.clone();
+                move || {
+                    let in_flight = in_flight.clone();
+                    let peak = peak.clone();
+                    async move {
+                        peak.fetch_max(in_flight.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                        sleep(Duration::from_millis(40 - i * 10)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<_, &str>(i)
+                    }
+                }
+            })
+            .collect();
+
+        let batch = rate_limiter.execute_batch(requests, 4).await;
+
+        assert_eq!(batch.results, vec![Ok(0), Ok(1), Ok(2), Ok(3)]);
+        assert!(peak.load(Ordering::SeqCst) > 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_queued_requests_start_in_submission_order() {
+        // Room for 8 requests per window and 2 at a time, so the rest queue behind the window
+        let config = RateLimitConfig {
+            max_requests: 8,
+            window_duration: Duration::from_millis(100),
+            max_retries: 0,
+            ..RateLimitConfig::default()
+        };
+        let rate_limiter = Arc::new(RateLimiter::new("test", config));
+        let started = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let start = Instant::now();
+        let mut tasks = Vec::new();
+        for i in 0..24u64 {
+            let rate_limiter = rate_limiter.clone();
+            let started = started.clone();
+            tasks.push(tokio::spawn(async move {
+                rate_limiter
+                    .execute(|| {
+                        let started = started.clone();
+                        async move {
+                            started.lock().unwrap().push(i);
+                            Ok::<_, &str>(i)
+                        }
+                    })
+                    .await
+            }));
+            // Each task queues before the next is submitted
+            sleep(Duration::from_millis(1)).await;
+        }
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(*started.lock().unwrap(), (0..24).collect::<Vec<_>>());
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_execute_batch_counts_successes_and_failures() {
+        let config = RateLimitConfig {
+            initial_backoff: Duration::from_millis(1),
+            max_retries: 1,
+            batch_delay: Duration::ZERO,
+            ..RateLimitConfig::default()
+        };
+        let rate_limiter = RateLimiter::new("test", config);
+
+        let requests: Vec<_> = (0..5u32)
+            .map(|i| move || async move { if i % 2 == 0 { Ok(i) } else { Err("odd") } })
+            .collect();
+        let batch = rate_limiter.execute_batch(requests, 2).await;
+
+        assert_eq!(batch.succeeded, 3);
+        assert_eq!(batch.failed, 2);
+        assert_eq!(batch.results, vec![Ok(0), Err("odd"), Ok(2), Err("odd"), Ok(4)]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_batch_only_sleeps_between_batches() {
+        let config = RateLimitConfig {
+            batch_delay: Duration::from_millis(200),
+            ..RateLimitConfig::default()
+        };
+        let rate_limiter = RateLimiter::new("test", config);
+        let requests = |count: u32| -> Vec<_> { (0..count).map(|i| move || async move { Ok::<_, &str>(i) }).collect() };
+
+        // A single batch has nothing to wait for
+        let start = Instant::now();
+        rate_limiter.execute_batch(requests(3), 3).await;
+        assert!(start.elapsed() < Duration::from_millis(200));
+
+        // Two batches wait once, not again after the last
+        let start = Instant::now();
+        let batch = rate_limiter.execute_batch(requests(4), 2).await;
+        let elapsed = start.elapsed();
+        assert_eq!(batch.succeeded, 4);
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_millis(400));
+    }
+}
@@ -0,0 +1,337 @@
+use crate::models::{ArtistFeatureVectors, Mood, Track};
+use crate::similarity::{feature_distance, SimilarityWeights};
+use std::collections::BTreeMap;
+
+/// How recommendation candidates are chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecommendationMode {
+    /// Nearest tracks by audio features
+    #[default]
+    Features,
+    /// Tracks sharing the most genres with the seeds' artists
+    Genre,
+    /// Only tracks sharing a genre with the seeds' artists, nearest by audio features
+    Hybrid,
+}
+
+impl RecommendationMode {
+    pub fn from_param(value: &str) -> Option<Self> {
+        match value {
+            "features" => Some(Self::Features),
+            "genre" => Some(Self::Genre),
+            "hybrid" => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+}
+
+/// A point in the feature space recommendations rank by, e.g. an artist's average sound
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeatureTarget {
+    pub valence: f64,
+    pub energy: f64,
+    pub danceability: f64,
+    pub tempo: f64,
+}
+
+impl FeatureTarget {
+    /// Mean of an artist's track features, or `None` if none of their tracks have any
+    pub fn centroid(vectors: &ArtistFeatureVectors) -> Option<Self> {
+        Some(Self {
+            valence: mean(&vectors.valence)?,
+            energy: mean(&vectors.energy)?,
+            danceability: mean(&vectors.danceability)?,
+            tempo: mean(&vectors.tempo)?,
+        })
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// Greedy maximal-marginal-relevance selection of `limit` tracks from `candidates`.
+///
+/// `diversity` runs from 0 (closest to the seeds only) to 1 (spread out as far
+/// as possible from the tracks already picked). Candidates should arrive
+/// closest-first, which also decides ties and the order used when the seeds
+/// have no features to measure against.
+pub fn select_diverse(seeds: &[Track], candidates: Vec<Track>, limit: usize, diversity: f64) -> Vec<Track> {
+    let diversity = diversity.clamp(0.0, 1.0);
+    let weights = SimilarityWeights::from_env();
+
+    let seed_distances: Option<Vec<f64>> = candidates
+        .iter()
+        .map(|candidate| {
+            let distances: Vec<f64> = seeds
+                .iter()
+                .filter_map(|seed| feature_distance(candidate, seed, &weights))
+                .collect();
+            if distances.is_empty() {
+                None
+            } else {
+                Some(distances.iter().sum::<f64>() / distances.len() as f64)
+            }
+        })
+        .collect();
+
+    let Some(seed_distances) = seed_distances else {
+        return candidates.into_iter().take(limit).collect();
+    };
+
+    let mut remaining: Vec<(Track, f64)> = candidates.into_iter().zip(seed_distances).collect();
+    let mut selected: Vec<Track> = Vec::with_capacity(limit.min(remaining.len()));
+
+    while selected.len() < limit && !remaining.is_empty() {
+        let mut best_index = 0;
+        let mut best_score = f64::NEG_INFINITY;
+
+        for (index, (candidate, seed_distance)) in remaining.iter().enumerate() {
+            // Distance to the nearest already-picked track rewards spreading out
+            let spread = selected
+                .iter()
+                .filter_map(|picked| feature_distance(candidate, picked, &weights))
+                .fold(None, |nearest: Option<f64>, d| Some(nearest.map_or(d, |n| n.min(d))))
+                .unwrap_or(0.0);
+            let score = diversity * spread - (1.0 - diversity) * seed_distance;
+
+            if score > best_score {
+                best_score = score;
+                best_index = index;
+            }
+        }
+
+        selected.push(remaining.remove(best_index).0);
+    }
+
+    selected
+}
+
+/// Each new track's `neighbours` nearest tracks by feature distance, as
+/// `(track_id, other_id, distance)` pairs with the ids in sorted order. Pairs
+/// between two existing tracks are never produced, so the graph's existing
+/// edges don't need recomputing after an import.
+pub fn similarity_pairs(tracks: &[Track], new_ids: &[String], neighbours: usize) -> Vec<(String, String, f64)> {
+    let mut pairs = BTreeMap::new();
+    let weights = SimilarityWeights::from_env();
+
+    for new_track in tracks.iter().filter(|track| new_ids.contains(&track.id)) {
+        let mut nearest: Vec<(&Track, f64)> = tracks
+            .iter()
+            .filter(|other| other.id != new_track.id)
+            .filter_map(|other| feature_distance(new_track, other, &weights).map(|distance| (other, distance)))
+            .collect();
+        nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        for (other, distance) in nearest.into_iter().take(neighbours) {
+            let key = if new_track.id < other.id {
+                (new_track.id.clone(), other.id.clone())
+            } else {
+                (other.id.clone(), new_track.id.clone())
+            };
+            pairs.insert(key, distance);
+        }
+    }
+
+    pairs.into_iter().map(|((a, b), distance)| (a, b, distance)).collect()
+}
+
+// Valence and energy at or above this count as high
+const MOOD_SPLIT: f64 = 0.5;
+// Happy, energetic tracks at or above this danceability are party tracks
+const PARTY_DANCEABILITY: f64 = 0.7;
+
+/// Which quadrant of valence and energy the track sits in, with danceability
+/// splitting the happy, energetic corner
+pub fn classify_mood(track: &Track) -> Mood {
+    let (Some(valence), Some(energy), Some(danceability)) = (track.valence, track.energy, track.danceability) else {
+        return Mood::Unknown;
+    };
+
+    match (valence >= MOOD_SPLIT, energy >= MOOD_SPLIT) {
+        (true, true) if danceability >= PARTY_DANCEABILITY => Mood::Party,
+        (true, true) => Mood::FeelGood,
+        (true, false) => Mood::Chill,
+        (false, true) => Mood::Intense,
+        (false, false) => Mood::Melancholy,
+    }
+}
+
+/// Playlist names to suggest for a mood, best fit first
+pub fn playlist_names(mood: Mood) -> [&'static str; 3] {
+    match mood {
+        Mood::Party => ["Dance Floor Fillers", "Party Starters", "Move Your Feet"],
+        Mood::FeelGood => ["Feel-Good Bangers", "Sunshine Anthems", "Good Vibes Only"],
+        Mood::Chill => ["Easy Sunday", "Mellow Mood", "Laid-Back Grooves"],
+        Mood::Intense => ["Adrenaline Rush", "Dark Energy", "Rage Fuel"],
+        Mood::Melancholy => ["Rainy Day Blues", "Late Night Feels", "Quiet Hours"],
+        Mood::Unknown => ["Fresh Finds", "Hand-Picked Mix", "Something Different"],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommendation_mode_from_param() {
+        assert_eq!(RecommendationMode::from_param("features"), Some(RecommendationMode::Features));
+        assert_eq!(RecommendationMode::from_param("genre"), Some(RecommendationMode::Genre));
+        assert_eq!(RecommendationMode::from_param("hybrid"), Some(RecommendationMode::Hybrid));
+        assert_eq!(RecommendationMode::from_param("Hybrid"), None);
+    }
+
+    #[test]
+    fn test_feature_target_centroid() {
+        let mut vectors = ArtistFeatureVectors {
+            artist_id: "artist".to_string(),
+            track_ids: vec!["a".to_string(), "b".to_string()],
+            track_names: vec!["A".to_string(), "B".to_string()],
+            danceability: vec![0.2, 0.4],
+            energy: vec![0.5, 0.9],
+            loudness: vec![-5.0, -7.0],
+            speechiness: vec![0.1, 0.1],
+            acousticness: vec![0.3, 0.1],
+            instrumentalness: vec![0.0, 0.0],
+            liveness: vec![0.1, 0.2],
+            valence: vec![0.6, 0.8],
+            tempo: vec![100.0, 140.0],
+        };
+
+        let centroid = FeatureTarget::centroid(&vectors).unwrap();
+        assert!((centroid.valence - 0.7).abs() < 1e-9);
+        assert!((centroid.energy - 0.7).abs() < 1e-9);
+        assert!((centroid.danceability - 0.3).abs() < 1e-9);
+        assert_eq!(centroid.tempo, 120.0);
+
+        vectors.tempo.clear();
+        assert_eq!(FeatureTarget::centroid(&vectors), None);
+    }
+
+    fn track(id: &str, energy: f64) -> Track {
+        Track {
+            id: id.to_string(),
+            name: id.to_string(),
+            artist_ids: vec![],
+            artist_names: vec![],
+            album_id: String::new(),
+            album_name: String::new(),
+            album_image_url: None,
+            duration_ms: 0,
+            popularity: 0,
+            explicit: false,
+            danceability: Some(0.5),
+            energy: Some(energy),
+            key: None,
+            loudness: None,
+            mode: None,
+            speechiness: None,
+            acousticness: None,
+            instrumentalness: None,
+            liveness: None,
+            valence: Some(0.5),
+            tempo: Some(120.0),
+            time_signature: None,
+            preview_url: None,
+            updated_at: None,
+            created_at: None,
+            raw_json: None,
+            album_total_tracks: None,
+            album_artists: Vec::new(),
+        }
+    }
+
+    fn ids(tracks: &[Track]) -> Vec<&str> {
+        tracks.iter().map(|t| t.id.as_str()).collect()
+    }
+
+    fn candidates() -> Vec<Track> {
+        vec![track("a", 0.51), track("b", 0.52), track("c", 0.53), track("d", 0.9)]
+    }
+
+    #[test]
+    fn test_zero_diversity_keeps_nearest_order() {
+        let seeds = [track("seed", 0.5)];
+        let picked = select_diverse(&seeds, candidates(), 3, 0.0);
+        assert_eq!(ids(&picked), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_diversity_spreads_results() {
+        let seeds = [track("seed", 0.5)];
+        let picked = select_diverse(&seeds, candidates(), 2, 0.7);
+        // The closest track still comes first, then the far one beats near-duplicates
+        assert_eq!(ids(&picked), ["a", "d"]);
+    }
+
+    #[test]
+    fn test_seeds_without_features_fall_back_to_candidate_order() {
+        let mut seed = track("seed", 0.5);
+        seed.energy = None;
+        let picked = select_diverse(&[seed], candidates(), 2, 1.0);
+        assert_eq!(ids(&picked), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_similarity_pairs_only_involve_new_tracks() {
+        let tracks = vec![track("a", 0.1), track("b", 0.2), track("c", 0.3), track("new", 0.25)];
+        let new_ids = vec!["new".to_string()];
+
+        let pairs = similarity_pairs(&tracks, &new_ids, 2);
+
+        assert_eq!(pairs.len(), 2);
+        assert!(pairs.iter().all(|(a, b, _)| a == "new" || b == "new"));
+        let neighbours: Vec<&str> = pairs
+            .iter()
+            .map(|(a, b, _)| if a == "new" { b.as_str() } else { a.as_str() })
+            .collect();
+        assert_eq!(neighbours, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_similarity_pairs_between_new_tracks_are_deduplicated() {
+        let tracks = vec![track("x", 0.5), track("y", 0.55), track("old", 0.9)];
+        let new_ids = vec!["x".to_string(), "y".to_string()];
+
+        let pairs = similarity_pairs(&tracks, &new_ids, 1);
+
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].0.as_str(), pairs[0].1.as_str()), ("x", "y"));
+    }
+
+    fn mood_of(valence: f64, energy: f64, danceability: f64) -> Mood {
+        let mut seed = track("seed", energy);
+        seed.valence = Some(valence);
+        seed.danceability = Some(danceability);
+        classify_mood(&seed)
+    }
+
+    #[test]
+    fn test_classify_mood_covers_each_quadrant() {
+        assert_eq!(mood_of(0.9, 0.9, 0.8), Mood::Party);
+        assert_eq!(mood_of(0.9, 0.9, 0.4), Mood::FeelGood);
+        assert_eq!(mood_of(0.8, 0.2, 0.9), Mood::Chill);
+        assert_eq!(mood_of(0.1, 0.9, 0.9), Mood::Intense);
+        assert_eq!(mood_of(0.1, 0.1, 0.1), Mood::Melancholy);
+    }
+
+    #[test]
+    fn test_classify_mood_boundaries_count_as_high() {
+        assert_eq!(mood_of(MOOD_SPLIT, MOOD_SPLIT, PARTY_DANCEABILITY), Mood::Party);
+        assert_eq!(mood_of(MOOD_SPLIT - 0.01, MOOD_SPLIT, 0.0), Mood::Intense);
+        assert_eq!(mood_of(MOOD_SPLIT, MOOD_SPLIT - 0.01, 1.0), Mood::Chill);
+    }
+
+    #[test]
+    fn test_classify_mood_needs_every_feature() {
+        let mut seed = track("seed", 0.9);
+        seed.danceability = None;
+        assert_eq!(classify_mood(&seed), Mood::Unknown);
+        assert_eq!(playlist_names(Mood::Unknown).len(), 3);
+        assert_eq!(playlist_names(Mood::FeelGood)[0], "Feel-Good Bangers");
+    }
+}
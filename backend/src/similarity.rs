@@ -0,0 +1,216 @@
+use crate::models::{FeatureDeltas, SimilarityBreakdown, Track};
+use neo4rs::Query;
+
+/// Tempo is divided by this to sit on the same 0-1-ish scale as the other features.
+///
+/// Nearly all tracks fall between 60 and 200 BPM, and most between 90 and 140, so a typical
+/// tempo gap is 10-50 BPM. Divided by 100 that's 0.1-0.5, comparable to gaps in the 0-1
+/// features. Dividing by 200, the old value, halved it and left tempo barely affecting the distance.
+pub const DEFAULT_TEMPO_SCALE: f64 = 100.0;
+
+/// How much each feature counts towards the distance between two tracks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimilarityWeights {
+    pub valence: f64,
+    pub energy: f64,
+    pub danceability: f64,
+    /// Applied after tempo is divided by `tempo_scale`
+    pub tempo: f64,
+    /// BPM difference that counts as much as a full 0-1 difference in another feature
+    pub tempo_scale: f64,
+}
+
+impl Default for SimilarityWeights {
+    fn default() -> Self {
+        Self { valence: 1.0, energy: 1.0, danceability: 1.0, tempo: 1.0, tempo_scale: DEFAULT_TEMPO_SCALE }
+    }
+}
+
+impl SimilarityWeights {
+    /// The default weights, with the tempo divisor from `SIMILARITY_TEMPO_SCALE` when it's set
+    pub fn from_env() -> Self {
+        let tempo_scale = std::env::var("SIMILARITY_TEMPO_SCALE").ok();
+        Self { tempo_scale: parse_tempo_scale(tempo_scale.as_deref()), ..Self::default() }
+    }
+
+    /// Bind the weights as `$valence_weight`, `$energy_weight`, `$danceability_weight` and
+    /// `$tempo_weight` so Cypher can rank by the same distance. The tempo weight already
+    /// includes `tempo_scale`, so Cypher multiplies raw BPM differences by it.
+    pub fn bind(&self, query: Query) -> Query {
+        query
+            .param("valence_weight", self.valence)
+            .param("energy_weight", self.energy)
+            .param("danceability_weight", self.danceability)
+            .param("tempo_weight", self.tempo / self.tempo_scale)
+    }
+}
+
+// A positive, finite divisor, or the default with a warning for anything else
+fn parse_tempo_scale(value: Option<&str>) -> f64 {
+    let Some(value) = value else {
+        return DEFAULT_TEMPO_SCALE;
+    };
+    match value.trim().parse::<f64>() {
+        Ok(scale) if scale.is_finite() && scale > 0.0 => scale,
+        _ => {
+            tracing::warn!("Invalid SIMILARITY_TEMPO_SCALE '{}', using {}", value, DEFAULT_TEMPO_SCALE);
+            DEFAULT_TEMPO_SCALE
+        }
+    }
+}
+
+/// Each feature's weighted share of the distance between two tracks,
+/// or `None` if either track is missing one of the features
+pub fn feature_breakdown(a: &Track, b: &Track, weights: &SimilarityWeights) -> Option<SimilarityBreakdown> {
+    let valence = weights.valence * (a.valence? - b.valence?).abs();
+    let energy = weights.energy * (a.energy? - b.energy?).abs();
+    let danceability = weights.danceability * (a.danceability? - b.danceability?).abs();
+    let tempo = weights.tempo * (a.tempo? - b.tempo?).abs() / weights.tempo_scale;
+
+    Some(SimilarityBreakdown {
+        valence,
+        energy,
+        danceability,
+        tempo,
+        distance: valence + energy + danceability + tempo,
+    })
+}
+
+/// Weighted distance between two tracks, lower is more similar.
+/// `None` if either track is missing one of the features it compares.
+pub fn feature_distance(a: &Track, b: &Track, weights: &SimilarityWeights) -> Option<f64> {
+    feature_breakdown(a, b, weights).map(|breakdown| breakdown.distance)
+}
+
+/// How far each feature of `b` is from `a`'s, signed
+pub fn feature_deltas(a: &Track, b: &Track) -> FeatureDeltas {
+    let delta = |a: Option<f64>, b: Option<f64>| Some(b? - a?);
+    FeatureDeltas {
+        danceability: delta(a.danceability, b.danceability),
+        energy: delta(a.energy, b.energy),
+        loudness: delta(a.loudness, b.loudness),
+        speechiness: delta(a.speechiness, b.speechiness),
+        acousticness: delta(a.acousticness, b.acousticness),
+        instrumentalness: delta(a.instrumentalness, b.instrumentalness),
+        liveness: delta(a.liveness, b.liveness),
+        valence: delta(a.valence, b.valence),
+        tempo: delta(a.tempo, b.tempo),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-9;
+
+    fn track(valence: f64, energy: f64, danceability: f64, tempo: f64) -> Track {
+        Track {
+            id: "t".to_string(),
+            name: "t".to_string(),
+            artist_ids: vec![],
+            artist_names: vec![],
+            album_id: String::new(),
+            album_name: String::new(),
+            album_image_url: None,
+            duration_ms: 0,
+            popularity: 0,
+            explicit: false,
+            danceability: Some(danceability),
+            energy: Some(energy),
+            key: None,
+            loudness: None,
+            mode: None,
+            speechiness: None,
+            acousticness: None,
+            instrumentalness: None,
+            liveness: None,
+            valence: Some(valence),
+            tempo: Some(tempo),
+            time_signature: None,
+            preview_url: None,
+            updated_at: None,
+            created_at: None,
+            raw_json: None,
+            album_total_tracks: None,
+            album_artists: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_distance_sums_absolute_differences() {
+        let a = track(0.2, 0.9, 0.5, 100.0);
+        let b = track(0.6, 0.4, 0.5, 140.0);
+        let weights = SimilarityWeights::default();
+
+        let breakdown = feature_breakdown(&a, &b, &weights).unwrap();
+        assert!((breakdown.valence - 0.4).abs() < EPSILON);
+        assert!((breakdown.energy - 0.5).abs() < EPSILON);
+        assert_eq!(breakdown.danceability, 0.0);
+        assert!((breakdown.tempo - 0.4).abs() < EPSILON);
+        assert!((breakdown.distance - 1.3).abs() < EPSILON);
+        assert_eq!(feature_distance(&a, &b, &weights), Some(breakdown.distance));
+    }
+
+    #[test]
+    fn test_distance_is_a_metric() {
+        let a = track(0.2, 0.9, 0.5, 100.0);
+        let b = track(0.6, 0.4, 0.7, 140.0);
+        let c = track(0.9, 0.1, 0.3, 90.0);
+        let weights = SimilarityWeights::default();
+        let d = |x: &Track, y: &Track| feature_distance(x, y, &weights).unwrap();
+
+        assert_eq!(d(&a, &a), 0.0);
+        assert!((d(&a, &b) - d(&b, &a)).abs() < EPSILON);
+        assert!(d(&a, &c) <= d(&a, &b) + d(&b, &c) + EPSILON);
+    }
+
+    #[test]
+    fn test_weights_scale_each_feature() {
+        let a = track(0.2, 0.9, 0.5, 100.0);
+        let b = track(0.6, 0.4, 0.5, 140.0);
+        let weights = SimilarityWeights { valence: 2.0, energy: 0.0, danceability: 1.0, tempo: 0.5, tempo_scale: 100.0 };
+
+        let breakdown = feature_breakdown(&a, &b, &weights).unwrap();
+        assert!((breakdown.valence - 0.8).abs() < EPSILON);
+        assert_eq!(breakdown.energy, 0.0);
+        assert!((breakdown.tempo - 0.2).abs() < EPSILON);
+        assert!((breakdown.distance - 1.0).abs() < EPSILON);
+
+        let wide = SimilarityWeights { tempo_scale: 200.0, ..SimilarityWeights::default() };
+        assert!((feature_breakdown(&a, &b, &wide).unwrap().tempo - 0.2).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_tempo_scale_falls_back_to_default() {
+        assert_eq!(parse_tempo_scale(None), DEFAULT_TEMPO_SCALE);
+        assert_eq!(parse_tempo_scale(Some(" 60 ")), 60.0);
+        assert_eq!(parse_tempo_scale(Some("0")), DEFAULT_TEMPO_SCALE);
+        assert_eq!(parse_tempo_scale(Some("-100")), DEFAULT_TEMPO_SCALE);
+        assert_eq!(parse_tempo_scale(Some("fast")), DEFAULT_TEMPO_SCALE);
+    }
+
+    #[test]
+    fn test_missing_features_have_no_distance() {
+        let a = track(0.2, 0.9, 0.5, 100.0);
+        let mut b = track(0.6, 0.4, 0.5, 140.0);
+        b.tempo = None;
+
+        assert!(feature_breakdown(&a, &b, &SimilarityWeights::default()).is_none());
+        assert_eq!(feature_distance(&b, &a, &SimilarityWeights::default()), None);
+    }
+
+    #[test]
+    fn test_feature_deltas_are_signed_and_skip_missing() {
+        let quiet = track(0.5, 0.2, 0.5, 120.0);
+        let mut loud = track(0.5, 0.9, 0.5, 140.0);
+        loud.valence = None;
+
+        let deltas = feature_deltas(&quiet, &loud);
+        assert!((deltas.energy.unwrap() - 0.7).abs() < EPSILON);
+        assert!((feature_deltas(&loud, &quiet).energy.unwrap() + 0.7).abs() < EPSILON);
+        assert_eq!(deltas.tempo, Some(20.0));
+        assert_eq!(deltas.valence, None);
+        assert_eq!(deltas.loudness, None);
+    }
+}
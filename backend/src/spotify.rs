@@ -1,50 +1,208 @@
-use crate::models::{Artist, Track};
+use crate::models::{AnalysisSection, Artist, ArtistImage, AudioAnalysis, AudioFeatures, PlaylistRange, Track};
 use crate::rate_limiter::{RateLimiter, RateLimitConfig};
-use reqwest::Client;
+use reqwest::{header::RETRY_AFTER, Client, Response, StatusCode};
 use serde_json::Value;
-use anyhow::{Result, anyhow};
 use tracing::{info, warn, error, debug, instrument};
 use serde::Deserialize;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub enum SpotifyError {
+    /// Missing credentials, or Spotify rejected the access token
+    Auth(String),
+    /// Spotify returned 429; `retry_after` is the `Retry-After` header in seconds
+    RateLimited { retry_after: Option<u64> },
+    NotFound(String),
+    Network(reqwest::Error),
+    Parse(String),
+    Api { status: StatusCode, body: String },
+}
+
+impl SpotifyError {
+    /// Classify a non-success response from the Spotify API
+    async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let body = response.text().await.unwrap_or_else(|_| "Unable to read error body".to_string());
+
+        match status {
+            StatusCode::UNAUTHORIZED => SpotifyError::Auth(body),
+            StatusCode::TOO_MANY_REQUESTS => SpotifyError::RateLimited { retry_after },
+            StatusCode::NOT_FOUND => SpotifyError::NotFound(body),
+            _ => SpotifyError::Api { status, body },
+        }
+    }
+}
+
+impl fmt::Display for SpotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpotifyError::Auth(message) => write!(f, "Spotify authentication failed: {}", message),
+            SpotifyError::RateLimited { retry_after: Some(seconds) } => {
+                write!(f, "Spotify rate limit exceeded, retry after {}s", seconds)
+            }
+            SpotifyError::RateLimited { retry_after: None } => write!(f, "Spotify rate limit exceeded"),
+            SpotifyError::NotFound(message) => write!(f, "Spotify resource not found: {}", message),
+            SpotifyError::Network(e) => write!(f, "Network error: {}", e),
+            SpotifyError::Parse(message) => write!(f, "Invalid Spotify response: {}", message),
+            SpotifyError::Api { status, body } => write!(f, "Spotify API error {}: {}", status, body),
+        }
+    }
+}
+
+impl std::error::Error for SpotifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SpotifyError::Network(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for SpotifyError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            SpotifyError::Parse(e.to_string())
+        } else {
+            SpotifyError::Network(e)
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, SpotifyError>;
+
+const SPOTIFY_API_URL: &str = "https://api.spotify.com/v1";
+const SPOTIFY_TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+/// Most playlist or library items Spotify returns per page
+const PLAYLIST_PAGE_SIZE: usize = 50;
+
+/// Tracks fetched from a `PlaylistRange`, with the position just past the last entry Spotify had.
+/// Entries that aren't tracks still count towards positions, so `end` can be past the last track.
+#[derive(Debug)]
+pub struct PlaylistSlice {
+    pub tracks: Vec<Track>,
+    pub end: usize,
+}
+
+// The subset of `/audio-analysis` kept; everything else in the response is ignored
+#[derive(Deserialize)]
+struct AnalysisResponse {
+    track: AnalysisTrack,
+    #[serde(default)]
+    sections: Vec<AnalysisSection>,
+}
+
+#[derive(Deserialize)]
+struct AnalysisTrack {
+    tempo_confidence: f64,
+}
 
 #[derive(Deserialize)]
 struct TokenResponse {
     access_token: String,
-    token_type: String,
     expires_in: u64,
 }
 
+/// How long before a Client Credentials token expires it's replaced, so requests in flight don't
+/// carry it past its expiry
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// An app token and when to replace it; tokens from `SPOTIFY_ACCESS_TOKEN` have no known expiry
+#[derive(Clone)]
+struct AccessToken {
+    value: String,
+    refresh_at: Option<Instant>,
+}
+
+/// The app's Client Credentials, from `SPOTIFY_CLIENT_ID` and `SPOTIFY_CLIENT_SECRET`
+struct ClientCredentials {
+    id: String,
+    secret: String,
+}
+
+impl ClientCredentials {
+    fn from_env() -> Option<Self> {
+        Some(Self {
+            id: std::env::var("SPOTIFY_CLIENT_ID").ok()?,
+            secret: std::env::var("SPOTIFY_CLIENT_SECRET").ok()?,
+        })
+    }
+}
+
+/// Whether `STORE_RAW_JSON` asks for raw track JSON to be kept, off by default
+fn store_raw_json_from_env() -> bool {
+    std::env::var("STORE_RAW_JSON")
+        .map(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
 pub struct SpotifyClient {
     client: Client,
     rate_limiter: Arc<RateLimiter>,
+    api_base_url: String,
+    token_url: String,
+    credentials: Option<ClientCredentials>,
+    /// Current access token, replaced as it nears expiry or when Spotify rejects it
+    access_token: Mutex<Option<AccessToken>>,
+    /// Keep each track's original JSON so it can be reprocessed later
+    store_raw_json: bool,
 }
 
 impl SpotifyClient {
-    pub fn new() -> Self {
+    /// Starts from `SPOTIFY_ACCESS_TOKEN` when set, otherwise requests a token on first use
+    pub fn new(client: Client) -> Self {
+        Self {
+            credentials: ClientCredentials::from_env(),
+            store_raw_json: store_raw_json_from_env(),
+            ..Self::with_endpoints(client, SPOTIFY_API_URL, SPOTIFY_TOKEN_URL, std::env::var("SPOTIFY_ACCESS_TOKEN").ok())
+        }
+    }
+
+    fn with_endpoints(client: Client, api_base_url: &str, token_url: &str, access_token: Option<String>) -> Self {
         Self {
-            client: Client::new(),
-            rate_limiter: Arc::new(RateLimiter::new(RateLimitConfig::spotify_config())),
+            client,
+            rate_limiter: Arc::new(RateLimiter::new("spotify", RateLimitConfig::spotify_config())),
+            api_base_url: api_base_url.to_string(),
+            token_url: token_url.to_string(),
+            credentials: None,
+            access_token: Mutex::new(access_token.map(|value| AccessToken { value, refresh_at: None })),
+            store_raw_json: false,
         }
     }
 
-    /// Get access token using Client Credentials flow
+    /// The current access token, requesting one if the client has none yet or it's about to expire
+    async fn access_token(&self) -> Result<String> {
+        let cached = self.access_token.lock().unwrap().clone();
+        match cached {
+            Some(token) if token.refresh_at.is_none_or(|refresh_at| Instant::now() < refresh_at) => Ok(token.value),
+            _ => self.get_access_token().await,
+        }
+    }
+
+    /// Get a new access token using Client Credentials flow and keep it for later requests
     #[instrument(skip(self))]
-    pub async fn get_access_token(&self) -> Result<String> {
-        let client_id = std::env::var("SPOTIFY_CLIENT_ID")
-            .map_err(|_| anyhow!("SPOTIFY_CLIENT_ID environment variable not found"))?;
-        let client_secret = std::env::var("SPOTIFY_CLIENT_SECRET")
-            .map_err(|_| anyhow!("SPOTIFY_CLIENT_SECRET environment variable not found"))?;
+    async fn get_access_token(&self) -> Result<String> {
+        let credentials = self.credentials.as_ref().ok_or_else(|| {
+            SpotifyError::Auth("SPOTIFY_CLIENT_ID and SPOTIFY_CLIENT_SECRET environment variables not found".to_string())
+        })?;
 
         debug!("Requesting new Spotify access token");
 
         let params = [
             ("grant_type", "client_credentials"),
-            ("client_id", &client_id),
-            ("client_secret", &client_secret),
+            ("client_id", &credentials.id),
+            ("client_secret", &credentials.secret),
         ];
 
         let response = self.client
-            .post("https://accounts.spotify.com/api/token")
+            .post(&self.token_url)
             .header("Content-Type", "application/x-www-form-urlencoded")
             .form(&params)
             .send()
@@ -53,43 +211,118 @@ impl SpotifyClient {
         if !response.status().is_success() {
             let error_text = response.text().await?;
             error!("Failed to get Spotify access token: {}", error_text);
-            return Err(anyhow!("Failed to get Spotify access token: {}", error_text));
+            return Err(SpotifyError::Auth(error_text));
         }
 
         let token_response: TokenResponse = response.json().await?;
         info!("Successfully obtained Spotify access token (expires in {} seconds)", token_response.expires_in);
-        
+
+        let lifetime = Duration::from_secs(token_response.expires_in).saturating_sub(TOKEN_REFRESH_MARGIN);
+        *self.access_token.lock().unwrap() = Some(AccessToken {
+            value: token_response.access_token.clone(),
+            refresh_at: Some(Instant::now() + lifetime),
+        });
         Ok(token_response.access_token)
     }
 
-    #[instrument(skip(self, access_token), fields(playlist_id = %playlist_id))]
-    pub async fn get_playlist_tracks(&self, playlist_id: &str, access_token: &str) -> Result<Vec<Track>> {
-        debug!("Starting playlist tracks fetch");
+    /// GET an API URL, requesting a fresh token and retrying once if Spotify answers 401
+    async fn get_authorized(&self, url: &str) -> Result<Response> {
+        let access_token = self.access_token().await?;
+        let response = self.send_get(url, &access_token).await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        warn!("Spotify rejected the access token, requesting a fresh one");
+        let access_token = self.get_access_token().await?;
+        self.send_get(url, &access_token).await
+    }
+
+    /// Only network failures (including timeouts), rate limiting and server errors are retried
+    async fn send_get(&self, url: &str, access_token: &str) -> Result<Response> {
+        let client = &self.client;
+        let auth_header = format!("Bearer {}", access_token);
+
+        self.rate_limiter.execute(|| async {
+            let response = client
+                .get(url)
+                .header("Authorization", &auth_header)
+                .send()
+                .await
+                .map_err(SpotifyError::Network)?;
+
+            if is_retryable_status(response.status()) {
+                return Err(SpotifyError::from_response(response).await);
+            }
+
+            Ok(response)
+        }).await
+    }
+
+    /// Every track in a playlist. With `with_features` unset the audio features lookups are
+    /// skipped and tracks come back without features.
+    pub async fn get_playlist_tracks(&self, playlist_id: &str, with_features: bool) -> Result<Vec<Track>> {
+        Ok(self.get_playlist_range(playlist_id, with_features, PlaylistRange::default()).await?.tracks)
+    }
+
+    /// The tracks at the positions in `range`. Pagination starts at the range's offset and stops
+    /// at its end, so nothing outside it is fetched.
+    #[instrument(skip(self), fields(playlist_id = %playlist_id))]
+    pub async fn get_playlist_range(
+        &self,
+        playlist_id: &str,
+        with_features: bool,
+        range: PlaylistRange,
+    ) -> Result<PlaylistSlice> {
+        let items_url = format!("{}/playlists/{}/tracks", self.api_base_url, playlist_id);
+        self.get_track_items(&items_url, None, with_features, range).await
+    }
+
+    /// The user's saved ("Liked Songs") tracks at the positions in `range`, newest first. Needs a
+    /// user access token with the `user-library-read` scope; it isn't refreshed if Spotify rejects
+    /// it. Audio features are still looked up with the app's own token.
+    #[instrument(skip(self, access_token))]
+    pub async fn get_saved_tracks(
+        &self,
+        access_token: &str,
+        with_features: bool,
+        range: PlaylistRange,
+    ) -> Result<PlaylistSlice> {
+        let items_url = format!("{}/me/tracks", self.api_base_url);
+        self.get_track_items(&items_url, Some(access_token), with_features, range).await
+    }
+
+    /// Page through a list of `{ "track": ... }` items, such as a playlist's or the user's
+    /// library, fetching each page's audio features in one batch. `user_token` is sent as is
+    /// instead of the app's token.
+    async fn get_track_items(
+        &self,
+        items_url: &str,
+        user_token: Option<&str>,
+        with_features: bool,
+        range: PlaylistRange,
+    ) -> Result<PlaylistSlice> {
+        debug!("Starting tracks fetch from {}", items_url);
         let mut tracks = Vec::new();
-        let mut offset = 0;
-        let limit = 50;
+        let mut offset = range.offset;
+        let end = range.limit.map(|limit| range.offset.saturating_add(limit));
         let mut page_count = 0;
 
         loop {
+            let limit = match end {
+                Some(end) if end <= offset => break,
+                Some(end) => (end - offset).min(PLAYLIST_PAGE_SIZE),
+                None => PLAYLIST_PAGE_SIZE,
+            };
             page_count += 1;
-            let url = format!(
-                "https://api.spotify.com/v1/playlists/{}/tracks?offset={}&limit={}",
-                playlist_id, offset, limit
-            );
+            let url = format!("{}?offset={}&limit={}", items_url, offset, limit);
 
-            debug!("Fetching playlist page {} (offset: {}, limit: {})", page_count, offset, limit);
+            debug!("Fetching page {} (offset: {}, limit: {})", page_count, offset, limit);
             let request_start = std::time::Instant::now();
-            let client = &self.client;
-            let auth_header = format!("Bearer {}", access_token);
-
-            let response = self.rate_limiter.execute(|| async {
-                client
-                    .get(&url)
-                    .header("Authorization", &auth_header)
-                    .send()
-                    .await
-                    .map_err(|e| anyhow!("Network error: {}", e))
-            }).await?;
+            let response = match user_token {
+                Some(user_token) => self.send_get(&url, user_token).await?,
+                None => self.get_authorized(&url).await?,
+            };
 
             let request_duration = request_start.elapsed();
             debug!(
@@ -99,11 +332,10 @@ impl SpotifyClient {
             );
 
             if !response.status().is_success() {
-                let status = response.status();
-                error!("Spotify API returned error status: {}", status);
-                let error_body = response.text().await.unwrap_or_else(|_| "Unable to read error body".to_string());
-                error!("Error response body: {}", error_body);
-                return Err(anyhow!("Failed to fetch playlist tracks: {} - {}", status, error_body));
+                error!("Spotify API returned error status: {}", response.status());
+                let error = SpotifyError::from_response(response).await;
+                error!("Failed to fetch tracks: {}", error);
+                return Err(error);
             }
 
             let parse_start = std::time::Instant::now();
@@ -111,7 +343,7 @@ impl SpotifyClient {
                 Ok(data) => data,
                 Err(e) => {
                     error!("Failed to parse JSON response: {}", e);
-                    return Err(anyhow!("Invalid JSON response: {}", e));
+                    return Err(SpotifyError::Parse(e.to_string()));
                 }
             };
             
@@ -128,37 +360,86 @@ impl SpotifyClient {
                 break;
             }
 
+            let page_data: Vec<&serde_json::Map<String, Value>> = items
+                .iter()
+                .filter_map(|item| item["track"].as_object())
+                .collect();
+            let features = if with_features {
+                self.features_for(&page_data).await
+            } else {
+                HashMap::new()
+            };
+
             let mut page_tracks = 0;
             let mut page_errors = 0;
-            for item in items {
-                if let Some(track_data) = item["track"].as_object() {
-                    match self.parse_track(track_data, access_token).await {
-                        Ok(track) => {
-                            tracks.push(track);
-                            page_tracks += 1;
-                        }
-                        Err(e) => {
-                            page_errors += 1;
-                            warn!("Failed to parse track: {}", e);
-                        }
+            for track_data in page_data {
+                let id = track_data.get("id").and_then(Value::as_str).unwrap_or_default();
+                match self.parse_track(track_data, features.get(id)) {
+                    Ok(track) => {
+                        tracks.push(track);
+                        page_tracks += 1;
+                    }
+                    Err(e) => {
+                        page_errors += 1;
+                        warn!("Failed to parse track: {}", e);
                     }
                 }
             }
 
             debug!("Page {} completed: {} tracks parsed, {} errors", page_count, page_tracks, page_errors);
-            offset += limit;
+            offset += items_count;
+            if items_count < limit {
+                break;
+            }
         }
 
-        info!("Playlist tracks fetch completed: {} total tracks from {} pages", tracks.len(), page_count);
-        Ok(tracks)
+        info!("Tracks fetch completed: {} total tracks from {} pages", tracks.len(), page_count);
+        Ok(PlaylistSlice { tracks, end: offset })
     }
 
-    async fn parse_track(&self, track_data: &serde_json::Map<String, Value>, access_token: &str) -> Result<Track> {
-        let id = track_data["id"].as_str().ok_or(anyhow!("Missing track id"))?.to_string();
-        let name = track_data["name"].as_str().ok_or(anyhow!("Missing track name"))?.to_string();
+    /// Audio features for a page of track objects in one batched lookup.
+    /// A failed lookup leaves every track without features rather than failing the page.
+    async fn features_for(&self, track_data: &[&serde_json::Map<String, Value>]) -> HashMap<String, AudioFeatures> {
+        let ids: Vec<&str> = track_data
+            .iter()
+            .filter_map(|data| data.get("id").and_then(Value::as_str))
+            .collect();
+
+        match self.get_tracks_features(&ids).await {
+            Ok(features) => features,
+            Err(e) => {
+                warn!("Failed to fetch audio features for {} tracks: {}", ids.len(), e);
+                HashMap::new()
+            }
+        }
+    }
+
+    fn parse_track(&self, track_data: &serde_json::Map<String, Value>, features: Option<&AudioFeatures>) -> Result<Track> {
+        let mut track = Self::track_from_json(track_data, features)?;
+        if self.store_raw_json {
+            track.raw_json = Some(Value::Object(track_data.clone()).to_string());
+        }
+        Ok(track)
+    }
+
+    /// Build a track from a Spotify track object without any network calls.
+    /// Only a missing `id` or `name` is an error; other absent or null fields get defaults.
+    pub fn track_from_json(
+        track_data: &serde_json::Map<String, Value>,
+        features: Option<&AudioFeatures>,
+    ) -> Result<Track> {
+        // Indexing the map itself panics on a missing key, so go through `Value::Null` instead
+        let field = |key: &str| track_data.get(key).unwrap_or(&Value::Null);
+
+        let id = field("id").as_str()
+            .ok_or_else(|| SpotifyError::Parse("Missing track id".to_string()))?
+            .to_string();
+        let name = field("name").as_str()
+            .ok_or_else(|| SpotifyError::Parse("Missing track name".to_string()))?
+            .to_string();
         
         let empty_vec = vec![];
-        let artists = track_data["artists"].as_array().unwrap_or(&empty_vec);
+        let artists = field("artists").as_array().unwrap_or(&empty_vec);
         let mut artist_ids = Vec::new();
         let mut artist_names = Vec::new();
         
@@ -171,12 +452,17 @@ impl SpotifyClient {
             }
         }
 
-        let album = &track_data["album"];
+        let album = field("album");
         let album_id = album["id"].as_str().unwrap_or("").to_string();
         let album_name = album["name"].as_str().unwrap_or("").to_string();
-        
-        // Get audio features
-        let audio_features = self.get_audio_features(&id, access_token).await?;
+        let album_image_url = album["images"][0]["url"].as_str().map(|s| s.to_string());
+        // Album artists without both an id and a name can't be stored, so they're left out
+        let album_artists = album["artists"]
+            .as_array()
+            .unwrap_or(&empty_vec)
+            .iter()
+            .filter_map(|artist| Some((artist["id"].as_str()?.to_string(), artist["name"].as_str()?.to_string())))
+            .collect();
 
         Ok(Track {
             id,
@@ -185,63 +471,119 @@ impl SpotifyClient {
             artist_names,
             album_id,
             album_name,
-            duration_ms: track_data["duration_ms"].as_i64().unwrap_or(0) as i32,
-            popularity: track_data["popularity"].as_i64().unwrap_or(0) as i32,
-            explicit: track_data["explicit"].as_bool().unwrap_or(false),
-            danceability: audio_features["danceability"].as_f64().unwrap_or(0.0),
-            energy: audio_features["energy"].as_f64().unwrap_or(0.0),
-            key: audio_features["key"].as_i64().unwrap_or(0) as i32,
-            loudness: audio_features["loudness"].as_f64().unwrap_or(0.0),
-            mode: audio_features["mode"].as_i64().unwrap_or(0) as i32,
-            speechiness: audio_features["speechiness"].as_f64().unwrap_or(0.0),
-            acousticness: audio_features["acousticness"].as_f64().unwrap_or(0.0),
-            instrumentalness: audio_features["instrumentalness"].as_f64().unwrap_or(0.0),
-            liveness: audio_features["liveness"].as_f64().unwrap_or(0.0),
-            valence: audio_features["valence"].as_f64().unwrap_or(0.0),
-            tempo: audio_features["tempo"].as_f64().unwrap_or(0.0),
-            time_signature: audio_features["time_signature"].as_i64().unwrap_or(4) as i32,
-            preview_url: track_data["preview_url"].as_str().map(|s| s.to_string()),
+            album_image_url,
+            duration_ms: field("duration_ms").as_i64().unwrap_or(0) as i32,
+            popularity: field("popularity").as_i64().unwrap_or(0) as i32,
+            explicit: field("explicit").as_bool().unwrap_or(false),
+            danceability: features.map(|f| f.danceability),
+            energy: features.map(|f| f.energy),
+            key: features.map(|f| f.key),
+            loudness: features.map(|f| f.loudness),
+            mode: features.map(|f| f.mode),
+            speechiness: features.map(|f| f.speechiness),
+            acousticness: features.map(|f| f.acousticness),
+            instrumentalness: features.map(|f| f.instrumentalness),
+            liveness: features.map(|f| f.liveness),
+            valence: features.map(|f| f.valence),
+            tempo: features.map(|f| f.tempo),
+            time_signature: features.map(|f| f.time_signature),
+            preview_url: field("preview_url").as_str().map(|s| s.to_string()),
+            updated_at: None,
+            created_at: None,
+            raw_json: None,
+            album_total_tracks: album["total_tracks"].as_i64().map(|v| v as i32),
+            album_artists,
         })
     }
 
-    async fn get_audio_features(&self, track_id: &str, access_token: &str) -> Result<Value> {
-        let url = format!("https://api.spotify.com/v1/audio-features/{}", track_id);
-        let client = &self.client;
-        let auth_header = format!("Bearer {}", access_token);
-        
-        self.rate_limiter.execute(|| async {
-            let response = client
-                .get(&url)
-                .header("Authorization", &auth_header)
-                .send()
-                .await
-                .map_err(|e| anyhow!("Request failed: {}", e))?;
+    /// Fetch audio features for a single track, returning `None` when Spotify has none
+    #[instrument(skip(self), fields(track_id = %track_id))]
+    pub async fn get_track_features(&self, track_id: &str) -> Result<Option<AudioFeatures>> {
+        let Some(data) = self.get_audio_features(track_id).await? else {
+            return Ok(None);
+        };
 
-            if response.status().is_success() {
-                response.json().await.map_err(|e| anyhow!("JSON parse failed: {}", e))
-            } else {
-                // Return empty object if audio features not available
-                Ok(serde_json::json!({}))
+        if data.as_object().is_none_or(|fields| fields.is_empty()) {
+            debug!("No audio features available");
+            return Ok(None);
+        }
+
+        Ok(Some(parse_audio_features(&data)))
+    }
+
+    /// Fetch a track's audio analysis sections, returning `None` when Spotify won't provide one
+    #[instrument(skip(self), fields(track_id = %track_id))]
+    pub async fn get_audio_analysis(&self, track_id: &str) -> Result<Option<AudioAnalysis>> {
+        let url = format!("{}/audio-analysis/{}", self.api_base_url, track_id);
+        let response = self.get_authorized(&url).await?;
+
+        let status = response.status();
+        if status == StatusCode::FORBIDDEN || status == StatusCode::NOT_FOUND {
+            debug!("Spotify has no audio analysis for track {} ({})", track_id, status);
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(SpotifyError::from_response(response).await);
+        }
+
+        let analysis: AnalysisResponse = response.json().await?;
+        Ok(Some(AudioAnalysis {
+            track_id: track_id.to_string(),
+            tempo_confidence: analysis.track.tempo_confidence,
+            sections: analysis.sections,
+        }))
+    }
+
+    /// Fetch audio features for many tracks, batching ids to Spotify's limit per request.
+    /// Tracks Spotify has no features for are absent from the map.
+    #[instrument(skip(self, track_ids), fields(track_count = track_ids.len()))]
+    pub async fn get_tracks_features(&self, track_ids: &[&str]) -> Result<HashMap<String, AudioFeatures>> {
+        let mut features = HashMap::new();
+
+        for chunk in track_ids.chunks(AUDIO_FEATURES_BATCH_SIZE) {
+            let url = format!("{}/audio-features?ids={}", self.api_base_url, chunk.join(","));
+            let response = self.get_authorized(&url).await?;
+
+            let status = response.status();
+            if status == StatusCode::FORBIDDEN || status == StatusCode::NOT_FOUND {
+                debug!("Spotify has no audio features for this batch ({})", status);
+                continue;
             }
-        }).await
+            if !status.is_success() {
+                return Err(SpotifyError::from_response(response).await);
+            }
+
+            let data: Value = response.json().await?;
+            features.extend(parse_audio_features_batch(&data));
+        }
+
+        debug!("Fetched audio features for {} of {} tracks", features.len(), track_ids.len());
+        Ok(features)
     }
 
-    #[instrument(skip(self, access_token), fields(artist_id = %artist_id))]
-    pub async fn get_artist(&self, artist_id: &str, access_token: &str) -> Result<Artist> {
+    /// Returns `None` when Spotify definitively has no features for the track
+    async fn get_audio_features(&self, track_id: &str) -> Result<Option<Value>> {
+        let url = format!("{}/audio-features/{}", self.api_base_url, track_id);
+        let response = self.get_authorized(&url).await?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(Some(response.json().await?))
+        } else if status == StatusCode::FORBIDDEN || status == StatusCode::NOT_FOUND {
+            debug!("Spotify has no audio features for track {} ({})", track_id, status);
+            Ok(None)
+        } else {
+            Err(SpotifyError::from_response(response).await)
+        }
+    }
+
+    #[instrument(skip(self), fields(artist_id = %artist_id))]
+    pub async fn get_artist(&self, artist_id: &str) -> Result<Artist> {
         debug!("Fetching artist details");
-        let url = format!("https://api.spotify.com/v1/artists/{}", artist_id);
-        let client = &self.client;
-        let auth_header = format!("Bearer {}", access_token);
-        
+        let url = format!("{}/artists/{}", self.api_base_url, artist_id);
+
         let request_start = std::time::Instant::now();
-        let response = self.rate_limiter.execute(|| async {
-            client
-                .get(&url)
-                .header("Authorization", &auth_header)
-                .send()
-                .await
-                .map_err(|e| anyhow!("Network error: {}", e))
-        }).await?;
+        let response = self.get_authorized(&url).await?;
 
         let request_duration = request_start.elapsed();
         debug!(
@@ -251,11 +593,10 @@ impl SpotifyClient {
         );
 
         if !response.status().is_success() {
-            let status = response.status();
-            error!("Spotify Artist API returned error status: {}", status);
-            let error_body = response.text().await.unwrap_or_else(|_| "Unable to read error body".to_string());
-            error!("Error response body: {}", error_body);
-            return Err(anyhow!("Failed to fetch artist: {} - {}", status, error_body));
+            error!("Spotify Artist API returned error status: {}", response.status());
+            let error = SpotifyError::from_response(response).await;
+            error!("Failed to fetch artist: {}", error);
+            return Err(error);
         }
 
         let parse_start = std::time::Instant::now();
@@ -263,44 +604,62 @@ impl SpotifyClient {
             Ok(data) => data,
             Err(e) => {
                 error!("Failed to parse artist JSON response: {}", e);
-                return Err(anyhow!("Invalid JSON response: {}", e));
+                return Err(SpotifyError::Parse(e.to_string()));
             }
         };
         
         debug!("Parsed artist JSON response in {:.3}s", parse_start.elapsed().as_secs_f64());
-        
-        let genres = data["genres"].as_array()
-            .map(|arr| arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect())
-            .unwrap_or_default();
-
-        let image_url = data["images"].as_array()
-            .and_then(|images| images.first())
-            .and_then(|img| img["url"].as_str())
-            .map(|s| s.to_string());
-
-        let artist_name = data["name"].as_str().ok_or(anyhow!("Missing artist name"))?.to_string();
-        debug!("Successfully parsed artist: {}", artist_name);
-
-        Ok(Artist {
-            id: data["id"].as_str().ok_or(anyhow!("Missing artist id"))?.to_string(),
-            name: artist_name,
-            genres,
-            popularity: data["popularity"].as_i64().unwrap_or(0) as i32,
-            followers: data["followers"]["total"].as_i64().unwrap_or(0) as i32,
-            image_url,
-        })
+
+        let artist = artist_from_json(&data)?;
+        debug!("Successfully parsed artist: {}", artist.name);
+        Ok(artist)
     }
 
+    /// Fetch many artists, batching ids to Spotify's limit per request.
+    /// Ids Spotify doesn't know are left out of the result.
+    #[instrument(skip(self, artist_ids), fields(artist_count = artist_ids.len()))]
+    pub async fn get_artists(&self, artist_ids: &[&str]) -> Result<Vec<Artist>> {
+        let mut artists = Vec::with_capacity(artist_ids.len());
+
+        for chunk in artist_ids.chunks(ARTISTS_BATCH_SIZE) {
+            let url = format!("{}/artists?ids={}", self.api_base_url, chunk.join(","));
+            let response = self.get_authorized(&url).await?;
+
+            if !response.status().is_success() {
+                return Err(SpotifyError::from_response(response).await);
+            }
+
+            let data: Value = response.json().await?;
+            let empty_vec = vec![];
+            for entry in data["artists"].as_array().unwrap_or(&empty_vec) {
+                // Unknown ids come back as `null`
+                if entry.is_null() {
+                    continue;
+                }
+                match artist_from_json(entry) {
+                    Ok(artist) => artists.push(artist),
+                    Err(e) => warn!("Failed to parse artist: {}", e),
+                }
+            }
+        }
+
+        debug!("Fetched {} of {} artists", artists.len(), artist_ids.len());
+        Ok(artists)
+    }
+
+    /// Spotify's own recommendations for up to five seed tracks. `user_token` is sent as is instead
+    /// of the app's token.
     pub async fn get_recommendations(&self, 
+        user_token: Option<&str>,
         seed_tracks: &[String], 
         target_valence: Option<f64>,
         target_energy: Option<f64>,
         target_danceability: Option<f64>,
         limit: i32,
-        access_token: &str
     ) -> Result<Vec<Track>> {
         let mut url = format!(
-            "https://api.spotify.com/v1/recommendations?seed_tracks={}&limit={}",
+            "{}/recommendations?seed_tracks={}&limit={}",
+            self.api_base_url,
             seed_tracks.join(","),
             limit
         );
@@ -315,22 +674,29 @@ impl SpotifyClient {
             url.push_str(&format!("&target_danceability={}", danceability));
         }
 
-        let response = self.client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?;
+        let response = match user_token {
+            Some(user_token) => self.send_get(&url, user_token).await?,
+            None => self.get_authorized(&url).await?,
+        };
 
         if !response.status().is_success() {
-            return Err(anyhow!("Failed to get recommendations: {}", response.status()));
+            return Err(SpotifyError::from_response(response).await);
         }
 
         let data: Value = response.json().await?;
-        let tracks = data["tracks"].as_array().ok_or(anyhow!("Invalid response format"))?;
+        let tracks = data["tracks"].as_array()
+            .ok_or_else(|| SpotifyError::Parse("Missing tracks array".to_string()))?;
         
+        let track_data: Vec<&serde_json::Map<String, Value>> = tracks
+            .iter()
+            .filter_map(Value::as_object)
+            .collect();
+        let features = self.features_for(&track_data).await;
+
         let mut result_tracks = Vec::new();
-        for track_value in tracks {
-            if let Ok(track) = self.parse_track(track_value.as_object().unwrap(), access_token).await {
+        for data in track_data {
+            let id = data.get("id").and_then(Value::as_str).unwrap_or_default();
+            if let Ok(track) = self.parse_track(data, features.get(id)) {
                 result_tracks.push(track);
             }
         }
@@ -338,3 +704,646 @@ impl SpotifyClient {
         Ok(result_tracks)
     }
 }
+
+/// Spotify's cap on ids per `/artists` request
+const ARTISTS_BATCH_SIZE: usize = 50;
+
+/// An artist from a Spotify artist object
+fn artist_from_json(data: &Value) -> Result<Artist> {
+    let genres = data["genres"].as_array()
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect())
+        .unwrap_or_default();
+
+    // Spotify lists images largest first
+    let image_urls: Vec<ArtistImage> = data["images"].as_array()
+        .map(|images| {
+            images.iter()
+                .filter_map(|img| {
+                    Some(ArtistImage {
+                        url: img["url"].as_str()?.to_string(),
+                        width: img["width"].as_i64().map(|width| width as i32),
+                        height: img["height"].as_i64().map(|height| height as i32),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let image_url = image_urls.first().map(|image| image.url.clone());
+
+    Ok(Artist {
+        id: data["id"].as_str()
+            .ok_or_else(|| SpotifyError::Parse("Missing artist id".to_string()))?
+            .to_string(),
+        name: data["name"].as_str()
+            .ok_or_else(|| SpotifyError::Parse("Missing artist name".to_string()))?
+            .to_string(),
+        genres,
+        popularity: data["popularity"].as_i64().unwrap_or(0) as i32,
+        followers: data["followers"]["total"].as_i64().unwrap_or(0) as i32,
+        image_url,
+        image_urls,
+        updated_at: None,
+        created_at: None,
+    })
+}
+
+/// Spotify's cap on ids per `/audio-features` request
+const AUDIO_FEATURES_BATCH_SIZE: usize = 100;
+
+/// Features by track id from a `/audio-features?ids=` response, which has `null` for unknown tracks
+fn parse_audio_features_batch(data: &Value) -> HashMap<String, AudioFeatures> {
+    let empty_vec = vec![];
+    data["audio_features"]
+        .as_array()
+        .unwrap_or(&empty_vec)
+        .iter()
+        .filter_map(|entry| {
+            let id = entry["id"].as_str()?;
+            Some((id.to_string(), parse_audio_features(entry)))
+        })
+        .collect()
+}
+
+fn parse_audio_features(audio_features: &Value) -> AudioFeatures {
+    AudioFeatures {
+        danceability: audio_features["danceability"].as_f64().unwrap_or(0.0),
+        energy: audio_features["energy"].as_f64().unwrap_or(0.0),
+        key: audio_features["key"].as_i64().unwrap_or(0) as i32,
+        loudness: audio_features["loudness"].as_f64().unwrap_or(0.0),
+        mode: audio_features["mode"].as_i64().unwrap_or(0) as i32,
+        speechiness: audio_features["speechiness"].as_f64().unwrap_or(0.0),
+        acousticness: audio_features["acousticness"].as_f64().unwrap_or(0.0),
+        instrumentalness: audio_features["instrumentalness"].as_f64().unwrap_or(0.0),
+        liveness: audio_features["liveness"].as_f64().unwrap_or(0.0),
+        valence: audio_features["valence"].as_f64().unwrap_or(0.0),
+        tempo: audio_features["tempo"].as_f64().unwrap_or(0.0),
+        time_signature: audio_features["time_signature"].as_i64().unwrap_or(4) as i32,
+    }
+}
+
+/// Whether a failed Spotify response is worth retrying
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        http::{HeaderMap, StatusCode as AxumStatusCode},
+        response::IntoResponse,
+        routing::{get, post},
+        Json, Router,
+    };
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serve a stub Spotify API on a random local port and return its address
+    async fn spawn_stub(app: Router) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        addr
+    }
+
+    #[test]
+    fn test_artist_keeps_every_image_size() {
+        let artist = artist_from_json(&json!({
+            "id": "a1",
+            "name": "Slowdive",
+            "images": [
+                { "url": "https://i.scdn.co/image/640", "width": 640, "height": 640 },
+                { "url": "https://i.scdn.co/image/160", "width": null, "height": null }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(artist.image_url.as_deref(), Some("https://i.scdn.co/image/640"));
+        assert_eq!(artist.image_urls.len(), 2);
+        assert_eq!(artist.image_urls[0].width, Some(640));
+        assert_eq!(artist.image_urls[1].url, "https://i.scdn.co/image/160");
+        assert_eq!(artist.image_urls[1].height, None);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+
+        assert!(!is_retryable_status(StatusCode::OK));
+        assert!(!is_retryable_status(StatusCode::FORBIDDEN));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+        assert!(!is_retryable_status(StatusCode::UNAUTHORIZED));
+    }
+
+    fn track_json(value: Value) -> serde_json::Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            other => panic!("expected a JSON object, got {}", other),
+        }
+    }
+
+    fn features() -> AudioFeatures {
+        AudioFeatures {
+            danceability: 0.5,
+            energy: 0.8,
+            key: 5,
+            loudness: -6.0,
+            mode: 1,
+            speechiness: 0.04,
+            acousticness: 0.1,
+            instrumentalness: 0.0,
+            liveness: 0.12,
+            valence: 0.6,
+            tempo: 120.0,
+            time_signature: 4,
+        }
+    }
+
+    #[test]
+    fn test_track_from_json_full_track() {
+        let data = track_json(json!({
+            "id": "t1",
+            "name": "Song",
+            "artists": [{ "id": "a1", "name": "Band" }, { "id": "a2", "name": "Guest" }],
+            "album": {
+                "id": "al1",
+                "name": "Record",
+                "total_tracks": 12,
+                "images": [{ "url": "https://i.scdn.co/image/1" }],
+                "artists": [{ "id": "va", "name": "Various Artists" }]
+            },
+            "duration_ms": 215000,
+            "popularity": 64,
+            "explicit": true,
+            "preview_url": "https://p.scdn.co/mp3-preview/1",
+            "is_local": false,
+            "available_markets": ["US", "GB"]
+        }));
+
+        let track = SpotifyClient::track_from_json(&data, Some(&features())).unwrap();
+
+        assert_eq!(track.id, "t1");
+        assert_eq!(track.artist_ids, vec!["a1", "a2"]);
+        assert_eq!(track.artist_names, vec!["Band", "Guest"]);
+        assert_eq!(track.album_id, "al1");
+        assert_eq!(track.album_image_url.as_deref(), Some("https://i.scdn.co/image/1"));
+        assert_eq!(track.album_total_tracks, Some(12));
+        assert_eq!(track.album_artists, vec![("va".to_string(), "Various Artists".to_string())]);
+        assert_eq!(track.duration_ms, 215000);
+        assert_eq!(track.popularity, 64);
+        assert!(track.explicit);
+        assert_eq!(track.energy, Some(0.8));
+        assert_eq!(track.time_signature, Some(4));
+    }
+
+    #[test]
+    fn test_track_from_json_defaults_missing_fields() {
+        // No album, no artists, null duration, no popularity, explicit or preview
+        let data = track_json(json!({
+            "id": "t1",
+            "name": "Song",
+            "artists": [],
+            "duration_ms": null
+        }));
+
+        let track = SpotifyClient::track_from_json(&data, None).unwrap();
+
+        assert!(track.artist_ids.is_empty());
+        assert!(track.artist_names.is_empty());
+        assert_eq!(track.album_id, "");
+        assert_eq!(track.album_name, "");
+        assert_eq!(track.album_image_url, None);
+        assert_eq!(track.album_total_tracks, None);
+        assert!(track.album_artists.is_empty());
+        assert_eq!(track.duration_ms, 0);
+        assert_eq!(track.popularity, 0);
+        assert!(!track.explicit);
+        assert_eq!(track.preview_url, None);
+        // Missing features stay missing rather than becoming zero
+        assert_eq!(track.energy, None);
+        assert_eq!(track.tempo, None);
+    }
+
+    #[test]
+    fn test_track_from_json_tolerates_partial_nested_objects() {
+        let data = track_json(json!({
+            "id": "t1",
+            "name": "Song",
+            "artists": [{ "name": "No ID Artist" }, { "id": "a2" }],
+            "album": { "name": "Record", "images": [], "artists": [{ "name": "No ID" }, { "id": "a3", "name": "Compiler" }] }
+        }));
+
+        let track = SpotifyClient::track_from_json(&data, None).unwrap();
+
+        assert_eq!(track.artist_ids, vec!["a2"]);
+        assert_eq!(track.artist_names, vec!["No ID Artist"]);
+        assert_eq!(track.album_id, "");
+        assert_eq!(track.album_name, "Record");
+        assert_eq!(track.album_image_url, None);
+        assert_eq!(track.album_artists, vec![("a3".to_string(), "Compiler".to_string())]);
+    }
+
+    #[test]
+    fn test_track_from_json_requires_id_and_name() {
+        let missing_id = track_json(json!({ "name": "Song" }));
+        let null_id = track_json(json!({ "id": null, "name": "Song" }));
+        let missing_name = track_json(json!({ "id": "t1" }));
+
+        assert!(matches!(SpotifyClient::track_from_json(&missing_id, None), Err(SpotifyError::Parse(_))));
+        assert!(matches!(SpotifyClient::track_from_json(&null_id, None), Err(SpotifyError::Parse(_))));
+        assert!(matches!(SpotifyClient::track_from_json(&missing_name, None), Err(SpotifyError::Parse(_))));
+    }
+
+    #[test]
+    fn test_parse_audio_features_batch_skips_nulls() {
+        let data = json!({
+            "audio_features": [
+                { "id": "t1", "energy": 0.8, "tempo": 120.0, "time_signature": 3 },
+                null,
+                { "energy": 0.1 }
+            ]
+        });
+
+        let features = parse_audio_features_batch(&data);
+
+        assert_eq!(features.len(), 1);
+        assert_eq!(features["t1"].energy, 0.8);
+        assert_eq!(features["t1"].time_signature, 3);
+        assert!(parse_audio_features_batch(&json!({})).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_audio_analysis_keeps_sections_and_treats_forbidden_as_none() {
+        let app = Router::new().route("/v1/audio-analysis/:id", get(|axum::extract::Path(id): axum::extract::Path<String>| async move {
+            if id == "locked" {
+                return AxumStatusCode::FORBIDDEN.into_response();
+            }
+            Json(json!({
+                "meta": { "analyzer_version": "4.0.0" },
+                "track": { "tempo": 118.2, "tempo_confidence": 0.73 },
+                "beats": [{ "start": 0.5, "duration": 0.5, "confidence": 0.9 }],
+                "sections": [
+                    { "start": 0.0, "duration": 12.5, "confidence": 1.0, "tempo": 118.0, "key": 7, "mode": 1 },
+                    { "start": 12.5, "duration": 30.1, "confidence": 0.6, "tempo": 121.4, "key": -1, "mode": -1 }
+                ]
+            }))
+            .into_response()
+        }));
+        let addr = spawn_stub(app).await;
+
+        let client = SpotifyClient::with_endpoints(
+            Client::new(),
+            &format!("http://{}/v1", addr),
+            &format!("http://{}/api/token", addr),
+            Some("token".to_string()),
+        );
+
+        let analysis = client.get_audio_analysis("t1").await.unwrap().unwrap();
+        assert_eq!(analysis.track_id, "t1");
+        assert_eq!(analysis.tempo_confidence, 0.73);
+        assert_eq!(
+            analysis.sections,
+            vec![
+                AnalysisSection { start: 0.0, duration: 12.5, tempo: 118.0, key: 7 },
+                AnalysisSection { start: 12.5, duration: 30.1, tempo: 121.4, key: -1 },
+            ]
+        );
+
+        assert!(client.get_audio_analysis("locked").await.unwrap().is_none());
+    }
+
+    // As `new` reads them from `SPOTIFY_CLIENT_ID` and `SPOTIFY_CLIENT_SECRET`
+    fn with_credentials(client: SpotifyClient) -> SpotifyClient {
+        SpotifyClient {
+            credentials: Some(ClientCredentials { id: "test-client".to_string(), secret: "test-secret".to_string() }),
+            ..client
+        }
+    }
+
+    // A token endpoint that counts how often it's asked for a token, handing out ones that last `expires_in` seconds
+    async fn token_stub(requests: Arc<AtomicUsize>, access_token: Option<String>, expires_in: u64) -> SpotifyClient {
+        let app = Router::new().route("/api/token", post(move || async move {
+            requests.fetch_add(1, Ordering::SeqCst);
+            Json(json!({ "access_token": "generated", "expires_in": expires_in }))
+        }));
+        let addr = spawn_stub(app).await;
+        with_credentials(SpotifyClient::with_endpoints(
+            Client::new(),
+            &format!("http://{}/v1", addr),
+            &format!("http://{}/api/token", addr),
+            access_token,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_access_token_uses_env_token() {
+        let token_requests = Arc::new(AtomicUsize::new(0));
+        // As `new` seeds it from `SPOTIFY_ACCESS_TOKEN`
+        let client = token_stub(token_requests.clone(), Some("env-token".to_string()), 3600).await;
+
+        assert_eq!(client.access_token().await.unwrap(), "env-token");
+        assert_eq!(token_requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_access_token_generates_and_caches_token() {
+        let token_requests = Arc::new(AtomicUsize::new(0));
+        let client = token_stub(token_requests.clone(), None, 3600).await;
+
+        assert_eq!(client.access_token().await.unwrap(), "generated");
+        assert_eq!(client.access_token().await.unwrap(), "generated");
+        assert_eq!(token_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_access_token_is_replaced_before_it_expires() {
+        let token_requests = Arc::new(AtomicUsize::new(0));
+        // Already within the refresh margin when it's handed out
+        let client = token_stub(token_requests.clone(), None, 30).await;
+
+        client.access_token().await.unwrap();
+        client.access_token().await.unwrap();
+        assert_eq!(token_requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_access_token_needs_credentials() {
+        let client = SpotifyClient::with_endpoints(Client::new(), "http://127.0.0.1:1/v1", "http://127.0.0.1:1/api/token", None);
+        assert!(matches!(client.access_token().await, Err(SpotifyError::Auth(_))));
+    }
+
+    #[tokio::test]
+    async fn test_playlist_page_fetches_features_in_one_batch() {
+        let feature_requests = Arc::new(AtomicUsize::new(0));
+        let counter = feature_requests.clone();
+        let app = Router::new()
+            .route("/v1/playlists/:id/tracks", get(|axum::extract::Query(query): axum::extract::Query<HashMap<String, String>>| async move {
+                let items = if query.get("offset").map(String::as_str) == Some("0") {
+                    json!([
+                        { "track": { "id": "t1", "name": "One" } },
+                        { "track": { "id": "t2", "name": "Two" } },
+                        { "track": null }
+                    ])
+                } else {
+                    json!([])
+                };
+                Json(json!({ "items": items }))
+            }))
+            .route("/v1/audio-features", get(move |axum::extract::Query(query): axum::extract::Query<HashMap<String, String>>| async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                assert_eq!(query.get("ids").map(String::as_str), Some("t1,t2"));
+                Json(json!({ "audio_features": [{ "id": "t1", "energy": 0.9 }, null] }))
+            }));
+        let addr = spawn_stub(app).await;
+
+        let client = SpotifyClient::with_endpoints(
+            Client::new(),
+            &format!("http://{}/v1", addr),
+            &format!("http://{}/api/token", addr),
+            Some("token".to_string()),
+        );
+
+        let tracks = client.get_playlist_tracks("playlist1", true).await.unwrap();
+
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].energy, Some(0.9));
+        assert_eq!(tracks[1].energy, None);
+        assert_eq!(feature_requests.load(Ordering::SeqCst), 1);
+
+        let tracks = client.get_playlist_tracks("playlist1", false).await.unwrap();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].energy, None);
+        assert_eq!(feature_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_playlist_range_only_fetches_requested_pages() {
+        let pages = Arc::new(Mutex::new(Vec::new()));
+        let seen = pages.clone();
+        // A 120-entry playlist whose entries are named by position
+        let app = Router::new().route("/v1/playlists/:id/tracks", get(move |axum::extract::Query(query): axum::extract::Query<HashMap<String, String>>| async move {
+            let offset: usize = query["offset"].parse().unwrap();
+            let limit: usize = query["limit"].parse().unwrap();
+            seen.lock().unwrap().push((offset, limit));
+            let items: Vec<Value> = (offset..(offset + limit).min(120))
+                .map(|position| json!({ "track": { "id": format!("t{}", position), "name": "Track" } }))
+                .collect();
+            Json(json!({ "items": items }))
+        }));
+        let addr = spawn_stub(app).await;
+
+        let client = SpotifyClient::with_endpoints(
+            Client::new(),
+            &format!("http://{}/v1", addr),
+            &format!("http://{}/api/token", addr),
+            Some("token".to_string()),
+        );
+
+        let slice = client
+            .get_playlist_range("playlist1", false, PlaylistRange { offset: 30, limit: Some(60) })
+            .await
+            .unwrap();
+        assert_eq!(slice.tracks.len(), 60);
+        assert_eq!(slice.tracks[0].id, "t30");
+        assert_eq!(slice.end, 90);
+        assert_eq!(*pages.lock().unwrap(), vec![(30, 50), (80, 10)]);
+
+        // A range running past the end stops at the last entry
+        pages.lock().unwrap().clear();
+        let slice = client
+            .get_playlist_range("playlist1", false, PlaylistRange { offset: 100, limit: Some(50) })
+            .await
+            .unwrap();
+        assert_eq!(slice.tracks.len(), 20);
+        assert_eq!(slice.end, 120);
+        assert_eq!(*pages.lock().unwrap(), vec![(100, 50)]);
+
+        let slice = client
+            .get_playlist_range("playlist1", false, PlaylistRange { offset: 10, limit: Some(0) })
+            .await
+            .unwrap();
+        assert!(slice.tracks.is_empty());
+        assert_eq!(slice.end, 10);
+    }
+
+    #[tokio::test]
+    async fn test_get_artists_batches_ids_and_skips_unknown() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let seen = batches.clone();
+        let app = Router::new().route("/v1/artists", get(move |axum::extract::Query(query): axum::extract::Query<HashMap<String, String>>| async move {
+            let ids: Vec<String> = query["ids"].split(',').map(str::to_string).collect();
+            seen.lock().unwrap().push(ids.len());
+            let artists: Vec<Value> = ids
+                .iter()
+                .map(|id| if id == "gone" { Value::Null } else { json!({ "id": id, "name": id, "popularity": 40 }) })
+                .collect();
+            Json(json!({ "artists": artists }))
+        }));
+        let addr = spawn_stub(app).await;
+
+        let client = SpotifyClient::with_endpoints(
+            Client::new(),
+            &format!("http://{}/v1", addr),
+            &format!("http://{}/api/token", addr),
+            Some("token".to_string()),
+        );
+
+        let mut ids: Vec<String> = (0..60).map(|i| format!("a{}", i)).collect();
+        ids.push("gone".to_string());
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+
+        let artists = client.get_artists(&ids).await.unwrap();
+
+        assert_eq!(artists.len(), 60);
+        assert_eq!(artists[59].id, "a59");
+        assert_eq!(artists[0].popularity, 40);
+        assert_eq!(*batches.lock().unwrap(), vec![ARTISTS_BATCH_SIZE, 11]);
+    }
+
+    #[tokio::test]
+    async fn test_saved_tracks_use_the_user_token_and_app_token_for_features() {
+        // A 70-track library; features only answer to the app's token
+        let app = Router::new()
+            .route("/v1/me/tracks", get(|headers: HeaderMap, axum::extract::Query(query): axum::extract::Query<HashMap<String, String>>| async move {
+                if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some("Bearer user-token") {
+                    return AxumStatusCode::UNAUTHORIZED.into_response();
+                }
+                let offset: usize = query["offset"].parse().unwrap();
+                let limit: usize = query["limit"].parse().unwrap();
+                let items: Vec<Value> = (offset..(offset + limit).min(70))
+                    .map(|position| json!({ "added_at": "2024-01-01T00:00:00Z", "track": { "id": format!("t{}", position), "name": "Track" } }))
+                    .collect();
+                Json(json!({ "items": items })).into_response()
+            }))
+            .route("/v1/audio-features", get(|headers: HeaderMap| async move {
+                if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some("Bearer app-token") {
+                    return AxumStatusCode::UNAUTHORIZED.into_response();
+                }
+                Json(json!({ "audio_features": [{ "id": "t0", "energy": 0.9 }] })).into_response()
+            }));
+        let addr = spawn_stub(app).await;
+
+        let client = SpotifyClient::with_endpoints(
+            Client::new(),
+            &format!("http://{}/v1", addr),
+            &format!("http://{}/api/token", addr),
+            Some("app-token".to_string()),
+        );
+
+        let slice = client.get_saved_tracks("user-token", true, PlaylistRange::default()).await.unwrap();
+        assert_eq!(slice.tracks.len(), 70);
+        assert_eq!(slice.end, 70);
+        assert_eq!(slice.tracks[0].energy, Some(0.9));
+
+        let rejected = client.get_saved_tracks("expired", false, PlaylistRange::default()).await;
+        assert!(matches!(rejected, Err(SpotifyError::Auth(_))));
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_token_after_401() {
+        let token_requests = Arc::new(AtomicUsize::new(0));
+        let counter = token_requests.clone();
+        let app = Router::new()
+            .route("/api/token", post(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Json(json!({ "access_token": "fresh", "expires_in": 3600 }))
+            }))
+            .route("/v1/artists/:id", get(|headers: HeaderMap| async move {
+                if headers.get("authorization").and_then(|v| v.to_str().ok()) == Some("Bearer fresh") {
+                    Json(json!({ "id": "artist1", "name": "Test Artist" })).into_response()
+                } else {
+                    AxumStatusCode::UNAUTHORIZED.into_response()
+                }
+            }));
+        let addr = spawn_stub(app).await;
+
+        let client = with_credentials(SpotifyClient::with_endpoints(
+            Client::new(),
+            &format!("http://{}/v1", addr),
+            &format!("http://{}/api/token", addr),
+            Some("expired".to_string()),
+        ));
+
+        let artist = client.get_artist("artist1").await.unwrap();
+        assert_eq!(artist.name, "Test Artist");
+        assert_eq!(token_requests.load(Ordering::SeqCst), 1);
+
+        // The refreshed token is reused without another token request
+        client.get_artist("artist1").await.unwrap();
+        assert_eq!(token_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_recommendations_refresh_token_after_401() {
+        let token_requests = Arc::new(AtomicUsize::new(0));
+        let counter = token_requests.clone();
+        let app = Router::new()
+            .route("/api/token", post(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Json(json!({ "access_token": "fresh", "expires_in": 3600 }))
+            }))
+            .route("/v1/recommendations", get(|headers: HeaderMap| async move {
+                if headers.get("authorization").and_then(|v| v.to_str().ok()) == Some("Bearer fresh") {
+                    Json(json!({ "tracks": [{ "id": "t1", "name": "One" }] })).into_response()
+                } else {
+                    AxumStatusCode::UNAUTHORIZED.into_response()
+                }
+            }))
+            .route("/v1/audio-features", get(|| async { Json(json!({ "audio_features": [null] })) }));
+        let addr = spawn_stub(app).await;
+
+        // Holding a token Spotify has since expired
+        let client = with_credentials(SpotifyClient::with_endpoints(
+            Client::new(),
+            &format!("http://{}/v1", addr),
+            &format!("http://{}/api/token", addr),
+            Some("expired".to_string()),
+        ));
+
+        let tracks = client.get_recommendations(None, &["seed".to_string()], None, None, None, 20).await.unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(token_requests.load(Ordering::SeqCst), 1);
+
+        // A caller's own token isn't swapped for the app's
+        let rejected = client.get_recommendations(Some("user-token"), &["seed".to_string()], None, None, None, 20).await;
+        assert!(matches!(rejected, Err(SpotifyError::Auth(_))));
+        assert_eq!(token_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_times_out_instead_of_hanging() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = Router::new().route("/v1/artists/:id", get(move || async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+            Json(json!({ "id": "artist1", "name": "Too Late" }))
+        }));
+        let addr = spawn_stub(app).await;
+
+        let http = Client::builder()
+            .timeout(std::time::Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let client = SpotifyClient::with_endpoints(
+            http,
+            &format!("http://{}/v1", addr),
+            &format!("http://{}/api/token", addr),
+            Some("token".to_string()),
+        );
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(10), client.get_artist("artist1"))
+            .await
+            .expect("request should time out rather than hang");
+
+        match result {
+            Err(SpotifyError::Network(e)) => assert!(e.is_timeout()),
+            other => panic!("expected a timeout error, got {:?}", other.map(|artist| artist.name)),
+        }
+        // Timeouts are retried like any other network failure
+        let max_retries = RateLimitConfig::spotify_config().max_retries as usize;
+        assert_eq!(requests.load(Ordering::SeqCst), max_retries + 1);
+    }
+}
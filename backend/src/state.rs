@@ -0,0 +1,139 @@
+use crate::{
+    cancellation::Cancellations,
+    handlers::ApiError,
+    idempotency::IdempotencyStore,
+    inflight::InFlight,
+    models::{CreatedPlaylist, ImportOptions, TrackStoredEvent},
+    neo4j_db::Neo4jClient,
+    spotify::SpotifyClient,
+    youtube::YouTubeClient,
+};
+use axum::{extract::FromRef, http::StatusCode};
+use reqwest::Client;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+const DEFAULT_HTTP_TIMEOUT_SECS: u64 = 30;
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// How long a created playlist is replayed for a repeated `Idempotency-Key`
+const IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Track events buffered per subscriber before a slow one starts missing them
+const TRACK_EVENTS_CAPACITY: usize = 256;
+
+/// Playlist imports in flight, keyed by playlist ID and the options they run with
+pub type ImportRegistry = InFlight<(String, ImportOptions), Result<Value, StatusCode>>;
+
+/// Cancellation for running imports, by the job id each request gave
+pub type ImportJobs = Cancellations<(String, ImportOptions)>;
+
+/// Every track stored by any import, fanned out to WebSocket subscribers
+pub type TrackEvents = broadcast::Sender<TrackStoredEvent>;
+
+/// Created YouTube playlists by `Idempotency-Key`
+pub type PlaylistStore = IdempotencyStore<CreatedPlaylist, ApiError>;
+
+/// Clients created once at startup and shared by every handler
+#[derive(Clone)]
+pub struct AppState {
+    pub neo4j: Neo4jClient,
+    pub spotify: Arc<SpotifyClient>,
+    pub youtube: Arc<YouTubeClient>,
+    pub imports: Arc<ImportRegistry>,
+    pub import_jobs: Arc<ImportJobs>,
+    pub playlists: Arc<PlaylistStore>,
+    pub track_events: TrackEvents,
+}
+
+impl AppState {
+    pub fn new(neo4j: Neo4jClient) -> reqwest::Result<Self> {
+        // One pooled HTTP client, shared by the Spotify and YouTube clients
+        let http = Client::builder()
+            .timeout(http_timeout())
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            neo4j,
+            spotify: Arc::new(SpotifyClient::new(http.clone())),
+            youtube: Arc::new(YouTubeClient::new(http)),
+            imports: InFlight::new(),
+            import_jobs: Cancellations::new(),
+            playlists: Arc::new(IdempotencyStore::new(IDEMPOTENCY_TTL)),
+            track_events: broadcast::channel(TRACK_EVENTS_CAPACITY).0,
+        })
+    }
+
+    /// Warn up front about missing optional config instead of failing on first use
+    pub fn warn_on_missing_config(&self) {
+        if self.youtube.api_key().is_none() {
+            tracing::warn!(
+                "YOUTUBE_API_KEY is not set: YouTube playlist creation is unavailable and similar tracks won't include videos"
+            );
+        }
+
+        let has_spotify_token = std::env::var("SPOTIFY_ACCESS_TOKEN").is_ok_and(|token| !token.is_empty());
+        let has_spotify_credentials =
+            std::env::var("SPOTIFY_CLIENT_ID").is_ok() && std::env::var("SPOTIFY_CLIENT_SECRET").is_ok();
+        if !has_spotify_token && !has_spotify_credentials {
+            tracing::warn!(
+                "Neither SPOTIFY_ACCESS_TOKEN nor SPOTIFY_CLIENT_ID/SPOTIFY_CLIENT_SECRET is set: Spotify imports will fail"
+            );
+        }
+    }
+}
+
+/// Overall deadline for each outbound request, from `HTTP_TIMEOUT_SECS`
+fn http_timeout() -> Duration {
+    let seconds = match std::env::var("HTTP_TIMEOUT_SECS") {
+        Ok(value) => value.parse::<u64>().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+            tracing::warn!("Invalid HTTP_TIMEOUT_SECS '{}', using {}", value, DEFAULT_HTTP_TIMEOUT_SECS);
+            DEFAULT_HTTP_TIMEOUT_SECS
+        }),
+        Err(_) => DEFAULT_HTTP_TIMEOUT_SECS,
+    };
+    Duration::from_secs(seconds)
+}
+
+impl FromRef<AppState> for Neo4jClient {
+    fn from_ref(state: &AppState) -> Self {
+        state.neo4j.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<SpotifyClient> {
+    fn from_ref(state: &AppState) -> Self {
+        state.spotify.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<YouTubeClient> {
+    fn from_ref(state: &AppState) -> Self {
+        state.youtube.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ImportRegistry> {
+    fn from_ref(state: &AppState) -> Self {
+        state.imports.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ImportJobs> {
+    fn from_ref(state: &AppState) -> Self {
+        state.import_jobs.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<PlaylistStore> {
+    fn from_ref(state: &AppState) -> Self {
+        state.playlists.clone()
+    }
+}
+
+impl FromRef<AppState> for TrackEvents {
+    fn from_ref(state: &AppState) -> Self {
+        state.track_events.clone()
+    }
+}
@@ -0,0 +1,98 @@
+use crate::prerender::FRONTEND_DIST;
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tower_http::services::ServeFile;
+use tracing::error;
+
+/// For assets whose name changes with their content, so a cached copy can never go stale
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// For everything else, `index.html` above all, so a new build is picked up on the next visit
+const REVALIDATE: &str = "no-cache";
+/// Length of the hash trunk puts in built file names, e.g. `app-96695845edd9173e_bg.wasm`
+const CONTENT_HASH_LEN: usize = 16;
+
+/// Set `Cache-Control` on files served from the frontend build
+pub async fn cache_control(request: Request, next: Next) -> Response {
+    let hashed = is_content_hashed(request.uri().path());
+    let mut response = next.run(request).await;
+
+    // Unknown paths fall back to `index.html`, which must never be cached as if it were the asset
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    let value = if hashed && !is_html && response.status().is_success() { IMMUTABLE } else { REVALIDATE };
+    response.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static(value));
+    response
+}
+
+/// `index.html` for paths the frontend router handles, so deep links like `/artists` load the app.
+/// Unknown `/api` paths and missing files stay `404` rather than getting the page in their place.
+pub async fn spa_fallback(request: Request) -> Response {
+    if !is_client_route(request.uri().path()) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match ServeFile::new(format!("{FRONTEND_DIST}/index.html")).try_call(request).await {
+        Ok(response) => response.into_response(),
+        Err(e) => {
+            error!("Failed to serve index.html: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+// Anything outside `/api` whose last segment doesn't look like a file name
+fn is_client_route(path: &str) -> bool {
+    let is_api = path == "/api" || path.starts_with("/api/");
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    !is_api && !file_name.contains('.')
+}
+
+// Whether the file name carries a content hash, as in `name-<16 hex digits>.ext` or `name-<hash>_bg.ext`
+fn is_content_hashed(path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    let Some((stem, _extension)) = file_name.split_once('.') else {
+        return false;
+    };
+    let stem = stem.strip_suffix("_bg").unwrap_or(stem);
+    stem.rsplit_once('-').is_some_and(|(_, hash)| {
+        hash.len() == CONTENT_HASH_LEN && hash.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_routes_exclude_api_and_files() {
+        assert!(is_client_route("/"));
+        assert!(is_client_route("/artists"));
+        assert!(is_client_route("/tracks/4uLU6hMCjMI75M1A2tKUQC"));
+        assert!(is_client_route("/apiary"));
+
+        assert!(!is_client_route("/api"));
+        assert!(!is_client_route("/api/unknown"));
+        assert!(!is_client_route("/favicon.ico"));
+        assert!(!is_client_route("/spotify-neo4j-frontend-96695845edd9173e_bg.wasm"));
+    }
+
+    #[test]
+    fn test_only_trunk_hashed_names_count_as_content_hashed() {
+        assert!(is_content_hashed("/spotify-neo4j-frontend-96695845edd9173e.js"));
+        assert!(is_content_hashed("/spotify-neo4j-frontend-96695845edd9173e_bg.wasm"));
+        assert!(is_content_hashed("/assets/styles-0123456789abcdef.css"));
+
+        assert!(!is_content_hashed("/"));
+        assert!(!is_content_hashed("/index.html"));
+        assert!(!is_content_hashed("/artists"));
+        assert!(!is_content_hashed("/tracks/4uLU6hMCjMI75M1A2tKUQC"));
+        assert!(!is_content_hashed("/favicon-32x32.png"));
+        assert!(!is_content_hashed("/app-96695845edd9173.js"));
+    }
+}
@@ -0,0 +1,155 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use std::time::{Duration, Instant};
+use tracing::Subscriber;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+const DEFAULT_LOG_FILTER: &str = "spotify_neo4j_backend=info,tower_http=debug";
+const DEFAULT_SLOW_REQUEST_MS: u64 = 2000;
+
+/// How log lines are written, from `LOG_FORMAT`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable lines for local development
+    #[default]
+    Pretty,
+    /// One JSON object per line for log aggregators
+    Json,
+}
+
+impl LogFormat {
+    pub fn from_env() -> Self {
+        match std::env::var("LOG_FORMAT") {
+            Ok(value) if value.eq_ignore_ascii_case("json") => Self::Json,
+            Ok(value) if value.is_empty() || value.eq_ignore_ascii_case("pretty") => Self::Pretty,
+            Ok(value) => {
+                // Logging isn't up yet, so this can only go to stderr
+                eprintln!("Unknown LOG_FORMAT '{}', using pretty logs", value);
+                Self::Pretty
+            }
+            Err(_) => Self::Pretty,
+        }
+    }
+}
+
+/// Install the global tracing subscriber, filtered by `RUST_LOG`
+pub fn init_tracing(format: LogFormat) {
+    subscriber(format).init();
+}
+
+fn subscriber(format: LogFormat) -> Box<dyn Subscriber + Send + Sync> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| DEFAULT_LOG_FILTER.into());
+    let registry = tracing_subscriber::registry().with(filter);
+
+    match format {
+        LogFormat::Pretty => Box::new(registry.with(tracing_subscriber::fmt::layer())),
+        LogFormat::Json => Box::new(registry.with(tracing_subscriber::fmt::layer().json())),
+    }
+}
+
+/// Install the global Prometheus recorder, returning the handle `/metrics` renders from
+pub fn install_recorder() -> anyhow::Result<PrometheusHandle> {
+    Ok(PrometheusBuilder::new().install_recorder()?)
+}
+
+/// Requests taking longer than this are logged as slow, from `SLOW_REQUEST_MS` (default 2000)
+pub fn slow_request_threshold_from_env() -> Duration {
+    slow_request_threshold(std::env::var("SLOW_REQUEST_MS").ok().as_deref())
+}
+
+fn slow_request_threshold(value: Option<&str>) -> Duration {
+    let millis = match value {
+        Some(value) => value.trim().parse::<u64>().unwrap_or_else(|_| {
+            tracing::warn!("Invalid SLOW_REQUEST_MS '{}', using {}", value, DEFAULT_SLOW_REQUEST_MS);
+            DEFAULT_SLOW_REQUEST_MS
+        }),
+        None => DEFAULT_SLOW_REQUEST_MS,
+    };
+    Duration::from_millis(millis)
+}
+
+// Route pattern rather than raw path, so requests for different IDs read as the same endpoint
+fn matched_route(request: &Request) -> String {
+    request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string())
+}
+
+// Warn about requests that took longer than `threshold` to answer. Streamed bodies are timed up to
+// their first byte, since the rest is sent after the handler returns.
+pub async fn warn_slow_requests(State(threshold): State<Duration>, request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().clone();
+    let route = matched_route(&request);
+
+    let response = next.run(request).await;
+
+    let elapsed = start.elapsed();
+    if elapsed > threshold {
+        tracing::warn!(
+            method = %method,
+            route = %route,
+            status = response.status().as_u16(),
+            elapsed_ms = elapsed.as_millis() as u64,
+            "Slow request took {:.2}s",
+            elapsed.as_secs_f64()
+        );
+    }
+
+    response
+}
+
+// Count and time every request by method, matched route and status
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    // Label by route pattern rather than raw path so IDs don't explode the label set
+    let route = matched_route(&request);
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    metrics::counter!(
+        "http_requests_total",
+        "method" => method.clone(),
+        "route" => route.clone(),
+        "status" => status
+    )
+    .increment(1);
+    metrics::histogram!(
+        "http_request_duration_seconds",
+        "method" => method,
+        "route" => route
+    )
+    .record(start.elapsed().as_secs_f64());
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_both_log_formats_build_and_log() {
+        for format in [LogFormat::Pretty, LogFormat::Json] {
+            tracing::subscriber::with_default(subscriber(format), || {
+                tracing::info!(format = ?format, "log format works");
+            });
+        }
+    }
+
+    #[test]
+    fn test_slow_request_threshold_defaults_to_two_seconds() {
+        assert_eq!(slow_request_threshold(None), Duration::from_secs(2));
+        assert_eq!(slow_request_threshold(Some(" 500 ")), Duration::from_millis(500));
+        assert_eq!(slow_request_threshold(Some("0")), Duration::ZERO);
+        assert_eq!(slow_request_threshold(Some("2s")), Duration::from_secs(2));
+    }
+}
@@ -1,59 +1,356 @@
-use crate::models::{YouTubeVideo, CreatedPlaylist};
+use crate::models::{ApprovedVideo, PlaylistFailure, YouTubeVideo, CreatedPlaylist, PlaylistMatch, TrackMatch};
 use crate::rate_limiter::{RateLimiter, RateLimitConfig};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use futures::TryFutureExt;
 use serde_json::{Value, json};
-use anyhow::{Result, anyhow};
+use std::fmt;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_BATCH_SIZE: usize = 10;
+const MAX_BATCH_SIZE: usize = 50;
+const MAX_BATCH_DELAY_MS: u64 = 60_000;
+/// Results requested per search, so blacklisted ones can be skipped. A search costs the same quota
+/// however many results it returns.
+const SEARCH_CANDIDATES: usize = 5;
+
+/// How a playlist is built. The client's defaults come from the environment; requests can override them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaylistOptions {
+    /// Matches below this confidence are reported but not added
+    pub min_confidence: Option<f64>,
+    /// Tracks searched and added between pauses
+    pub batch_size: usize,
+    /// Pause after each batch but the last. Zero by default since the rate limiter already spaces out
+    /// requests; a pause only helps stretch a small quota further.
+    pub batch_delay: Duration,
+}
+
+impl Default for PlaylistOptions {
+    fn default() -> Self {
+        Self {
+            min_confidence: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            batch_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl PlaylistOptions {
+    /// Defaults from `YOUTUBE_BATCH_SIZE` and `YOUTUBE_BATCH_DELAY_MS`
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            batch_size: env_or("YOUTUBE_BATCH_SIZE", defaults.batch_size, |&n| (1..=MAX_BATCH_SIZE).contains(&n)),
+            batch_delay: Duration::from_millis(env_or("YOUTUBE_BATCH_DELAY_MS", 0, |&ms| ms <= MAX_BATCH_DELAY_MS)),
+            ..defaults
+        }
+    }
+
+    /// Apply a request's overrides, rejecting out-of-range values
+    pub fn with_overrides(
+        self,
+        min_confidence: Option<f64>,
+        batch_size: Option<usize>,
+        batch_delay_ms: Option<u64>,
+    ) -> std::result::Result<Self, String> {
+        if min_confidence.is_some_and(|min| !(0.0..=1.0).contains(&min)) {
+            return Err("min_confidence must be between 0 and 1".to_string());
+        }
+        if batch_size.is_some_and(|size| !(1..=MAX_BATCH_SIZE).contains(&size)) {
+            return Err(format!("batch_size must be between 1 and {}", MAX_BATCH_SIZE));
+        }
+        if batch_delay_ms.is_some_and(|ms| ms > MAX_BATCH_DELAY_MS) {
+            return Err(format!("batch_delay_ms must be at most {}", MAX_BATCH_DELAY_MS));
+        }
+
+        Ok(Self {
+            min_confidence: min_confidence.or(self.min_confidence),
+            batch_size: batch_size.unwrap_or(self.batch_size),
+            batch_delay: batch_delay_ms.map(Duration::from_millis).unwrap_or(self.batch_delay),
+        })
+    }
+}
+
+/// Search results to skip when picking a video, matched case-insensitively as substrings
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchBlacklist {
+    /// Skip results from channels whose title contains any of these
+    pub channels: Vec<String>,
+    /// Skip results whose title contains any of these
+    pub keywords: Vec<String>,
+}
+
+impl MatchBlacklist {
+    /// From the comma-separated `YOUTUBE_BLOCKED_CHANNELS` and `YOUTUBE_BLOCKED_KEYWORDS`
+    fn from_env() -> Self {
+        let list = |name: &str| std::env::var(name).map(|value| parse_list(&value)).unwrap_or_default();
+        Self {
+            channels: list("YOUTUBE_BLOCKED_CHANNELS"),
+            keywords: list("YOUTUBE_BLOCKED_KEYWORDS"),
+        }
+    }
+
+    fn blocks(&self, result: &SearchResult) -> bool {
+        let contains_any = |text: &str, needles: &[String]| {
+            let text = text.to_lowercase();
+            needles.iter().any(|needle| text.contains(needle.as_str()))
+        };
+        contains_any(&result.snippet.channel_title, &self.channels) || contains_any(&result.snippet.title, &self.keywords)
+    }
+}
+
+// Lowercased non-empty entries of a comma-separated list
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|entry| entry.trim().to_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+// Parse an optional env var, warning and falling back to `default` when it's invalid
+fn env_or<T: FromStr + fmt::Display>(name: &str, default: T, valid: impl Fn(&T) -> bool) -> T {
+    match std::env::var(name) {
+        Ok(value) => value.parse::<T>().ok().filter(|parsed| valid(parsed)).unwrap_or_else(|| {
+            tracing::warn!("Invalid {} '{}', using {}", name, value, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+// Typed views of Google's responses, only the fields we read. Google uses camelCase names;
+// the `YouTubeVideo` we return keeps the API's own snake_case.
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    items: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    id: SearchResultId,
+    snippet: SearchSnippet,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResultId {
+    video_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchSnippet {
+    title: String,
+    channel_title: String,
+    #[serde(default)]
+    thumbnails: Thumbnails,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Thumbnails {
+    medium: Option<Thumbnail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Thumbnail {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistResource {
+    id: String,
+}
+
+/// A playlist item as `playlistItems.insert` returns it, with the place it was given
+#[derive(Debug, Deserialize)]
+struct PlaylistItemResource {
+    id: String,
+    snippet: PlaylistItemSnippet,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistItemSnippet {
+    position: usize,
+}
+
+impl SearchResult {
+    fn into_video(self, query: &str) -> YouTubeVideo {
+        YouTubeVideo {
+            id: self.id.video_id,
+            confidence: match_confidence(query, &self.snippet.title, &self.snippet.channel_title),
+            title: self.snippet.title,
+            channel_title: self.snippet.channel_title,
+            duration: "Unknown".to_string(), // Would need additional API call to get duration
+            thumbnail_url: self.snippet.thumbnails.medium.map(|thumbnail| thumbnail.url).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum YouTubeError {
+    /// The daily API quota is used up; retrying before it resets won't help
+    QuotaExceeded,
+    Unauthorized(String),
+    NotFound(String),
+    Network(reqwest::Error),
+    Parse(String),
+    Api { status: StatusCode, message: String },
+}
+
+impl YouTubeError {
+    /// Classify a non-success response using the `error.errors[].reason` fields
+    async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        let message = body["error"]["message"].as_str().unwrap_or_default().to_string();
+        let reasons: Vec<&str> = body["error"]["errors"]
+            .as_array()
+            .map(|errors| errors.iter().filter_map(|e| e["reason"].as_str()).collect())
+            .unwrap_or_default();
+
+        if reasons.iter().any(|reason| matches!(*reason, "quotaExceeded" | "dailyLimitExceeded")) {
+            return YouTubeError::QuotaExceeded;
+        }
+
+        match status {
+            StatusCode::UNAUTHORIZED => YouTubeError::Unauthorized(message),
+            StatusCode::NOT_FOUND => YouTubeError::NotFound(message),
+            _ => YouTubeError::Api { status, message },
+        }
+    }
+}
+
+impl fmt::Display for YouTubeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            YouTubeError::QuotaExceeded => write!(f, "YouTube API quota exceeded, try again tomorrow"),
+            YouTubeError::Unauthorized(message) => write!(f, "YouTube authorization failed: {}", message),
+            YouTubeError::NotFound(message) => write!(f, "YouTube resource not found: {}", message),
+            YouTubeError::Network(e) => write!(f, "Network error: {}", e),
+            YouTubeError::Parse(message) => write!(f, "Invalid YouTube response: {}", message),
+            YouTubeError::Api { status, message } => write!(f, "YouTube API error {}: {}", status, message),
+        }
+    }
+}
+
+impl std::error::Error for YouTubeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            YouTubeError::Network(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for YouTubeError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            YouTubeError::Parse(e.to_string())
+        } else {
+            YouTubeError::Network(e)
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, YouTubeError>;
 
 pub struct YouTubeClient {
     client: Client,
     rate_limiter: Arc<RateLimiter>,
+    api_base_url: String,
+    /// `YOUTUBE_API_KEY`, `None` when YouTube features are unavailable
+    api_key: Option<String>,
+    playlist_defaults: PlaylistOptions,
+    blacklist: MatchBlacklist,
 }
 
 impl YouTubeClient {
-    pub fn new() -> Self {
+    pub fn new(client: Client) -> Self {
+        Self::with_api_base_url(client, YOUTUBE_API_URL)
+    }
+
+    fn with_api_base_url(client: Client, api_base_url: &str) -> Self {
         Self {
-            client: Client::new(),
-            rate_limiter: Arc::new(RateLimiter::new(RateLimitConfig::youtube_config())),
+            client,
+            rate_limiter: Arc::new(RateLimiter::new("youtube", RateLimitConfig::youtube_config())),
+            api_base_url: api_base_url.to_string(),
+            api_key: std::env::var("YOUTUBE_API_KEY").ok().filter(|key| !key.is_empty()),
+            playlist_defaults: PlaylistOptions::from_env(),
+            blacklist: MatchBlacklist::from_env(),
         }
     }
 
+    pub fn api_key(&self) -> Option<&str> {
+        self.api_key.as_deref()
+    }
+
+    pub fn playlist_defaults(&self) -> PlaylistOptions {
+        self.playlist_defaults
+    }
+
     pub async fn search_video(&self, query: &str, api_key: &str) -> Result<Option<YouTubeVideo>> {
-        let url = format!(
-            "https://www.googleapis.com/youtube/v3/search?part=snippet&type=video&q={}&maxResults=1&key={}",
-            urlencoding::encode(query),
-            api_key
-        );
+        let url = search_url(&self.api_base_url, query, api_key);
         let client = &self.client;
 
         let response = self.rate_limiter.execute(|| async {
-            client.get(&url).send().await.map_err(|e| anyhow!("Request failed: {}", e))
+            client.get(&url).send().await.map_err(YouTubeError::Network)
         }).await?;
-        
-        if !response.status().is_success() {
-            return Err(anyhow!("YouTube search failed: {}", response.status()));
-        }
 
-        let data: Value = response.json().await?;
-        let items = data["items"].as_array().ok_or(anyhow!("Invalid YouTube response"))?;
+        search_result(response, query, &self.blacklist).await
+    }
 
-        if items.is_empty() {
-            return Ok(None);
-        }
+    /// Search for every query at once, as many in parallel as the rate limiter allows.
+    /// Results are in query order.
+    async fn search_videos(&self, queries: &[String], api_key: &str) -> Vec<Result<Option<YouTubeVideo>>> {
+        let client = &self.client;
+        let requests: Vec<_> = queries
+            .iter()
+            .map(|query| {
+                let url = search_url(&self.api_base_url, query, api_key);
+                move || client.get(&url).send().map_err(YouTubeError::Network)
+            })
+            .collect();
 
-        let video = &items[0];
-        let snippet = &video["snippet"];
+        let responses = self.rate_limiter.execute_batch(requests, queries.len().max(1)).await;
+        tracing::debug!("{} of {} YouTube searches got a response", responses.succeeded, queries.len());
 
-        Ok(Some(YouTubeVideo {
-            id: video["id"]["videoId"].as_str().ok_or(anyhow!("Missing video ID"))?.to_string(),
-            title: snippet["title"].as_str().ok_or(anyhow!("Missing video title"))?.to_string(),
-            channel_title: snippet["channelTitle"].as_str().ok_or(anyhow!("Missing channel title"))?.to_string(),
-            duration: "Unknown".to_string(), // Would need additional API call to get duration
-        }))
+        let mut results = Vec::with_capacity(queries.len());
+        for (response, query) in responses.results.into_iter().zip(queries) {
+            results.push(match response {
+                Ok(response) => search_result(response, query, &self.blacklist).await,
+                Err(e) => Err(e),
+            });
+        }
+        results
+    }
+
+    /// Search for every track without creating anything, so the matches can be reviewed before a
+    /// playlist is made from them. Once the quota runs out the remaining tracks aren't searched.
+    pub async fn resolve_matches(&self, track_names: &[String], api_key: &str) -> Vec<TrackMatch> {
+        let mut matches = Vec::with_capacity(track_names.len());
+        let mut quota_exceeded = false;
+        for batch in track_names.chunks(self.playlist_defaults.batch_size) {
+            if quota_exceeded {
+                matches.extend(batch.iter().map(|track_name| track_match(track_name, Err(YouTubeError::QuotaExceeded))));
+                continue;
+            }
+            let searches = self.search_videos(batch, api_key).await;
+            for (track_name, search) in batch.iter().zip(searches) {
+                quota_exceeded |= matches!(search, Err(YouTubeError::QuotaExceeded));
+                matches.push(track_match(track_name, search));
+            }
+        }
+        matches
     }
 
     pub async fn create_playlist(&self, name: &str, description: Option<&str>, access_token: &str) -> Result<String> {
-        let url = "https://www.googleapis.com/youtube/v3/playlists?part=snippet,status";
+        let url = format!("{}/playlists?part=snippet,status", self.api_base_url);
         
         let payload = json!({
             "snippet": {
@@ -67,7 +364,7 @@ impl YouTubeClient {
         });
 
         let response = self.client
-            .post(url)
+            .post(&url)
             .header("Authorization", format!("Bearer {}", access_token))
             .header("Content-Type", "application/json")
             .json(&payload)
@@ -75,49 +372,151 @@ impl YouTubeClient {
             .await?;
 
         if !response.status().is_success() {
-            return Err(anyhow!("Failed to create YouTube playlist: {}", response.status()));
+            return Err(YouTubeError::from_response(response).await);
         }
 
-        let data: Value = response.json().await?;
-        let playlist_id = data["id"].as_str().ok_or(anyhow!("Missing playlist ID"))?.to_string();
-
-        Ok(playlist_id)
+        let playlist: PlaylistResource = response.json().await?;
+        Ok(playlist.id)
     }
 
     pub async fn add_video_to_playlist(&self, playlist_id: &str, video_id: &str, access_token: &str) -> Result<()> {
-        let url = "https://www.googleapis.com/youtube/v3/playlistItems?part=snippet";
-        
-        let payload = json!({
-            "snippet": {
-                "playlistId": playlist_id,
-                "resourceId": {
-                    "kind": "youtube#video",
-                    "videoId": video_id
+        let payload = playlist_item(playlist_id, video_id, None);
+
+        let client = &self.client;
+        let url = self.playlist_items_url();
+        let auth_header = format!("Bearer {}", access_token);
+
+        let response = self.rate_limiter.execute(|| {
+            send_retryable(
+                client
+                    .post(&url)
+                    .header("Authorization", &auth_header)
+                    .header("Content-Type", "application/json")
+                    .json(&payload),
+            )
+        }).await?;
+
+        if !response.status().is_success() {
+            return Err(YouTubeError::from_response(response).await);
+        }
+
+        Ok(())
+    }
+
+    /// Insert videos at once, as many in parallel as the rate limiter allows. They're appended in
+    /// whatever order the inserts land, then the ones that landed out of place are moved so the
+    /// playlist keeps video order from `first_position`. Results are in video order.
+    async fn add_videos_to_playlist(
+        &self,
+        playlist_id: &str,
+        video_ids: &[&str],
+        first_position: usize,
+        access_token: &str,
+    ) -> Vec<Result<()>> {
+        let client = &self.client;
+        let url = &self.playlist_items_url();
+        let auth_header = format!("Bearer {}", access_token);
+        let auth_header = &auth_header;
+        let requests: Vec<_> = video_ids
+            .iter()
+            .map(|video_id| {
+                let payload = playlist_item(playlist_id, video_id, None);
+                move || {
+                    send_retryable(
+                        client
+                            .post(url)
+                            .header("Authorization", auth_header)
+                            .header("Content-Type", "application/json")
+                            .json(&payload),
+                    )
+                }
+            })
+            .collect();
+
+        let responses = self.rate_limiter.execute_batch(requests, video_ids.len().max(1)).await;
+
+        let mut results = Vec::with_capacity(video_ids.len());
+        let mut inserted = Vec::new();
+        for (index, response) in responses.results.into_iter().enumerate() {
+            let item = match response {
+                Ok(response) if response.status().is_success() => response.json::<PlaylistItemResource>().await.map_err(YouTubeError::from),
+                Ok(response) => Err(YouTubeError::from_response(response).await),
+                Err(e) => Err(e),
+            };
+            match item {
+                Ok(item) => {
+                    inserted.push((index, item));
+                    results.push(Ok(()));
                 }
+                Err(e) => results.push(Err(e)),
             }
-        });
+        }
+
+        // Landed order, as indices into `inserted`, which is itself in video order
+        let mut order: Vec<usize> = (0..inserted.len()).collect();
+        order.sort_by_key(|&slot| inserted[slot].1.snippet.position);
+        for (target, (index, item)) in inserted.iter().enumerate() {
+            let current = order.iter().position(|&slot| slot == target).unwrap_or(target);
+            if current == target {
+                continue;
+            }
+            let moved = self
+                .move_playlist_item(playlist_id, &item.id, video_ids[*index], first_position + target, access_token)
+                .await;
+            match moved {
+                Ok(()) => {
+                    order.remove(current);
+                    order.insert(target, target);
+                }
+                Err(e) => {
+                    // The video is in the playlist either way, just not in its place
+                    tracing::warn!(video_id = %video_ids[*index], error = %e, "Failed to move video into place");
+                    if matches!(e, YouTubeError::QuotaExceeded) {
+                        break;
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    /// Move a playlist item to `position` with `playlistItems.update`
+    async fn move_playlist_item(
+        &self,
+        playlist_id: &str,
+        item_id: &str,
+        video_id: &str,
+        position: usize,
+        access_token: &str,
+    ) -> Result<()> {
+        let mut payload = playlist_item(playlist_id, video_id, Some(position));
+        payload["id"] = json!(item_id);
 
         let client = &self.client;
+        let url = self.playlist_items_url();
         let auth_header = format!("Bearer {}", access_token);
 
-        let response = self.rate_limiter.execute(|| async {
-            client
-                .post(url)
-                .header("Authorization", &auth_header)
-                .header("Content-Type", "application/json")
-                .json(&payload)
-                .send()
-                .await
-                .map_err(|e| anyhow!("Request failed: {}", e))
+        let response = self.rate_limiter.execute(|| {
+            send_retryable(
+                client
+                    .put(&url)
+                    .header("Authorization", &auth_header)
+                    .header("Content-Type", "application/json")
+                    .json(&payload),
+            )
         }).await?;
 
         if !response.status().is_success() {
-            return Err(anyhow!("Failed to add video to playlist: {}", response.status()));
+            return Err(YouTubeError::from_response(response).await);
         }
 
         Ok(())
     }
 
+    fn playlist_items_url(&self) -> String {
+        format!("{}/playlistItems?part=snippet", self.api_base_url)
+    }
+
     pub async fn create_playlist_from_tracks(
         &self,
         name: &str,
@@ -125,44 +524,178 @@ impl YouTubeClient {
         track_names: &[String],
         access_token: &str,
         youtube_api_key: &str,
+        options: PlaylistOptions,
     ) -> Result<CreatedPlaylist> {
         // Create the playlist
         let playlist_id = self.create_playlist(name, description, access_token).await?;
         
         let mut tracks_added: i32 = 0;
         let mut tracks_not_found = Vec::new();
+        let mut tracks_skipped = Vec::new();
+        let mut quota_exceeded = false;
+        let mut matches = Vec::new();
+        let mut failures = Vec::new();
 
-        // Process tracks in smaller batches to avoid overwhelming the API
-        let batch_size = 10;
-        for batch in track_names.chunks(batch_size) {
-            for track_name in batch {
-                match self.search_video(track_name, youtube_api_key).await {
-                    Ok(Some(video)) => {
-                        match self.add_video_to_playlist(&playlist_id, &video.id, access_token).await {
-                            Ok(_) => {
-                                tracks_added += 1;
-                                println!("Added: {} - {}", track_name, video.title);
-                            }
-                            Err(e) => {
-                                println!("Failed to add {} to playlist: {}", track_name, e);
-                                tracks_not_found.push(track_name.clone());
-                            }
-                        }
+        let PlaylistOptions { min_confidence, batch_size, batch_delay } = options;
+        let batch_count = track_names.len().div_ceil(batch_size);
+        for (batch_index, batch) in track_names.chunks(batch_size).enumerate() {
+            // Searches and inserts are each independent, so a batch runs them in parallel
+            let searches = self.search_videos(batch, youtube_api_key).await;
+
+            // Offsets into the batch of the videos to insert, and of the searches the quota stopped
+            let mut to_add = Vec::new();
+            let mut unsearched = Vec::new();
+            for (offset, (track_name, search)) in batch.iter().zip(searches).enumerate() {
+                match search {
+                    Ok(Some(video)) if min_confidence.is_some_and(|min| video.confidence < min) => {
+                        tracing::warn!(
+                            confidence = video.confidence,
+                            track = %track_name,
+                            title = %video.title,
+                            "Low-confidence match, not adding"
+                        );
+                        matches.push(PlaylistMatch {
+                            track_name: track_name.clone(),
+                            video_id: video.id,
+                            video_title: video.title,
+                            confidence: video.confidence,
+                            added: false,
+                        });
                     }
+                    Ok(Some(video)) => to_add.push((offset, video)),
                     Ok(None) => {
-                        println!("No video found for: {}", track_name);
+                        tracing::debug!(track = %track_name, "No video found");
                         tracks_not_found.push(track_name.clone());
                     }
+                    Err(YouTubeError::QuotaExceeded) => unsearched.push(offset),
                     Err(e) => {
-                        println!("Search failed for {}: {}", track_name, e);
+                        tracing::warn!(track = %track_name, error = %e, "YouTube search failed");
                         tracks_not_found.push(track_name.clone());
+                        failures.push(PlaylistFailure {
+                            track_name: track_name.clone(),
+                            error: e.to_string(),
+                        });
                     }
                 }
             }
 
-            // Rate limiting is now handled by the RateLimiter, but add a small pause between batches
-            if batch.len() == batch_size && tracks_added < track_names.len() as i32 {
-                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            let remaining = &track_names[((batch_index + 1) * batch_size).min(track_names.len())..];
+            if !unsearched.is_empty() {
+                // The inserts would fail the same way, so the rest of the batch is skipped with what follows it
+                tracing::warn!(track = %batch[unsearched[0]], "YouTube quota exceeded, skipping the remaining tracks");
+                let mut skipped: Vec<usize> = to_add.iter().map(|(offset, _)| *offset).chain(unsearched).collect();
+                skipped.sort_unstable();
+                quota_exceeded = true;
+                tracks_skipped = skipped.into_iter().map(|offset| batch[offset].clone()).collect();
+                tracks_skipped.extend_from_slice(remaining);
+                break;
+            }
+
+            // The playlist is new, so everything added so far sits ahead of this batch
+            let video_ids: Vec<&str> = to_add.iter().map(|(_, video)| video.id.as_str()).collect();
+            let inserts = self
+                .add_videos_to_playlist(&playlist_id, &video_ids, tracks_added as usize, access_token)
+                .await;
+
+            for ((offset, video), insert) in to_add.into_iter().zip(inserts) {
+                let track_name = &batch[offset];
+                match insert {
+                    Ok(()) => {
+                        tracks_added += 1;
+                        tracing::debug!(track = %track_name, title = %video.title, "Added video to playlist");
+                        matches.push(PlaylistMatch {
+                            track_name: track_name.clone(),
+                            video_id: video.id,
+                            video_title: video.title,
+                            confidence: video.confidence,
+                            added: true,
+                        });
+                    }
+                    Err(YouTubeError::QuotaExceeded) => {
+                        quota_exceeded = true;
+                        tracks_skipped.push(track_name.clone());
+                    }
+                    Err(e) => {
+                        tracing::warn!(track = %track_name, error = %e, "Failed to add video to playlist");
+                        tracks_not_found.push(track_name.clone());
+                        failures.push(PlaylistFailure {
+                            track_name: track_name.clone(),
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            }
+
+            if quota_exceeded {
+                // Nothing else will succeed until the quota resets, so stop here
+                tracing::warn!(track = %tracks_skipped[0], "YouTube quota exceeded, skipping the remaining tracks");
+                tracks_skipped.extend_from_slice(remaining);
+                break;
+            }
+
+            // The rate limiter paces individual requests; this optional pause comes on top of it
+            if !batch_delay.is_zero() && batch_index + 1 < batch_count {
+                tokio::time::sleep(batch_delay).await;
+            }
+        }
+
+        Ok(CreatedPlaylist {
+            id: playlist_id.clone(),
+            name: name.to_string(),
+            url: format!("https://www.youtube.com/playlist?list={}", playlist_id),
+            tracks_added,
+            tracks_not_found,
+            tracks_skipped,
+            quota_exceeded,
+            matches,
+            failures,
+        })
+    }
+
+    /// Create a playlist from videos already picked, such as those approved from `resolve_matches`.
+    /// Nothing is searched, so only inserts count against the quota.
+    pub async fn create_playlist_from_videos(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        videos: &[ApprovedVideo],
+        access_token: &str,
+    ) -> Result<CreatedPlaylist> {
+        let playlist_id = self.create_playlist(name, description, access_token).await?;
+
+        let mut tracks_added: i32 = 0;
+        let mut tracks_not_found = Vec::new();
+        let mut tracks_skipped = Vec::new();
+        let mut quota_exceeded = false;
+        let mut matches = Vec::new();
+        let mut failures = Vec::new();
+
+        for (index, video) in videos.iter().enumerate() {
+            match self.add_video_to_playlist(&playlist_id, &video.video_id, access_token).await {
+                Ok(()) => {
+                    tracks_added += 1;
+                    matches.push(PlaylistMatch {
+                        track_name: video.track_name.clone(),
+                        video_id: video.video_id.clone(),
+                        video_title: video.video_title.clone(),
+                        confidence: video.confidence,
+                        added: true,
+                    });
+                }
+                Err(YouTubeError::QuotaExceeded) => {
+                    tracing::warn!(track = %video.track_name, "YouTube quota exceeded, skipping the remaining videos");
+                    quota_exceeded = true;
+                    tracks_skipped = videos[index..].iter().map(|video| video.track_name.clone()).collect();
+                    break;
+                }
+                Err(e) => {
+                    tracing::warn!(track = %video.track_name, error = %e, "Failed to add video to playlist");
+                    tracks_not_found.push(video.track_name.clone());
+                    failures.push(PlaylistFailure {
+                        track_name: video.track_name.clone(),
+                        error: e.to_string(),
+                    });
+                }
             }
         }
 
@@ -172,9 +705,34 @@ impl YouTubeClient {
             url: format!("https://www.youtube.com/playlist?list={}", playlist_id),
             tracks_added,
             tracks_not_found,
+            tracks_skipped,
+            quota_exceeded,
+            matches,
+            failures,
         })
     }
 
+    /// `create_playlist_from_videos` for bare video ids. With no track names to go on, results
+    /// report each video by its id.
+    pub async fn create_playlist_from_video_ids(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        video_ids: &[String],
+        access_token: &str,
+    ) -> Result<CreatedPlaylist> {
+        let videos: Vec<ApprovedVideo> = video_ids
+            .iter()
+            .map(|video_id| ApprovedVideo {
+                track_name: video_id.clone(),
+                video_id: video_id.clone(),
+                video_title: String::new(),
+                confidence: 0.0,
+            })
+            .collect();
+        self.create_playlist_from_videos(name, description, &videos, access_token).await
+    }
+
     pub fn format_search_query(track_name: &str, artist_names: &[String]) -> String {
         if artist_names.is_empty() {
             track_name.to_string()
@@ -183,3 +741,306 @@ impl YouTubeClient {
         }
     }
 }
+
+// A preview entry for one track's search
+fn track_match(track_name: &str, search: Result<Option<YouTubeVideo>>) -> TrackMatch {
+    let (video, error) = match search {
+        Ok(video) => (video, None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    TrackMatch { track_name: track_name.to_string(), video, error }
+}
+
+// Words that mark a different recording than the one searched for, unless the query asked for them
+const ALTERNATE_VERSION_WORDS: [&str; 7] = ["cover", "karaoke", "instrumental", "remix", "reaction", "tutorial", "live"];
+
+const YOUTUBE_API_URL: &str = "https://www.googleapis.com/youtube/v3";
+
+fn search_url(api_base_url: &str, query: &str, api_key: &str) -> String {
+    format!(
+        "{}/search?part=snippet&type=video&q={}&maxResults={}&key={}",
+        api_base_url,
+        urlencoding::encode(query),
+        SEARCH_CANDIDATES,
+        api_key
+    )
+}
+
+/// Send a request, turning the statuses worth another try (conflicts from concurrent writes to one
+/// playlist, throttling, server errors) into errors so the rate limiter retries them. Any other
+/// response is the caller's to check.
+async fn send_retryable(request: RequestBuilder) -> Result<Response> {
+    let response = request.send().await.map_err(YouTubeError::Network)?;
+    let status = response.status();
+    if status == StatusCode::CONFLICT || status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        return Err(YouTubeError::from_response(response).await);
+    }
+    Ok(response)
+}
+
+/// A `playlistItems.insert` or `update` body; without a position YouTube appends the video
+fn playlist_item(playlist_id: &str, video_id: &str, position: Option<usize>) -> Value {
+    let mut item = json!({
+        "snippet": {
+            "playlistId": playlist_id,
+            "resourceId": {
+                "kind": "youtube#video",
+                "videoId": video_id
+            }
+        }
+    });
+    if let Some(position) = position {
+        item["snippet"]["position"] = json!(position);
+    }
+    item
+}
+
+/// The top video from a search response that the blacklist doesn't skip, if any
+async fn search_result(response: Response, query: &str, blacklist: &MatchBlacklist) -> Result<Option<YouTubeVideo>> {
+    if !response.status().is_success() {
+        return Err(YouTubeError::from_response(response).await);
+    }
+
+    // A result missing a required field fails to decode and surfaces as `YouTubeError::Parse`
+    let data: SearchResponse = response.json().await?;
+    Ok(top_result(data, query, blacklist))
+}
+
+fn top_result(data: SearchResponse, query: &str, blacklist: &MatchBlacklist) -> Option<YouTubeVideo> {
+    data.items
+        .into_iter()
+        .find(|result| !blacklist.blocks(result))
+        .map(|result| result.into_video(query))
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Score 0-1 for how well a search result matches the query: the share of query
+/// words found in the title or channel, halved if the result looks like an
+/// alternate version (cover, karaoke, ...) the query didn't ask for.
+pub fn match_confidence(query: &str, title: &str, channel_title: &str) -> f64 {
+    let query_words = words(query);
+    if query_words.is_empty() {
+        return 0.0;
+    }
+
+    let mut result_words = words(title);
+    result_words.extend(words(channel_title));
+
+    let found = query_words.iter().filter(|word| result_words.contains(word)).count();
+    let mut confidence = found as f64 / query_words.len() as f64;
+
+    let alternate_version = ALTERNATE_VERSION_WORDS
+        .iter()
+        .any(|marker| result_words.iter().any(|word| word == marker) && !query_words.iter().any(|word| word == marker));
+    if alternate_version {
+        confidence /= 2.0;
+    }
+
+    confidence
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode as AxumStatusCode, response::IntoResponse, routing::post, Json, Router};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    /// Serve a stub YouTube API on a random local port and return its address
+    async fn spawn_stub(app: Router) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        addr
+    }
+
+    #[test]
+    fn test_match_confidence() {
+        let query = "Radiohead Karma Police";
+
+        assert_eq!(match_confidence(query, "Radiohead - Karma Police", "Radiohead"), 1.0);
+        // The artist can come from the channel name
+        assert_eq!(match_confidence(query, "Karma Police (Official Video)", "Radiohead"), 1.0);
+        assert_eq!(match_confidence(query, "Karma Police", "Some Channel"), 2.0 / 3.0);
+        assert_eq!(match_confidence(query, "Karma Police piano cover", "Radiohead Fan"), 0.5);
+        assert_eq!(match_confidence(query, "Completely unrelated", "Nobody"), 0.0);
+        assert_eq!(match_confidence("", "Anything", "Anyone"), 0.0);
+    }
+
+    #[test]
+    fn test_match_confidence_allows_requested_versions() {
+        assert_eq!(match_confidence("Song Live", "Song (Live)", "Band"), 1.0);
+    }
+
+    #[test]
+    fn test_search_response_deserializes_camel_case() {
+        let data: SearchResponse = serde_json::from_value(json!({
+            "kind": "youtube#searchListResponse",
+            "items": [{
+                "id": { "kind": "youtube#video", "videoId": "abc123" },
+                "snippet": {
+                    "title": "Radiohead - Karma Police",
+                    "channelTitle": "Radiohead",
+                    "thumbnails": { "medium": { "url": "https://i.ytimg.com/vi/abc123/mqdefault.jpg" } }
+                }
+            }]
+        }))
+        .unwrap();
+
+        let video = data.items.into_iter().next().unwrap().into_video("Radiohead Karma Police");
+        assert_eq!(video.id, "abc123");
+        assert_eq!(video.channel_title, "Radiohead");
+        assert_eq!(video.thumbnail_url, "https://i.ytimg.com/vi/abc123/mqdefault.jpg");
+        assert_eq!(video.confidence, 1.0);
+    }
+
+    #[test]
+    fn test_search_response_tolerates_missing_optional_fields() {
+        let empty: SearchResponse = serde_json::from_value(json!({ "kind": "youtube#searchListResponse" })).unwrap();
+        assert!(empty.items.is_empty());
+
+        let no_thumbnails: SearchResponse = serde_json::from_value(json!({
+            "items": [{ "id": { "videoId": "abc123" }, "snippet": { "title": "Song", "channelTitle": "Band" } }]
+        }))
+        .unwrap();
+        assert_eq!(no_thumbnails.items.into_iter().next().unwrap().into_video("Song").thumbnail_url, "");
+
+        // A channel (no videoId) is a malformed result rather than a silent empty ID
+        let missing_id = serde_json::from_value::<SearchResponse>(json!({
+            "items": [{ "id": { "channelId": "xyz" }, "snippet": { "title": "Song", "channelTitle": "Band" } }]
+        }));
+        assert!(missing_id.is_err());
+    }
+
+    #[test]
+    fn test_blacklisted_top_result_is_skipped() {
+        let data = || -> SearchResponse {
+            serde_json::from_value(json!({
+                "items": [
+                    { "id": { "videoId": "nightcore" }, "snippet": { "title": "Karma Police", "channelTitle": "Nightcore Hub" } },
+                    { "id": { "videoId": "sped" }, "snippet": { "title": "Karma Police (Sped Up)", "channelTitle": "Radiohead" } },
+                    { "id": { "videoId": "official" }, "snippet": { "title": "Karma Police", "channelTitle": "Radiohead" } }
+                ]
+            }))
+            .unwrap()
+        };
+        let query = "Radiohead Karma Police";
+
+        assert_eq!(top_result(data(), query, &MatchBlacklist::default()).unwrap().id, "nightcore");
+
+        let blacklist = MatchBlacklist {
+            channels: parse_list("nightcore, ,"),
+            keywords: parse_list("SPED UP"),
+        };
+        assert_eq!(blacklist.channels, vec!["nightcore"]);
+        assert_eq!(top_result(data(), query, &blacklist).unwrap().id, "official");
+
+        let block_all = MatchBlacklist { channels: parse_list("nightcore,radiohead"), keywords: vec![] };
+        assert!(top_result(data(), query, &block_all).is_none());
+    }
+
+    #[test]
+    fn test_track_match_reports_search_errors() {
+        let video = YouTubeVideo {
+            id: "v1".to_string(),
+            title: "Hey Jude".to_string(),
+            channel_title: "The Beatles".to_string(),
+            duration: String::new(),
+            thumbnail_url: String::new(),
+            confidence: 1.0,
+        };
+
+        let found = track_match("The Beatles - Hey Jude", Ok(Some(video)));
+        assert_eq!(found.video.map(|video| video.id).as_deref(), Some("v1"));
+        assert_eq!(found.error, None);
+
+        let missing = track_match("Unknown", Ok(None));
+        assert!(missing.video.is_none() && missing.error.is_none());
+
+        let quota = track_match("Later", Err(YouTubeError::QuotaExceeded));
+        assert!(quota.video.is_none());
+        assert_eq!(quota.error, Some(YouTubeError::QuotaExceeded.to_string()));
+    }
+
+    #[test]
+    fn test_playlist_item_position() {
+        let appended = playlist_item("PL1", "v1", None);
+        assert_eq!(appended["snippet"]["resourceId"]["videoId"], "v1");
+        assert!(appended["snippet"].get("position").is_none());
+
+        assert_eq!(playlist_item("PL1", "v2", Some(3))["snippet"]["position"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_inserts_keep_video_order_around_failures() {
+        // The playlist as (item id, video id), with one video from an earlier batch
+        let playlist = Arc::new(Mutex::new(vec![("item-v-prev".to_string(), "v-prev".to_string())]));
+        let unavailable_once = Arc::new(AtomicBool::new(true));
+        let inserts = {
+            let playlist = playlist.clone();
+            move |Json(body): Json<Value>| async move {
+                let video_id = body["snippet"]["resourceId"]["videoId"].as_str().unwrap().to_string();
+                assert!(body["snippet"].get("position").is_none());
+                match video_id.as_str() {
+                    "v0" => {
+                        let error = json!({ "error": { "message": "Video not found", "errors": [{ "reason": "videoNotFound" }] } });
+                        return (AxumStatusCode::BAD_REQUEST, Json(error)).into_response();
+                    }
+                    // Lands after the videos that follow it
+                    "v1" => tokio::time::sleep(Duration::from_millis(100)).await,
+                    "v3" if unavailable_once.swap(false, Ordering::SeqCst) => {
+                        return AxumStatusCode::SERVICE_UNAVAILABLE.into_response();
+                    }
+                    _ => {}
+                }
+                let mut playlist = playlist.lock().unwrap();
+                let item_id = format!("item-{}", video_id);
+                playlist.push((item_id.clone(), video_id));
+                Json(json!({ "id": item_id, "snippet": { "position": playlist.len() - 1 } })).into_response()
+            }
+        };
+        let updates = {
+            let playlist = playlist.clone();
+            move |Json(body): Json<Value>| async move {
+                let mut playlist = playlist.lock().unwrap();
+                let current = playlist.iter().position(|(id, _)| body["id"] == id.as_str()).unwrap();
+                let item = playlist.remove(current);
+                playlist.insert(body["snippet"]["position"].as_u64().unwrap() as usize, item);
+                Json(json!({}))
+            }
+        };
+        let addr = spawn_stub(Router::new().route("/playlistItems", post(inserts).put(updates))).await;
+        let client = YouTubeClient::with_api_base_url(Client::new(), &format!("http://{}", addr));
+
+        let results = client.add_videos_to_playlist("PL1", &["v0", "v1", "v2", "v3"], 1, "token").await;
+
+        assert!(matches!(results[0], Err(YouTubeError::Api { status: StatusCode::BAD_REQUEST, .. })));
+        // The unavailable insert is retried rather than reported
+        assert!(results[1..].iter().all(Result::is_ok));
+        let videos: Vec<String> = playlist.lock().unwrap().iter().map(|(_, video_id)| video_id.clone()).collect();
+        assert_eq!(videos, ["v-prev", "v1", "v2", "v3"]);
+    }
+
+    #[test]
+    fn test_playlist_options_overrides() {
+        let defaults = PlaylistOptions::default();
+
+        assert_eq!(defaults.with_overrides(None, None, None), Ok(defaults));
+
+        let options = defaults.with_overrides(Some(0.7), Some(25), Some(1500)).unwrap();
+        assert_eq!(options.min_confidence, Some(0.7));
+        assert_eq!(options.batch_size, 25);
+        assert_eq!(options.batch_delay, Duration::from_millis(1500));
+
+        assert!(defaults.with_overrides(None, Some(0), None).is_err());
+        assert!(defaults.with_overrides(None, Some(MAX_BATCH_SIZE + 1), None).is_err());
+        assert!(defaults.with_overrides(None, None, Some(MAX_BATCH_DELAY_MS + 1)).is_err());
+        assert!(defaults.with_overrides(Some(1.5), None, None).is_err());
+    }
+}
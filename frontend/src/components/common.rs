@@ -1,3 +1,5 @@
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{HtmlElement, KeyboardEvent};
 use yew::prelude::*;
 
 #[derive(Properties, PartialEq)]
@@ -39,6 +41,70 @@ pub fn loading(props: &LoadingProps) -> Html {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum SkeletonLayout {
+    /// Grid of cards, matching the Artists page
+    CardGrid,
+    /// Stacked full-width rows, matching the Tracks page
+    ListRows,
+    /// A single form field, e.g. a select that is still loading its options
+    Field,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SkeletonProps {
+    pub layout: SkeletonLayout,
+    #[prop_or_default]
+    pub count: Option<usize>,
+}
+
+#[function_component(Skeleton)]
+pub fn skeleton(props: &SkeletonProps) -> Html {
+    let count = props.count.unwrap_or(6);
+
+    match props.layout {
+        SkeletonLayout::CardGrid => html! {
+            <div class="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-3 gap-6 animate-pulse">
+                {for (0..count).map(|_| html! {
+                    <div class="bg-white rounded-lg shadow-md p-6">
+                        <div class="flex items-start space-x-4">
+                            <div class="w-16 h-16 rounded-full bg-gray-200"></div>
+                            <div class="flex-1 space-y-3">
+                                <div class="h-5 bg-gray-200 rounded w-3/4"></div>
+                                <div class="h-3 bg-gray-200 rounded w-1/2"></div>
+                                <div class="h-3 bg-gray-200 rounded w-1/3"></div>
+                                <div class="flex space-x-1">
+                                    <div class="h-5 w-16 bg-gray-200 rounded-full"></div>
+                                    <div class="h-5 w-12 bg-gray-200 rounded-full"></div>
+                                </div>
+                            </div>
+                        </div>
+                    </div>
+                })}
+            </div>
+        },
+        SkeletonLayout::ListRows => html! {
+            <div class="space-y-4 animate-pulse">
+                {for (0..count).map(|_| html! {
+                    <div class="bg-white rounded-lg shadow-md p-6">
+                        <div class="space-y-3">
+                            <div class="h-5 bg-gray-200 rounded w-1/3"></div>
+                            <div class="h-4 bg-gray-200 rounded w-1/4"></div>
+                            <div class="h-3 bg-gray-200 rounded w-1/5"></div>
+                        </div>
+                        <div class="mt-4 border-t pt-4 grid grid-cols-2 md:grid-cols-4 lg:grid-cols-6 gap-2">
+                            {for (0..6).map(|_| html! { <div class="h-8 bg-gray-200 rounded"></div> })}
+                        </div>
+                    </div>
+                })}
+            </div>
+        },
+        SkeletonLayout::Field => html! {
+            <div class="h-10 w-full bg-gray-200 rounded-md animate-pulse"></div>
+        },
+    }
+}
+
 #[derive(Properties, PartialEq)]
 pub struct CardProps {
     pub title: String,
@@ -84,3 +150,125 @@ pub fn button(props: &ButtonProps) -> Html {
         </button>
     }
 }
+
+const FOCUSABLE_SELECTOR: &str =
+    "a[href], button:not([disabled]), input, select, textarea, audio[controls], [tabindex]:not([tabindex='-1'])";
+
+#[derive(Properties, PartialEq)]
+pub struct ModalProps {
+    pub title: String,
+    pub on_close: Callback<()>,
+    pub children: Children,
+}
+
+#[function_component(Modal)]
+pub fn modal(props: &ModalProps) -> Html {
+    let dialog_ref = use_node_ref();
+
+    // Close on Escape, keep Tab focus inside the dialog, and hand focus back on close
+    {
+        let dialog_ref = dialog_ref.clone();
+        let on_close = props.on_close.clone();
+
+        use_effect_with((), move |_| {
+            let document = web_sys::window().and_then(|window| window.document());
+            let previously_focused = document
+                .as_ref()
+                .and_then(|document| document.active_element())
+                .and_then(|element| element.dyn_into::<HtmlElement>().ok());
+
+            if let Some(dialog) = dialog_ref.cast::<HtmlElement>() {
+                let _ = dialog.focus();
+            }
+
+            let listener = Closure::<dyn Fn(KeyboardEvent)>::new(move |e: KeyboardEvent| {
+                match e.key().as_str() {
+                    "Escape" => on_close.emit(()),
+                    "Tab" => {
+                        if let Some(dialog) = dialog_ref.cast::<HtmlElement>() {
+                            trap_focus(&dialog, &e);
+                        }
+                    }
+                    _ => {}
+                }
+            });
+
+            if let Some(document) = &document {
+                let _ = document.add_event_listener_with_callback("keydown", listener.as_ref().unchecked_ref());
+            }
+
+            move || {
+                if let Some(document) = &document {
+                    let _ = document.remove_event_listener_with_callback("keydown", listener.as_ref().unchecked_ref());
+                }
+                if let Some(element) = previously_focused {
+                    let _ = element.focus();
+                }
+            }
+        });
+    }
+
+    let close = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_: MouseEvent| on_close.emit(()))
+    };
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <div
+                ref={dialog_ref}
+                role="dialog"
+                aria-modal="true"
+                aria-labelledby="modal-title"
+                tabindex="-1"
+                class="bg-white rounded-lg max-w-4xl max-h-[80vh] overflow-y-auto p-6 m-4 w-full focus:outline-none"
+            >
+                <div class="flex justify-between items-center mb-4">
+                    <h2 id="modal-title" class="text-2xl font-bold text-gray-900">
+                        {&props.title}
+                    </h2>
+                    <button
+                        class="text-gray-500 hover:text-gray-700"
+                        aria-label="Close"
+                        onclick={close}
+                    >
+                        <svg class="w-6 h-6" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                            <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M6 18L18 6M6 6l12 12"></path>
+                        </svg>
+                    </button>
+                </div>
+                {for props.children.iter()}
+            </div>
+        </div>
+    }
+}
+
+/// Wrap Tab / Shift+Tab around the focusable elements inside `dialog`
+fn trap_focus(dialog: &HtmlElement, e: &KeyboardEvent) {
+    let Ok(nodes) = dialog.query_selector_all(FOCUSABLE_SELECTOR) else {
+        return;
+    };
+    let focusable: Vec<HtmlElement> = (0..nodes.length())
+        .filter_map(|i| nodes.get(i))
+        .filter_map(|node| node.dyn_into::<HtmlElement>().ok())
+        .collect();
+
+    let (Some(first), Some(last)) = (focusable.first(), focusable.last()) else {
+        e.prevent_default();
+        return;
+    };
+
+    let active = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.active_element());
+    let is_active = |element: &HtmlElement| active.as_ref().is_some_and(|active| active == element.as_ref());
+    let focus_inside = active.as_ref().is_some_and(|active| dialog.contains(Some(active)));
+
+    if e.shift_key() && (is_active(first) || !focus_inside || is_active(dialog)) {
+        e.prevent_default();
+        let _ = last.focus();
+    } else if !e.shift_key() && (is_active(last) || !focus_inside) {
+        e.prevent_default();
+        let _ = first.focus();
+    }
+}
@@ -1,2 +1,4 @@
 pub mod navbar;
 pub mod common;
+pub mod player;
+pub mod toast;
@@ -2,6 +2,16 @@ use yew::prelude::*;
 use yew_router::prelude::*;
 use crate::Route;
 
+// Every navigable `Route` should appear here, see the test below
+const NAV_LINKS: &[(Route, &str)] = &[
+    (Route::Home, "Home"),
+    (Route::Artists, "Artists"),
+    (Route::Albums, "Albums"),
+    (Route::Tracks, "Tracks"),
+    (Route::Recommendations, "Recommendations"),
+    (Route::Playlists, "Playlists"),
+];
+
 #[function_component(Navbar)]
 pub fn navbar() -> Html {
     html! {
@@ -13,43 +23,46 @@ pub fn navbar() -> Html {
                     </Link<Route>>
                     
                     <div class="flex space-x-6">
-                        <Link<Route> 
-                            to={Route::Home} 
-                            classes="text-gray-700 hover:text-purple-600 px-3 py-2 rounded-md text-sm font-medium transition-colors"
-                        >
-                            {"Home"}
-                        </Link<Route>>
-                        
-                        <Link<Route> 
-                            to={Route::Artists} 
-                            classes="text-gray-700 hover:text-purple-600 px-3 py-2 rounded-md text-sm font-medium transition-colors"
-                        >
-                            {"Artists"}
-                        </Link<Route>>
-                        
-                        <Link<Route> 
-                            to={Route::Tracks} 
-                            classes="text-gray-700 hover:text-purple-600 px-3 py-2 rounded-md text-sm font-medium transition-colors"
-                        >
-                            {"Tracks"}
-                        </Link<Route>>
-                        
-                        <Link<Route> 
-                            to={Route::Recommendations} 
-                            classes="text-gray-700 hover:text-purple-600 px-3 py-2 rounded-md text-sm font-medium transition-colors"
-                        >
-                            {"Recommendations"}
-                        </Link<Route>>
-                        
-                        <Link<Route> 
-                            to={Route::Playlists} 
-                            classes="text-gray-700 hover:text-purple-600 px-3 py-2 rounded-md text-sm font-medium transition-colors"
-                        >
-                            {"Playlists"}
-                        </Link<Route>>
+                        {for NAV_LINKS.iter().map(|(route, label)| {
+                            html! {
+                                <Link<Route> 
+                                    to={route.clone()} 
+                                    classes="text-gray-700 hover:text-purple-600 px-3 py-2 rounded-md text-sm font-medium transition-colors"
+                                >
+                                    {*label}
+                                </Link<Route>>
+                            }
+                        })}
                     </div>
                 </div>
             </div>
         </nav>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_route_has_a_navbar_link() {
+        for path in Route::routes() {
+            // Routes with parameters are reached from other pages, not the navbar
+            if path.contains(':') {
+                continue;
+            }
+
+            let route = Route::recognize(path)
+                .unwrap_or_else(|| panic!("route path {} is not recognized", path));
+            // The server-rendered Tracks page is reached through the Tracks link's page
+            if route == Route::TracksSnapshot {
+                continue;
+            }
+            assert!(
+                NAV_LINKS.iter().any(|(linked, _)| *linked == route),
+                "route {} has no navbar link",
+                path
+            );
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crate::Route;
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+const PREVIEW_HISTORY_KEY: &str = "preview_history";
+const MAX_PREVIEW_HISTORY: usize = 20;
+
+#[derive(Clone, PartialEq)]
+pub struct NowPlaying {
+    pub track_id: String,
+    pub url: String,
+    pub title: String,
+}
+
+/// A track whose preview has been played, kept in `localStorage` across visits
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PreviewedTrack {
+    pub id: String,
+    pub title: String,
+}
+
+pub enum PlayerAction {
+    Play(NowPlaying),
+    Stop,
+    /// The current clip started playing, so it goes to the front of the history
+    Played,
+}
+
+/// The single preview clip playing app-wide, if any, and the previews played so far
+#[derive(Default, PartialEq)]
+pub struct PlayerState {
+    pub now_playing: Option<NowPlaying>,
+    /// Most recent first, at most `MAX_PREVIEW_HISTORY`
+    pub previewed: Vec<PreviewedTrack>,
+}
+
+impl PlayerState {
+    fn load() -> Self {
+        Self {
+            now_playing: None,
+            previewed: LocalStorage::get(PREVIEW_HISTORY_KEY).unwrap_or_default(),
+        }
+    }
+}
+
+impl Reducible for PlayerState {
+    type Action = PlayerAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let (now_playing, previewed) = match action {
+            PlayerAction::Play(track) => (Some(track), self.previewed.clone()),
+            PlayerAction::Stop => (None, self.previewed.clone()),
+            PlayerAction::Played => {
+                let Some(track) = &self.now_playing else {
+                    return self;
+                };
+                let previewed = record_preview(&self.previewed, PreviewedTrack {
+                    id: track.track_id.clone(),
+                    title: track.title.clone(),
+                });
+                (self.now_playing.clone(), previewed)
+            }
+        };
+        Rc::new(PlayerState { now_playing, previewed })
+    }
+}
+
+// Move `track` to the front of the history, dropping the oldest past the cap
+fn record_preview(history: &[PreviewedTrack], track: PreviewedTrack) -> Vec<PreviewedTrack> {
+    std::iter::once(track.clone())
+        .chain(history.iter().filter(|previewed| previewed.id != track.id).cloned())
+        .take(MAX_PREVIEW_HISTORY)
+        .collect()
+}
+
+pub type PlayerContext = UseReducerHandle<PlayerState>;
+
+#[derive(Properties, PartialEq)]
+pub struct PlayerProviderProps {
+    pub children: Children,
+}
+
+#[function_component(PlayerProvider)]
+pub fn player_provider(props: &PlayerProviderProps) -> Html {
+    let player = use_reducer(PlayerState::load);
+
+    use_effect_with(player.previewed.clone(), |previewed| {
+        if let Err(error) = LocalStorage::set(PREVIEW_HISTORY_KEY, previewed) {
+            gloo_console::warn!(format!("Failed to save preview history: {}", error));
+        }
+        || ()
+    });
+
+    html! {
+        <ContextProvider<PlayerContext> context={player}>
+            {for props.children.iter()}
+        </ContextProvider<PlayerContext>>
+    }
+}
+
+/// Previews played so far, most recent first
+#[hook]
+pub fn use_preview_history() -> Vec<PreviewedTrack> {
+    use_context::<PlayerContext>()
+        .map(|player| player.previewed.clone())
+        .unwrap_or_default()
+}
+
+#[derive(Properties, PartialEq)]
+pub struct PreviewButtonProps {
+    pub track_id: String,
+    pub url: String,
+    pub title: String,
+}
+
+/// Plays a preview clip in the shared mini-player, or stops it if it's already playing.
+/// Tracks previewed before are shown muted.
+#[function_component(PreviewButton)]
+pub fn preview_button(props: &PreviewButtonProps) -> Html {
+    let player = use_context::<PlayerContext>().expect("PreviewButton must be inside a PlayerProvider");
+    let is_playing = player
+        .now_playing
+        .as_ref()
+        .is_some_and(|now_playing| now_playing.url == props.url);
+    let previewed = player.previewed.iter().any(|track| track.id == props.track_id);
+
+    let onclick = {
+        let player = player.clone();
+        let track = NowPlaying {
+            track_id: props.track_id.clone(),
+            url: props.url.clone(),
+            title: props.title.clone(),
+        };
+        Callback::from(move |_| {
+            if is_playing {
+                player.dispatch(PlayerAction::Stop);
+            } else {
+                player.dispatch(PlayerAction::Play(track.clone()));
+            }
+        })
+    };
+
+    let class = if previewed && !is_playing {
+        "px-3 py-1 text-sm rounded-md bg-gray-100 text-gray-600 hover:bg-gray-200"
+    } else {
+        "px-3 py-1 text-sm rounded-md bg-purple-100 text-purple-800 hover:bg-purple-200"
+    };
+
+    html! {
+        <button
+            {class}
+            aria-label={format!("{} preview of {}", if is_playing { "Stop" } else { "Play" }, props.title)}
+            {onclick}
+        >
+            if is_playing {
+                {"■ Stop"}
+            } else if previewed {
+                {"✓ Previewed"}
+            } else {
+                {"▶ Preview"}
+            }
+        </button>
+    }
+}
+
+/// Links to the most recently previewed tracks, hidden until something has been played
+#[function_component(RecentlyPreviewed)]
+pub fn recently_previewed() -> Html {
+    let history = use_preview_history();
+    if history.is_empty() {
+        return html! {};
+    }
+
+    html! {
+        <div class="bg-white rounded-lg shadow-md p-4 mb-6">
+            <h2 class="text-sm font-medium text-gray-700 mb-2">{"Recently previewed"}</h2>
+            <div class="flex flex-wrap gap-2">
+                {for history.iter().take(8).map(|track| html! {
+                    <Link<Route>
+                        to={Route::TrackDetail { id: track.id.clone() }}
+                        classes="px-2 py-1 text-xs rounded bg-gray-100 text-gray-700 hover:bg-purple-100 hover:text-purple-800"
+                    >
+                        {&track.title}
+                    </Link<Route>>
+                })}
+            </div>
+        </div>
+    }
+}
+
+/// Persistent player pinned to the bottom of the page while a preview plays
+#[function_component(MiniPlayer)]
+pub fn mini_player() -> Html {
+    let player = use_context::<PlayerContext>().expect("MiniPlayer must be inside a PlayerProvider");
+
+    let Some(now_playing) = player.now_playing.clone() else {
+        return html! {};
+    };
+
+    let stop = {
+        let player = player.clone();
+        Callback::from(move |_| player.dispatch(PlayerAction::Stop))
+    };
+    let on_ended = {
+        let player = player.clone();
+        Callback::from(move |_: Event| player.dispatch(PlayerAction::Stop))
+    };
+    let on_play = {
+        let player = player.clone();
+        Callback::from(move |_: Event| player.dispatch(PlayerAction::Played))
+    };
+
+    html! {
+        <>
+            // Keeps page content from ending up underneath the fixed player
+            <div class="h-20"></div>
+            <div class="fixed bottom-0 inset-x-0 bg-white border-t shadow-lg">
+                <div class="container mx-auto px-4 py-3 flex items-center space-x-4">
+                    <span class="text-sm font-medium text-gray-900 truncate flex-1">
+                        {"Now playing: "}{&now_playing.title}
+                    </span>
+                    // Keyed by URL so switching clips replaces the element and stops the old one
+                    <audio
+                        key={now_playing.url.clone()}
+                        src={now_playing.url.clone()}
+                        controls={true}
+                        autoplay={true}
+                        class="w-64"
+                        onended={on_ended}
+                        onplay={on_play}
+                    />
+                    <button
+                        class="text-gray-500 hover:text-gray-700"
+                        aria-label="Close player"
+                        onclick={stop}
+                    >
+                        {"✕"}
+                    </button>
+                </div>
+            </div>
+        </>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn previewed(id: &str) -> PreviewedTrack {
+        PreviewedTrack { id: id.to_string(), title: format!("Track {}", id) }
+    }
+
+    #[test]
+    fn test_record_preview_moves_replays_to_front_and_caps() {
+        let history = record_preview(&[previewed("a"), previewed("b")], previewed("b"));
+        assert_eq!(history, vec![previewed("b"), previewed("a")]);
+
+        let full: Vec<PreviewedTrack> = (0..MAX_PREVIEW_HISTORY).map(|i| previewed(&i.to_string())).collect();
+        let history = record_preview(&full, previewed("new"));
+        assert_eq!(history.len(), MAX_PREVIEW_HISTORY);
+        assert_eq!(history[0], previewed("new"));
+        assert_eq!(history.last(), Some(&previewed(&(MAX_PREVIEW_HISTORY - 2).to_string())));
+    }
+}
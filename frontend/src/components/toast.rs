@@ -0,0 +1,184 @@
+use gloo_timers::callback::Timeout;
+use std::rc::Rc;
+use yew::prelude::*;
+
+/// How long a toast stays up before dismissing itself
+const TOAST_DURATION_MS: u32 = 5_000;
+/// Toasts shown at once; pushing past this drops the oldest
+const MAX_TOASTS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToastKind {
+    Success,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Toast {
+    pub id: u32,
+    pub message: String,
+    pub kind: ToastKind,
+}
+
+pub enum ToastAction {
+    Push(String, ToastKind),
+    Dismiss(u32),
+}
+
+/// The toasts currently shown app-wide, oldest first
+#[derive(Default, PartialEq)]
+pub struct ToastQueue {
+    next_id: u32,
+    pub toasts: Vec<Toast>,
+}
+
+impl Reducible for ToastQueue {
+    type Action = ToastAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        match action {
+            ToastAction::Push(message, kind) => {
+                let toast = Toast { id: self.next_id, message, kind };
+                let skip = (self.toasts.len() + 1).saturating_sub(MAX_TOASTS);
+                let toasts = self.toasts.iter().skip(skip).cloned().chain(std::iter::once(toast)).collect();
+                Rc::new(ToastQueue { next_id: self.next_id.wrapping_add(1), toasts })
+            }
+            ToastAction::Dismiss(id) => {
+                if !self.toasts.iter().any(|toast| toast.id == id) {
+                    return self;
+                }
+                let toasts = self.toasts.iter().filter(|toast| toast.id != id).cloned().collect();
+                Rc::new(ToastQueue { next_id: self.next_id, toasts })
+            }
+        }
+    }
+}
+
+pub type ToastContext = UseReducerHandle<ToastQueue>;
+
+#[derive(Properties, PartialEq)]
+pub struct ToastProviderProps {
+    pub children: Children,
+}
+
+#[function_component(ToastProvider)]
+pub fn toast_provider(props: &ToastProviderProps) -> Html {
+    let queue = use_reducer(ToastQueue::default);
+
+    html! {
+        <ContextProvider<ToastContext> context={queue}>
+            {for props.children.iter()}
+        </ContextProvider<ToastContext>>
+    }
+}
+
+/// Pushes toasts onto the app-wide queue
+#[derive(Clone, PartialEq)]
+pub struct Toaster {
+    queue: ToastContext,
+}
+
+impl Toaster {
+    pub fn success(&self, message: impl Into<String>) {
+        self.queue.dispatch(ToastAction::Push(message.into(), ToastKind::Success));
+    }
+
+    pub fn error(&self, message: impl Into<String>) {
+        self.queue.dispatch(ToastAction::Push(message.into(), ToastKind::Error));
+    }
+}
+
+#[hook]
+pub fn use_toast() -> Toaster {
+    let queue = use_context::<ToastContext>().expect("use_toast must be inside a ToastProvider");
+    Toaster { queue }
+}
+
+#[derive(Properties, PartialEq)]
+struct ToastItemProps {
+    toast: Toast,
+    on_dismiss: Callback<u32>,
+}
+
+#[function_component(ToastItem)]
+fn toast_item(props: &ToastItemProps) -> Html {
+    // Dropping the timeout when the toast goes away early cancels it
+    {
+        let on_dismiss = props.on_dismiss.clone();
+        use_effect_with(props.toast.id, move |&id| {
+            let timeout = Timeout::new(TOAST_DURATION_MS, move || on_dismiss.emit(id));
+            move || drop(timeout)
+        });
+    }
+
+    let dismiss = {
+        let on_dismiss = props.on_dismiss.clone();
+        let id = props.toast.id;
+        Callback::from(move |_| on_dismiss.emit(id))
+    };
+    let variant_classes = match props.toast.kind {
+        ToastKind::Success => "bg-green-50 border-green-200 text-green-800",
+        ToastKind::Error => "bg-red-50 border-red-200 text-red-800",
+    };
+
+    html! {
+        <div
+            class={format!("flex items-start space-x-3 p-4 rounded-lg border shadow-md {}", variant_classes)}
+            role={if props.toast.kind == ToastKind::Error { "alert" } else { "status" }}
+        >
+            <span class="flex-1 text-sm break-words">{&props.toast.message}</span>
+            <button class="opacity-60 hover:opacity-100" aria-label="Dismiss notification" onclick={dismiss}>
+                {"✕"}
+            </button>
+        </div>
+    }
+}
+
+/// Stacks the queued toasts in the top-right corner, newest at the bottom
+#[function_component(ToastContainer)]
+pub fn toast_container() -> Html {
+    let queue = use_context::<ToastContext>().expect("ToastContainer must be inside a ToastProvider");
+    let on_dismiss = {
+        let queue = queue.clone();
+        Callback::from(move |id| queue.dispatch(ToastAction::Dismiss(id)))
+    };
+
+    html! {
+        <div class="fixed top-4 right-4 z-50 w-80 space-y-2" aria-live="polite">
+            {for queue.toasts.iter().map(|toast| html! {
+                <ToastItem key={toast.id} toast={toast.clone()} on_dismiss={on_dismiss.clone()} />
+            })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(queue: Rc<ToastQueue>, message: &str) -> Rc<ToastQueue> {
+        queue.reduce(ToastAction::Push(message.to_string(), ToastKind::Success))
+    }
+
+    fn messages(queue: &ToastQueue) -> Vec<&str> {
+        queue.toasts.iter().map(|toast| toast.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_toast_queue_dismisses_by_id_and_caps() {
+        let queue = push(push(Rc::new(ToastQueue::default()), "saved"), "imported");
+        let first = queue.toasts[0].id;
+        assert_ne!(first, queue.toasts[1].id);
+
+        let queue = queue.reduce(ToastAction::Dismiss(first));
+        assert_eq!(messages(&queue), vec!["imported"]);
+        // Dismissing a toast that's already gone, say by its timer after a click, changes nothing
+        let unchanged = queue.clone().reduce(ToastAction::Dismiss(first));
+        assert!(Rc::ptr_eq(&queue, &unchanged));
+
+        let queue = (0..MAX_TOASTS).fold(queue, |queue, i| push(queue, &i.to_string()));
+        assert_eq!(queue.toasts.len(), MAX_TOASTS);
+        assert_eq!(messages(&queue)[0], "0");
+        assert_eq!(messages(&queue).last(), Some(&(MAX_TOASTS - 1).to_string().as_str()));
+    }
+}
@@ -5,9 +5,12 @@ mod components;
 mod pages;
 mod services;
 mod types;
+mod utils;
 
 use components::navbar::Navbar;
-use pages::{Home, Artists, Tracks, Playlists, Recommendations};
+use components::player::{MiniPlayer, PlayerProvider};
+use components::toast::{ToastContainer, ToastProvider};
+use pages::{Home, Artists, Albums, Tracks, TrackDetailPage, Playlists, Recommendations};
 
 #[derive(Clone, Routable, PartialEq)]
 pub enum Route {
@@ -15,8 +18,15 @@ pub enum Route {
     Home,
     #[at("/artists")]
     Artists,
+    #[at("/albums")]
+    Albums,
     #[at("/tracks")]
     Tracks,
+    // Served by the backend with the list already rendered in
+    #[at("/tracks.html")]
+    TracksSnapshot,
+    #[at("/tracks/:id")]
+    TrackDetail { id: String },
     #[at("/playlists")]
     Playlists,
     #[at("/recommendations")]
@@ -27,7 +37,9 @@ fn switch(routes: Route) -> Html {
     match routes {
         Route::Home => html! { <Home /> },
         Route::Artists => html! { <Artists /> },
-        Route::Tracks => html! { <Tracks /> },
+        Route::Albums => html! { <Albums /> },
+        Route::Tracks | Route::TracksSnapshot => html! { <Tracks /> },
+        Route::TrackDetail { id } => html! { <TrackDetailPage {id} /> },
         Route::Playlists => html! { <Playlists /> },
         Route::Recommendations => html! { <Recommendations /> },
     }
@@ -37,12 +49,18 @@ fn switch(routes: Route) -> Html {
 pub fn app() -> Html {
     html! {
         <BrowserRouter>
-            <div class="min-h-screen bg-gray-100">
-                <Navbar />
-                <main class="container mx-auto px-4 py-8">
-                    <Switch<Route> render={switch} />
-                </main>
-            </div>
+            <ToastProvider>
+                <PlayerProvider>
+                    <div class="min-h-screen bg-gray-100">
+                        <Navbar />
+                        <main class="container mx-auto px-4 py-8">
+                            <Switch<Route> render={switch} />
+                        </main>
+                        <MiniPlayer />
+                        <ToastContainer />
+                    </div>
+                </PlayerProvider>
+            </ToastProvider>
         </BrowserRouter>
     }
 }
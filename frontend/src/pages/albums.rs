@@ -0,0 +1,180 @@
+use yew::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use crate::services::api::ApiService;
+use crate::types::{Album, Track};
+use crate::components::common::{Alert, Card, Skeleton, SkeletonLayout};
+use crate::utils::format_duration;
+
+#[function_component(Albums)]
+pub fn albums() -> Html {
+    let albums = use_state(Vec::<Album>::new);
+    let is_loading = use_state(|| false);
+    let error_message = use_state(|| None::<String>);
+    let selected_album = use_state(|| None::<Album>);
+    let album_tracks = use_state(Vec::<Track>::new);
+    let is_loading_tracks = use_state(|| false);
+
+    // Load albums on component mount
+    {
+        let albums = albums.clone();
+        let is_loading = is_loading.clone();
+        let error_message = error_message.clone();
+
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                is_loading.set(true);
+                match ApiService::get_albums().await {
+                    Ok(data) => {
+                        albums.set(data);
+                        error_message.set(None);
+                    }
+                    Err(error) => {
+                        error_message.set(Some(error));
+                    }
+                }
+                is_loading.set(false);
+            });
+            || ()
+        });
+    }
+
+    let select_album = {
+        let selected_album = selected_album.clone();
+        let album_tracks = album_tracks.clone();
+        let is_loading_tracks = is_loading_tracks.clone();
+        let error_message = error_message.clone();
+
+        Callback::from(move |album: Album| {
+            let album_tracks = album_tracks.clone();
+            let is_loading_tracks = is_loading_tracks.clone();
+            let error_message = error_message.clone();
+            let album_id = album.id.clone();
+            selected_album.set(Some(album));
+
+            spawn_local(async move {
+                is_loading_tracks.set(true);
+                album_tracks.set(Vec::new());
+                match ApiService::get_album_tracks(album_id).await {
+                    Ok(data) => {
+                        album_tracks.set(data);
+                        error_message.set(None);
+                    }
+                    Err(error) => {
+                        error_message.set(Some(error));
+                    }
+                }
+                is_loading_tracks.set(false);
+            });
+        })
+    };
+
+    let cover = |image_url: &Option<String>, name: &str, size: &str| -> Html {
+        match image_url.as_deref().filter(|url| !url.is_empty()) {
+            Some(url) => html! {
+                <img
+                    src={url.to_string()}
+                    alt={format!("{} cover art", name)}
+                    class={classes!(size.to_string(), "rounded-md", "object-cover")}
+                />
+            },
+            None => html! {
+                <div class={classes!(size.to_string(), "rounded-md", "bg-gray-200", "flex", "items-center", "justify-center")}>
+                    <span class="text-2xl">{"💿"}</span>
+                </div>
+            },
+        }
+    };
+
+    html! {
+        <div class="max-w-6xl mx-auto">
+            <div class="flex justify-between items-center mb-6">
+                <h1 class="text-3xl font-bold text-gray-900">{"Albums"}</h1>
+            </div>
+
+            if let Some(error) = (*error_message).clone() {
+                <Alert message={error} error={true} />
+            }
+
+            if let Some(album) = (*selected_album).clone() {
+                <div class="bg-white rounded-lg shadow-md p-6 mb-6">
+                    <div class="flex items-start space-x-4 mb-4">
+                        {cover(&album.image_url, &album.name, "w-24 h-24")}
+                        <div class="flex-1 min-w-0">
+                            <h2 class="text-2xl font-semibold text-gray-900 truncate">{&album.name}</h2>
+                            <p class="text-gray-600">{album.artist_names.join(", ")}</p>
+                        </div>
+                        <button
+                            class="text-gray-500 hover:text-gray-700"
+                            aria-label="Close album"
+                            onclick={
+                                let selected_album = selected_album.clone();
+                                Callback::from(move |_| selected_album.set(None))
+                            }
+                        >
+                            {"✕"}
+                        </button>
+                    </div>
+
+                    if *is_loading_tracks {
+                        <Skeleton layout={SkeletonLayout::ListRows} count={4} />
+                    } else {
+                        <ol class="divide-y divide-gray-200">
+                            {for album_tracks.iter().map(|track| {
+                                html! {
+                                    <li class="flex justify-between py-2 text-sm">
+                                        <span class="text-gray-900">{&track.name}</span>
+                                        <span class="text-gray-500">{format_duration(track.duration_ms)}</span>
+                                    </li>
+                                }
+                            })}
+                        </ol>
+                    }
+                </div>
+            }
+
+            if *is_loading && albums.is_empty() {
+                <Skeleton layout={SkeletonLayout::CardGrid} />
+            } else if albums.is_empty() {
+                <Card title="No Albums Found">
+                    <p class="text-gray-600">
+                        {"No albums have been imported yet. Import a Spotify playlist to see albums here."}
+                    </p>
+                </Card>
+            } else {
+                <div class="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-3 gap-6">
+                    {for albums.iter().map(|album| {
+                        let onclick = {
+                            let select_album = select_album.clone();
+                            let album = album.clone();
+                            Callback::from(move |_| select_album.emit(album.clone()))
+                        };
+
+                        html! {
+                            <button
+                                class="bg-white rounded-lg shadow-md p-6 hover:shadow-lg transition-shadow text-left"
+                                {onclick}
+                            >
+                                <div class="flex items-start space-x-4">
+                                    {cover(&album.image_url, &album.name, "w-16 h-16")}
+
+                                    <div class="flex-1 min-w-0">
+                                        <h3 class="text-lg font-semibold text-gray-900 truncate">
+                                            {&album.name}
+                                        </h3>
+                                        <p class="text-sm text-gray-600 truncate">
+                                            {album.artist_names.join(", ")}
+                                        </p>
+                                        <div class="mt-2 flex items-center text-sm text-gray-600">
+                                            <span class="font-medium">{"Tracks:"}</span>
+                                            <span class="ml-1">{album.track_count}</span>
+                                        </div>
+                                    </div>
+                                </div>
+                            </button>
+                        }
+                    })}
+                </div>
+            }
+        </div>
+    }
+}
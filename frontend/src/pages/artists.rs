@@ -1,31 +1,38 @@
 use yew::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use crate::services::api::ApiService;
-use crate::types::Artist;
-use crate::components::common::{Alert, Loading, Card};
+use crate::types::ArtistSummary;
+use crate::utils::image_for_width;
+use crate::components::common::{Card, Skeleton, SkeletonLayout};
+use crate::components::toast::use_toast;
+
+const PAGE_SIZE: u32 = 60;
 
 #[function_component(Artists)]
 pub fn artists() -> Html {
-    let artists = use_state(|| Vec::<Artist>::new());
+    let artists = use_state(Vec::<ArtistSummary>::new);
+    let total_artists = use_state(|| 0u32);
     let is_loading = use_state(|| false);
-    let error_message = use_state(|| None::<String>);
+    let is_loading_more = use_state(|| false);
+    let toast = use_toast();
 
     // Load artists on component mount
     {
         let artists = artists.clone();
+        let total_artists = total_artists.clone();
         let is_loading = is_loading.clone();
-        let error_message = error_message.clone();
+        let toast = toast.clone();
         
         use_effect_with((), move |_| {
             spawn_local(async move {
                 is_loading.set(true);
-                match ApiService::get_artists().await {
-                    Ok(data) => {
+                match ApiService::get_artists(0, PAGE_SIZE).await {
+                    Ok((data, total)) => {
                         artists.set(data);
-                        error_message.set(None);
+                        total_artists.set(total);
                     }
                     Err(error) => {
-                        error_message.set(Some(error));
+                        toast.error(error);
                     }
                 }
                 is_loading.set(false);
@@ -36,23 +43,25 @@ pub fn artists() -> Html {
 
     let refresh_artists = {
         let artists = artists.clone();
+        let total_artists = total_artists.clone();
         let is_loading = is_loading.clone();
-        let error_message = error_message.clone();
+        let toast = toast.clone();
         
         Callback::from(move |_| {
             let artists = artists.clone();
+            let total_artists = total_artists.clone();
             let is_loading = is_loading.clone();
-            let error_message = error_message.clone();
+            let toast = toast.clone();
             
             spawn_local(async move {
                 is_loading.set(true);
-                match ApiService::get_artists().await {
-                    Ok(data) => {
+                match ApiService::get_artists(0, PAGE_SIZE).await {
+                    Ok((data, total)) => {
                         artists.set(data);
-                        error_message.set(None);
+                        total_artists.set(total);
                     }
                     Err(error) => {
-                        error_message.set(Some(error));
+                        toast.error(error);
                     }
                 }
                 is_loading.set(false);
@@ -60,6 +69,37 @@ pub fn artists() -> Html {
         })
     };
 
+    let load_more_artists = {
+        let artists = artists.clone();
+        let total_artists = total_artists.clone();
+        let is_loading_more = is_loading_more.clone();
+        let toast = toast.clone();
+
+        Callback::from(move |_| {
+            let artists = artists.clone();
+            let total_artists = total_artists.clone();
+            let is_loading_more = is_loading_more.clone();
+            let toast = toast.clone();
+
+            spawn_local(async move {
+                is_loading_more.set(true);
+                let offset = artists.len() as u32;
+                match ApiService::get_artists(offset, PAGE_SIZE).await {
+                    Ok((data, total)) => {
+                        let mut all_artists = (*artists).clone();
+                        all_artists.extend(data);
+                        artists.set(all_artists);
+                        total_artists.set(total);
+                    }
+                    Err(error) => {
+                        toast.error(error);
+                    }
+                }
+                is_loading_more.set(false);
+            });
+        })
+    };
+
     html! {
         <div class="max-w-6xl mx-auto">
             <div class="flex justify-between items-center mb-6">
@@ -77,12 +117,8 @@ pub fn artists() -> Html {
                 </button>
             </div>
 
-            if let Some(error) = (*error_message).clone() {
-                <Alert message={error} error={true} />
-            }
-
             if *is_loading && artists.is_empty() {
-                <Loading message={Some("Loading artists...".to_string())} />
+                <Skeleton layout={SkeletonLayout::CardGrid} />
             } else if artists.is_empty() {
                 <Card title="No Artists Found">
                     <p class="text-gray-600">
@@ -95,21 +131,14 @@ pub fn artists() -> Html {
                         html! {
                             <div class="bg-white rounded-lg shadow-md p-6 hover:shadow-lg transition-shadow">
                                 <div class="flex items-start space-x-4">
-                                    {if let Some(image_url) = &artist.image_url {
-                                        if !image_url.is_empty() {
-                                            html! {
-                                                <img 
-                                                    src={image_url.clone()} 
-                                                    alt={format!("{} image", artist.name)}
-                                                    class="w-16 h-16 rounded-full object-cover"
-                                                />
-                                            }
-                                        } else {
-                                            html! {
-                                                <div class="w-16 h-16 rounded-full bg-gray-200 flex items-center justify-center">
-                                                    <span class="text-2xl">{"🎤"}</span>
-                                                </div>
-                                            }
+                                    // 64px avatars, so twice that for high-density screens
+                                    {if let Some(image_url) = image_for_width(&artist.image_urls, artist.image_url.as_deref(), 128) {
+                                        html! {
+                                            <img 
+                                                src={image_url} 
+                                                alt={format!("{} image", artist.name)}
+                                                class="w-16 h-16 rounded-full object-cover"
+                                            />
                                         }
                                     } else {
                                         html! {
@@ -129,45 +158,32 @@ pub fn artists() -> Html {
                                                 <span class="font-medium">{"Popularity:"}</span>
                                                 <span class="ml-1">{artist.popularity}{"/100"}</span>
                                             </div>
-                                            
-                                            <div class="flex items-center text-sm text-gray-600">
-                                                <span class="font-medium">{"Followers:"}</span>
-                                                <span class="ml-1">{artist.followers}</span>
-                                            </div>
                                         </div>
-                                        
-                                        {if !artist.genres.is_empty() {
-                                            html! {
-                                                <div class="mt-3">
-                                                    <div class="flex flex-wrap gap-1">
-                                                        {for artist.genres.iter().take(3).map(|genre| {
-                                                            html! {
-                                                                <span class="px-2 py-1 bg-purple-100 text-purple-800 text-xs rounded-full">
-                                                                    {genre}
-                                                                </span>
-                                                            }
-                                                        })}
-                                                        {if artist.genres.len() > 3 {
-                                                            html! {
-                                                                <span class="px-2 py-1 bg-gray-100 text-gray-600 text-xs rounded-full">
-                                                                    {format!("+{} more", artist.genres.len() - 3)}
-                                                                </span>
-                                                            }
-                                                        } else {
-                                                            html! {}
-                                                        }}
-                                                    </div>
-                                                </div>
-                                            }
-                                        } else {
-                                            html! {}
-                                        }}
                                     </div>
                                 </div>
                             </div>
                         }
                     })}
                 </div>
+
+                <div class="flex flex-col items-center mt-6 space-y-2">
+                    <p class="text-sm text-gray-600">
+                        {format!("Showing {} of {} artists", artists.len(), *total_artists)}
+                    </p>
+                    if (artists.len() as u32) < *total_artists {
+                        <button
+                            class="px-4 py-2 bg-purple-600 text-white rounded-md hover:bg-purple-700 disabled:bg-purple-300"
+                            onclick={load_more_artists}
+                            disabled={*is_loading_more}
+                        >
+                            if *is_loading_more {
+                                {"Loading..."}
+                            } else {
+                                {"Load more"}
+                            }
+                        </button>
+                    }
+                </div>
             }
         </div>
     }
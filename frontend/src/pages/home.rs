@@ -1,35 +1,41 @@
 use yew::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use crate::services::api::ApiService;
-use crate::components::common::{Alert, Button, Card};
+use crate::components::common::{Button, Card};
+use crate::components::toast::use_toast;
+use crate::types::Track;
+use crate::utils::format_duration;
 
 #[function_component(Home)]
 pub fn home() -> Html {
     let status = use_state(|| "Ready".to_string());
     let is_loading = use_state(|| false);
-    let playlist_url = use_state(|| String::new());
-    let alert_message = use_state(|| None::<(String, bool)>);
+    let playlist_url = use_state(String::new);
+    let toast = use_toast();
+    let preview = use_state(|| None::<Vec<Track>>);
+    // Set while an import runs, so it can be cancelled
+    let import_job = use_state(|| None::<String>);
 
     let check_health = {
         let status = status.clone();
         let is_loading = is_loading.clone();
-        let alert_message = alert_message.clone();
+        let toast = toast.clone();
         
         Callback::from(move |_| {
             let status = status.clone();
             let is_loading = is_loading.clone();
-            let alert_message = alert_message.clone();
+            let toast = toast.clone();
             
             spawn_local(async move {
                 is_loading.set(true);
                 match ApiService::health_check().await {
                     Ok(response) => {
                         status.set("Connected".to_string());
-                        alert_message.set(Some((response, false)));
+                        toast.success(response);
                     }
                     Err(error) => {
                         status.set("Error".to_string());
-                        alert_message.set(Some((error, true)));
+                        toast.error(error);
                     }
                 }
                 is_loading.set(false);
@@ -40,26 +46,82 @@ pub fn home() -> Html {
     let import_spotify_data = {
         let playlist_url = playlist_url.clone();
         let is_loading = is_loading.clone();
-        let alert_message = alert_message.clone();
+        let toast = toast.clone();
+        let import_job = import_job.clone();
         
         Callback::from(move |_| {
             let url = (*playlist_url).clone();
             let is_loading = is_loading.clone();
-            let alert_message = alert_message.clone();
+            let toast = toast.clone();
+            let import_job = import_job.clone();
             
             if url.trim().is_empty() {
-                alert_message.set(Some(("Please enter a Spotify playlist URL".to_string(), true)));
+                toast.error("Please enter a Spotify playlist URL");
                 return;
             }
             
+            let job_id = format!("import-{}-{}", js_sys::Date::now() as u64, (js_sys::Math::random() * 1e9) as u64);
             spawn_local(async move {
                 is_loading.set(true);
-                match ApiService::import_spotify_data(url).await {
+                import_job.set(Some(job_id.clone()));
+                match ApiService::import_spotify_data(url, job_id).await {
                     Ok(response) => {
-                        alert_message.set(Some((response, false)));
+                        toast.success(response);
                     }
                     Err(error) => {
-                        alert_message.set(Some((error, true)));
+                        toast.error(error);
+                    }
+                }
+                import_job.set(None);
+                is_loading.set(false);
+            });
+        })
+    };
+
+    let cancel_import = {
+        let import_job = import_job.clone();
+        let toast = toast.clone();
+
+        Callback::from(move |_| {
+            let Some(job_id) = (*import_job).clone() else {
+                return;
+            };
+            let toast = toast.clone();
+
+            spawn_local(async move {
+                if let Err(error) = ApiService::cancel_import(&job_id).await {
+                    toast.error(error);
+                }
+            });
+        })
+    };
+
+    let preview_playlist = {
+        let playlist_url = playlist_url.clone();
+        let is_loading = is_loading.clone();
+        let toast = toast.clone();
+        let preview = preview.clone();
+
+        Callback::from(move |_| {
+            let url = (*playlist_url).clone();
+            let is_loading = is_loading.clone();
+            let toast = toast.clone();
+            let preview = preview.clone();
+
+            if url.trim().is_empty() {
+                toast.error("Please enter a Spotify playlist URL");
+                return;
+            }
+
+            spawn_local(async move {
+                is_loading.set(true);
+                match ApiService::preview_playlist(url).await {
+                    Ok(tracks) => {
+                        preview.set(Some(tracks));
+                    }
+                    Err(error) => {
+                        preview.set(None);
+                        toast.error(error);
                     }
                 }
                 is_loading.set(false);
@@ -86,10 +148,6 @@ pub fn home() -> Html {
                 </p>
             </div>
 
-            if let Some((message, is_error)) = (*alert_message).clone() {
-                <Alert message={message} error={is_error} />
-            }
-
             <div class="grid grid-cols-1 md:grid-cols-2 gap-6">
                 <Card title="System Status">
                     <div class="space-y-4">
@@ -133,21 +191,55 @@ pub fn home() -> Html {
                                 oninput={on_url_change}
                             />
                         </div>
-                        <Button
-                            onclick={import_spotify_data}
-                            disabled={Some(*is_loading)}
-                            variant="primary"
-                        >
-                            if *is_loading {
-                                {"Importing..."}
-                            } else {
-                                {"Import Playlist"}
+                        <div class="flex space-x-2">
+                            <Button
+                                onclick={preview_playlist}
+                                disabled={Some(*is_loading)}
+                                variant="secondary"
+                            >
+                                {"Preview"}
+                            </Button>
+                            <Button
+                                onclick={import_spotify_data}
+                                disabled={Some(*is_loading)}
+                                variant="primary"
+                            >
+                                if *is_loading {
+                                    {"Working..."}
+                                } else {
+                                    {"Import Playlist"}
+                                }
+                            </Button>
+                            if import_job.is_some() {
+                                <Button onclick={cancel_import} disabled={Some(false)} variant="danger">
+                                    {"Cancel"}
+                                </Button>
                             }
-                        </Button>
+                        </div>
                     </div>
                 </Card>
             </div>
 
+            if let Some(tracks) = (*preview).clone() {
+                <Card title={format!("Playlist Preview ({} tracks)", tracks.len())}>
+                    if tracks.is_empty() {
+                        <p class="text-gray-600">{"This playlist has no tracks."}</p>
+                    } else {
+                        <ol class="divide-y divide-gray-200 max-h-96 overflow-y-auto">
+                            {for tracks.iter().map(|track| html! {
+                                <li class="flex justify-between py-2 text-sm">
+                                    <span class="text-gray-900">
+                                        {&track.name}
+                                        <span class="text-gray-500">{" · "}{track.artist_names.join(", ")}</span>
+                                    </span>
+                                    <span class="text-gray-500">{format_duration(track.duration_ms)}</span>
+                                </li>
+                            })}
+                        </ol>
+                    }
+                </Card>
+            }
+
             <Card title="Features">
                 <div class="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-4 gap-4">
                     <div class="text-center p-4">
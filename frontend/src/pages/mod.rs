@@ -1,11 +1,15 @@
 pub mod home;
 pub mod artists;
+pub mod albums;
 pub mod tracks;
+pub mod track_detail;
 pub mod recommendations;
 pub mod playlists;
 
 pub use home::Home;
 pub use artists::Artists;
+pub use albums::Albums;
 pub use tracks::Tracks;
+pub use track_detail::TrackDetailPage;
 pub use recommendations::Recommendations;
 pub use playlists::Playlists;
@@ -1,18 +1,34 @@
+use std::collections::HashSet;
 use yew::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use crate::services::api::ApiService;
-use crate::types::{Track, CreatedPlaylist};
-use crate::components::common::{/*Alert,*/ Loading, Card, Button};
+use crate::types::{ApprovedPlaylistRequest, ApprovedVideo, Track, TrackMatch, CreatedPlaylist};
+use crate::components::common::{Alert, Loading, Card, Button};
+
+// Non-empty lines of the manual track list
+fn query_lines(queries: &str) -> Vec<String> {
+    queries
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
 
 #[function_component(Playlists)]
 pub fn playlists() -> Html {
-    let tracks = use_state(|| Vec::<Track>::new());
-    let selected_track_id = use_state(|| String::new());
-    let playlist_title = use_state(|| String::new());
-    let playlist_description = use_state(|| String::new());
+    let tracks = use_state(Vec::<Track>::new);
+    let selected_track_id = use_state(String::new);
+    let playlist_title = use_state(String::new);
+    let playlist_description = use_state(String::new);
     let created_playlist = use_state(|| None::<CreatedPlaylist>);
-    let track_queries = use_state(|| String::new());
+    let track_queries = use_state(String::new);
     let recommendation_limit = use_state(|| 10u32);
+    let suggested_names = use_state(Vec::<String>::new);
+    // Matches for the manual track list awaiting approval, and which of them are ticked
+    let preview = use_state(|| None::<Vec<TrackMatch>>);
+    let approved = use_state(HashSet::<usize>::new);
+    let youtube_token = use_state(String::new);
+    let is_previewing = use_state(|| false);
     
     let is_loading_tracks = use_state(|| false);
     let is_loading_playlist = use_state(|| false);
@@ -28,8 +44,8 @@ pub fn playlists() -> Html {
         use_effect_with((), move |_| {
             spawn_local(async move {
                 is_loading_tracks.set(true);
-                match ApiService::get_tracks().await {
-                    Ok(data) => {
+                match ApiService::get_tracks(false).await {
+                    Ok((data, _)) => {
                         tracks.set(data);
                         error_message.set(None);
                     }
@@ -43,6 +59,31 @@ pub fn playlists() -> Html {
         });
     }
 
+    // Suggest names for the picked seed track, filling in the title unless the user has typed their own
+    {
+        let playlist_title = playlist_title.clone();
+        let suggested_names = suggested_names.clone();
+
+        use_effect_with((*selected_track_id).clone(), move |track_id| {
+            let track_id = track_id.clone();
+            let fill_title = playlist_title.trim().is_empty() || suggested_names.contains(&*playlist_title);
+            if track_id.is_empty() {
+                suggested_names.set(Vec::new());
+            } else {
+                spawn_local(async move {
+                    // A suggestion is a convenience, so a failure just leaves the title alone
+                    if let Ok(suggestions) = ApiService::suggest_playlist_names(track_id).await {
+                        if let Some(name) = suggestions.names.first().filter(|_| fill_title) {
+                            playlist_title.set(name.clone());
+                        }
+                        suggested_names.set(suggestions.names);
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
     let create_manual_playlist = {
         let playlist_title = playlist_title.clone();
         let playlist_description = playlist_description.clone();
@@ -69,11 +110,7 @@ pub fn playlists() -> Html {
                 return;
             }
             
-            let query_list: Vec<String> = queries
-                .lines()
-                .map(|line| line.trim().to_string())
-                .filter(|line| !line.is_empty())
-                .collect();
+            let query_list = query_lines(&queries);
             
             if query_list.is_empty() {
                 error_message.set(Some("Please enter valid track queries".to_string()));
@@ -96,6 +133,110 @@ pub fn playlists() -> Html {
         })
     };
 
+    let preview_matches = {
+        let track_queries = track_queries.clone();
+        let preview = preview.clone();
+        let approved = approved.clone();
+        let is_previewing = is_previewing.clone();
+        let error_message = error_message.clone();
+
+        Callback::from(move |_: MouseEvent| {
+            let query_list = query_lines(&track_queries);
+            if query_list.is_empty() {
+                error_message.set(Some("Please enter some track queries".to_string()));
+                return;
+            }
+
+            let preview = preview.clone();
+            let approved = approved.clone();
+            let is_previewing = is_previewing.clone();
+            let error_message = error_message.clone();
+            spawn_local(async move {
+                is_previewing.set(true);
+                match ApiService::preview_youtube_playlist(query_list).await {
+                    Ok(matches) => {
+                        // Every found video starts approved
+                        approved.set(matches.iter().enumerate().filter(|(_, m)| m.video.is_some()).map(|(i, _)| i).collect());
+                        preview.set(Some(matches));
+                        error_message.set(None);
+                    }
+                    Err(error) => {
+                        error_message.set(Some(error));
+                    }
+                }
+                is_previewing.set(false);
+            });
+        })
+    };
+
+    let create_approved_playlist = {
+        let playlist_title = playlist_title.clone();
+        let playlist_description = playlist_description.clone();
+        let preview = preview.clone();
+        let approved = approved.clone();
+        let youtube_token = youtube_token.clone();
+        let created_playlist = created_playlist.clone();
+        let is_loading_playlist = is_loading_playlist.clone();
+        let error_message = error_message.clone();
+
+        Callback::from(move |_: MouseEvent| {
+            let title = (*playlist_title).clone();
+            if title.trim().is_empty() {
+                error_message.set(Some("Please enter a playlist title".to_string()));
+                return;
+            }
+            if youtube_token.trim().is_empty() {
+                error_message.set(Some("Please enter your YouTube access token".to_string()));
+                return;
+            }
+
+            let videos: Vec<ApprovedVideo> = preview
+                .iter()
+                .flatten()
+                .enumerate()
+                .filter(|(index, _)| approved.contains(index))
+                .filter_map(|(_, m)| {
+                    m.video.as_ref().map(|video| ApprovedVideo {
+                        track_name: m.track_name.clone(),
+                        video_id: video.id.clone(),
+                        video_title: video.title.clone(),
+                        confidence: video.confidence,
+                    })
+                })
+                .collect();
+            if videos.is_empty() {
+                error_message.set(Some("Please approve at least one video".to_string()));
+                return;
+            }
+
+            let description = Some((*playlist_description).clone()).filter(|d| !d.trim().is_empty());
+            let request = ApprovedPlaylistRequest {
+                name: title,
+                description,
+                access_token: youtube_token.trim().to_string(),
+                videos,
+            };
+            let preview = preview.clone();
+            let created_playlist = created_playlist.clone();
+            let is_loading_playlist = is_loading_playlist.clone();
+            let error_message = error_message.clone();
+            spawn_local(async move {
+                is_loading_playlist.set(true);
+                match ApiService::create_playlist_from_videos(request).await {
+                    Ok(playlist) => {
+                        created_playlist.set(Some(playlist));
+                        preview.set(None);
+                        error_message.set(None);
+                    }
+                    Err(error) => {
+                        error_message.set(Some(error));
+                    }
+                }
+                is_loading_playlist.set(false);
+            });
+        })
+    };
+
     let create_recommendation_playlist = {
         let playlist_title = playlist_title.clone();
         let playlist_description = playlist_description.clone();
@@ -153,9 +294,9 @@ pub fn playlists() -> Html {
         <div class="max-w-4xl mx-auto space-y-6">
             <h1 class="text-3xl font-bold text-gray-900">{"Create YouTube Playlists"}</h1>
 
-            // if let Some(error) = (*error_message).clone() {
-            //     <Alert message={error} error={true} />
-            // }
+            if let Some(error) = (*error_message).clone() {
+                <Alert message={error} error={true} />
+            }
 
             // Tab Navigation
             <div class="border-b border-gray-200">
@@ -214,6 +355,27 @@ pub fn playlists() -> Html {
                                 })
                             }
                         />
+                        if *active_tab == "recommendations" && !suggested_names.is_empty() {
+                            <div class="flex flex-wrap items-center gap-2 mt-2 text-xs">
+                                <span class="text-gray-500">{"Suggestions:"}</span>
+                                {for suggested_names.iter().map(|name| {
+                                    let playlist_title = playlist_title.clone();
+                                    let name = name.clone();
+                                    html! {
+                                        <button
+                                            type="button"
+                                            class="px-2 py-1 rounded bg-purple-50 text-purple-700 hover:bg-purple-100"
+                                            onclick={
+                                                let name = name.clone();
+                                                Callback::from(move |_| playlist_title.set(name.clone()))
+                                            }
+                                        >
+                                            {name}
+                                        </button>
+                                    }
+                                })}
+                            </div>
+                        }
                     </div>
                     
                     <div>
@@ -237,139 +399,224 @@ pub fn playlists() -> Html {
                 </div>
             </Card>
 
-            //{if *active_tab == "manual" {
-//                html! {
-//                    <Card title="Manual Track Queries">
-//                        <div class="space-y-4">
-//                            <div>
-//                                <label class="block text-sm font-medium text-gray-700 mb-2">
-//                                    {"Track Queries (one per line) *"}
-//                                </label>
-//                                <textarea
-//                                    class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
-//                                    rows="10"
-//                                    placeholder={concat!(
-//                                        "Enter track queries, one per line:\n",
-//                                        "The Beatles - Hey Jude\n",
-//                                        "Queen - Bohemian Rhapsody\n",
-//                                        "Led Zeppelin - Stairway to Heaven"
-//                                    )}
-//                                    value={(*track_queries).clone()}
-//                                    oninput={
-//                                        let track_queries = track_queries.clone();
-//                                        Callback::from(move |e: InputEvent| {
-//                                            let textarea: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
-//                                            track_queries.set(textarea.value());
-//                                        })
-//                                    }
-//                                />
-//                                <p class="text-sm text-gray-600 mt-1">
-//                                    {"Format: Artist - Song Title (one per line)"}
-//                                </p>
-//                            </div>
-//                            
-//                            <Button
-//                                onclick={create_manual_playlist}
-//                                disabled={Some(*is_loading_playlist)}
-//                                variant="primary"
-//                            >
-//                                if *is_loading_playlist {
-//                                    {"Creating Playlist..."}
-//                                } else {
-//                                    {"Create YouTube Playlist"}
-//                                }
-//                            </Button>
-//                        </div>
-//                    </Card>
-//                }
-//            } else {
-//                html! {
-//                    <Card title="Create from Recommendations">
-//                        <div class="space-y-4">
-//                            <div>
-//                                <label class="block text-sm font-medium text-gray-700 mb-2">
-//                                    {"Select a track to base recommendations on: *"}
-//                                </label>
-//                                {if *is_loading_tracks {
-//                                    <div class="text-gray-500">{"Loading tracks..."</div>
-//                                } else if tracks.is_empty() {
-//                                    <div class="text-gray-500">
-//                                        {"No tracks available. Import a Spotify playlist first."}
-//                                    </div>
-//                                } else {
-//                                    html! {
-//                                        <select
-//                                            class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
-//                                            value={(*selected_track_id).clone()}
-//                                            onchange={
-//                                                let selected_track_id = selected_track_id.clone();
-//                                                Callback::from(move |e: Event| {
-//                                                    let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
-//                                                    selected_track_id.set(select.value());
-//                                                })
-//                                            }
-//                                        >
-//                                            <option value="">{"-- Select a track --"}</option>
-//                                            {for tracks.iter().map(|track| {
-//                                                html! {
-//                                                    <option value={track.id.clone()}>
-//                                                        {format!("{} - {}", track.name, track.artist_names.join(", "))}
-//                                                    </option>
-//                                                }
-//                                            })}
-//                                        </select>
-//                                    }
-//                                }}
-//                            </div>
-//                            
-//                            <div>
-//                                <label class="block text-sm font-medium text-gray-700 mb-2">
-//                                    {"Number of recommended tracks:"}
-//                                </label>
-//                                <input
-//                                    type="number"
-//                                    min="1"
-//                                    max="50"
-//                                    value={recommendation_limit.to_string()}
-//                                    oninput={
-//                                        let recommendation_limit = recommendation_limit.clone();
-//                                        Callback::from(move |e: InputEvent| {
-//                                            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
-//                                            if let Ok(value) = input.value().parse::<u32>() {
-//                                                recommendation_limit.set(value.max(1).min(50));
-//                                            }
-//                                        })
-//                                    }
-//                                    class="w-24 px-2 py-1 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
-//                                />
-//                            </div>
-//                            
-//                            <Button
-//                                onclick={create_recommendation_playlist}
-//                                disabled={Some(*is_loading_playlist || selected_track_id.is_empty())}
-//                                variant="primary"
-//                            >
-//                                if *is_loading_playlist {
-//                                    {"Creating Playlist from Recommendations..."}
-//                                } else {
-//                                    {"Create Playlist from Recommendations"}
-//                                }
-//                            </Button>
-//                        </div>
-//                    </Card>
-//                //}
-//            //}}
+            {if *active_tab == "manual" {
+                html! {
+                    <Card title="Manual Track Queries">
+                        <div class="space-y-4">
+                            <div>
+                                <label class="block text-sm font-medium text-gray-700 mb-2">
+                                    {"Track Queries (one per line) *"}
+                                </label>
+                                <textarea
+                                    class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
+                                    rows="10"
+                                    placeholder={concat!(
+                                        "Enter track queries, one per line:\n",
+                                        "The Beatles - Hey Jude\n",
+                                        "Queen - Bohemian Rhapsody\n",
+                                        "Led Zeppelin - Stairway to Heaven"
+                                    )}
+                                    value={(*track_queries).clone()}
+                                    oninput={
+                                        let track_queries = track_queries.clone();
+                                        let preview = preview.clone();
+                                        Callback::from(move |e: InputEvent| {
+                                            let textarea: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
+                                            track_queries.set(textarea.value());
+                                            // The matches no longer describe the list
+                                            preview.set(None);
+                                        })
+                                    }
+                                />
+                                <p class="text-sm text-gray-600 mt-1">
+                                    {"Format: Artist - Song Title (one per line)"}
+                                </p>
+                            </div>
+                            
+                            <div class="flex gap-3">
+                                <Button
+                                    onclick={preview_matches}
+                                    disabled={Some(*is_previewing || *is_loading_playlist)}
+                                    variant="secondary"
+                                >
+                                    if *is_previewing {
+                                        {"Searching..."}
+                                    } else {
+                                        {"Preview Matches"}
+                                    }
+                                </Button>
+                                <Button
+                                    onclick={create_manual_playlist}
+                                    disabled={Some(*is_loading_playlist)}
+                                    variant="primary"
+                                >
+                                    if *is_loading_playlist {
+                                        {"Creating Playlist..."}
+                                    } else {
+                                        {"Create YouTube Playlist"}
+                                    }
+                                </Button>
+                            </div>
+                        </div>
+                    </Card>
+                }
+            } else {
+                html! {
+                    <Card title="Create from Recommendations">
+                        <div class="space-y-4">
+                            <div>
+                                <label class="block text-sm font-medium text-gray-700 mb-2">
+                                    {"Select a track to base recommendations on: *"}
+                                </label>
+                                {if *is_loading_tracks {
+                                    html! { <div class="text-gray-500">{"Loading tracks..."}</div> }
+                                } else if tracks.is_empty() {
+                                    html! {
+                                        <div class="text-gray-500">
+                                            {"No tracks available. Import a Spotify playlist first."}
+                                        </div>
+                                    }
+                                } else {
+                                    html! {
+                                        <select
+                                            class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
+                                            value={(*selected_track_id).clone()}
+                                            onchange={
+                                                let selected_track_id = selected_track_id.clone();
+                                                Callback::from(move |e: Event| {
+                                                    let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+                                                    selected_track_id.set(select.value());
+                                                })
+                                            }
+                                        >
+                                            <option value="">{"-- Select a track --"}</option>
+                                            {for tracks.iter().map(|track| {
+                                                html! {
+                                                    <option value={track.id.clone()}>
+                                                        {format!("{} - {}", track.name, track.artist_names.join(", "))}
+                                                    </option>
+                                                }
+                                            })}
+                                        </select>
+                                    }
+                                }}
+                            </div>
+                            
+                            <div>
+                                <label class="block text-sm font-medium text-gray-700 mb-2">
+                                    {"Number of recommended tracks:"}
+                                </label>
+                                <input
+                                    type="number"
+                                    min="1"
+                                    max="50"
+                                    value={recommendation_limit.to_string()}
+                                    oninput={
+                                        let recommendation_limit = recommendation_limit.clone();
+                                        Callback::from(move |e: InputEvent| {
+                                            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                                            if let Ok(value) = input.value().parse::<u32>() {
+                                                recommendation_limit.set(value.clamp(1, 50));
+                                            }
+                                        })
+                                    }
+                                    class="w-24 px-2 py-1 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
+                                />
+                            </div>
+                            
+                            <Button
+                                onclick={create_recommendation_playlist}
+                                disabled={Some(*is_loading_playlist || selected_track_id.is_empty())}
+                                variant="primary"
+                            >
+                                if *is_loading_playlist {
+                                    {"Creating Playlist from Recommendations..."}
+                                } else {
+                                    {"Create Playlist from Recommendations"}
+                                }
+                            </Button>
+                        </div>
+                    </Card>
+                }
+            }}
+
+            {if let (true, Some(matches)) = (*active_tab == "manual", (*preview).clone()) {
+                html! {
+                    <Card title="Review Matches">
+                        <div class="space-y-4">
+                            <p class="text-sm text-gray-600">
+                                {format!("{} of {} videos approved. Untick any that aren't the right recording.", approved.len(), matches.len())}
+                            </p>
+                            <ul class="divide-y divide-gray-200">
+                                {for matches.iter().enumerate().map(|(index, m)| {
+                                    let approved = approved.clone();
+                                    match &m.video {
+                                        Some(video) => html! {
+                                            <li class="flex items-center gap-3 py-2">
+                                                <input
+                                                    type="checkbox"
+                                                    checked={approved.contains(&index)}
+                                                    onchange={Callback::from(move |_: Event| {
+                                                        let mut ticked = (*approved).clone();
+                                                        if !ticked.remove(&index) {
+                                                            ticked.insert(index);
+                                                        }
+                                                        approved.set(ticked);
+                                                    })}
+                                                />
+                                                if !video.thumbnail_url.is_empty() {
+                                                    <img src={video.thumbnail_url.clone()} alt="" class="w-16 h-9 object-cover rounded" />
+                                                }
+                                                <div class="min-w-0 flex-1">
+                                                    <p class="text-sm font-medium text-gray-900 truncate">{&video.title}</p>
+                                                    <p class="text-xs text-gray-500 truncate">
+                                                        {format!("{} · for \"{}\" · {:.0}% match", video.channel_title, m.track_name, video.confidence * 100.0)}
+                                                    </p>
+                                                </div>
+                                            </li>
+                                        },
+                                        None => html! {
+                                            <li class="py-2 text-sm text-gray-400">
+                                                {format!("{}: {}", m.track_name, m.error.clone().unwrap_or_else(|| "No match found".to_string()))}
+                                            </li>
+                                        },
+                                    }
+                                })}
+                            </ul>
+                            <div>
+                                <label class="block text-sm font-medium text-gray-700 mb-2">
+                                    {"YouTube Access Token *"}
+                                </label>
+                                <input
+                                    type="password"
+                                    class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
+                                    value={(*youtube_token).clone()}
+                                    oninput={
+                                        let youtube_token = youtube_token.clone();
+                                        Callback::from(move |e: InputEvent| {
+                                            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                                            youtube_token.set(input.value());
+                                        })
+                                    }
+                                />
+                            </div>
+                            <Button
+                                onclick={create_approved_playlist}
+                                disabled={Some(*is_loading_playlist || approved.is_empty())}
+                                variant="primary"
+                            >
+                                {format!("Create Playlist with {} Videos", approved.len())}
+                            </Button>
+                        </div>
+                    </Card>
+                }
+            } else {
+                html! {}
+            }}
 
-            //{if *is_loading_playlist {
-            //    html! {
-            //        <div class="flex items-center justify-center py-8">
-            //            <div class="animate-spin rounded-full h-8 w-8 border-b-2 border-purple-600"></div>
-            //            <span class="ml-3 text-gray-600">{"Creating your YouTube playlist..."}</span>
-            //        </div>
-            //    }
-            //} else {
-            //    html! {}
-            //}}
+            if *is_loading_playlist {
+                <Loading message={Some("Creating your YouTube playlist...".to_string())} />
+            }
 
             {if let Some(playlist) = (*created_playlist).clone() {
                 html! {
@@ -388,7 +635,7 @@ pub fn playlists() -> Html {
                                         >
                                             {"🎵 Open in YouTube"}
                                             <svg class="ml-2 w-4 h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
-                                                <path stroke_linecap="round" stroke_linejoin="round" stroke_width="2" d="M10 6H6a2 2 0 00-2 2v10a2 2 0 002 2h10a2 2 0 002-2v-4M14 4h6m0 0v6m0-6L10 14"></path>
+                                                <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M10 6H6a2 2 0 00-2 2v10a2 2 0 002 2h10a2 2 0 002-2v-4M14 4h6m0 0v6m0-6L10 14"></path>
                                             </svg>
                                         </a>
                                     </div>
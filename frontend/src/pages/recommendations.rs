@@ -1,18 +1,34 @@
 use yew::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use crate::services::api::ApiService;
-use crate::types::Track;
-use crate::components::common::{/*Alert,*/ Loading, Card, Button};
+use crate::types::{ArtistSummary, RecommendationSeed, Track};
+use crate::components::common::{Alert, Card, Button, Skeleton, SkeletonLayout};
+use crate::components::player::PreviewButton;
+use crate::utils::{format_percent, format_tempo};
+
+/// How many of the most popular artists the artist seed selector offers
+const ARTIST_SEED_OPTIONS: u32 = 200;
 
 #[function_component(Recommendations)]
 pub fn recommendations() -> Html {
-    let tracks = use_state(|| Vec::<Track>::new());
-    let recommendations = use_state(|| Vec::<Track>::new());
-    let selected_track_id = use_state(|| String::new());
+    let tracks = use_state(Vec::<Track>::new);
+    let recommendations = use_state(Vec::<Track>::new);
+    // Set when the library had fewer matching tracks than the requested limit
+    let limit_exceeded = use_state(|| false);
+    let selected_track_id = use_state(String::new);
+    let artists = use_state(Vec::<ArtistSummary>::new);
+    let selected_artist_id = use_state(String::new);
+    // Seed from an artist's average sound instead of a single track
+    let seed_by_artist = use_state(|| false);
     let is_loading_tracks = use_state(|| false);
     let is_loading_recommendations = use_state(|| false);
     let error_message = use_state(|| None::<String>);
     let limit = use_state(|| 10u32);
+    let genres = use_state(Vec::<String>::new);
+    let selected_genres = use_state(Vec::<String>::new);
+    let hide_explicit = use_state(|| false);
+    // How many explicit tracks the last request left out
+    let explicit_filtered = use_state(|| 0u32);
 
     // Load tracks on component mount
     {
@@ -23,8 +39,8 @@ pub fn recommendations() -> Html {
         use_effect_with((), move |_| {
             spawn_local(async move {
                 is_loading_tracks.set(true);
-                match ApiService::get_tracks().await {
-                    Ok(data) => {
+                match ApiService::get_tracks(false).await {
+                    Ok((data, _)) => {
                         tracks.set(data);
                         error_message.set(None);
                     }
@@ -38,30 +54,83 @@ pub fn recommendations() -> Html {
         });
     }
 
+    // Load the artists offered as seeds
+    {
+        let artists = artists.clone();
+
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                // Track seeding still works without them, so a failure just leaves the list empty
+                if let Ok((data, _)) = ApiService::get_artists(0, ARTIST_SEED_OPTIONS).await {
+                    artists.set(data);
+                }
+            });
+            || ()
+        });
+    }
+
+    // Load the genres offered as filters
+    {
+        let genres = genres.clone();
+
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                // The filter is optional, so a failure here just leaves it empty
+                if let Ok(data) = ApiService::get_genres().await {
+                    genres.set(data);
+                }
+            });
+            || ()
+        });
+    }
+
     let get_recommendations = {
         let selected_track_id = selected_track_id.clone();
+        let selected_artist_id = selected_artist_id.clone();
+        let seed_by_artist = seed_by_artist.clone();
         let recommendations = recommendations.clone();
+        let limit_exceeded = limit_exceeded.clone();
         let is_loading_recommendations = is_loading_recommendations.clone();
         let error_message = error_message.clone();
         let limit = limit.clone();
+        let selected_genres = selected_genres.clone();
+        let hide_explicit = hide_explicit.clone();
+        let explicit_filtered = explicit_filtered.clone();
         
         Callback::from(move |_| {
-            let track_id = (*selected_track_id).clone();
+            let seed = if *seed_by_artist {
+                RecommendationSeed::Artist((*selected_artist_id).clone())
+            } else {
+                RecommendationSeed::Track((*selected_track_id).clone())
+            };
+            let genres = (*selected_genres).clone();
             let recommendations = recommendations.clone();
+            let limit_exceeded = limit_exceeded.clone();
             let is_loading_recommendations = is_loading_recommendations.clone();
             let error_message = error_message.clone();
             let limit = *limit;
+            let exclude_explicit = *hide_explicit;
+            let explicit_filtered = explicit_filtered.clone();
             
-            if track_id.is_empty() {
-                error_message.set(Some("Please select a track first".to_string()));
-                return;
+            match &seed {
+                RecommendationSeed::Track(id) if id.is_empty() => {
+                    error_message.set(Some("Please select a track first".to_string()));
+                    return;
+                }
+                RecommendationSeed::Artist(id) if id.is_empty() => {
+                    error_message.set(Some("Please select an artist first".to_string()));
+                    return;
+                }
+                _ => {}
             }
             
             spawn_local(async move {
                 is_loading_recommendations.set(true);
-                match ApiService::get_recommendations(track_id, Some(limit)).await {
-                    Ok(data) => {
+                match ApiService::get_recommendations(&seed, Some(limit), &genres, exclude_explicit).await {
+                    Ok((data, exceeded, filtered)) => {
                         recommendations.set(data);
+                        limit_exceeded.set(exceeded);
+                        explicit_filtered.set(filtered);
                         error_message.set(None);
                     }
                     Err(error) => {
@@ -81,61 +150,173 @@ pub fn recommendations() -> Html {
         })
     };
 
+    let on_artist_select = {
+        let selected_artist_id = selected_artist_id.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            selected_artist_id.set(select.value());
+        })
+    };
+
     let on_limit_change = {
         let limit = limit.clone();
         Callback::from(move |e: InputEvent| {
             let input: web_sys::HtmlInputElement = e.target_unchecked_into();
             if let Ok(value) = input.value().parse::<u32>() {
-                limit.set(value.max(1).min(50));
+                limit.set(value.clamp(1, 50));
             }
         })
     };
 
-    let selected_track = tracks.iter().find(|t| t.id == *selected_track_id);
+    let toggle_genre = {
+        let selected_genres = selected_genres.clone();
+        Callback::from(move |genre: String| {
+            let mut genres = (*selected_genres).clone();
+            if let Some(index) = genres.iter().position(|g| *g == genre) {
+                genres.remove(index);
+            } else {
+                genres.push(genre);
+            }
+            selected_genres.set(genres);
+        })
+    };
+
+    let selected_track = tracks.iter().find(|t| t.id == *selected_track_id).filter(|_| !*seed_by_artist);
+    let has_seed = if *seed_by_artist { !selected_artist_id.is_empty() } else { !selected_track_id.is_empty() };
+
+    let seed_tab = |label: &str, by_artist: bool| -> Html {
+        let is_active = *seed_by_artist == by_artist;
+        let onclick = {
+            let seed_by_artist = seed_by_artist.clone();
+            Callback::from(move |_| seed_by_artist.set(by_artist))
+        };
+        let tab_classes = if is_active {
+            "bg-purple-600 text-white border-purple-600"
+        } else {
+            "bg-white text-gray-700 border-gray-300 hover:border-purple-400"
+        };
+
+        html! {
+            <button
+                type="button"
+                class={format!("px-3 py-1 text-sm rounded-md border transition-colors {}", tab_classes)}
+                aria-pressed={is_active.to_string()}
+                {onclick}
+            >
+                {label.to_string()}
+            </button>
+        }
+    };
 
     html! {
         <div class="max-w-6xl mx-auto space-y-6">
             <h1 class="text-3xl font-bold text-gray-900">{"Music Recommendations"}</h1>
 
-            //{if let Some(error) = (*error_message).clone() {
-            //    <Alert message={error} error={true} />
-            //}}
+            if let Some(error) = (*error_message).clone() {
+                <Alert message={error} error={true} />
+            }
 
             <Card title="Find Similar Tracks">
                 <div class="space-y-4">
-                    <div>
-                        <label class="block text-sm font-medium text-gray-700 mb-2">
-                            {"Select a track to get recommendations for:"}
-                        </label>
-                        {
-                            if *is_loading_tracks {
-                                html! { <div class="text-gray-500">{"Loading tracks..."}</div> }
-                            } else if tracks.is_empty() {
-                                html! {
-                                    <div class="text-gray-500">
-                                        {"No tracks available. Import a Spotify playlist first."}
-                                    </div>
-                                }
+                    <div class="flex space-x-2">
+                        {seed_tab("Seed from a track", false)}
+                        {seed_tab("Seed from an artist", true)}
+                    </div>
+
+                    if *seed_by_artist {
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700 mb-2">
+                                {"Select an artist to get recommendations for:"}
+                            </label>
+                            if artists.is_empty() {
+                                <div class="text-gray-500">
+                                    {"No artists available. Import a Spotify playlist first."}
+                                </div>
                             } else {
-                                html! {
-                                    <select
-                                        class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
-                                        value={(*selected_track_id).clone()}
-                                        onchange={on_track_select}
-                                        >
-                                            <option value="">{"-- Select a track --"}</option>
-                                            {for tracks.iter().map(|track| {
-                                                html! {
-                                                    <option value={track.id.clone()}>
-                                                        {format!("{} - {}", track.name, track.artist_names.join(", "))}
-                                                    </option>
-                                                }
-                                            })}
-                                    </select>
+                                <select
+                                    class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
+                                    value={(*selected_artist_id).clone()}
+                                    onchange={on_artist_select}
+                                >
+                                    <option value="">{"-- Select an artist --"}</option>
+                                    {for artists.iter().map(|artist| html! {
+                                        <option value={artist.id.clone()}>{&artist.name}</option>
+                                    })}
+                                </select>
+                            }
+                            <p class="text-xs text-gray-500 mt-1">
+                                {"Recommends tracks by other artists closest to this artist's average sound."}
+                            </p>
+                        </div>
+                    } else {
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700 mb-2">
+                                {"Select a track to get recommendations for:"}
+                            </label>
+                            {
+                                if *is_loading_tracks {
+                                    html! { <Skeleton layout={SkeletonLayout::Field} /> }
+                                } else if tracks.is_empty() {
+                                    html! {
+                                        <div class="text-gray-500">
+                                            {"No tracks available. Import a Spotify playlist first."}
+                                        </div>
+                                    }
+                                } else {
+                                    html! {
+                                        <select
+                                            class="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-purple-500"
+                                            value={(*selected_track_id).clone()}
+                                            onchange={on_track_select}
+                                            >
+                                                <option value="">{"-- Select a track --"}</option>
+                                                {for tracks.iter().map(|track| {
+                                                    html! {
+                                                        <option value={track.id.clone()}>
+                                                            {format!("{} - {}", track.name, track.artist_names.join(", "))}
+                                                        </option>
+                                                    }
+                                                })}
+                                        </select>
+                                    }
                                 }
                             }
-                        }
-                    </div>
+                        </div>
+                    }
+
+                    if !genres.is_empty() {
+                        <div>
+                            <span class="block text-sm font-medium text-gray-700 mb-2">
+                                {"Only include genres (none selected means any genre):"}
+                            </span>
+                            <div class="flex flex-wrap gap-2">
+                                {for genres.iter().map(|genre| {
+                                    let is_selected = selected_genres.contains(genre);
+                                    let onclick = {
+                                        let toggle_genre = toggle_genre.clone();
+                                        let genre = genre.clone();
+                                        Callback::from(move |_| toggle_genre.emit(genre.clone()))
+                                    };
+                                    let chip_classes = if is_selected {
+                                        "bg-purple-600 text-white border-purple-600"
+                                    } else {
+                                        "bg-white text-gray-700 border-gray-300 hover:border-purple-400"
+                                    };
+
+                                    html! {
+                                        <button
+                                            type="button"
+                                            class={format!("px-3 py-1 text-xs rounded-full border transition-colors {}", chip_classes)}
+                                            aria-pressed={is_selected.to_string()}
+                                            {onclick}
+                                        >
+                                            {genre}
+                                        </button>
+                                    }
+                                })}
+                            </div>
+                        </div>
+                    }
 
                     <div class="flex items-center space-x-4">
                         <div>
@@ -152,11 +333,23 @@ pub fn recommendations() -> Html {
                             />
                         </div>
 
+                        <label class="flex items-center space-x-2 text-sm text-gray-700 mt-5">
+                            <input
+                                type="checkbox"
+                                checked={*hide_explicit}
+                                onchange={
+                                    let hide_explicit = hide_explicit.clone();
+                                    Callback::from(move |_| hide_explicit.set(!*hide_explicit))
+                                }
+                            />
+                            <span>{"Hide explicit"}</span>
+                        </label>
+
                         <div class="flex-1"></div>
 
                         <Button
                             onclick={get_recommendations}
-                            disabled={Some(*is_loading_recommendations || selected_track_id.is_empty())}
+                            disabled={Some(*is_loading_recommendations || !has_seed)}
                             variant="primary"
                         >
                             {if *is_loading_recommendations {
@@ -181,23 +374,23 @@ pub fn recommendations() -> Html {
                                 <div class={"grid grid-cols-3 md:grid-cols-6 gap-2 mt-3 text-xs"}>
                                     <div class={"bg-blue-100 text-blue-800 px-2 py-1 rounded text-center"}>
                                         <div>{"Energy"}</div>
-                                        <div>{format!("{:.0}%", track.energy * 100.0)}</div>
+                                        <div>{format_percent(track.energy)}</div>
                                     </div>
                                     <div class={"bg-green-100 text-green-800 px-2 py-1 rounded text-center"}>
                                         <div>{"Dance"}</div>
-                                        <div>{format!("{:.0}%", track.danceability * 100.0)}</div>
+                                        <div>{format_percent(track.danceability)}</div>
                                     </div>
                                     <div class={"bg-purple-100 text-purple-800 px-2 py-1 rounded text-center"}>
                                         <div>{"Valence"}</div>
-                                        <div>{format!("{:.0}%", track.valence * 100.0)}</div>
+                                        <div>{format_percent(track.valence)}</div>
                                     </div>
                                     <div class={"bg-yellow-100 text-yellow-800 px-2 py-1 rounded text-center"}>
                                         <div>{"Tempo"}</div>
-                                        <div>{format!("{:.0}", track.tempo)}</div>
+                                        <div>{format_tempo(track.tempo)}</div>
                                     </div>
                                     <div class={"bg-indigo-100 text-indigo-800 px-2 py-1 rounded text-center"}>
                                         <div>{"Acoustic"}</div>
-                                        <div>{format!("{:.0}%", track.acousticness * 100.0)}</div>
+                                        <div>{format_percent(track.acousticness)}</div>
                                     </div>
                                     <div class={"bg-pink-100 text-pink-800 px-2 py-1 rounded text-center"}>
                                         <div>{"Popular"}</div>
@@ -206,16 +399,8 @@ pub fn recommendations() -> Html {
                                 </div>
                             </div>
                             
-                            {if let Some(preview_url) = &track.preview_url {
-                                if !preview_url.is_empty() {
-                                    html! {
-                                        <audio controls={true} class={"w-64"}>
-                                            <source src={preview_url.clone()} type="audio/mpeg" />
-                                        </audio>
-                                    }
-                                } else {
-                                    html! {}
-                                }
+                            {if let Some(preview_url) = track.preview_url.as_ref().filter(|url| !url.is_empty()) {
+                                html! { <PreviewButton track_id={track.id.clone()} url={preview_url.clone()} title={track.name.clone()} /> }
                             } else {
                                 html! {}
                             }}
@@ -229,6 +414,16 @@ pub fn recommendations() -> Html {
             {if !recommendations.is_empty() {
                 html! {
                     <Card title={format!("Recommended Tracks ({})", recommendations.len())}>
+                        if *limit_exceeded {
+                            <p class={"text-sm text-gray-600 mb-3"}>
+                                {format!("Only {} matching tracks are in your library. Import more playlists for more recommendations.", recommendations.len())}
+                            </p>
+                        }
+                        if *explicit_filtered > 0 {
+                            <p class={"text-sm text-gray-600 mb-3"}>
+                                {format!("{} explicit tracks hidden.", *explicit_filtered)}
+                            </p>
+                        }
                         <div class={"space-y-3"}>
                             {for recommendations.iter().enumerate().map(|(index, track)| {
                                 html! {
@@ -247,26 +442,18 @@ pub fn recommendations() -> Html {
 
                                         <div class={"flex space-x-1 text-xs"}>
                                             <span class={"bg-blue-100 text-blue-800 px-2 py-1 rounded"}>
-                                                {"E: "}{format!("{:.0}%", track.energy * 100.0)}
+                                                {"E: "}{format_percent(track.energy)}
                                             </span>
                                             <span class={"bg-green-100 text-green-800 px-2 py-1 rounded"}>
-                                                {"D: "}{format!("{:.0}%", track.danceability * 100.0)}
+                                                {"D: "}{format_percent(track.danceability)}
                                             </span>
                                             <span class={"bg-purple-100 text-purple-800 px-2 py-1 rounded"}>
-                                                {"V: "}{format!("{:.0}%", track.valence * 100.0)}
+                                                {"V: "}{format_percent(track.valence)}
                                             </span>
                                         </div>
                                         
-                                        {if let Some(preview_url) = &track.preview_url {
-                                            if !preview_url.is_empty() {
-                                                html! {
-                                                    <audio controls={true} class="w-48">
-                                                        <source src={preview_url.clone()} type="audio/mpeg" />
-                                                    </audio>
-                                                }
-                                            } else {
-                                                html! {}
-                                            }
+                                        {if let Some(preview_url) = track.preview_url.as_ref().filter(|url| !url.is_empty()) {
+                                            html! { <PreviewButton track_id={track.id.clone()} url={preview_url.clone()} title={track.name.clone()} /> }
                                         } else {
                                             html! {}
                                         }}
@@ -0,0 +1,149 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use crate::services::api::ApiService;
+use crate::types::TrackDetail;
+use crate::components::common::{Alert, Card, Skeleton, SkeletonLayout};
+use crate::components::player::PreviewButton;
+use crate::Route;
+use crate::utils::{format_duration, format_percent, format_tempo};
+
+#[derive(Properties, PartialEq)]
+pub struct TrackDetailProps {
+    pub id: String,
+}
+
+#[function_component(TrackDetailPage)]
+pub fn track_detail(props: &TrackDetailProps) -> Html {
+    let detail = use_state(|| None::<TrackDetail>);
+    let is_loading = use_state(|| false);
+    let error_message = use_state(|| None::<String>);
+
+    // Reload whenever the route's track changes, e.g. following a similar track
+    {
+        let detail = detail.clone();
+        let is_loading = is_loading.clone();
+        let error_message = error_message.clone();
+
+        use_effect_with(props.id.clone(), move |track_id| {
+            let track_id = track_id.clone();
+            spawn_local(async move {
+                is_loading.set(true);
+                match ApiService::get_track_detail(track_id).await {
+                    Ok(data) => {
+                        detail.set(Some(data));
+                        error_message.set(None);
+                    }
+                    Err(error) => {
+                        detail.set(None);
+                        error_message.set(Some(error));
+                    }
+                }
+                is_loading.set(false);
+            });
+            || ()
+        });
+    }
+
+    let feature = |label: &str, value: String| -> Html {
+        html! {
+            <div class="text-center">
+                <div class="text-lg font-semibold text-gray-900">{value}</div>
+                <div class="text-xs text-gray-500">{label.to_string()}</div>
+            </div>
+        }
+    };
+
+    html! {
+        <div class="max-w-4xl mx-auto">
+            <div class="mb-6">
+                <Link<Route> to={Route::Tracks} classes="text-sm text-purple-600 hover:text-purple-800">
+                    {"← All tracks"}
+                </Link<Route>>
+            </div>
+
+            if let Some(error) = (*error_message).clone() {
+                <Alert message={error} error={true} />
+            }
+
+            if *is_loading && detail.is_none() {
+                <Skeleton layout={SkeletonLayout::ListRows} count={4} />
+            } else if let Some(detail) = (*detail).clone() {
+                <div class="bg-white rounded-lg shadow-md p-6 mb-6">
+                    <div class="flex items-start justify-between">
+                        <div class="flex-1 min-w-0">
+                            <h1 class="text-3xl font-bold text-gray-900">{&detail.track.name}</h1>
+                            <p class="text-gray-600 mt-1">{"by "}{detail.track.artist_names.join(", ")}</p>
+                            <p class="text-sm text-gray-500 mt-1">
+                                {format_duration(detail.track.duration_ms)}
+                                {" · Popularity "}{detail.track.popularity}
+                                if detail.track.explicit {
+                                    {" · Explicit"}
+                                }
+                            </p>
+                        </div>
+                        if let Some(preview_url) = &detail.track.preview_url {
+                            <PreviewButton track_id={detail.track.id.clone()} url={preview_url.clone()} title={detail.track.name.clone()} />
+                        }
+                    </div>
+
+                    <div class="grid grid-cols-3 md:grid-cols-6 gap-4 mt-6">
+                        {feature("Energy", format_percent(detail.track.energy))}
+                        {feature("Valence", format_percent(detail.track.valence))}
+                        {feature("Danceability", format_percent(detail.track.danceability))}
+                        {feature("Acousticness", format_percent(detail.track.acousticness))}
+                        {feature("Speechiness", format_percent(detail.track.speechiness))}
+                        {feature("BPM", format_tempo(detail.track.tempo))}
+                    </div>
+                </div>
+
+                <div class="grid grid-cols-1 md:grid-cols-2 gap-6 mb-6">
+                    <Card title="Artists">
+                        <ul class="space-y-2">
+                            {for detail.artists.iter().map(|artist| html! {
+                                <li>
+                                    <div class="font-medium text-gray-900">{&artist.name}</div>
+                                    if !artist.genres.is_empty() {
+                                        <div class="text-sm text-gray-500">{artist.genres.join(", ")}</div>
+                                    }
+                                </li>
+                            })}
+                        </ul>
+                    </Card>
+
+                    <Card title="Album">
+                        if let Some(album) = &detail.album {
+                            <div class="font-medium text-gray-900">{&album.name}</div>
+                            <div class="text-sm text-gray-500">
+                                {album.release_date.clone().unwrap_or_default()}
+                                {" · "}{album.track_count}{" tracks in library"}
+                            </div>
+                        } else {
+                            <p class="text-gray-600">{"Not part of an imported album."}</p>
+                        }
+                    </Card>
+                </div>
+
+                <Card title="Similar Tracks">
+                    if detail.similar.is_empty() {
+                        <p class="text-gray-600">{"No similar tracks have been linked to this one yet."}</p>
+                    } else {
+                        <ol class="divide-y divide-gray-200">
+                            {for detail.similar.iter().map(|track| html! {
+                                <li class="flex justify-between py-2 text-sm">
+                                    <Link<Route>
+                                        to={Route::TrackDetail { id: track.id.clone() }}
+                                        classes="text-gray-900 hover:text-purple-600"
+                                    >
+                                        {&track.name}
+                                    </Link<Route>>
+                                    <span class="text-gray-500">{track.artist_names.join(", ")}</span>
+                                </li>
+                            })}
+                        </ol>
+                    }
+                </Card>
+            }
+        </div>
+    }
+}
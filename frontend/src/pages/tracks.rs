@@ -1,60 +1,120 @@
 use yew::prelude::*;
+use yew_router::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use crate::services::api::ApiService;
-use crate::types::{Track, SimilarTracksResponse};
-use crate::components::common::{Alert, Loading, Card};
+use crate::types::SimilarTracksResponse;
+use crate::components::common::{Card, Modal, Skeleton, SkeletonLayout};
+use crate::components::toast::use_toast;
+use crate::components::player::{PreviewButton, RecentlyPreviewed};
+use crate::Route;
+use crate::utils::{
+    explain_similarity, format_duration, format_percent, format_tempo, get_audio_feature_color, preview_url,
+    take_prerendered_tracks,
+};
+
+/// How many tracks "Surprise Me" picks
+const SURPRISE_COUNT: u32 = 10;
 
 #[function_component(Tracks)]
 pub fn tracks() -> Html {
-    let tracks = use_state(|| Vec::<Track>::new());
+    // Set when the page was served as `/tracks.html`, which already has every track
+    let prerendered = use_mut_ref(take_prerendered_tracks);
+    let tracks = use_state(|| prerendered.borrow().clone().unwrap_or_default());
+    // From the server's count; the embedded tracks are all there are
+    let total_tracks = use_state(|| None::<u32>);
     let is_loading = use_state(|| false);
-    let error_message = use_state(|| None::<String>);
+    let toast = use_toast();
     let similar_tracks_data = use_state(|| None::<SimilarTracksResponse>);
     let loading_similar_for = use_state(|| None::<String>);
+    let only_previews = use_state(|| false);
+    // Set while the list shows a random pick rather than the library
+    let surprise = use_state(|| false);
 
-    // Load tracks on component mount
+    // Load tracks on mount and whenever the preview filter changes
     {
         let tracks = tracks.clone();
+        let total_tracks = total_tracks.clone();
+        let surprise = surprise.clone();
         let is_loading = is_loading.clone();
-        let error_message = error_message.clone();
+        let toast = toast.clone();
         
-        use_effect_with((), move |_| {
+        use_effect_with(*only_previews, move |only_previews| {
+            let only_previews = *only_previews;
+            // The embedded tracks stand in for the first unfiltered fetch only
+            let embedded = prerendered.borrow_mut().take().is_some();
+            if !embedded || only_previews {
+                spawn_local(async move {
+                    is_loading.set(true);
+                    match ApiService::get_tracks(only_previews).await {
+                        Ok((data, total)) => {
+                            tracks.set(data);
+                            total_tracks.set(Some(total));
+                            surprise.set(false);
+                        }
+                        Err(error) => {
+                            toast.error(error);
+                        }
+                    }
+                    is_loading.set(false);
+                });
+            }
+            || ()
+        });
+    }
+
+    let refresh_tracks = {
+        let tracks = tracks.clone();
+        let total_tracks = total_tracks.clone();
+        let surprise = surprise.clone();
+        let is_loading = is_loading.clone();
+        let toast = toast.clone();
+        let only_previews = *only_previews;
+        
+        Callback::from(move |_| {
+            let tracks = tracks.clone();
+            let total_tracks = total_tracks.clone();
+            let surprise = surprise.clone();
+            let is_loading = is_loading.clone();
+            let toast = toast.clone();
+            
             spawn_local(async move {
                 is_loading.set(true);
-                match ApiService::get_tracks().await {
-                    Ok(data) => {
+                match ApiService::get_tracks(only_previews).await {
+                    Ok((data, total)) => {
                         tracks.set(data);
-                        error_message.set(None);
+                        total_tracks.set(Some(total));
+                        surprise.set(false);
                     }
                     Err(error) => {
-                        error_message.set(Some(error));
+                        toast.error(error);
                     }
                 }
                 is_loading.set(false);
             });
-            || ()
-        });
-    }
+        })
+    };
 
-    let refresh_tracks = {
+    let surprise_me = {
         let tracks = tracks.clone();
+        let surprise = surprise.clone();
         let is_loading = is_loading.clone();
-        let error_message = error_message.clone();
-        
+        let toast = toast.clone();
+
         Callback::from(move |_| {
             let tracks = tracks.clone();
+            let surprise = surprise.clone();
             let is_loading = is_loading.clone();
-            let error_message = error_message.clone();
-            
+            let toast = toast.clone();
+
             spawn_local(async move {
                 is_loading.set(true);
-                match ApiService::get_tracks().await {
+                match ApiService::get_random_tracks(SURPRISE_COUNT).await {
                     Ok(data) => {
                         tracks.set(data);
-                        error_message.set(None);
+                        surprise.set(true);
                     }
                     Err(error) => {
-                        error_message.set(Some(error));
+                        toast.error(error);
                     }
                 }
                 is_loading.set(false);
@@ -65,23 +125,22 @@ pub fn tracks() -> Html {
     let find_similar_tracks = {
         let similar_tracks_data = similar_tracks_data.clone();
         let loading_similar_for = loading_similar_for.clone();
-        let error_message = error_message.clone();
+        let toast = toast.clone();
         
-        Callback::from(move |track_id: String| {
+        Callback::from(move |(track_id, track_name): (String, String)| {
             let similar_tracks_data = similar_tracks_data.clone();
             let loading_similar_for = loading_similar_for.clone();
-            let error_message = error_message.clone();
+            let toast = toast.clone();
             let track_id_clone = track_id.clone();
             
             spawn_local(async move {
                 loading_similar_for.set(Some(track_id_clone.clone()));
-                match ApiService::get_similar_tracks_with_youtube(track_id_clone, Some(5)).await {
+                match ApiService::get_similar_tracks_with_youtube(track_id_clone, Some(track_name), Some(5)).await {
                     Ok(data) => {
                         similar_tracks_data.set(Some(data));
-                        error_message.set(None);
                     }
                     Err(error) => {
-                        error_message.set(Some(error));
+                        toast.error(error);
                     }
                 }
                 loading_similar_for.set(None);
@@ -91,54 +150,73 @@ pub fn tracks() -> Html {
 
     let close_similar_tracks = {
         let similar_tracks_data = similar_tracks_data.clone();
-        Callback::from(move |_| {
+        Callback::from(move |_: ()| {
             similar_tracks_data.set(None);
         })
     };
 
-    fn format_duration(ms: u32) -> String {
-        let seconds = ms / 1000;
-        let minutes = seconds / 60;
-        let remaining_seconds = seconds % 60;
-        format!("{}:{:02}", minutes, remaining_seconds)
-    }
-
-    fn get_audio_feature_color(value: f32) -> &'static str {
-        if value >= 0.7 { "bg-green-100 text-green-800" }
-        else if value >= 0.4 { "bg-yellow-100 text-yellow-800" }
-        else { "bg-red-100 text-red-800" }
-    }
-
     html! {
         <div class="max-w-7xl mx-auto">
             <div class="flex justify-between items-center mb-6">
                 <h1 class="text-3xl font-bold text-gray-900">{"Tracks"}</h1>
-                <button
-                    class="px-4 py-2 bg-purple-600 text-white rounded-md hover:bg-purple-700 disabled:bg-purple-300"
-                    onclick={refresh_tracks}
-                    disabled={*is_loading}
-                >
-                    if *is_loading {
-                        {"Refreshing..."}
-                    } else {
-                        {"Refresh"}
-                    }
-                </button>
+                <div class="flex items-center space-x-4">
+                    <label class="flex items-center space-x-2 text-sm text-gray-700">
+                        <input
+                            type="checkbox"
+                            checked={*only_previews}
+                            onchange={
+                                let only_previews = only_previews.clone();
+                                Callback::from(move |_| only_previews.set(!*only_previews))
+                            }
+                        />
+                        <span>{"Only with previews"}</span>
+                    </label>
+                    <button
+                        class="px-4 py-2 border border-purple-600 text-purple-600 rounded-md hover:bg-purple-50 disabled:opacity-50"
+                        onclick={surprise_me}
+                        disabled={*is_loading}
+                    >
+                        {"Surprise Me"}
+                    </button>
+                    <button
+                        class="px-4 py-2 bg-purple-600 text-white rounded-md hover:bg-purple-700 disabled:bg-purple-300"
+                        onclick={refresh_tracks}
+                        disabled={*is_loading}
+                    >
+                        if *is_loading {
+                            {"Refreshing..."}
+                        } else {
+                            {"Refresh"}
+                        }
+                    </button>
+                </div>
             </div>
 
-            if let Some(error) = (*error_message).clone() {
-                <Alert message={error} error={true} />
-            }
+            <RecentlyPreviewed />
 
             if *is_loading && tracks.is_empty() {
-                <Loading message={Some("Loading tracks...".to_string())} />
+                <Skeleton layout={SkeletonLayout::ListRows} count={Some(4)} />
             } else if tracks.is_empty() {
                 <Card title="No Tracks Found">
                     <p class="text-gray-600">
-                        {"No tracks have been imported yet. Import a Spotify playlist to see tracks here."}
+                        if *only_previews {
+                            {"None of the imported tracks have a preview clip."}
+                        } else {
+                            {"No tracks have been imported yet. Import a Spotify playlist to see tracks here."}
+                        }
                     </p>
                 </Card>
             } else {
+                <p class="text-sm text-gray-600 mb-4">
+                    if *surprise {
+                        {format!(
+                            "{} random tracks. Use Find Similar Tracks on any of them for recommendations, or Refresh to see the whole library.",
+                            tracks.len()
+                        )}
+                    } else {
+                        {format!("Showing {} of {} tracks", tracks.len(), total_tracks.unwrap_or(tracks.len() as u32))}
+                    }
+                </p>
                 <div class="space-y-4">
                     {for tracks.iter().map(|track| {
                         html! {
@@ -148,7 +226,12 @@ pub fn tracks() -> Html {
                                         <div class="flex items-start space-x-4">
                                             <div class="flex-1">
                                                 <h3 class="text-lg font-semibold text-gray-900 truncate">
-                                                    {&track.name}
+                                                    <Link<Route>
+                                                        to={Route::TrackDetail { id: track.id.clone() }}
+                                                        classes="hover:text-purple-600"
+                                                    >
+                                                        {&track.name}
+                                                    </Link<Route>>
                                                 </h3>
                                                 
                                                 <p class="text-gray-600 mt-1">
@@ -171,20 +254,20 @@ pub fn tracks() -> Html {
                                                     } else {
                                                         html! {}
                                                     }}
+                                                    {if preview_url(track).is_some() {
+                                                        html! {
+                                                            <span class="px-2 py-1 bg-green-100 text-green-800 text-xs rounded">
+                                                                {"PREVIEW"}
+                                                            </span>
+                                                        }
+                                                    } else {
+                                                        html! {}
+                                                    }}
                                                 </div>
                                             </div>
                                             
-                                            {if let Some(preview_url) = &track.preview_url {
-                                                if !preview_url.is_empty() {
-                                                    html! {
-                                                        <audio controls={true} class="w-64">
-                                                            <source src={preview_url.clone()} type="audio/mpeg" />
-                                                            {"Your browser does not support the audio element."}
-                                                        </audio>
-                                                    }
-                                                } else {
-                                                    html! {}
-                                                }
+                                            {if let Some(preview_url) = preview_url(track) {
+                                                html! { <PreviewButton track_id={track.id.clone()} url={preview_url.to_string()} title={track.name.clone()} /> }
                                             } else {
                                                 html! {}
                                             }}
@@ -196,32 +279,32 @@ pub fn tracks() -> Html {
                                             <div class="grid grid-cols-2 md:grid-cols-4 lg:grid-cols-6 gap-2 text-xs">
                                                 <div class={format!("px-2 py-1 rounded text-center {}", get_audio_feature_color(track.danceability))}>
                                                     <div class="font-medium">{"Dance"}</div>
-                                                    <div>{format!("{:.0}%", track.danceability * 100.0)}</div>
+                                                    <div>{format_percent(track.danceability)}</div>
                                                 </div>
                                                 
                                                 <div class={format!("px-2 py-1 rounded text-center {}", get_audio_feature_color(track.energy))}>
                                                     <div class="font-medium">{"Energy"}</div>
-                                                    <div>{format!("{:.0}%", track.energy * 100.0)}</div>
+                                                    <div>{format_percent(track.energy)}</div>
                                                 </div>
                                                 
                                                 <div class={format!("px-2 py-1 rounded text-center {}", get_audio_feature_color(track.valence))}>
                                                     <div class="font-medium">{"Valence"}</div>
-                                                    <div>{format!("{:.0}%", track.valence * 100.0)}</div>
+                                                    <div>{format_percent(track.valence)}</div>
                                                 </div>
                                                 
                                                 <div class="px-2 py-1 rounded text-center bg-blue-100 text-blue-800">
                                                     <div class="font-medium">{"Tempo"}</div>
-                                                    <div>{format!("{:.0}", track.tempo)}</div>
+                                                    <div>{format_tempo(track.tempo)}</div>
                                                 </div>
                                                 
                                                 <div class={format!("px-2 py-1 rounded text-center {}", get_audio_feature_color(track.acousticness))}>
                                                     <div class="font-medium">{"Acoustic"}</div>
-                                                    <div>{format!("{:.0}%", track.acousticness * 100.0)}</div>
+                                                    <div>{format_percent(track.acousticness)}</div>
                                                 </div>
                                                 
                                                 <div class={format!("px-2 py-1 rounded text-center {}", get_audio_feature_color(track.instrumentalness))}>
                                                     <div class="font-medium">{"Instrum."}</div>
-                                                    <div>{format!("{:.0}%", track.instrumentalness * 100.0)}</div>
+                                                    <div>{format_percent(track.instrumentalness)}</div>
                                                 </div>
                                             </div>
                                         </div>
@@ -232,8 +315,9 @@ pub fn tracks() -> Html {
                                                 class="px-4 py-2 bg-purple-600 text-white rounded-md hover:bg-purple-700 disabled:bg-purple-300 disabled:cursor-not-allowed"
                                                 onclick={
                                                     let track_id = track.id.clone();
+                                                    let track_name = track.name.clone();
                                                     let find_similar_tracks = find_similar_tracks.clone();
-                                                    move |_| find_similar_tracks.emit(track_id.clone())
+                                                    move |_| find_similar_tracks.emit((track_id.clone(), track_name.clone()))
                                                 }
                                                 disabled={loading_similar_for.as_ref() == Some(&track.id)}
                                             >
@@ -255,63 +339,62 @@ pub fn tracks() -> Html {
             // Similar Tracks Modal/Section
             {if let Some(similar_data) = &*similar_tracks_data {
                 html! {
-                    <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
-                        <div class="bg-white rounded-lg max-w-4xl max-h-[80vh] overflow-y-auto p-6 m-4 w-full">
-                            <div class="flex justify-between items-center mb-4">
-                                <h2 class="text-2xl font-bold text-gray-900">
-                                    {"Similar to: "}{&similar_data.original_track.name}
-                                </h2>
-                                <button
-                                    class="text-gray-500 hover:text-gray-700"
-                                    onclick={close_similar_tracks}
-                                >
-                                    <svg class="w-6 h-6" fill="none" stroke="currentColor" viewBox="0 0 24 24">
-                                        <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M6 18L18 6M6 6l12 12"></path>
-                                    </svg>
-                                </button>
-                            </div>
-
-                            <div class="mb-4 p-4 bg-gray-50 rounded-lg">
-                                <h3 class="font-semibold text-gray-800">{"Original Track"}</h3>
-                                <p class="text-gray-600">
-                                    {&similar_data.original_track.name}{" by "}{similar_data.original_track.artist_names.join(", ")}
-                                </p>
-                            </div>
+                    <Modal
+                        title={format!("Similar to: {}", similar_data.original_track.name)}
+                        on_close={close_similar_tracks}
+                    >
+                        <div class="mb-4 p-4 bg-gray-50 rounded-lg">
+                            <h3 class="font-semibold text-gray-800">{"Original Track"}</h3>
+                            <p class="text-gray-600">
+                                {&similar_data.original_track.name}{" by "}{similar_data.original_track.artist_names.join(", ")}
+                            </p>
+                        </div>
 
-                            <div class="space-y-3">
-                                <h3 class="font-semibold text-gray-800 text-lg">{"Similar Tracks"}</h3>
-                                {for similar_data.similar_tracks.iter().enumerate().map(|(index, track_with_youtube)| {
-                                    let track = &track_with_youtube.track;
-                                    html! {
-                                        <div class="flex items-center space-x-4 p-4 bg-gray-50 rounded-lg">
-                                            <div class="flex-shrink-0 w-8 h-8 bg-purple-100 text-purple-800 rounded-full flex items-center justify-center text-sm font-medium">
-                                                {index + 1}
-                                            </div>
+                        <div class="space-y-3">
+                            <h3 class="font-semibold text-gray-800 text-lg">{"Similar Tracks"}</h3>
+                            {for similar_data.similar_tracks.iter().enumerate().map(|(index, track_with_youtube)| {
+                                let track = &track_with_youtube.track;
+                                html! {
+                                    <div class="flex items-center space-x-4 p-4 bg-gray-50 rounded-lg">
+                                        <div class="flex-shrink-0 w-8 h-8 bg-purple-100 text-purple-800 rounded-full flex items-center justify-center text-sm font-medium">
+                                            {index + 1}
+                                        </div>
 
-                                            <div class="flex-1 min-w-0">
-                                                <h4 class="font-medium text-gray-900 truncate">{&track.name}</h4>
-                                                <p class="text-sm text-gray-600 truncate">
-                                                    {"by "}{track.artist_names.join(", ")}
-                                                </p>
-                                                <p class="text-xs text-gray-500">{&track.album_name}</p>
-                                                
-                                                <div class="flex space-x-1 text-xs mt-2">
-                                                    <span class="bg-blue-100 text-blue-800 px-2 py-1 rounded">
-                                                        {"E: "}{format!("{:.0}%", track.energy * 100.0)}
-                                                    </span>
-                                                    <span class="bg-green-100 text-green-800 px-2 py-1 rounded">
-                                                        {"D: "}{format!("{:.0}%", track.danceability * 100.0)}
-                                                    </span>
-                                                    <span class="bg-purple-100 text-purple-800 px-2 py-1 rounded">
-                                                        {"V: "}{format!("{:.0}%", track.valence * 100.0)}
-                                                    </span>
-                                                </div>
+                                        <div class="flex-1 min-w-0">
+                                            <h4 class="font-medium text-gray-900 truncate">{&track.name}</h4>
+                                            <p class="text-sm text-gray-600 truncate">
+                                                {"by "}{track.artist_names.join(", ")}
+                                            </p>
+                                            <p class="text-xs text-gray-500">{&track.album_name}</p>
+                                            
+                                            <div class="flex space-x-1 text-xs mt-2">
+                                                <span class="bg-blue-100 text-blue-800 px-2 py-1 rounded">
+                                                    {"E: "}{format_percent(track.energy)}
+                                                </span>
+                                                <span class="bg-green-100 text-green-800 px-2 py-1 rounded">
+                                                    {"D: "}{format_percent(track.danceability)}
+                                                </span>
+                                                <span class="bg-purple-100 text-purple-800 px-2 py-1 rounded">
+                                                    {"V: "}{format_percent(track.valence)}
+                                                </span>
                                             </div>
+                                            if let Some(explanation) = &track_with_youtube.explanation {
+                                                <p class="text-xs text-gray-500 mt-1">{explain_similarity(explanation)}</p>
+                                            }
+                                        </div>
 
-                                            // YouTube section
-                                            <div class="flex-shrink-0">
-                                                {if let Some(youtube_video) = &track_with_youtube.youtube_video {
-                                                    html! {
+                                        // YouTube section
+                                        <div class="flex-shrink-0">
+                                            {if let Some(youtube_video) = &track_with_youtube.youtube_video {
+                                                html! {
+                                                    <div class="flex items-center space-x-3">
+                                                        if !youtube_video.thumbnail_url.is_empty() {
+                                                            <img
+                                                                src={youtube_video.thumbnail_url.clone()}
+                                                                alt={youtube_video.title.clone()}
+                                                                class="w-24 h-14 rounded object-cover"
+                                                            />
+                                                        }
                                                         <div class="text-center">
                                                             <p class="text-xs text-gray-500 mb-1">{"YouTube"}</p>
                                                             <a
@@ -328,36 +411,28 @@ pub fn tracks() -> Html {
                                                                 {&youtube_video.title}
                                                             </p>
                                                         </div>
-                                                    }
-                                                } else {
-                                                    html! {
-                                                        <div class="text-center">
-                                                            <p class="text-xs text-gray-400">{"Not found"}</p>
-                                                            <p class="text-xs text-gray-400">{"on YouTube"}</p>
-                                                        </div>
-                                                    }
-                                                }}
-                                            </div>
-
-                                            {if let Some(preview_url) = &track.preview_url {
-                                                if !preview_url.is_empty() {
-                                                    html! {
-                                                        <audio controls={true} class="w-48">
-                                                            <source src={preview_url.clone()} type="audio/mpeg" />
-                                                        </audio>
-                                                    }
-                                                } else {
-                                                    html! {}
+                                                    </div>
                                                 }
                                             } else {
-                                                html! {}
+                                                html! {
+                                                    <div class="text-center">
+                                                        <p class="text-xs text-gray-400">{"Not found"}</p>
+                                                        <p class="text-xs text-gray-400">{"on YouTube"}</p>
+                                                    </div>
+                                                }
                                             }}
                                         </div>
-                                    }
-                                })}
-                            </div>
+
+                                        {if let Some(preview_url) = track.preview_url.as_ref().filter(|url| !url.is_empty()) {
+                                            html! { <PreviewButton track_id={track.id.clone()} url={preview_url.clone()} title={track.name.clone()} /> }
+                                        } else {
+                                            html! {}
+                                        }}
+                                    </div>
+                                }
+                            })}
                         </div>
-                    </div>
+                    </Modal>
                 }
             } else {
                 html! {}
@@ -4,6 +4,14 @@ use serde_json::Value;
 
 const API_BASE_URL: &str = "http://localhost:3000/api";
 
+// The `error` message from a JSON error body, or the body itself when it isn't one
+fn error_message(body: String) -> String {
+    serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|value| value["error"].as_str().map(str::to_string))
+        .unwrap_or(body)
+}
+
 pub struct ApiService;
 
 impl ApiService {
@@ -23,8 +31,8 @@ impl ApiService {
         }
     }
 
-    pub async fn import_spotify_data(playlist_url: String) -> Result<String, String> {
-        let request_body = SpotifyImportRequest { playlist_url };
+    pub async fn import_spotify_data(playlist_url: String, job_id: String) -> Result<String, String> {
+        let request_body = SpotifyImportRequest { playlist_url, job_id };
         
         let response = Request::post(&format!("{}/spotify/import", API_BASE_URL))
             .header("Content-Type", "application/json")
@@ -44,28 +52,144 @@ impl ApiService {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            Err(format!("Import failed: {}", error_text))
+            Err(format!("Import failed: {}", error_message(error_text)))
+        }
+    }
+
+    /// Ask the import started with `job_id` to stop; its own request then returns what was stored
+    pub async fn cancel_import(job_id: &str) -> Result<(), String> {
+        let url = format!("{}/spotify/import/{}", API_BASE_URL, js_sys::encode_uri_component(job_id));
+        let response = Request::delete(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        match response.status() {
+            202 => Ok(()),
+            404 => Err("The import has already finished".to_string()),
+            status => Err(format!("Failed to cancel import: {}", status)),
         }
     }
 
-    pub async fn get_artists() -> Result<Vec<Artist>, String> {
-        let response = Request::get(&format!("{}/spotify/artists", API_BASE_URL))
+    /// A playlist's tracks without importing them; audio features are skipped for speed
+    pub async fn preview_playlist(playlist_url: String) -> Result<Vec<Track>, String> {
+        let url = format!(
+            "{}/spotify/playlist/preview?url={}&with_features=false",
+            API_BASE_URL,
+            String::from(js_sys::encode_uri_component(&playlist_url))
+        );
+        let response = Request::get(&url)
             .send()
             .await
             .map_err(|e| format!("Request failed: {}", e))?;
 
         if response.ok() {
             response
-                .json::<Vec<Artist>>()
+                .json::<Vec<Track>>()
                 .await
                 .map_err(|e| format!("Failed to parse response: {}", e))
+        } else if response.status() == 404 {
+            let error_text = response.text().await.unwrap_or_default();
+            Err(format!("Preview failed: {}", error_message(error_text)))
+        } else {
+            Err(format!("Preview failed: {}", response.status()))
+        }
+    }
+
+    pub async fn suggest_playlist_names(track_id: String) -> Result<NameSuggestions, String> {
+        let url = format!(
+            "{}/recommendations/name-suggestion?track_id={}",
+            API_BASE_URL,
+            String::from(js_sys::encode_uri_component(&track_id))
+        );
+        let response = Request::get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if response.ok() {
+            response
+                .json::<NameSuggestions>()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))
+        } else {
+            Err(format!("Failed to suggest playlist names: {}", response.status()))
+        }
+    }
+
+    /// Fetch one page of artist summaries along with the total number of artists
+    pub async fn get_artists(offset: u32, limit: u32) -> Result<(Vec<ArtistSummary>, u32), String> {
+        let url = format!("{}/spotify/artists?fields=summary&offset={}&limit={}", API_BASE_URL, offset, limit);
+        let response = Request::get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if response.ok() {
+            let total = response
+                .headers()
+                .get("x-total-count")
+                .and_then(|value| value.parse::<u32>().ok());
+            let artists = response
+                .json::<Vec<ArtistSummary>>()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))?;
+            let total = total.unwrap_or(offset + artists.len() as u32);
+            Ok((artists, total))
         } else {
             Err(format!("Failed to fetch artists: {}", response.status()))
         }
     }
 
-    pub async fn get_tracks() -> Result<Vec<Track>, String> {
-        let response = Request::get(&format!("{}/spotify/tracks", API_BASE_URL))
+    /// All tracks, or only those with a preview clip when `only_with_previews` is set, with the
+    /// total the server counted for them
+    pub async fn get_tracks(only_with_previews: bool) -> Result<(Vec<Track>, u32), String> {
+        let url = if only_with_previews {
+            format!("{}/spotify/tracks?has_preview=true", API_BASE_URL)
+        } else {
+            format!("{}/spotify/tracks", API_BASE_URL)
+        };
+        let response = Request::get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if response.ok() {
+            let total = response
+                .headers()
+                .get("x-total-count")
+                .and_then(|value| value.parse::<u32>().ok());
+            let tracks = response
+                .json::<Vec<Track>>()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))?;
+            let total = total.unwrap_or(tracks.len() as u32);
+            Ok((tracks, total))
+        } else {
+            Err(format!("Failed to fetch tracks: {}", response.status()))
+        }
+    }
+
+    pub async fn get_albums() -> Result<Vec<Album>, String> {
+        let response = Request::get(&format!("{}/spotify/albums", API_BASE_URL))
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if response.ok() {
+            response
+                .json::<Vec<Album>>()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))
+        } else {
+            Err(format!("Failed to fetch albums: {}", response.status()))
+        }
+    }
+
+    /// `count` tracks picked at random from the library
+    pub async fn get_random_tracks(count: u32) -> Result<Vec<Track>, String> {
+        let url = format!("{}/spotify/tracks/random?count={}", API_BASE_URL, count);
+        let response = Request::get(&url)
             .send()
             .await
             .map_err(|e| format!("Request failed: {}", e))?;
@@ -76,15 +200,79 @@ impl ApiService {
                 .await
                 .map_err(|e| format!("Failed to parse response: {}", e))
         } else {
-            Err(format!("Failed to fetch tracks: {}", response.status()))
+            Err(format!("Failed to fetch random tracks: {}", response.status()))
         }
     }
 
-    pub async fn get_recommendations(track_id: String, limit: Option<u32>) -> Result<Vec<Track>, String> {
-        let mut url = format!("{}/recommendations?track_id={}", API_BASE_URL, track_id);
+    pub async fn get_track_detail(track_id: String) -> Result<TrackDetail, String> {
+        let response = Request::get(&format!("{}/spotify/tracks/{}", API_BASE_URL, track_id))
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if response.ok() {
+            response
+                .json::<TrackDetail>()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))
+        } else if response.status() == 404 {
+            Err("Track not found".to_string())
+        } else {
+            Err(format!("Failed to fetch track: {}", response.status()))
+        }
+    }
+
+    pub async fn get_album_tracks(album_id: String) -> Result<Vec<Track>, String> {
+        let response = Request::get(&format!("{}/spotify/albums/{}/tracks", API_BASE_URL, album_id))
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if response.ok() {
+            response
+                .json::<Vec<Track>>()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))
+        } else {
+            Err(format!("Failed to fetch album tracks: {}", response.status()))
+        }
+    }
+
+    pub async fn get_genres() -> Result<Vec<String>, String> {
+        let response = Request::get(&format!("{}/spotify/genres", API_BASE_URL))
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if response.ok() {
+            response
+                .json::<Vec<String>>()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))
+        } else {
+            Err(format!("Failed to fetch genres: {}", response.status()))
+        }
+    }
+
+    /// Recommended tracks, plus whether fewer tracks were available than `limit` asked for
+    /// and how many explicit tracks `exclude_explicit` left out
+    pub async fn get_recommendations(
+        seed: &RecommendationSeed,
+        limit: Option<u32>,
+        genres: &[String],
+        exclude_explicit: bool,
+    ) -> Result<(Vec<Track>, bool, u32), String> {
+        let mut url = format!("{}/recommendations?{}", API_BASE_URL, seed.query_param());
         if let Some(limit) = limit {
             url.push_str(&format!("&limit={}", limit));
         }
+        if !genres.is_empty() {
+            let genres = String::from(js_sys::encode_uri_component(&genres.join(",")));
+            url.push_str(&format!("&genres={}", genres));
+        }
+        if exclude_explicit {
+            url.push_str("&exclude_explicit=true");
+        }
 
         let response = Request::get(&url)
             .send()
@@ -92,10 +280,20 @@ impl ApiService {
             .map_err(|e| format!("Request failed: {}", e))?;
 
         if response.ok() {
-            response
+            let limit_exceeded = response
+                .headers()
+                .get("x-limit-exceeded")
+                .is_some_and(|value| value == "true");
+            let explicit_filtered = response
+                .headers()
+                .get("x-explicit-filtered-count")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0);
+            let tracks = response
                 .json::<Vec<Track>>()
                 .await
-                .map_err(|e| format!("Failed to parse response: {}", e))
+                .map_err(|e| format!("Failed to parse response: {}", e))?;
+            Ok((tracks, limit_exceeded, explicit_filtered))
         } else {
             Err(format!("Failed to fetch recommendations: {}", response.status()))
         }
@@ -134,6 +332,48 @@ impl ApiService {
         }
     }
 
+    /// Each query's YouTube match, without creating anything
+    pub async fn preview_youtube_playlist(track_names: Vec<String>) -> Result<Vec<TrackMatch>, String> {
+        let response = Request::post(&format!("{}/youtube/playlist/preview", API_BASE_URL))
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({ "track_names": track_names }))
+            .map_err(|e| format!("Failed to serialize request: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if response.ok() {
+            response
+                .json::<Vec<TrackMatch>>()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            Err(format!("Preview failed: {}", error_message(error_text)))
+        }
+    }
+
+    /// Create a playlist from videos approved in a preview
+    pub async fn create_playlist_from_videos(request_body: ApprovedPlaylistRequest) -> Result<CreatedPlaylist, String> {
+        let response = Request::post(&format!("{}/youtube/playlist/from-videos", API_BASE_URL))
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+            .map_err(|e| format!("Failed to serialize request: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if response.ok() {
+            response
+                .json::<CreatedPlaylist>()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            Err(format!("Playlist creation failed: {}", error_message(error_text)))
+        }
+    }
+
     pub async fn create_playlist_from_recommendations(
         title: String,
         description: String,
@@ -172,8 +412,17 @@ impl ApiService {
         }
     }
 
-    pub async fn get_similar_tracks_with_youtube(track_id: String, limit: Option<u32>) -> Result<SimilarTracksResponse, String> {
+    /// Similar tracks for `track_id`, or for the track closest to `fallback_name` if the id is no
+    /// longer stored
+    pub async fn get_similar_tracks_with_youtube(
+        track_id: String,
+        fallback_name: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<SimilarTracksResponse, String> {
         let mut url = format!("{}/similar-tracks?track_id={}", API_BASE_URL, track_id);
+        if let Some(name) = fallback_name {
+            url.push_str(&format!("&fallback_name={}", String::from(js_sys::encode_uri_component(&name))));
+        }
         if let Some(limit) = limit {
             url.push_str(&format!("&limit={}", limit));
         }
@@ -197,3 +446,24 @@ impl ApiService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_message_reads_json_error_bodies() {
+        assert_eq!(error_message(r#"{"error":"Playlist not found"}"#.to_string()), "Playlist not found");
+        assert_eq!(error_message("Bad Gateway".to_string()), "Bad Gateway");
+    }
+
+    #[test]
+    fn test_created_playlist_reads_backend_field_names() {
+        let playlist: CreatedPlaylist = serde_json::from_str(
+            r#"{"id": "PL1", "name": "Mix", "url": "https://www.youtube.com/playlist?list=PL1", "tracks_added": 2}"#,
+        )
+        .unwrap();
+        assert_eq!(playlist.playlist_id, "PL1");
+        assert_eq!(playlist.title, "Mix");
+    }
+}
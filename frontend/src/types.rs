@@ -8,6 +8,31 @@ pub struct Artist {
     pub popularity: u32,
     pub followers: u32,
     pub image_url: Option<String>,
+    #[serde(default)]
+    pub image_urls: Vec<ArtistImage>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// The fields the artist lists show, from `?fields=summary`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtistSummary {
+    pub id: String,
+    pub name: String,
+    pub popularity: u32,
+    pub image_url: Option<String>,
+    /// Every size of the artist's image, largest first
+    #[serde(default)]
+    pub image_urls: Vec<ArtistImage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtistImage {
+    pub url: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -18,45 +43,120 @@ pub struct Track {
     pub artist_names: Vec<String>,
     pub album_id: String,
     pub album_name: String,
+    #[serde(default)]
+    pub album_image_url: Option<String>,
     pub duration_ms: u32,
     pub popularity: u32,
     pub explicit: bool,
     pub preview_url: Option<String>,
-    // Audio features
-    pub danceability: f32,
-    pub energy: f32,
-    pub key: i32,
-    pub loudness: f32,
-    pub mode: i32,
-    pub speechiness: f32,
-    pub acousticness: f32,
-    pub instrumentalness: f32,
-    pub liveness: f32,
-    pub valence: f32,
-    pub tempo: f32,
-    pub time_signature: i32,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    // Audio features, `None` when Spotify had none for the track
+    pub danceability: Option<f32>,
+    pub energy: Option<f32>,
+    pub key: Option<i32>,
+    pub loudness: Option<f32>,
+    pub mode: Option<i32>,
+    pub speechiness: Option<f32>,
+    pub acousticness: Option<f32>,
+    pub instrumentalness: Option<f32>,
+    pub liveness: Option<f32>,
+    pub valence: Option<f32>,
+    pub tempo: Option<f32>,
+    pub time_signature: Option<i32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct YouTubeVideo {
-    pub video_id: String,
-    pub title: String,
-    pub channel_title: String,
-    pub thumbnail_url: String,
+pub struct Album {
+    pub id: String,
+    pub name: String,
+    pub artist_ids: Vec<String>,
+    pub artist_names: Vec<String>,
+    pub release_date: Option<String>,
+    pub total_tracks: Option<u32>,
+    pub image_url: Option<String>,
+    pub track_count: u32,
+}
+
+/// What recommendations are seeded from: one track, or an artist's average sound
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecommendationSeed {
+    Track(String),
+    Artist(String),
+}
+
+impl RecommendationSeed {
+    /// The query parameter the recommendations endpoint reads this seed from
+    pub fn query_param(&self) -> String {
+        match self {
+            RecommendationSeed::Track(id) => format!("seed_tracks={}", id),
+            RecommendationSeed::Artist(id) => format!("seed_artist={}", id),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct BackendYouTubeVideo {
+pub struct TrackDetail {
+    pub track: Track,
+    pub artists: Vec<Artist>,
+    pub album: Option<Album>,
+    pub similar: Vec<Track>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YouTubeVideo {
     pub id: String,
     pub title: String,
     pub channel_title: String,
     pub duration: String,
+    #[serde(default)]
+    pub thumbnail_url: String,
+    #[serde(default)]
+    pub confidence: f64,
+}
+
+/// A track's YouTube search result from a playlist preview
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackMatch {
+    pub track_name: String,
+    pub video: Option<YouTubeVideo>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovedVideo {
+    pub track_name: String,
+    pub video_id: String,
+    pub video_title: String,
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovedPlaylistRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub access_token: String,
+    pub videos: Vec<ApprovedVideo>,
+}
+
+/// Each feature's share of a similar track's distance; tempo is BPM / 200
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimilarityBreakdown {
+    pub valence: f64,
+    pub energy: f64,
+    pub danceability: f64,
+    pub tempo: f64,
+    pub distance: f64,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TrackWithYouTube {
     pub track: Track,
-    pub youtube_video: Option<BackendYouTubeVideo>,
+    pub youtube_video: Option<YouTubeVideo>,
+    #[serde(default)]
+    pub explanation: Option<SimilarityBreakdown>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -65,9 +165,19 @@ pub struct SimilarTracksResponse {
     pub similar_tracks: Vec<TrackWithYouTube>,
 }
 
+/// Playlist names fitting a seed track's mood, best fit first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NameSuggestions {
+    pub track_id: String,
+    pub mood: String,
+    pub names: Vec<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CreatedPlaylist {
+    #[serde(alias = "id")]
     pub playlist_id: String,
+    #[serde(alias = "name")]
     pub title: String,
     pub url: String,
 }
@@ -75,6 +185,8 @@ pub struct CreatedPlaylist {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SpotifyImportRequest {
     pub playlist_url: String,
+    /// Names the import so it can be cancelled while it runs
+    pub job_id: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -83,21 +195,3 @@ pub struct YouTubePlaylistRequest {
     pub description: String,
     pub track_queries: Vec<String>,
 }
-
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct RecommendationRequest {
-    pub track_id: String,
-    pub limit: Option<u32>,
-}
-
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub data: T,
-    pub message: Option<String>,
-}
-
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct ApiError {
-    pub error: String,
-    pub message: String,
-}
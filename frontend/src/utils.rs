@@ -0,0 +1,154 @@
+use crate::types::{ArtistImage, SimilarityBreakdown, Track};
+
+/// Placeholder shown for audio features Spotify had no data for
+pub const MISSING_FEATURE: &str = "—";
+
+/// Formats a duration as `M:SS`, or `H:MM:SS` once it reaches an hour
+pub fn format_duration(ms: u32) -> String {
+    let total_seconds = ms / 1000;
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let seconds = total_seconds % 60;
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+// NaN or infinite feature values are shown the same as missing ones
+fn finite(value: Option<f32>) -> Option<f32> {
+    value.filter(|value| value.is_finite())
+}
+
+pub fn format_percent(value: Option<f32>) -> String {
+    match finite(value) {
+        Some(value) => format!("{:.0}%", value * 100.0),
+        None => MISSING_FEATURE.to_string(),
+    }
+}
+
+pub fn format_tempo(tempo: Option<f32>) -> String {
+    match finite(tempo) {
+        Some(tempo) => format!("{:.0}", tempo),
+        None => MISSING_FEATURE.to_string(),
+    }
+}
+
+pub fn get_audio_feature_color(value: Option<f32>) -> &'static str {
+    match finite(value) {
+        Some(value) if value >= 0.7 => "bg-green-100 text-green-800",
+        Some(value) if value >= 0.4 => "bg-yellow-100 text-yellow-800",
+        Some(_) => "bg-red-100 text-red-800",
+        None => "bg-gray-100 text-gray-500",
+    }
+}
+
+/// The track's preview clip URL, if Spotify provided a usable one
+pub fn preview_url(track: &Track) -> Option<&str> {
+    track.preview_url.as_deref().filter(|url| !url.is_empty())
+}
+
+/// The smallest image at least `width` pixels wide, or the widest when none is, falling back to
+/// `fallback` for artists stored without their sizes
+pub fn image_for_width(images: &[ArtistImage], fallback: Option<&str>, width: u32) -> Option<String> {
+    let sized = images.iter().filter_map(|image| Some((image.width?, image)));
+    let large_enough = sized.clone().filter(|(image_width, _)| *image_width >= width).min_by_key(|(image_width, _)| *image_width);
+    large_enough
+        .or_else(|| sized.max_by_key(|(image_width, _)| *image_width))
+        .map(|(_, image)| image.url.clone())
+        .or_else(|| fallback.filter(|url| !url.is_empty()).map(str::to_string))
+}
+
+/// Summarize which feature a similar track is closest in and which it differs most in
+pub fn explain_similarity(breakdown: &SimilarityBreakdown) -> String {
+    let mut features = [
+        ("valence", breakdown.valence),
+        ("energy", breakdown.energy),
+        ("danceability", breakdown.danceability),
+        ("tempo", breakdown.tempo),
+    ];
+    features.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let (closest, _) = features[0];
+    let (furthest, furthest_diff) = features[features.len() - 1];
+
+    if furthest_diff == 0.0 {
+        "Matches on every feature".to_string()
+    } else {
+        format!("Closest in {}, differs most in {}", closest, furthest)
+    }
+}
+
+/// Tracks the server embedded in `/tracks.html`. They're taken out of the page so only the first
+/// render uses them, and the static list shown before the app loaded is removed with them.
+pub fn take_prerendered_tracks() -> Option<Vec<Track>> {
+    let document = web_sys::window()?.document()?;
+    if let Some(list) = document.get_element_by_id("prerendered-tracks") {
+        list.remove();
+    }
+    let data = document.get_element_by_id("initial-tracks")?;
+    data.remove();
+    serde_json::from_str(&data.text_content()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0:00");
+        assert_eq!(format_duration(999), "0:00");
+        assert_eq!(format_duration(61_000), "1:01");
+        assert_eq!(format_duration(3_599_999), "59:59");
+        assert_eq!(format_duration(3_600_000), "1:00:00");
+        assert_eq!(format_duration(4_325_000), "1:12:05");
+        assert_eq!(format_duration(u32::MAX), "1193:02:47");
+    }
+
+    #[test]
+    fn test_image_for_width_picks_smallest_large_enough() {
+        let image = |url: &str, width: Option<u32>| ArtistImage { url: url.to_string(), width, height: width };
+        let images = vec![image("640", Some(640)), image("320", Some(320)), image("160", Some(160))];
+
+        assert_eq!(image_for_width(&images, None, 128).as_deref(), Some("160"));
+        assert_eq!(image_for_width(&images, None, 200).as_deref(), Some("320"));
+        assert_eq!(image_for_width(&images, None, 1000).as_deref(), Some("640"));
+        assert_eq!(image_for_width(&[image("unsized", None)], Some("largest"), 128).as_deref(), Some("largest"));
+        assert_eq!(image_for_width(&[], Some(""), 128), None);
+    }
+
+    #[test]
+    fn test_feature_color_boundaries() {
+        assert_eq!(get_audio_feature_color(Some(0.39)), "bg-red-100 text-red-800");
+        assert_eq!(get_audio_feature_color(Some(0.4)), "bg-yellow-100 text-yellow-800");
+        assert_eq!(get_audio_feature_color(Some(0.69)), "bg-yellow-100 text-yellow-800");
+        assert_eq!(get_audio_feature_color(Some(0.7)), "bg-green-100 text-green-800");
+        assert_eq!(get_audio_feature_color(None), "bg-gray-100 text-gray-500");
+        assert_eq!(get_audio_feature_color(Some(f32::NAN)), "bg-gray-100 text-gray-500");
+    }
+
+    #[test]
+    fn test_explain_similarity() {
+        let breakdown = SimilarityBreakdown {
+            valence: 0.1,
+            energy: 0.01,
+            danceability: 0.05,
+            tempo: 0.2,
+            distance: 0.36,
+        };
+        assert_eq!(explain_similarity(&breakdown), "Closest in energy, differs most in tempo");
+
+        let identical = SimilarityBreakdown { valence: 0.0, energy: 0.0, danceability: 0.0, tempo: 0.0, distance: 0.0 };
+        assert_eq!(explain_similarity(&identical), "Matches on every feature");
+    }
+
+    #[test]
+    fn test_missing_features_use_placeholder() {
+        assert_eq!(format_percent(Some(0.456)), "46%");
+        assert_eq!(format_percent(None), MISSING_FEATURE);
+        assert_eq!(format_tempo(Some(120.4)), "120");
+        assert_eq!(format_tempo(Some(f32::INFINITY)), MISSING_FEATURE);
+    }
+}